@@ -0,0 +1,213 @@
+use std::{cmp, ops::Range};
+
+use crate::{
+    document::{marks::Marks, Document, Row},
+    utils::Position,
+};
+
+/// Rows a change replaced, enough to undo it: rows `first` up to
+/// `first + len` of the document were `rows` before it, and its marks were
+/// `marks`.
+#[derive(Clone)]
+pub struct RowChange {
+    first: usize,
+    len: usize,
+    rows: Vec<Row>,
+    marks: Marks,
+}
+
+impl RowChange {
+    /// A change starting on `line`, before the marks `marks` were moved.
+    pub fn new(line: usize, marks: Marks) -> Self {
+        Self {
+            first: line,
+            len: 0,
+            rows: Vec::new(),
+            marks,
+        }
+    }
+
+    /// Rows of the document the change made.
+    pub fn lines(&self) -> Range<usize> {
+        self.first..self.first + self.len
+    }
+
+    /// Keeps the rows of `rows` in `lines` as they are before they are
+    /// edited. The rows between them and the ones already kept are kept
+    /// too.
+    pub fn keep(&mut self, rows: &[Row], lines: Range<usize>) {
+        let end = cmp::min(lines.end, rows.len());
+        let start = cmp::min(lines.start, end);
+
+        if start < self.first {
+            self.rows
+                .splice(0..0, rows[start..self.first].iter().cloned());
+            self.len += self.first - start;
+            self.first = start;
+        }
+        let kept_end = self.first + self.len;
+        if end > kept_end {
+            self.rows.extend(rows[kept_end..end].iter().cloned());
+            self.len = end - self.first;
+        }
+    }
+
+    /// `count` rows were inserted among the ones kept.
+    pub fn rows_inserted(&mut self, count: usize) {
+        self.len += count;
+    }
+
+    /// `count` of the rows kept were deleted.
+    pub fn rows_deleted(&mut self, count: usize) {
+        self.len -= count;
+    }
+
+    /// Whether `rows`, the rows of the document, differ from the ones kept.
+    pub fn is_change_of(&self, rows: &[Row]) -> bool {
+        rows[self.lines()] != *self.rows
+    }
+
+    /// Puts back the rows and marks the change replaced in `rows` and
+    /// `marks`, and returns the change that makes them again.
+    pub fn undo(self, rows: &mut Vec<Row>, marks: &mut Marks) -> Self {
+        let lines = self.lines();
+        let count = self.rows.len();
+        let replaced = rows.splice(lines, self.rows).collect();
+
+        Self {
+            first: self.first,
+            len: count,
+            rows: replaced,
+            marks: std::mem::replace(marks, self.marks),
+        }
+    }
+
+    /// First and last positions of the text the change made, `None` if
+    /// nothing changed.
+    pub fn changed_range(&self, doc: &Document) -> Option<(Position, Position)> {
+        let new_len = doc.lines_len();
+        let old_len = new_len - self.len + self.rows.len();
+        let new = |y: usize| doc.row(y).map_or("", |row| row.content.as_str());
+        let old = |y: usize| {
+            if y < self.first {
+                new(y)
+            } else if y < self.first + self.rows.len() {
+                self.rows[y - self.first].content.as_str()
+            } else {
+                new(y + self.len - self.rows.len())
+            }
+        };
+
+        let first = (self.first..new_len).find(|y| *y >= old_len || new(*y) != old(*y));
+        let first = match first {
+            Some(first) => first,
+            // only rows after the last one were deleted
+            None if old_len > new_len => new_len.saturating_sub(1),
+            None => return None,
+        };
+
+        // rows from the end that are the same, without going before
+        // `first`, those after the change are
+        let limit = cmp::min(new_len, old_len).saturating_sub(first);
+        let after = cmp::min(new_len - (self.first + self.len), limit);
+        let same_end = after
+            + (after..limit)
+                .take_while(|i| new(new_len - 1 - i) == old(old_len - 1 - i))
+                .count();
+        let last = cmp::max(first, new_len.saturating_sub(same_end + 1));
+
+        let (new_row, old_row) = (new(first), old(first));
+        let start_x = new_row
+            .char_indices()
+            .zip(old_row.chars())
+            .find(|((_, a), b)| a != b)
+            .map_or(cmp::min(new_row.len(), old_row.len()), |((x, _), _)| x);
+
+        let (new_row, old_row) = (new(last), old(old_len.saturating_sub(same_end + 1)));
+        let same_suffix = new_row
+            .chars()
+            .rev()
+            .zip(old_row.chars().rev())
+            .take_while(|(a, b)| a == b)
+            .map(|(c, _)| c.len_utf8())
+            .sum::<usize>();
+        let end_x = new_row.len().saturating_sub(same_suffix + 1);
+
+        let start = Position {
+            x: start_x,
+            y: first,
+        };
+        let end = cmp::max(start, Position { x: end_x, y: last });
+        Some((start, end))
+    }
+}
+mod test {
+    #[cfg(test)]
+    use crate::document::Document;
+
+    #[cfg(test)]
+    fn lines(doc: &Document) -> Vec<String> {
+        (0..doc.lines_len())
+            .filter_map(|y| doc.row(y).map(|row| row.content.clone()))
+            .collect()
+    }
+
+    /// A document with a row deleted and text inserted below it.
+    #[cfg(test)]
+    fn edited_doc() -> Document {
+        use crate::utils::Position;
+
+        let mut doc = Document::from(vec!["a", "b", "c", "d"]);
+        doc.delete_rows(1..2);
+        doc.insert_text(
+            &Position { x: 1, y: 2 },
+            &[String::from("x"), String::from("y")],
+        );
+        doc
+    }
+
+    #[test]
+    fn should_keep_the_rows_changed() {
+        use crate::utils::Position;
+
+        let mut doc = edited_doc();
+        let change = doc.take_change().unwrap();
+
+        assert_eq!(change.lines(), 1..4);
+        assert_eq!(
+            change.changed_range(&doc),
+            Some((Position { x: 0, y: 1 }, Position { x: 0, y: 3 }))
+        );
+    }
+
+    #[test]
+    fn should_undo_the_rows_changed() {
+        let mut doc = edited_doc();
+        let change = doc.take_change().unwrap();
+
+        let redo = doc.undo_change(change);
+        assert_eq!(lines(&doc), ["a", "b", "c", "d"]);
+        assert_eq!(redo.lines(), 1..4);
+    }
+
+    #[test]
+    fn should_redo_the_rows_changed() {
+        let mut doc = edited_doc();
+        let change = doc.take_change().unwrap();
+        let redo = doc.undo_change(change);
+
+        doc.undo_change(redo);
+        assert_eq!(lines(&doc), ["a", "c", "dx", "y"]);
+    }
+
+    #[test]
+    fn should_not_keep_edits_that_change_nothing() {
+        use crate::utils::Position;
+
+        let mut doc = Document::from(vec!["a"]);
+        doc.insert_char(&Position { x: 0, y: 0 }, 'z');
+        doc.remove_char(&Position { x: 0, y: 0 });
+
+        assert!(doc.take_change().is_none());
+    }
+}
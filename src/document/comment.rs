@@ -0,0 +1,159 @@
+use std::ops::Range;
+
+use crate::document::Document;
+
+impl Document {
+    /// Comments out the rows in `lines`, or uncomments them when they all
+    /// are. Markers go at the indentation of the least indented row and
+    /// blank rows are left as they are. Languages without line comments get
+    /// a block comment on each row. Returns `false` when the language has
+    /// no comments.
+    pub fn toggle_comments(&mut self, lines: Range<usize>) -> bool {
+        let Some((open, close)) = self.comment_markers() else {
+            return false;
+        };
+
+        let rows: Vec<usize> = lines
+            .filter(|y| {
+                self.row(*y)
+                    .is_some_and(|row| !row.content.trim().is_empty())
+            })
+            .collect();
+        // only spaces and tabs are indentation, as for `row_indent`
+        let commented = rows.iter().all(|y| {
+            let text = self
+                .row(*y)
+                .map_or("", |row| row.content.trim_matches([' ', '\t']));
+            text.len() >= open.len() + close.len()
+                && text.starts_with(&open)
+                && text.ends_with(&close)
+        });
+
+        if commented {
+            for y in rows {
+                let Some(content) = self.row(y).map(|row| row.content.clone()) else {
+                    continue;
+                };
+                let indent = self.row_indent(y).len();
+                let text = content[indent..].trim_end_matches([' ', '\t']);
+                let text = &text[open.len()..text.len() - close.len()];
+                let text = text.strip_prefix(' ').unwrap_or(text);
+                let text = if close.is_empty() {
+                    text
+                } else {
+                    text.strip_suffix(' ').unwrap_or(text)
+                };
+
+                let uncommented = format!("{}{text}", &content[..indent]);
+                if let Some(row) = self.row_mut(y) {
+                    row.remove_range(0..usize::MAX);
+                    row.insert_str(0, &uncommented);
+                }
+            }
+        } else {
+            let column = rows
+                .iter()
+                .map(|y| self.row_indent(*y).len())
+                .min()
+                .unwrap_or(0);
+            for y in rows {
+                if let Some(row) = self.row_mut(y) {
+                    if !close.is_empty() {
+                        row.insert_str(usize::MAX, &format!(" {close}"));
+                    }
+                    row.insert_str(column, &format!("{open} "));
+                }
+            }
+        }
+
+        true
+    }
+
+    /// Markers commenting out a row, the closing one empty for line
+    /// comments.
+    fn comment_markers(&self) -> Option<(String, String)> {
+        let syntax = self.syntax()?;
+        if let Some(open) = syntax.line_comment() {
+            return Some((open.to_string(), String::new()));
+        }
+        let (open, close) = syntax.block_comment()?;
+
+        Some((open.to_string(), close.to_string()))
+    }
+}
+
+mod test {
+    #[cfg(test)]
+    use crate::document::Document;
+
+    #[cfg(test)]
+    fn rows(doc: &Document) -> Vec<String> {
+        (0..doc.lines_len())
+            .map(|y| doc.row(y).unwrap().content.clone())
+            .collect()
+    }
+
+    #[cfg(test)]
+    fn python_doc(rows: Vec<&str>) -> Document {
+        use crate::syntax::languages::PYTHON;
+        use std::rc::Rc;
+
+        let mut doc = Document::from(rows);
+        doc.set_syntax(Some(Rc::new(PYTHON)));
+        doc
+    }
+
+    #[test]
+    fn should_not_comment_without_syntax() {
+        let mut doc = Document::from(vec!["def a():", "    if b:", "", "        c()"]);
+
+        assert!(!doc.toggle_comments(0..4));
+    }
+
+    #[test]
+    fn should_comment_at_the_least_indentation() {
+        let mut doc = python_doc(vec!["def a():", "    if b:", "", "        c()"]);
+
+        assert!(doc.toggle_comments(1..4));
+        assert_eq!(
+            rows(&doc),
+            vec!["def a():", "    # if b:", "", "    #     c()"]
+        );
+    }
+
+    #[test]
+    fn should_uncomment_when_all_rows_are_commented() {
+        let mut doc = python_doc(vec!["def a():", "    # if b:", "", "    #     c()"]);
+
+        assert!(doc.toggle_comments(0..2));
+        assert_eq!(rows(&doc)[..2], ["# def a():", "#     # if b:"]);
+        assert!(doc.toggle_comments(0..2));
+        assert_eq!(rows(&doc)[..2], ["def a():", "    # if b:"]);
+        assert!(doc.toggle_comments(1..4));
+        assert_eq!(rows(&doc), vec!["def a():", "    if b:", "", "        c()"]);
+    }
+
+    #[test]
+    fn should_not_take_other_spaces_as_indentation() {
+        let mut doc = python_doc(vec!["\u{a0}# x"]);
+
+        assert!(doc.toggle_comments(0..1));
+        assert_eq!(rows(&doc), vec!["# \u{a0}# x"]);
+        assert!(doc.toggle_comments(0..1));
+        assert_eq!(rows(&doc), vec!["\u{a0}# x"]);
+    }
+
+    #[test]
+    fn should_use_block_comments_without_line_ones() {
+        use crate::syntax::Markdown;
+        use std::rc::Rc;
+
+        let mut doc = Document::from(vec!["# Title", "  text"]);
+        doc.set_syntax(Some(Rc::new(Markdown)));
+
+        assert!(doc.toggle_comments(0..2));
+        assert_eq!(rows(&doc), vec!["<!-- # Title -->", "<!--   text -->"]);
+        assert!(doc.toggle_comments(0..2));
+        assert_eq!(rows(&doc), vec!["# Title", "  text"]);
+    }
+}
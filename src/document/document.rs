@@ -1,39 +1,129 @@
-use crate::{document::Row, utils::Position};
-use std::{fs::read_to_string, io::Error};
+use crate::{
+    document::{indent, marks::Marks, BufferOptions, DetectedIndent, LineEdit, Row, RowChange},
+    syntax::{Highlighting, Syntax, Token},
+    utils::Position,
+};
+use std::{cmp, fs::read_to_string, io::Error, ops::Range, rc::Rc};
 
 #[derive(Default, Clone)]
 pub struct Document {
     rows: Vec<Row>,
+    file_name: Option<String>,
+    marks: Marks,
+    line_edits: Vec<LineEdit>,
+    /// no row above this line is marked
+    marked_from: usize,
+    /// rows edited since the change was started
+    change: Option<RowChange>,
+    highlighting: Highlighting,
+    options: BufferOptions,
 }
 
 impl Document {
     pub fn open(path: &str) -> Result<Self, Error> {
         let content = read_to_string(path)?;
 
-        let rows = content.lines().map(Row::from).collect::<Vec<Row>>();
-        Ok(Self { rows })
+        let mut rows = content.lines().map(Row::from).collect::<Vec<Row>>();
+        // there is always a line to place the cursor on
+        if rows.is_empty() {
+            rows.push(Row::from(""));
+        }
+
+        Ok(Self {
+            rows,
+            file_name: Some(path.to_string()),
+            marks: Marks::default(),
+            line_edits: Vec::new(),
+            marked_from: 0,
+            change: None,
+            highlighting: Highlighting::default(),
+            options: BufferOptions::default(),
+        })
+    }
+
+    pub fn file_name(&self) -> Option<&str> {
+        self.file_name.as_deref()
     }
 
     pub fn row(&self, index: usize) -> Option<&Row> {
         self.rows.get(index)
     }
 
-    // melhorar essa maneira de pegar mut refs
+    pub fn row_mut(&mut self, index: usize) -> Option<&mut Row> {
+        self.change_rows(index..index + 1);
+        self.highlighting.invalidate(index);
+        self.rows.get_mut(index)
+    }
+
+    /// Highlights the document as written in `syntax`.
+    pub fn set_syntax(&mut self, syntax: Option<Rc<dyn Syntax>>) {
+        self.highlighting = Highlighting::new(syntax);
+    }
+
+    pub fn syntax(&self) -> Option<&dyn Syntax> {
+        self.highlighting.syntax()
+    }
+
+    pub fn options(&self) -> &BufferOptions {
+        &self.options
+    }
+
+    pub fn options_mut(&mut self) -> &mut BufferOptions {
+        &mut self.options
+    }
+
+    /// Indents as the rows already are, the options are left as they are
+    /// when no row tells.
+    pub fn detect_indent(&mut self) -> Option<DetectedIndent> {
+        let detected = indent::detect_indent(self.rows.iter().map(|row| row.content.as_str()))?;
+        match detected {
+            DetectedIndent::Tabs => {
+                self.options.expandtab = false;
+                self.options.shiftwidth = self.options.tabstop;
+            }
+            DetectedIndent::Spaces(width) => {
+                self.options.expandtab = true;
+                self.options.shiftwidth = width;
+            }
+        }
+
+        Some(detected)
+    }
+
+    /// Highlighted tokens of row `line`.
+    pub fn row_tokens(&self, line: usize) -> Vec<Token> {
+        self.highlighting.tokens(&self.rows, line)
+    }
+
+    pub fn mark(&self, name: char) -> Option<Position> {
+        self.marks.get(name)
+    }
+
+    pub fn set_mark(&mut self, name: char, pos: Position) {
+        self.marks.set(name, pos);
+    }
+
+    pub fn marks(&self) -> &Marks {
+        &self.marks
+    }
+
     pub fn insert_char(&mut self, pos: &Position, c: char) {
-        match self.rows.get(pos.y) {
-            Some(_) => self.rows[pos.y].insert_char(pos.x, c),
-            None => {}
-        };
+        self.change_rows(pos.y..pos.y + 1);
+        self.highlighting.invalidate(pos.y);
+        if let Some(row) = self.rows.get_mut(pos.y) {
+            row.insert_char(pos.x, c);
+        }
     }
 
-    // melhorar essa maneira de pegar mut refs
     pub fn remove_char(&mut self, pos: &Position) {
-        match self.rows.get(pos.y) {
-            Some(_) => self.rows[pos.y].remove_char(pos.x),
-            None => {}
-        };
+        self.change_rows(pos.y..pos.y + 1);
+        self.highlighting.invalidate(pos.y);
+        if let Some(row) = self.rows.get_mut(pos.y) {
+            row.remove_char(pos.x);
+        }
     }
 
+    #[allow(dead_code)]
     pub fn is_empty(&self) -> bool {
         self.rows.is_empty()
     }
@@ -41,11 +131,298 @@ impl Document {
     pub fn lines_len(&self) -> usize {
         self.rows.len()
     }
+
+    /// Inserts `text` at `pos`, every item after the first one starts a new
+    /// line. Returns the position right after the inserted text.
+    pub fn insert_text(&mut self, pos: &Position, text: &[String]) -> Position {
+        if self.rows.is_empty() {
+            self.push_empty_row();
+        }
+
+        let y = cmp::min(pos.y, self.rows.len() - 1);
+        let Some((first, rest)) = text.split_first() else {
+            return Position { x: pos.x, y };
+        };
+        self.change_rows(y..y + 1);
+        self.highlighting.invalidate(y);
+
+        let x = cmp::min(pos.x, self.rows[y].content.len());
+        let tail = self.rows[y].split_off(x);
+        self.rows[y].insert_str(x, first);
+
+        let new_rows = rest.iter().map(|line| Row::from(line.as_str()));
+        self.rows.splice((y + 1)..=y, new_rows);
+        self.lines_inserted(y + 1, rest.len());
+
+        let last_y = y + rest.len();
+        let end = Position {
+            x: self.rows[last_y].content.len(),
+            y: last_y,
+        };
+        self.rows[last_y].insert_str(end.x, &tail.content);
+
+        end
+    }
+
+    /// Text from `start` up to `end` (exclusive), one item per line.
+    pub fn text_range(&self, start: &Position, end: &Position) -> Vec<String> {
+        let (start, end) = self.clamp_range(start, end);
+
+        (start.y..=end.y)
+            .filter_map(|y| {
+                let row = self.rows.get(y)?;
+                let from = if y == start.y { start.x } else { 0 };
+                let to = if y == end.y { end.x } else { row.content.len() };
+
+                Some(row.slice(from..to).to_string())
+            })
+            .collect()
+    }
+
+    /// Removes the text from `start` up to `end` (exclusive) and returns it,
+    /// one item per line.
+    pub fn delete_range(&mut self, start: &Position, end: &Position) -> Vec<String> {
+        let removed = self.text_range(start, end);
+        let (start, end) = self.clamp_range(start, end);
+        self.change_rows(start.y..end.y + 1);
+        self.marks.text_deleted(&start, &end);
+        if start.y < end.y {
+            self.follow_line_edit(LineEdit::Deleted(start.y + 1..end.y + 1));
+        }
+        self.highlighting.invalidate(start.y);
+
+        if start.y == end.y {
+            if let Some(row) = self.rows.get_mut(start.y) {
+                row.remove_range(start.x..end.x);
+            }
+            return removed;
+        }
+
+        let tail = self.rows[end.y].split_off(end.x);
+        self.rows[start.y].remove_range(start.x..usize::MAX);
+        self.rows[start.y].insert_str(usize::MAX, &tail.content);
+        self.rows.drain(start.y + 1..=end.y);
+
+        removed
+    }
+
+    /// Removes whole rows, the document is never left without a line.
+    pub fn delete_rows(&mut self, range: Range<usize>) -> Vec<String> {
+        let end = cmp::min(range.end, self.rows.len());
+        let start = cmp::min(range.start, end);
+        self.change_rows(start..end);
+
+        let removed = self.rows.drain(start..end).map(|row| row.content).collect();
+        self.marks.lines_deleted(start..end);
+        self.follow_line_edit(LineEdit::Deleted(start..end));
+        self.highlighting.invalidate(start);
+
+        if self.rows.is_empty() {
+            self.push_empty_row();
+        }
+
+        removed
+    }
+
+    /// Adds the line the cursor is placed on to a document without any.
+    fn push_empty_row(&mut self) {
+        self.change_rows(0..0);
+        self.rows.push(Row::from(""));
+        if let Some(change) = self.change.as_mut() {
+            change.rows_inserted(1);
+        }
+    }
+
+    fn lines_inserted(&mut self, line: usize, count: usize) {
+        self.marks.lines_inserted(line, count);
+        self.follow_line_edit(LineEdit::Inserted { line, count });
+    }
+
+    /// Keeps `edit` for the windows, the marked rows it moved are still
+    /// looked for from `marked_from`.
+    fn follow_line_edit(&mut self, edit: LineEdit) {
+        match &edit {
+            LineEdit::Inserted { line, count } if *line < self.marked_from => {
+                self.marked_from += count;
+            }
+            LineEdit::Deleted(lines) if self.marked_from >= lines.end => {
+                self.marked_from -= lines.len();
+            }
+            LineEdit::Deleted(lines) => self.marked_from = self.marked_from.min(lines.start),
+            LineEdit::Inserted { .. } => {}
+        }
+        match (&edit, self.change.as_mut()) {
+            (LineEdit::Inserted { count, .. }, Some(change)) => change.rows_inserted(*count),
+            (LineEdit::Deleted(lines), Some(change)) => change.rows_deleted(lines.len()),
+            (_, None) => {}
+        }
+        self.line_edits.push(edit);
+    }
+
+    /// Rows edited since the last call, `None` if their text is the same
+    /// as before.
+    pub fn take_change(&mut self) -> Option<RowChange> {
+        self.change
+            .take()
+            .filter(|change| change.is_change_of(&self.rows))
+    }
+
+    /// Puts back the rows `change` replaced, the options are kept as they
+    /// are now. Returns the change that makes the rows again.
+    pub fn undo_change(&mut self, change: RowChange) -> RowChange {
+        self.change = None;
+        let lines = change.lines();
+        let redo = change.undo(&mut self.rows, &mut self.marks);
+        let inserted = redo.lines();
+
+        self.follow_line_edit(LineEdit::Deleted(lines));
+        self.follow_line_edit(LineEdit::Inserted {
+            line: inserted.start,
+            count: inserted.len(),
+        });
+        self.highlighting.invalidate(inserted.start);
+        self.marked_from = 0;
+
+        redo
+    }
+
+    /// Keeps the rows in `lines` as they are before they are edited, for
+    /// the change in progress.
+    fn change_rows(&mut self, lines: Range<usize>) {
+        let first = cmp::min(lines.start, self.rows.len());
+        self.change
+            .get_or_insert_with(|| RowChange::new(first, self.marks.clone()))
+            .keep(&self.rows, lines);
+    }
+
+    /// Lines inserted and deleted since the last call, in the order they
+    /// were.
+    pub fn take_line_edits(&mut self) -> Vec<LineEdit> {
+        std::mem::take(&mut self.line_edits)
+    }
+
+    /// Inserts whole rows before `line`.
+    pub fn insert_rows(&mut self, line: usize, rows: &[String]) {
+        let line = cmp::min(line, self.rows.len());
+        self.change_rows(line..line);
+        self.rows
+            .splice(line..line, rows.iter().map(|row| Row::from(row.as_str())));
+        self.lines_inserted(line, rows.len());
+        self.highlighting.invalidate(line);
+    }
+
+    /// Adds one level of indentation to the non empty rows in `lines`.
+    pub fn indent_rows(&mut self, lines: Range<usize>) {
+        self.change_rows(lines.clone());
+        self.highlighting.invalidate(lines.start);
+        let indent = self.options.indent();
+        for row in self.rows_in(lines) {
+            if !row.content.is_empty() {
+                row.insert_str(0, &indent);
+            }
+        }
+    }
+
+    /// Removes up to one level of indentation from the rows in `lines`.
+    pub fn dedent_rows(&mut self, lines: Range<usize>) {
+        self.change_rows(lines.clone());
+        self.highlighting.invalidate(lines.start);
+        let shiftwidth = self.options.shiftwidth;
+        for row in self.rows_in(lines) {
+            let width = if row.content.starts_with('\t') {
+                1
+            } else {
+                cmp::min(row.first_non_blank(), shiftwidth)
+            };
+
+            row.remove_range(0..width);
+        }
+    }
+
+    /// Marks the rows in `lines` for which `f` is true, the other rows in
+    /// `lines` are unmarked. Returns how many were marked.
+    pub fn mark_rows(&mut self, lines: Range<usize>, f: impl Fn(&Row) -> bool) -> usize {
+        self.marked_from = self.marked_from.min(lines.start);
+        let mut marked = 0;
+        for row in self.rows_in(lines) {
+            row.marked = f(row);
+            marked += usize::from(row.marked);
+        }
+
+        marked
+    }
+
+    /// Unmarks the first marked row and returns its line. The rows are
+    /// looked for from the last one taken.
+    pub fn take_marked_row(&mut self) -> Option<usize> {
+        let from = cmp::min(self.marked_from, self.rows.len());
+        let Some(line) = self.rows[from..]
+            .iter()
+            .position(|row| row.marked)
+            .map(|i| from + i)
+        else {
+            self.marked_from = self.rows.len();
+            return None;
+        };
+        self.rows[line].marked = false;
+        self.marked_from = line + 1;
+
+        Some(line)
+    }
+
+    pub fn clear_marks(&mut self) {
+        for row in &mut self.rows {
+            row.marked = false;
+        }
+        self.marked_from = self.rows.len();
+    }
+
+    fn rows_in(&mut self, lines: Range<usize>) -> &mut [Row] {
+        let end = cmp::min(lines.end, self.rows.len());
+        let start = cmp::min(lines.start, end);
+
+        &mut self.rows[start..end]
+    }
+
+    /// Keeps a range inside the document, `end` may be right after the last
+    /// char of a line which includes its line break.
+    fn clamp_range(&self, start: &Position, end: &Position) -> (Position, Position) {
+        if self.rows.is_empty() {
+            return (Position::default(), Position::default());
+        }
+
+        let last_y = self.rows.len().saturating_sub(1);
+        let clamp = |pos: &Position| {
+            if pos.y > last_y {
+                return Position {
+                    x: self.rows.get(last_y).map_or(0, |row| row.content.len()),
+                    y: last_y,
+                };
+            }
+
+            Position {
+                x: cmp::min(pos.x, self.rows[pos.y].content.len()),
+                y: pos.y,
+            }
+        };
+
+        let (start, end) = (clamp(start), clamp(end));
+        (cmp::min(start, end), cmp::max(start, end))
+    }
 }
 
 impl From<Vec<&str>> for Document {
     fn from(value: Vec<&str>) -> Self {
         let rows: Vec<Row> = value.into_iter().map(Row::from).collect();
-        Self { rows }
+        Self {
+            rows,
+            file_name: None,
+            marks: Marks::default(),
+            line_edits: Vec::new(),
+            marked_from: 0,
+            change: None,
+            highlighting: Highlighting::default(),
+            options: BufferOptions::default(),
+        }
     }
 }
@@ -0,0 +1,421 @@
+use std::{
+    env, fs,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+};
+
+use regex::Regex;
+
+use crate::document::BufferOptions;
+
+const FILE_NAME: &str = ".editorconfig";
+
+/// A glob of an `.editorconfig` section, read as a regular expression. The
+/// numbers of `{1..9}` ranges are checked once it matches.
+pub struct Glob {
+    regex: Regex,
+    ranges: Vec<RangeInclusive<i64>>,
+}
+
+impl Glob {
+    /// Reads a section name. Without a `/` it matches files of any
+    /// directory, with one it is relative to the `.editorconfig` file.
+    pub fn new(pattern: &str) -> Option<Self> {
+        let mut ranges = Vec::new();
+        let chars: Vec<char> = pattern.chars().collect();
+        let body = translate(&chars, &mut ranges)?;
+
+        let prefix = if !pattern.contains('/') {
+            "(?:.*/)?"
+        } else if pattern.starts_with('/') {
+            ""
+        } else {
+            "/"
+        };
+        let regex = Regex::new(&format!("^/?{prefix}{body}$")).ok()?;
+
+        Some(Self { regex, ranges })
+    }
+
+    /// Whether `path`, relative to the `.editorconfig` file and written
+    /// with `/`, matches.
+    pub fn matches(&self, path: &str) -> bool {
+        let path = format!("/{}", path.trim_start_matches('/'));
+        let Some(captures) = self.regex.captures(&path) else {
+            return false;
+        };
+
+        // a range in an alternative left out matches nothing
+        self.ranges.iter().enumerate().all(|(i, range)| {
+            captures.get(i + 1).is_none_or(|number| {
+                number
+                    .as_str()
+                    .parse()
+                    .is_ok_and(|number| range.contains(&number))
+            })
+        })
+    }
+}
+
+/// Regular expression for the glob `chars`, `None` if it is malformed.
+fn translate(chars: &[char], ranges: &mut Vec<RangeInclusive<i64>>) -> Option<String> {
+    let mut regex = String::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if i + 1 < chars.len() => {
+                i += 1;
+                regex.push_str(&regex::escape(&chars[i].to_string()));
+            }
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                // `a/**/b` matches `a/b` too
+                if chars.get(i + 2) == Some(&'/') && (i == 0 || chars[i - 1] == '/') {
+                    regex.push_str("(?:.*/)?");
+                    i += 2;
+                } else {
+                    regex.push_str(".*");
+                    i += 1;
+                }
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            '[' => match chars[i..].iter().position(|c| *c == ']') {
+                Some(end) if end > 1 => {
+                    let class: String = chars[i + 1..i + end].iter().collect();
+                    let class = match class.strip_prefix('!') {
+                        Some(class) => format!("^{class}"),
+                        None => class,
+                    };
+                    regex.push('[');
+                    regex.push_str(&class.replace('\\', "\\\\").replace('[', "\\["));
+                    regex.push(']');
+                    i += end;
+                }
+                _ => regex.push_str("\\["),
+            },
+            '{' => {
+                let end = closing_brace(chars, i)?;
+                let inner: String = chars[i + 1..end].iter().collect();
+                regex.push_str(&braces(&inner, ranges)?);
+                i = end;
+            }
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+        i += 1;
+    }
+
+    Some(regex)
+}
+
+/// Index of the `}` closing the `{` at `start`.
+fn closing_brace(chars: &[char], start: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut i = start;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 1,
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    None
+}
+
+/// `{a,b}` alternatives and `{1..9}` number ranges. A brace without a comma
+/// or a range is taken as it is.
+fn braces(inner: &str, ranges: &mut Vec<RangeInclusive<i64>>) -> Option<String> {
+    if let Some((start, end)) = inner.split_once("..") {
+        if let (Ok(start), Ok(end)) = (start.parse::<i64>(), end.parse::<i64>()) {
+            ranges.push(start.min(end)..=start.max(end));
+            return Some(String::from("([+-]?[0-9]+)"));
+        }
+    }
+
+    let mut alternatives = Vec::new();
+    let mut depth = 0;
+    let mut current = String::new();
+    let mut escaped = false;
+    for c in inner.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            ',' if depth == 0 => {
+                alternatives.push(std::mem::take(&mut current));
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    alternatives.push(current);
+
+    if alternatives.len() == 1 {
+        let chars: Vec<char> = inner.chars().collect();
+        return Some(format!("\\{{{}\\}}", translate(&chars, ranges)?));
+    }
+
+    let translated: Option<Vec<String>> = alternatives
+        .iter()
+        .map(|alternative| {
+            let chars: Vec<char> = alternative.chars().collect();
+            translate(&chars, ranges)
+        })
+        .collect();
+    Some(format!("(?:{})", translated?.join("|")))
+}
+
+/// Sections of one `.editorconfig` file, and whether it is the root one.
+struct ConfigFile {
+    root: bool,
+    sections: Vec<(String, Vec<(String, String)>)>,
+}
+
+impl ConfigFile {
+    fn parse(text: &str) -> Self {
+        let mut root = false;
+        let mut sections: Vec<(String, Vec<(String, String)>)> = Vec::new();
+
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with(['#', ';']) {
+                continue;
+            }
+            if let Some(name) = line
+                .strip_prefix('[')
+                .and_then(|line| line.strip_suffix(']'))
+            {
+                sections.push((name.to_string(), Vec::new()));
+                continue;
+            }
+            let Some((key, value)) = line.split_once(['=', ':']) else {
+                continue;
+            };
+            let key = key.trim().to_lowercase();
+            let value = value.trim().to_string();
+
+            match sections.last_mut() {
+                Some((_, properties)) => properties.push((key, value)),
+                None if key == "root" => root = value.eq_ignore_ascii_case("true"),
+                None => {}
+            }
+        }
+
+        Self { root, sections }
+    }
+
+    /// Properties of the sections matching `path`, relative to the file.
+    fn properties<'a>(&'a self, path: &'a str) -> impl Iterator<Item = &'a (String, String)> {
+        self.sections
+            .iter()
+            .filter(move |(name, _)| Glob::new(name).is_some_and(|glob| glob.matches(path)))
+            .flat_map(|(_, properties)| properties)
+    }
+}
+
+/// Properties given to the file at `path` by the `.editorconfig` files of
+/// its directory and the ones above it, up to a root one. Closer files and
+/// later sections win.
+pub fn properties(path: &Path) -> Vec<(String, String)> {
+    let path = absolute(path);
+    let mut files = Vec::new();
+
+    for dir in path.ancestors().skip(1) {
+        let Ok(text) = fs::read_to_string(dir.join(FILE_NAME)) else {
+            continue;
+        };
+        let file = ConfigFile::parse(&text);
+        let root = file.root;
+        files.push((dir.to_path_buf(), file));
+        if root {
+            break;
+        }
+    }
+
+    let mut properties: Vec<(String, String)> = Vec::new();
+    for (dir, file) in files.iter().rev() {
+        let Ok(relative) = path.strip_prefix(dir) else {
+            continue;
+        };
+        let relative = relative.to_string_lossy().replace('\\', "/");
+
+        for (key, value) in file.properties(&relative) {
+            properties.retain(|(known, _)| known != key);
+            properties.push((key.clone(), value.clone()));
+        }
+    }
+
+    properties
+}
+
+fn absolute(path: &Path) -> PathBuf {
+    if path.is_absolute() {
+        return path.to_path_buf();
+    }
+    env::current_dir().map_or_else(|_| path.to_path_buf(), |dir| dir.join(path))
+}
+
+/// Sets the buffer options told by editorconfig `properties`, values it
+/// does not know are left out. Returns whether the indentation was set.
+pub fn apply(properties: &[(String, String)], options: &mut BufferOptions) -> bool {
+    let get = |key: &str| {
+        properties
+            .iter()
+            .find(|(known, _)| known == key)
+            .map(|(_, value)| value.to_lowercase())
+            .filter(|value| value != "unset")
+    };
+    let number = |key: &str| get(key).and_then(|value| value.parse::<usize>().ok());
+    // like `:set`, widths of 0 are refused
+    let width = |key: &str| number(key).filter(|width| *width > 0);
+    let flag = |key: &str| get(key).and_then(|value| value.parse::<bool>().ok());
+
+    let style = get("indent_style");
+    match style.as_deref() {
+        Some("tab") => options.expandtab = false,
+        Some("space") => options.expandtab = true,
+        _ => {}
+    }
+
+    let tab_width = width("tab_width");
+    let indent_size = match get("indent_size").as_deref() {
+        Some("tab") => tab_width.or(Some(options.tabstop)),
+        Some(_) => width("indent_size"),
+        None => None,
+    };
+    if let Some(width) = tab_width.or(indent_size.filter(|_| style.as_deref() == Some("tab"))) {
+        options.tabstop = width;
+    }
+    if let Some(width) = indent_size.or(tab_width.filter(|_| style.is_some())) {
+        options.shiftwidth = width;
+    }
+
+    if let Some(format) = get("end_of_line") {
+        match format.as_str() {
+            "lf" => options.fileformat = String::from("unix"),
+            "crlf" => options.fileformat = String::from("dos"),
+            "cr" => options.fileformat = String::from("mac"),
+            _ => {}
+        }
+    }
+    if let Some(charset) = get("charset") {
+        options.fileencoding = charset;
+    }
+    if let Some(trim) = flag("trim_trailing_whitespace") {
+        options.trimwhitespace = trim;
+    }
+    if let Some(newline) = flag("insert_final_newline") {
+        options.fixendofline = newline;
+    }
+    match get("max_line_length").as_deref() {
+        Some("off") => options.textwidth = 0,
+        Some(_) => options.textwidth = number("max_line_length").unwrap_or(options.textwidth),
+        None => {}
+    }
+
+    style.is_some() || indent_size.is_some()
+}
+
+mod test {
+    #[test]
+    fn should_match_globs_as_the_spec_says() {
+        use super::Glob;
+
+        let matches = |pattern: &str, path: &str| Glob::new(pattern).unwrap().matches(path);
+
+        // without a slash, files of any directory
+        assert!(matches("*.rs", "main.rs"));
+        assert!(matches("*.rs", "src/editor/main.rs"));
+        assert!(!matches("*.rs", "main.rsx"));
+        assert!(matches("Makefile", "sub/Makefile"));
+
+        // with one, relative to the file
+        assert!(matches("src/*.rs", "src/main.rs"));
+        assert!(!matches("src/*.rs", "src/editor/main.rs"));
+        assert!(!matches("src/*.rs", "other/src/main.rs"));
+        assert!(matches("/src/*.rs", "src/main.rs"));
+        assert!(matches("src/**/*.rs", "src/main.rs"));
+        assert!(matches("src/**/*.rs", "src/editor/control/main.rs"));
+        assert!(matches("**.md", "docs/a/b.md"));
+
+        assert!(matches("?.c", "a.c"));
+        assert!(!matches("?.c", "ab.c"));
+        assert!(matches("[abc].c", "b.c"));
+        assert!(!matches("[!abc].c", "b.c"));
+        assert!(matches("[!abc].c", "d.c"));
+
+        assert!(matches("*.{js,py}", "app.py"));
+        assert!(!matches("*.{js,py}", "app.rb"));
+        assert!(matches("{lib/**,test}/*.rs", "lib/a/b.rs"));
+        assert!(matches("{single}.c", "{single}.c"));
+
+        assert!(matches("file{1..3}.txt", "file2.txt"));
+        assert!(!matches("file{1..3}.txt", "file4.txt"));
+        assert!(matches("file{-3..3}.txt", "file-1.txt"));
+
+        assert!(matches("a\\*.c", "a*.c"));
+        assert!(!matches("a\\*.c", "ab.c"));
+    }
+
+    #[test]
+    fn should_apply_the_closest_editorconfig_files() {
+        use super::{apply, properties};
+        use crate::document::BufferOptions;
+        use std::{env, fs, process};
+
+        let dir = env::temp_dir().join(format!("text-editor-editorconfig-{}", process::id()));
+        let sub = dir.join("project").join("src");
+        fs::create_dir_all(&sub).unwrap();
+        fs::write(dir.join(".editorconfig"), "[*]\nindent_size = 8\n").unwrap();
+        fs::write(
+            dir.join("project").join(".editorconfig"),
+            "root = true\n\n[*]\nindent_style = space\nindent_size = 2\nend_of_line = crlf\n\n[*.go]\nindent_style = tab\nindent_size = tab\ntab_width = 4\n\n[src/**.go]\ncharset = latin1\nmax_line_length = 100\n",
+        )
+        .unwrap();
+        fs::write(
+            sub.join(".editorconfig"),
+            "# closer files win\n[*.go]\ntrim_trailing_whitespace = true\ninsert_final_newline = false\n",
+        )
+        .unwrap();
+
+        let mut rust = BufferOptions::default();
+        assert!(apply(&properties(&sub.join("main.rs")), &mut rust));
+        let mut go = BufferOptions::default();
+        assert!(apply(&properties(&sub.join("main.go")), &mut go));
+        fs::remove_dir_all(&dir).unwrap();
+
+        // the root file stops the search, its parent's size is left out
+        assert_eq!((rust.shiftwidth, rust.expandtab), (2, true));
+        assert_eq!(rust.fileformat, "dos");
+        assert_eq!((go.shiftwidth, go.tabstop, go.expandtab), (4, 4, false));
+        assert_eq!((go.fileencoding.as_str(), go.textwidth), ("latin1", 100));
+        assert!(go.trimwhitespace && !go.fixendofline);
+        assert!(!rust.trimwhitespace && rust.fileencoding == "utf-8");
+
+        // widths of 0 are left out
+        let zero = |key: &str| (String::from(key), String::from("0"));
+        let mut options = BufferOptions::default();
+        let defaults = BufferOptions::default();
+        apply(
+            &[
+                (String::from("indent_style"), String::from("tab")),
+                zero("tab_width"),
+                zero("indent_size"),
+            ],
+            &mut options,
+        );
+        assert_eq!(
+            (options.tabstop, options.shiftwidth),
+            (defaults.tabstop, defaults.shiftwidth)
+        );
+    }
+}
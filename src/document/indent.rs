@@ -0,0 +1,406 @@
+use std::collections::HashMap;
+
+use crate::{
+    document::Document,
+    syntax::{IndentRules, Scope, Syntax},
+    utils::Position,
+};
+
+/// Rows read at most to guess the indentation of a file.
+const SAMPLE_ROWS: usize = 1000;
+
+/// Indentation a file is written with, guessed from its rows.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DetectedIndent {
+    Tabs,
+    /// spaces, this many for one level
+    Spaces(usize),
+}
+
+/// Guesses the indentation of `lines` from their leading whitespace: tabs or
+/// spaces as most indented rows use, and for spaces the step indentation
+/// most often grows by. `None` without indented rows.
+pub fn detect_indent<'a>(lines: impl Iterator<Item = &'a str>) -> Option<DetectedIndent> {
+    let mut tabs = 0;
+    let mut spaces = 0;
+    let mut steps: HashMap<usize, usize> = HashMap::new();
+    let mut previous = 0;
+
+    for line in lines.take(SAMPLE_ROWS) {
+        let text = line.trim_start_matches([' ', '\t']);
+        // blank rows and the ` * ` of block comments tell nothing
+        if text.is_empty() || text.starts_with('*') {
+            continue;
+        }
+
+        let leading = &line[..line.len() - text.len()];
+        if leading.starts_with('\t') {
+            tabs += 1;
+            continue;
+        }
+
+        let width = leading.len();
+        if width > 0 {
+            spaces += 1;
+        }
+        if width > previous && width - previous > 1 && width - previous <= 8 {
+            *steps.entry(width - previous).or_default() += 1;
+        }
+        previous = width;
+    }
+
+    if tabs == 0 && spaces == 0 {
+        return None;
+    }
+    if tabs > spaces {
+        return Some(DetectedIndent::Tabs);
+    }
+
+    // the smaller step wins a tie, as `4` is also two steps of `2`
+    steps
+        .into_iter()
+        .max_by_key(|(step, count)| (*count, std::cmp::Reverse(*step)))
+        .map(|(step, _)| DetectedIndent::Spaces(step))
+}
+
+impl Document {
+    /// Leading whitespace of row `line`.
+    pub fn row_indent(&self, line: usize) -> &str {
+        self.row(line)
+            .map_or("", |row| leading_whitespace(&row.content))
+    }
+
+    /// Splits the row at `pos` as Enter does. The new row keeps the
+    /// indentation, one level more after a block opener, and a bracket
+    /// closed right after the cursor goes on a row of its own. Returns where
+    /// the cursor goes.
+    pub fn split_row(&mut self, pos: &Position) -> Position {
+        let Some(content) = self.row(pos.y).map(|row| row.content.clone()) else {
+            return *pos;
+        };
+        let x = pos.x.min(content.len());
+        let (left, right) = content.split_at(x);
+        let base = leading_whitespace(left).to_string();
+        let mut at = Position { x, y: pos.y };
+        // read before the row is changed
+        let opens = self.opens_block(pos.y, x);
+
+        // indentation is not left on a row nothing was typed on
+        let blank = left.trim().is_empty() && right.trim().is_empty();
+        let skipped = if blank {
+            at.x = 0;
+            content.len()
+        } else {
+            right.len() - right.trim_start().len()
+        };
+        self.delete_range(
+            &at,
+            &Position {
+                x: at.x + skipped,
+                y: pos.y,
+            },
+        );
+
+        let mut lines = vec![String::new()];
+        if !blank && opens {
+            lines.push(self.indent_more(&base));
+            if closes_bracket(left, right) {
+                lines.push(base);
+            }
+        } else {
+            lines.push(base);
+        }
+        let indent = lines[1].len();
+        self.insert_text(&at, &lines);
+
+        Position {
+            x: indent,
+            y: pos.y + 1,
+        }
+    }
+
+    /// Adds an indented row below or above row `line` (`o`, `O`) and
+    /// returns where the cursor goes on it.
+    pub fn open_row(&mut self, line: usize, above: bool) -> Position {
+        let content = self.row(line).map_or("", |row| row.content.as_str());
+        let base = leading_whitespace(content).to_string();
+        let text = content.trim();
+
+        let (y, indent) = if above {
+            let closing = text.starts_with(['}', ')', ']']) || self.rules().closers.contains(&text);
+            (
+                line,
+                if closing {
+                    self.indent_more(&base)
+                } else {
+                    base
+                },
+            )
+        } else if self.opens_block(line, content.len()) {
+            (line + 1, self.indent_more(&base))
+        } else {
+            (line + 1, base)
+        };
+
+        let x = indent.len();
+        self.insert_rows(y, &[indent]);
+        Position { x, y }
+    }
+
+    /// Fixes the indentation of the row a char was just typed on before
+    /// `pos`, once it starts with a closing bracket or is a block closer.
+    /// Returns where the cursor goes.
+    pub fn reindent_typed(&mut self, pos: &Position) -> Position {
+        let Some(content) = self.row(pos.y).map(|row| row.content.clone()) else {
+            return *pos;
+        };
+        let indent = leading_whitespace(&content);
+        let Some(typed) = content.get(indent.len()..pos.x) else {
+            return *pos;
+        };
+
+        let new_indent = if matches!(typed, "}" | ")" | "]") {
+            let bracket = Position {
+                x: indent.len(),
+                y: pos.y,
+            };
+            match self.matching_bracket(&bracket) {
+                Some(open) => self.row_indent(open.y).to_string(),
+                None => return *pos,
+            }
+        } else if indent.is_empty()
+            || typed != content.trim()
+            || !self.rules().closers.contains(&typed)
+        {
+            return *pos;
+        } else {
+            // only when still as indented as the block it closes
+            let previous = (0..pos.y)
+                .rev()
+                .find(|y| {
+                    self.row(*y)
+                        .is_some_and(|row| !row.content.trim().is_empty())
+                })
+                .map_or(0, |y| self.row_indent(y).len());
+            if indent.len() < previous {
+                return *pos;
+            }
+            self.indent_less(indent)
+        };
+
+        let old_len = indent.len();
+        if let Some(row) = self.row_mut(pos.y) {
+            row.remove_range(0..old_len);
+            row.insert_str(0, &new_indent);
+        }
+
+        Position {
+            x: pos.x - old_len + new_indent.len(),
+            y: pos.y,
+        }
+    }
+
+    fn rules(&self) -> &IndentRules {
+        self.syntax()
+            .map_or(&IndentRules::BRACKETS, Syntax::indent_rules)
+    }
+
+    /// Whether the row after the text of row `line` up to `end` is indented
+    /// one level more. Brackets and openers in strings and comments are
+    /// only text, as far as the row is highlighted.
+    fn opens_block(&self, line: usize, end: usize) -> bool {
+        let Some(row) = self.row(line) else {
+            return false;
+        };
+        let tokens = self.row_tokens(line);
+
+        let comment = tokens
+            .iter()
+            .filter(|token| token.scope == Scope::Comment)
+            .map(|token| token.range.start)
+            .min()
+            .unwrap_or(end);
+        let text = row.content[..end.min(comment)].trim_end();
+        let in_string = tokens.iter().any(|token| {
+            token.scope == Scope::String
+                && token.range.start < text.len()
+                && text.len() <= token.range.end
+        });
+
+        !in_string
+            && (text.ends_with(['{', '(', '['])
+                || self
+                    .rules()
+                    .openers
+                    .iter()
+                    .any(|opener| ends_with_word(text, opener)))
+    }
+
+    fn indent_more(&self, indent: &str) -> String {
+        format!("{indent}{}", self.options().indent())
+    }
+
+    fn indent_less(&self, indent: &str) -> String {
+        if let Some(rest) = indent.strip_suffix('\t') {
+            return rest.to_string();
+        }
+        let spaces = indent.len() - indent.trim_end_matches(' ').len();
+        indent[..indent.len() - spaces.min(self.options().shiftwidth)].to_string()
+    }
+}
+
+fn leading_whitespace(text: &str) -> &str {
+    &text[..text.len() - text.trim_start_matches([' ', '\t']).len()]
+}
+
+/// Whether `text` ends with `word`, a whole word when it is made of
+/// letters.
+fn ends_with_word(text: &str, word: &str) -> bool {
+    text.strip_suffix(word).is_some_and(|before| {
+        !word.starts_with(char::is_alphanumeric)
+            || !before.ends_with(|c: char| c.is_alphanumeric() || c == '_')
+    })
+}
+
+/// Whether `right` starts with the bracket closing the one `left` ends with.
+fn closes_bracket(left: &str, right: &str) -> bool {
+    let close = match left.trim_end().chars().next_back() {
+        Some('{') => '}',
+        Some('(') => ')',
+        Some('[') => ']',
+        _ => return false,
+    };
+    right.trim_start().starts_with(close)
+}
+
+mod test {
+    #[test]
+    fn should_detect_the_indentation_of_rows() {
+        use super::{detect_indent, DetectedIndent};
+
+        let detect = |text: &str| detect_indent(text.lines());
+
+        assert_eq!(
+            detect("fn a() {\n  b();\n  if c {\n    d();\n  }\n}"),
+            Some(DetectedIndent::Spaces(2))
+        );
+        assert_eq!(
+            detect("fn a() {\n    b();\n    if c {\n        d();\n    }\n}"),
+            Some(DetectedIndent::Spaces(4))
+        );
+        assert_eq!(
+            detect("fn a() {\n\tb();\n\tc(\n\t    d);\n}"),
+            Some(DetectedIndent::Tabs)
+        );
+        // block comments and blank rows are left out
+        assert_eq!(
+            detect("/**\n * doc\n */\nfn a() {\n\n    b();\n}"),
+            Some(DetectedIndent::Spaces(4))
+        );
+        assert_eq!(detect("a\nb\n\nc"), None);
+    }
+
+    #[test]
+    fn should_indent_rows_split_after_brackets() {
+        use crate::{document::Document, utils::Position};
+
+        let rows = |doc: &Document| {
+            (0..doc.lines_len())
+                .map(|y| doc.row(y).unwrap().content.clone())
+                .collect::<Vec<String>>()
+        };
+
+        let mut doc = Document::from(vec!["fn a() {}", "    let b = [1,"]);
+        assert_eq!(
+            doc.split_row(&Position { x: 8, y: 0 }),
+            Position { x: 4, y: 1 }
+        );
+        assert_eq!(rows(&doc), vec!["fn a() {", "    ", "}", "    let b = [1,"]);
+        assert_eq!(
+            doc.split_row(&Position { x: 15, y: 3 }),
+            Position { x: 4, y: 4 }
+        );
+        assert_eq!(doc.row(4).unwrap().content, "    ");
+
+        // a row left blank loses its indentation
+        assert_eq!(
+            doc.split_row(&Position { x: 4, y: 4 }),
+            Position { x: 4, y: 5 }
+        );
+        assert_eq!(doc.row(4).unwrap().content, "");
+
+        // a closing bracket goes back to the indentation of its opening one
+        let mut doc = Document::from(vec!["fn a() {", "    if b {", "        c();", "        }"]);
+        assert_eq!(
+            doc.reindent_typed(&Position { x: 9, y: 3 }),
+            Position { x: 5, y: 3 }
+        );
+        assert_eq!(doc.row(3).unwrap().content, "    }");
+
+        // `o` and `O`
+        assert_eq!(doc.open_row(1, false), Position { x: 8, y: 2 });
+        assert_eq!(doc.open_row(4, true), Position { x: 8, y: 4 });
+        doc.options_mut().expandtab = false;
+        assert_eq!(doc.open_row(0, false), Position { x: 1, y: 1 });
+        assert_eq!(doc.row(1).unwrap().content, "\t");
+    }
+
+    #[test]
+    fn should_indent_rows_as_their_language_does() {
+        use crate::{
+            document::Document,
+            syntax::languages::{PYTHON, RUST, SHELL},
+            utils::Position,
+        };
+        use std::rc::Rc;
+
+        let mut doc = Document::from(vec![
+            "def a(b):",
+            "    if b:",
+            "        c()",
+            "        else:",
+        ]);
+        doc.set_syntax(Some(Rc::new(PYTHON)));
+        assert_eq!(
+            doc.split_row(&Position { x: 9, y: 0 }),
+            Position { x: 4, y: 1 }
+        );
+        assert_eq!(
+            doc.reindent_typed(&Position { x: 13, y: 4 }),
+            Position { x: 9, y: 4 }
+        );
+        assert_eq!(doc.row(4).unwrap().content, "    else:");
+        // `else` is not a whole closer yet
+        doc.insert_rows(
+            5,
+            &[String::from("        c()"), String::from("        else")],
+        );
+        assert_eq!(
+            doc.reindent_typed(&Position { x: 12, y: 6 }),
+            Position { x: 12, y: 6 }
+        );
+
+        let mut doc = Document::from(vec!["for a in b; do", "  c", "  done", "echo undo"]);
+        doc.set_syntax(Some(Rc::new(SHELL)));
+        doc.options_mut().shiftwidth = 2;
+        assert_eq!(doc.open_row(0, false), Position { x: 2, y: 1 });
+        assert_eq!(
+            doc.reindent_typed(&Position { x: 6, y: 3 }),
+            Position { x: 4, y: 3 }
+        );
+        assert_eq!(doc.row(3).unwrap().content, "done");
+        // openers are whole words
+        assert_eq!(doc.open_row(4, false), Position { x: 0, y: 5 });
+
+        // and only code, not strings and comments
+        let mut doc = Document::from(vec!["    let s = \"(x\"; // (", "# Note:"]);
+        doc.set_syntax(Some(Rc::new(RUST)));
+        assert_eq!(doc.open_row(0, false), Position { x: 4, y: 1 });
+        assert_eq!(
+            doc.split_row(&Position { x: 14, y: 0 }),
+            Position { x: 4, y: 1 }
+        );
+        doc.set_syntax(Some(Rc::new(PYTHON)));
+        assert_eq!(doc.open_row(3, false), Position { x: 0, y: 4 });
+    }
+}
@@ -0,0 +1,125 @@
+use std::{collections::BTreeMap, ops::Range};
+
+use crate::utils::Position;
+
+/// Lines inserted or deleted in a document, for what follows its lines
+/// outside of it like the folds of a window.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum LineEdit {
+    /// `count` lines inserted before `line`
+    Inserted {
+        line: usize,
+        count: usize,
+    },
+    Deleted(Range<usize>),
+}
+
+/// Named positions in a document. They follow the text they are on when
+/// lines are inserted or deleted above them, and are dropped with their
+/// line.
+#[derive(Default, Clone, Debug)]
+pub struct Marks {
+    positions: BTreeMap<char, Position>,
+}
+
+impl Marks {
+    pub fn get(&self, name: char) -> Option<Position> {
+        self.positions.get(&name).copied()
+    }
+
+    pub fn set(&mut self, name: char, pos: Position) {
+        self.positions.insert(name, pos);
+    }
+
+    /// Every mark set, ordered by name.
+    pub fn iter(&self) -> impl Iterator<Item = (char, Position)> + '_ {
+        self.positions.iter().map(|(name, pos)| (*name, *pos))
+    }
+
+    /// `count` lines were inserted before `line`.
+    pub fn lines_inserted(&mut self, line: usize, count: usize) {
+        for pos in self.positions.values_mut() {
+            if pos.y >= line {
+                pos.y += count;
+            }
+        }
+    }
+
+    /// The lines in `lines` were deleted.
+    pub fn lines_deleted(&mut self, lines: Range<usize>) {
+        self.positions.retain(|_, pos| !lines.contains(&pos.y));
+        for pos in self.positions.values_mut() {
+            if pos.y >= lines.end {
+                pos.y -= lines.len();
+            }
+        }
+    }
+
+    /// The text from `start` up to `end` was deleted, joining their lines.
+    /// The marks after `end` on its line go where the text after it goes.
+    pub fn text_deleted(&mut self, start: &Position, end: &Position) {
+        if start.y == end.y {
+            for pos in self.positions.values_mut() {
+                if pos.y == start.y && pos.x >= end.x {
+                    pos.x -= end.x - start.x;
+                } else if pos.y == start.y && pos.x > start.x {
+                    pos.x = start.x;
+                }
+            }
+            return;
+        }
+
+        for pos in self.positions.values_mut() {
+            if pos.y == end.y && pos.x >= end.x {
+                *pos = Position {
+                    x: start.x + pos.x - end.x,
+                    y: start.y,
+                };
+            }
+        }
+        self.lines_deleted(start.y + 1..end.y + 1);
+    }
+}
+
+mod test {
+    #[cfg(test)]
+    use super::Marks;
+    #[cfg(test)]
+    use crate::utils::Position;
+
+    #[cfg(test)]
+    fn marks() -> Marks {
+        let mut marks = Marks::default();
+        marks.set('a', Position { x: 2, y: 1 });
+        marks.set('b', Position { x: 4, y: 5 });
+        marks.set('c', Position { x: 6, y: 3 });
+        marks
+    }
+
+    #[test]
+    fn should_shift_marks_below_inserted_lines() {
+        let mut marks = marks();
+        marks.lines_inserted(2, 3);
+
+        assert_eq!(marks.get('a'), Some(Position { x: 2, y: 1 }));
+        assert_eq!(marks.get('b'), Some(Position { x: 4, y: 8 }));
+    }
+
+    #[test]
+    fn should_drop_marks_of_deleted_lines() {
+        let mut marks = marks();
+        marks.lines_deleted(2..4);
+
+        assert_eq!(marks.get('c'), None);
+        assert_eq!(marks.get('b'), Some(Position { x: 4, y: 3 }));
+    }
+
+    #[test]
+    fn should_join_marks_to_the_start_of_deleted_text() {
+        let mut marks = marks();
+        marks.text_deleted(&Position { x: 1, y: 0 }, &Position { x: 1, y: 1 });
+
+        assert_eq!(marks.get('a'), Some(Position { x: 2, y: 0 }));
+        assert_eq!(marks.get('b'), Some(Position { x: 4, y: 4 }));
+    }
+}
@@ -1,5 +1,25 @@
+pub mod change;
+pub mod comment;
+#[allow(clippy::module_inception)]
 pub mod document;
+pub mod editorconfig;
+pub mod indent;
+pub mod marks;
+pub mod motion;
+pub mod options;
+pub mod pairs;
+pub mod replacement;
 pub mod row;
+pub mod search;
+pub mod text_object;
 
+pub use change::RowChange;
 pub use document::Document;
+pub use indent::DetectedIndent;
+pub use marks::LineEdit;
+pub use options::BufferOptions;
+pub use pairs::PairEdit;
+pub use replacement::Replacement;
 pub use row::Row;
+pub use search::{Pattern, SearchDirection};
+pub use text_object::{ObjectRange, TextObject};
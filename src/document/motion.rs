@@ -0,0 +1,209 @@
+use std::ops::Range;
+
+use crate::{document::Document, utils::Position};
+
+/// Words are runs of letters, digits and underscores or runs of other
+/// non blank chars, like in vim.
+#[derive(PartialEq, Eq)]
+pub enum CharClass {
+    Blank,
+    Punctuation,
+    Word,
+}
+
+impl From<char> for CharClass {
+    fn from(c: char) -> Self {
+        if c.is_whitespace() {
+            Self::Blank
+        } else if c.is_alphanumeric() || c == '_' {
+            Self::Word
+        } else {
+            Self::Punctuation
+        }
+    }
+}
+
+impl Document {
+    /// Char at `pos`, the end of a row reads as a line break.
+    pub fn char_at(&self, pos: &Position) -> Option<char> {
+        let row = self.row(pos.y)?;
+        Some(
+            row.content
+                .get(pos.x..)
+                .and_then(|rest| rest.chars().next())
+                .unwrap_or('\n'),
+        )
+    }
+
+    /// Position of the char after `pos`, going through line breaks.
+    pub fn next_position(&self, pos: &Position) -> Option<Position> {
+        let row = self.row(pos.y)?;
+        match row
+            .content
+            .get(pos.x..)
+            .and_then(|rest| rest.chars().next())
+        {
+            Some(c) => Some(Position {
+                x: pos.x + c.len_utf8(),
+                y: pos.y,
+            }),
+            None if pos.y + 1 < self.lines_len() => Some(Position { x: 0, y: pos.y + 1 }),
+            None => None,
+        }
+    }
+
+    /// Position of the char before `pos`, going through line breaks.
+    pub fn previous_position(&self, pos: &Position) -> Option<Position> {
+        let row = self.row(pos.y)?;
+        match row
+            .content
+            .get(..pos.x)
+            .and_then(|before| before.chars().last())
+        {
+            Some(c) => Some(Position {
+                x: pos.x - c.len_utf8(),
+                y: pos.y,
+            }),
+            None if pos.y > 0 => Some(Position {
+                x: self.row(pos.y - 1)?.content.len(),
+                y: pos.y - 1,
+            }),
+            None => None,
+        }
+    }
+
+    fn class_at(&self, pos: &Position) -> CharClass {
+        self.char_at(pos).map_or(CharClass::Blank, CharClass::from)
+    }
+
+    fn is_empty_row(&self, line: usize) -> bool {
+        self.row(line).is_some_and(|row| row.content.is_empty())
+    }
+
+    /// Start of the next word (`w`), empty rows count as words.
+    pub fn next_word_start(&self, pos: &Position) -> Position {
+        let class = self.class_at(pos);
+        let mut current = *pos;
+
+        // leave the current word
+        while class != CharClass::Blank && self.class_at(&current) == class {
+            match self.next_position(&current) {
+                Some(next) => current = next,
+                None => return current,
+            }
+        }
+
+        while self.class_at(&current) == CharClass::Blank {
+            if current.y != pos.y && self.is_empty_row(current.y) {
+                break;
+            }
+
+            match self.next_position(&current) {
+                Some(next) => current = next,
+                None => return current,
+            }
+        }
+
+        current
+    }
+
+    /// Start of the current or previous word (`b`).
+    pub fn previous_word_start(&self, pos: &Position) -> Position {
+        let Some(mut current) = self.previous_position(pos) else {
+            return *pos;
+        };
+
+        while self.class_at(&current) == CharClass::Blank {
+            if current.y != pos.y && self.is_empty_row(current.y) {
+                return current;
+            }
+
+            match self.previous_position(&current) {
+                Some(previous) => current = previous,
+                None => return current,
+            }
+        }
+
+        let class = self.class_at(&current);
+        while let Some(previous) = self.previous_position(&current) {
+            if previous.y != current.y || self.class_at(&previous) != class {
+                break;
+            }
+            current = previous;
+        }
+
+        current
+    }
+
+    /// End of the current or next word (`e`).
+    pub fn word_end(&self, pos: &Position) -> Position {
+        let Some(mut current) = self.next_position(pos) else {
+            return *pos;
+        };
+
+        while self.class_at(&current) == CharClass::Blank {
+            match self.next_position(&current) {
+                Some(next) => current = next,
+                None => return current,
+            }
+        }
+
+        let class = self.class_at(&current);
+        while let Some(next) = self.next_position(&current) {
+            if next.y != current.y || self.class_at(&next) != class {
+                break;
+            }
+            current = next;
+        }
+
+        current
+    }
+
+    /// Columns of the word under the cursor, or of the next one on the row
+    /// (`*`, `#`).
+    pub fn word_at(&self, pos: &Position) -> Option<Range<usize>> {
+        let content = &self.row(pos.y)?.content;
+        let is_word = |c: char| CharClass::from(c) == CharClass::Word;
+
+        let start = content
+            .char_indices()
+            .find(|(x, c)| *x >= pos.x && is_word(*c))?
+            .0;
+        // the word can start before the cursor
+        let start = content[..start]
+            .char_indices()
+            .rev()
+            .take_while(|(_, c)| is_word(*c))
+            .last()
+            .map_or(start, |(x, _)| x);
+        let end = content[start..]
+            .char_indices()
+            .find(|(_, c)| !is_word(*c))
+            .map_or(content.len(), |(x, _)| start + x);
+
+        Some(start..end)
+    }
+
+    /// Bracket matching the one under `pos`, or the first one after it on
+    /// its row (`%`).
+    pub fn matching_bracket(&self, pos: &Position) -> Option<Position> {
+        let content = &self.row(pos.y)?.content;
+        let (x, c) = content
+            .get(pos.x..)?
+            .char_indices()
+            .find(|(_, c)| matches!(c, '(' | ')' | '[' | ']' | '{' | '}'))?;
+        let at = Position {
+            x: pos.x + x,
+            y: pos.y,
+        };
+
+        match c {
+            '(' => self.find_unmatched(&self.next_position(&at)?, ')', '(', true),
+            '[' => self.find_unmatched(&self.next_position(&at)?, ']', '[', true),
+            '{' => self.find_unmatched(&self.next_position(&at)?, '}', '{', true),
+            ')' => self.find_unmatched(&self.previous_position(&at)?, '(', ')', false),
+            ']' => self.find_unmatched(&self.previous_position(&at)?, '[', ']', false),
+            _ => self.find_unmatched(&self.previous_position(&at)?, '{', '}', false),
+        }
+    }
+}
@@ -0,0 +1,51 @@
+/// Options that can differ from one document to another.
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct BufferOptions {
+    /// Columns of one level of indentation.
+    pub shiftwidth: usize,
+    /// Indent with spaces instead of tabs.
+    pub expandtab: bool,
+    /// Columns a tab takes.
+    pub tabstop: usize,
+    /// Line endings of the file: `unix`, `dos` or `mac`.
+    pub fileformat: String,
+    /// Encoding of the file, like `utf-8` or `latin1`.
+    pub fileencoding: String,
+    /// Remove the whitespace ending lines when the file is written.
+    pub trimwhitespace: bool,
+    /// End the file with a newline when it is written.
+    pub fixendofline: bool,
+    /// Columns lines are kept within, no limit when 0.
+    pub textwidth: usize,
+    /// Type the closing bracket or quote of the language with the opening
+    /// one.
+    pub autopairs: bool,
+}
+
+impl BufferOptions {
+    /// Text of one level of indentation.
+    pub fn indent(&self) -> String {
+        if self.expandtab {
+            " ".repeat(self.shiftwidth)
+        } else {
+            String::from("\t")
+        }
+    }
+}
+
+impl Default for BufferOptions {
+    fn default() -> Self {
+        Self {
+            shiftwidth: 4,
+            expandtab: true,
+            tabstop: 8,
+            fileformat: String::from("unix"),
+            fileencoding: String::from("utf-8"),
+            trimwhitespace: false,
+            fixendofline: true,
+            textwidth: 0,
+            autopairs: true,
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use crate::{
+    document::Document,
+    syntax::{Scope, PAIRS},
+    utils::Position,
+};
+
+/// What typing a char does with the pairs of the document.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PairEdit {
+    /// the char is inserted alone
+    Insert,
+    /// the char is inserted with this closing one after it
+    Close(char),
+    /// the cursor steps over the closing char already there
+    StepOver,
+}
+
+impl Document {
+    /// Pairs typed together in the document, none without `autopairs`.
+    pub fn pairs(&self) -> &[(char, char)] {
+        if !self.options().autopairs {
+            return &[];
+        }
+        self.syntax().map_or(PAIRS, |syntax| syntax.pairs())
+    }
+
+    /// What typing `c` at `pos` does. Pairs are not opened inside strings
+    /// and comments, nor quotes next to a word like in `don't`.
+    pub fn typed_pair(&self, pos: &Position, c: char) -> PairEdit {
+        let pairs = self.pairs();
+        let next = self.char_at(pos).unwrap_or('\n');
+
+        if next == c && pairs.iter().any(|(_, close)| *close == c) {
+            return PairEdit::StepOver;
+        }
+        let Some((open, close)) = pairs.iter().find(|(open, _)| *open == c) else {
+            return PairEdit::Insert;
+        };
+        if self.in_string_or_comment(pos) {
+            return PairEdit::Insert;
+        }
+
+        let previous = self
+            .row(pos.y)
+            .and_then(|row| row.content.get(..pos.x))
+            .and_then(|before| before.chars().next_back());
+        let is_word = |c: char| c.is_alphanumeric() || c == '_';
+        let closes_before = next.is_whitespace() || pairs.iter().any(|(_, close)| *close == next);
+        let quote = open == close;
+
+        if !closes_before || (quote && previous.is_some_and(is_word)) {
+            return PairEdit::Insert;
+        }
+        PairEdit::Close(*close)
+    }
+
+    /// Whether `pos` is between the two chars of an empty pair, Backspace
+    /// deleting both.
+    pub fn is_in_empty_pair(&self, pos: &Position) -> bool {
+        let Some(before) = pos
+            .x
+            .checked_sub(1)
+            .and_then(|x| self.row(pos.y)?.content.get(x..pos.x))
+            .and_then(|before| before.chars().next())
+        else {
+            return false;
+        };
+        let after = self.char_at(pos);
+
+        self.pairs()
+            .iter()
+            .any(|(open, close)| *open == before && Some(*close) == after)
+    }
+
+    /// Whether text typed at `pos` goes in a string or a comment, as far as
+    /// the row is highlighted.
+    fn in_string_or_comment(&self, pos: &Position) -> bool {
+        let Some(row) = self.row(pos.y) else {
+            return false;
+        };
+
+        self.row_tokens(pos.y).iter().any(|token| {
+            let inside = token.range.start < pos.x && pos.x < token.range.end;
+            match token.scope {
+                Scope::Comment => inside || pos.x == token.range.end,
+                // a string not closed yet goes on at its end
+                Scope::String => {
+                    let text = &row.content[token.range.clone()];
+                    let closed = text.len() > 1 && text.chars().next() == text.chars().next_back();
+                    inside || (pos.x == token.range.end && !closed)
+                }
+                _ => false,
+            }
+        })
+    }
+}
+
+mod test {
+    #[cfg(test)]
+    use crate::{document::Document, utils::Position};
+
+    #[cfg(test)]
+    fn at(x: usize) -> Position {
+        Position { x, y: 0 }
+    }
+
+    #[cfg(test)]
+    fn plain_doc() -> Document {
+        Document::from(vec!["let a = (b) + \"c\"; // d", "it x"])
+    }
+
+    /// The document highlighted as Rust, with an empty pair in a string.
+    #[cfg(test)]
+    fn rust_doc() -> Document {
+        use crate::syntax::languages::RUST;
+        use std::rc::Rc;
+
+        let mut doc = Document::from(vec!["let a = (b) + \"c\"; \"()\" // d"]);
+        doc.set_syntax(Some(Rc::new(RUST)));
+        doc
+    }
+
+    #[test]
+    fn should_close_typed_pairs() {
+        use super::PairEdit;
+
+        let doc = plain_doc();
+        assert_eq!(doc.typed_pair(&at(7), '['), PairEdit::Close(']'));
+        assert_eq!(doc.typed_pair(&at(7), '\''), PairEdit::Close('\''));
+    }
+
+    #[test]
+    fn should_step_over_closing_chars() {
+        use super::PairEdit;
+
+        assert_eq!(plain_doc().typed_pair(&at(10), ')'), PairEdit::StepOver);
+    }
+
+    #[test]
+    fn should_not_pair_next_to_words() {
+        use super::PairEdit;
+
+        let doc = plain_doc();
+        assert_eq!(doc.typed_pair(&at(8), '('), PairEdit::Insert);
+        let after_word = Position { x: 2, y: 1 };
+        assert_eq!(doc.typed_pair(&after_word, '\''), PairEdit::Insert);
+    }
+
+    #[test]
+    fn should_not_pair_inside_strings_and_comments() {
+        use super::PairEdit;
+
+        let doc = rust_doc();
+        assert_eq!(doc.typed_pair(&at(15), '('), PairEdit::Insert);
+        assert_eq!(doc.typed_pair(&at(28), '{'), PairEdit::Insert);
+        assert_eq!(doc.typed_pair(&at(7), '{'), PairEdit::Close('}'));
+    }
+
+    #[test]
+    fn should_pair_the_chars_of_the_language() {
+        use super::PairEdit;
+
+        // Rust has lifetimes, not `'` pairs
+        assert_eq!(rust_doc().typed_pair(&at(7), '\''), PairEdit::Insert);
+    }
+
+    #[test]
+    fn should_find_empty_pairs() {
+        assert!(!plain_doc().is_in_empty_pair(&at(9)));
+        assert!(rust_doc().is_in_empty_pair(&at(21)));
+    }
+
+    #[test]
+    fn should_not_pair_without_autopairs() {
+        use super::PairEdit;
+
+        let mut doc = rust_doc();
+        doc.options_mut().autopairs = false;
+        assert_eq!(doc.typed_pair(&at(7), '{'), PairEdit::Insert);
+        assert!(!doc.is_in_empty_pair(&at(21)));
+    }
+}
@@ -0,0 +1,190 @@
+use std::ops::Range;
+
+use regex::Captures;
+
+use crate::document::{Document, Pattern};
+
+#[derive(Clone, Copy)]
+enum Case {
+    Upper,
+    Lower,
+}
+
+impl Case {
+    fn apply(self, c: char, text: &mut String) {
+        match self {
+            Self::Upper => text.extend(c.to_uppercase()),
+            Self::Lower => text.extend(c.to_lowercase()),
+        }
+    }
+}
+
+#[derive(Clone)]
+enum Part {
+    Text(String),
+    /// `&` or `\0` for the whole match, `\1` to `\9` for the groups
+    Group(usize),
+    /// `\u` and `\l`, only for the next char
+    NextChar(Case),
+    /// `\U` and `\L`, until `\E` or `\e`
+    Following(Case),
+    EndCase,
+}
+
+/// Replacement text of `:substitute`.
+#[derive(Clone)]
+pub struct Replacement {
+    parts: Vec<Part>,
+}
+
+impl Replacement {
+    pub fn parse(text: &str) -> Self {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = text.chars();
+
+        while let Some(c) = chars.next() {
+            let part = match c {
+                '&' => Part::Group(0),
+                '\\' => match chars.next() {
+                    Some(digit @ '0'..='9') => Part::Group(digit as usize - '0' as usize),
+                    Some('u') => Part::NextChar(Case::Upper),
+                    Some('l') => Part::NextChar(Case::Lower),
+                    Some('U') => Part::Following(Case::Upper),
+                    Some('L') => Part::Following(Case::Lower),
+                    Some('E' | 'e') => Part::EndCase,
+                    Some('t') => {
+                        literal.push('\t');
+                        continue;
+                    }
+                    // `\&`, `\\` and any other escaped char stand for themselves
+                    Some(escaped) => {
+                        literal.push(escaped);
+                        continue;
+                    }
+                    None => {
+                        literal.push('\\');
+                        continue;
+                    }
+                },
+                _ => {
+                    literal.push(c);
+                    continue;
+                }
+            };
+
+            if !literal.is_empty() {
+                parts.push(Part::Text(std::mem::take(&mut literal)));
+            }
+            parts.push(part);
+        }
+
+        if !literal.is_empty() {
+            parts.push(Part::Text(literal));
+        }
+
+        Self { parts }
+    }
+
+    /// The replacement of a match, with its groups filled in.
+    pub fn expand(&self, captures: &Captures) -> String {
+        let mut text = String::new();
+        let mut next_char = None;
+        let mut following = None;
+
+        for part in &self.parts {
+            let inserted = match part {
+                Part::Text(literal) => literal.as_str(),
+                Part::Group(group) => captures.get(*group).map_or("", |group| group.as_str()),
+                Part::NextChar(case) => {
+                    next_char = Some(*case);
+                    continue;
+                }
+                Part::Following(case) => {
+                    following = Some(*case);
+                    continue;
+                }
+                Part::EndCase => {
+                    following = None;
+                    continue;
+                }
+            };
+
+            for c in inserted.chars() {
+                match next_char.take().or(following) {
+                    Some(case) => case.apply(c, &mut text),
+                    None => text.push(c),
+                }
+            }
+        }
+
+        text
+    }
+}
+
+impl Document {
+    /// Replaces the first match of `pattern` from byte `start` of row `y`.
+    /// Returns where the replacement is in the row, `None` without a match.
+    pub fn replace_match(
+        &mut self,
+        y: usize,
+        start: usize,
+        pattern: &Pattern,
+        replacement: &Replacement,
+    ) -> Option<Range<usize>> {
+        let content = &self.row(y)?.content;
+        let captures = pattern.captures_at(content, start)?;
+        let found = captures.get(0)?.range();
+        let text = replacement.expand(&captures);
+
+        let row = self.row_mut(y)?;
+        row.remove_range(found.clone());
+        row.insert_str(found.start, &text);
+
+        Some(found.start..found.start + text.len())
+    }
+}
+
+mod test {
+    #[test]
+    fn should_expand_groups_and_case_modifiers() {
+        use crate::document::{replacement::Replacement, Document, Pattern};
+
+        let mut doc = Document::from(vec!["first_name = last_name"]);
+        let pattern = Pattern::new(r"(\w+)_(\w+)").unwrap();
+
+        let replacement = Replacement::parse(r"\u\1\U\2\E!&");
+        assert_eq!(doc.replace_match(0, 0, &pattern, &replacement), Some(0..20));
+        assert_eq!(
+            doc.row(0).unwrap().content,
+            "FirstNAME!first_name = last_name"
+        );
+    }
+
+    #[test]
+    fn should_replace_escaped_chars() {
+        use crate::document::{replacement::Replacement, Document, Pattern};
+
+        let mut doc = Document::from(vec!["first_name = last_name"]);
+        let pattern = Pattern::new(r"(\w+)_(\w+)").unwrap();
+
+        let replacement = Replacement::parse(r"\L\2\&\\");
+        assert_eq!(
+            doc.replace_match(0, 10, &pattern, &replacement),
+            Some(13..19)
+        );
+        assert_eq!(doc.row(0).unwrap().content, "first_name = name&\\");
+    }
+
+    #[test]
+    fn should_not_replace_past_the_last_match() {
+        use crate::document::{replacement::Replacement, Document, Pattern};
+
+        let mut doc = Document::from(vec!["first_name = last_name"]);
+        let pattern = Pattern::new(r"(\w+)_(\w+)").unwrap();
+
+        let replacement = Replacement::parse("x");
+        assert_eq!(doc.replace_match(0, 22, &pattern, &replacement), None);
+        assert_eq!(doc.row(0).unwrap().content, "first_name = last_name");
+    }
+}
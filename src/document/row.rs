@@ -1,35 +1,147 @@
-use std::cmp;
+use std::{cmp, ops::Range};
 
-#[derive(Clone)]
+use crate::syntax::{Span, Token};
+
+#[derive(Clone, Eq)]
 pub struct Row {
     pub content: String,
+    /// picked by `:global`, the mark follows the row when lines around it
+    /// are inserted or deleted
+    pub marked: bool,
 }
 
 impl From<&str> for Row {
     fn from(value: &str) -> Self {
         Self {
             content: String::from(value),
+            marked: false,
         }
     }
 }
 
+/// Rows are the same when their text is, marks are not part of it.
+impl PartialEq for Row {
+    fn eq(&self, other: &Self) -> bool {
+        self.content == other.content
+    }
+}
+
 impl Row {
-    pub fn render(&self, start: usize, end: usize) -> String {
+    /// Text from `start` up to `end` as spans styled by `tokens`.
+    pub fn render(&self, start: usize, end: usize, tokens: &[Token]) -> Vec<Span> {
         let end = cmp::min(end, self.content.len());
         let start = cmp::min(start, end);
 
-        self.content.get(start..end).unwrap_or_default().to_string()
+        let mut spans = Vec::new();
+        let mut x = start;
+        for token in tokens {
+            let from = token.range.start.clamp(x, end);
+            let to = token.range.end.clamp(from, end);
+            if from == to {
+                continue;
+            }
+
+            if x < from {
+                spans.push(Span::plain(self.slice(x..from)));
+            }
+            spans.push(Span {
+                text: self.slice(from..to).to_string(),
+                scope: Some(token.scope),
+            });
+            x = to;
+        }
+        if x < end {
+            spans.push(Span::plain(self.slice(x..end)));
+        }
+
+        spans
     }
 
     pub fn insert_char(&mut self, pos: usize, c: char) {
-        self.content.insert(pos, c)
+        self.content.insert(self.char_start(pos), c);
     }
 
     pub fn remove_char(&mut self, pos: usize) {
-        self.content.remove(pos);
+        let pos = self.char_start(pos);
+        if pos < self.content.len() {
+            self.content.remove(pos);
+        }
+    }
+
+    pub fn insert_str(&mut self, pos: usize, text: &str) {
+        self.content.insert_str(self.char_start(pos), text);
+    }
+
+    /// Start of the char `pos` is in, positions are byte offsets that can
+    /// fall inside a char of several bytes.
+    pub fn char_start(&self, pos: usize) -> usize {
+        let mut pos = cmp::min(pos, self.content.len());
+        while !self.content.is_char_boundary(pos) {
+            pos -= 1;
+        }
+        pos
+    }
+
+    /// Start of the char `count` chars after the one at `pos`, at most the
+    /// end of the row.
+    pub fn next_char_index(&self, pos: usize, count: usize) -> usize {
+        let pos = self.char_start(pos);
+        self.content[pos..]
+            .char_indices()
+            .nth(count)
+            .map_or(self.content.len(), |(i, _)| pos + i)
     }
 
+    /// Start of the char `count` chars before the one at `pos`, at least
+    /// the start of the row.
+    pub fn previous_char_index(&self, pos: usize, count: usize) -> usize {
+        let pos = self.char_start(pos);
+        match count.checked_sub(1) {
+            Some(skip) => self.content[..pos]
+                .char_indices()
+                .nth_back(skip)
+                .map_or(0, |(i, _)| i),
+            None => pos,
+        }
+    }
+
+    /// Removes the text in `range`, clamped to the row, and returns it.
+    pub fn remove_range(&mut self, range: Range<usize>) -> String {
+        let range = self.clamp_range(range);
+        self.content.drain(range).collect()
+    }
+
+    /// Text in `range`, clamped to the row.
+    pub fn slice(&self, range: Range<usize>) -> &str {
+        let range = self.clamp_range(range);
+        self.content.get(range).unwrap_or_default()
+    }
+
+    /// Splits the row at `pos`, keeping the text before it.
+    pub fn split_off(&mut self, pos: usize) -> Row {
+        Row {
+            content: self.content.split_off(self.char_start(pos)),
+            marked: false,
+        }
+    }
+
+    pub fn first_non_blank(&self) -> usize {
+        self.content
+            .find(|c: char| !c.is_whitespace())
+            .unwrap_or(self.content.len())
+    }
+
+    #[allow(dead_code)]
     pub fn next_word_index(&self, pos: usize) -> Option<usize> {
-        self.content[pos..self.content.len()].find(" ")
+        self.content[pos..self.content.len()].find(' ')
+    }
+
+    /// `range` within the row, widened to the whole chars it is in.
+    fn clamp_range(&self, range: Range<usize>) -> Range<usize> {
+        let mut end = cmp::min(range.end, self.content.len());
+        while !self.content.is_char_boundary(end) {
+            end += 1;
+        }
+        self.char_start(cmp::min(range.start, end))..end
     }
 }
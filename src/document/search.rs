@@ -0,0 +1,233 @@
+use std::{cmp, ops::Range};
+
+use regex::{Captures, Regex, RegexBuilder};
+
+use crate::{document::Document, utils::Position};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SearchDirection {
+    Forward,
+    Backward,
+}
+
+impl SearchDirection {
+    pub fn reversed(self) -> Self {
+        match self {
+            Self::Forward => Self::Backward,
+            Self::Backward => Self::Forward,
+        }
+    }
+}
+
+/// Regular expression searched in the rows of a document, one row at a time.
+#[derive(Clone)]
+pub struct Pattern {
+    pub text: String,
+    regex: Regex,
+}
+
+impl Pattern {
+    /// Case is ignored unless the pattern has uppercase letters (smart-case).
+    pub fn new(text: &str) -> Result<Self, regex::Error> {
+        Self::with_case(text, !has_uppercase(text))
+    }
+
+    pub fn with_case(text: &str, ignore_case: bool) -> Result<Self, regex::Error> {
+        Ok(Self {
+            text: text.to_string(),
+            regex: RegexBuilder::new(text)
+                .case_insensitive(ignore_case)
+                .build()?,
+        })
+    }
+
+    /// Matches `word` as a whole word, like the searches of `*` and `#`.
+    pub fn word(word: &str, ignore_case: bool) -> Self {
+        let text = format!(r"\b{}\b", regex::escape(word));
+        Self {
+            regex: RegexBuilder::new(&text)
+                .case_insensitive(ignore_case)
+                .build()
+                .unwrap_or_else(|err| panic!("escaped word is not a valid pattern: {err}")),
+            text,
+        }
+    }
+
+    /// First match in `line` starting at byte `start` or after it.
+    pub fn find_at(&self, line: &str, start: usize) -> Option<Range<usize>> {
+        if start > line.len() {
+            return None;
+        }
+
+        self.regex.find_at(line, start).map(|found| found.range())
+    }
+
+    /// Groups of the first match in `line` from byte `start`.
+    pub fn captures_at<'l>(&self, line: &'l str, start: usize) -> Option<Captures<'l>> {
+        if start > line.len() {
+            return None;
+        }
+
+        self.regex.captures_at(line, start)
+    }
+
+    /// Every match of the pattern in `line`, without overlaps nor empty ones.
+    pub fn find_all(&self, line: &str) -> Vec<Range<usize>> {
+        self.regex
+            .find_iter(line)
+            .map(|found| found.range())
+            .filter(|range| !range.is_empty())
+            .collect()
+    }
+}
+
+/// Where a search stopped.
+pub struct SearchMatch {
+    pub position: Position,
+    /// the search went past the end, or the start, of the document
+    pub wrapped: bool,
+}
+
+impl Document {
+    /// Next match of `pattern` from `from`, excluded, going around the
+    /// document when its end is reached.
+    pub fn search(
+        &self,
+        pattern: &Pattern,
+        from: &Position,
+        direction: SearchDirection,
+    ) -> Option<SearchMatch> {
+        let len = self.lines_len();
+        if len == 0 || pattern.text.is_empty() {
+            return None;
+        }
+
+        // the row of `from` is looked at twice, before and after wrapping
+        for step in 0..=len {
+            let (y, wrapped) = match direction {
+                SearchDirection::Forward => ((from.y + step) % len, from.y + step >= len),
+                SearchDirection::Backward => ((from.y + len - step % len) % len, step > from.y),
+            };
+            let Some(row) = self.row(y) else {
+                continue;
+            };
+
+            // an empty row can only match at its start, like `^$` does
+            let line = &row.content;
+            let end = cmp::max(line.len(), 1);
+            let starts = match (step, direction) {
+                (0, SearchDirection::Forward) => char_boundary(line, from.x + 1)..end,
+                (0, SearchDirection::Backward) => 0..cmp::min(from.x, end),
+                (_, SearchDirection::Forward) if step == len => 0..cmp::min(from.x + 1, end),
+                (_, SearchDirection::Backward) if step == len => char_boundary(line, from.x)..end,
+                _ => 0..end,
+            };
+            if starts.is_empty() {
+                continue;
+            }
+
+            let found = match direction {
+                SearchDirection::Forward => pattern
+                    .find_at(line, starts.start)
+                    .map(|found| found.start)
+                    .filter(|x| starts.contains(x)),
+                SearchDirection::Backward => pattern
+                    .regex
+                    .find_iter(line)
+                    .map(|found| found.start())
+                    .skip_while(|x| *x < starts.start)
+                    .take_while(|x| *x < starts.end)
+                    .last(),
+            };
+            if let Some(x) = found {
+                return Some(SearchMatch {
+                    position: Position { x, y },
+                    wrapped,
+                });
+            }
+        }
+
+        None
+    }
+}
+
+/// Whether `text` has uppercase letters other than escaped ones, like `\S`,
+/// the `Lu` of `\p{Lu}` or the hex digits of `\x7F`.
+fn has_uppercase(text: &str) -> bool {
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            if c.is_uppercase() {
+                return true;
+            }
+            continue;
+        }
+
+        let digits = match chars.next() {
+            Some('p' | 'P') => 1,
+            Some('x') => 2,
+            Some('u') => 4,
+            Some('U') => 8,
+            _ => 0,
+        };
+        if digits > 0 && chars.clone().next() == Some('{') {
+            chars.by_ref().find(|c| *c == '}');
+        } else {
+            chars.by_ref().take(digits).for_each(drop);
+        }
+    }
+
+    false
+}
+
+/// First char boundary of `line` at byte `x` or after it.
+fn char_boundary(line: &str, x: usize) -> usize {
+    (x..line.len())
+        .find(|x| line.is_char_boundary(*x))
+        .unwrap_or(cmp::max(x, line.len()))
+}
+
+mod test {
+    #[test]
+    fn should_ignore_case_without_uppercase_letters() {
+        use super::Pattern;
+
+        let found = |pattern: &str, line: &str| Pattern::new(pattern).unwrap().find_at(line, 0);
+        assert_eq!(found("foo", "FOO"), Some(0..3));
+        assert_eq!(found("Foo", "FOO"), None);
+        // escaped letters are not uppercase letters
+        assert_eq!(found(r"\Sfoo", "aFOO"), Some(0..4));
+        assert_eq!(found(r"\p{Lu}x\W", "AX!"), Some(0..3));
+        assert_eq!(found(r"\x41b\pL", "aBC"), Some(0..3));
+        assert_eq!(found(r"\Wa\P{Lu}B", "!abB"), Some(0..4));
+        assert_eq!(found(r"\Wa\P{Lu}B", "!AbB"), None);
+    }
+
+    #[test]
+    fn should_search_around_the_document() {
+        use super::{Pattern, SearchDirection};
+        use crate::{document::Document, utils::Position};
+
+        let doc = Document::from(vec!["ab ab", "", "éab"]);
+        let pattern = Pattern::new("ab").unwrap();
+        let search = |x, y, direction| {
+            doc.search(&pattern, &Position { x, y }, direction)
+                .map(|found| (found.position.x, found.position.y, found.wrapped))
+        };
+
+        assert_eq!(search(0, 0, SearchDirection::Forward), Some((3, 0, false)));
+        assert_eq!(search(3, 0, SearchDirection::Forward), Some((2, 2, false)));
+        assert_eq!(search(2, 2, SearchDirection::Forward), Some((0, 0, true)));
+        assert_eq!(search(2, 2, SearchDirection::Backward), Some((3, 0, false)));
+        assert_eq!(search(4, 0, SearchDirection::Backward), Some((3, 0, false)));
+        assert_eq!(search(0, 0, SearchDirection::Backward), Some((2, 2, true)));
+
+        // an empty row matches only at its start
+        let pattern = Pattern::new("^$").unwrap();
+        assert_eq!(
+            doc.search(&pattern, &Position { x: 0, y: 0 }, SearchDirection::Forward)
+                .map(|found| found.position),
+            Some(Position { x: 0, y: 1 })
+        );
+    }
+}
@@ -0,0 +1,524 @@
+use std::{cmp, ops::Range};
+
+use regex::Regex;
+
+use crate::{
+    document::{motion::CharClass, Document},
+    utils::Position,
+};
+
+/// Text around the cursor an operator or visual mode can take at once,
+/// like a word or what is between brackets.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TextObject {
+    Word,
+    /// any run of non blank chars (`W`)
+    BigWord,
+    Sentence,
+    Paragraph,
+    Quote(char),
+    Bracket(char, char),
+    Tag,
+}
+
+impl TextObject {
+    /// Object named by the char typed after `i` or `a`.
+    pub fn from_char(c: char) -> Option<Self> {
+        match c {
+            'w' => Some(Self::Word),
+            'W' => Some(Self::BigWord),
+            's' => Some(Self::Sentence),
+            'p' => Some(Self::Paragraph),
+            '"' | '\'' | '`' => Some(Self::Quote(c)),
+            '(' | ')' | 'b' => Some(Self::Bracket('(', ')')),
+            '{' | '}' | 'B' => Some(Self::Bracket('{', '}')),
+            '[' | ']' => Some(Self::Bracket('[', ']')),
+            '<' | '>' => Some(Self::Bracket('<', '>')),
+            't' => Some(Self::Tag),
+            _ => None,
+        }
+    }
+}
+
+/// Text taken by a text object.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ObjectRange {
+    /// from the first position up to the second one, not included
+    Chars(Position, Position),
+    /// whole rows, both ends included
+    Lines(usize, usize),
+}
+
+/// Part of some text that is blank or not, as the inner objects (`iw`, `is`,
+/// `ip`) take them.
+struct Run {
+    range: Range<usize>,
+    blank: bool,
+}
+
+/// Rows joined by line breaks, to look for objects going through them.
+struct JoinedRows {
+    text: String,
+    first_line: usize,
+    /// offset of each row in `text`
+    starts: Vec<usize>,
+}
+
+impl JoinedRows {
+    fn new(doc: &Document, lines: Range<usize>) -> Self {
+        let first_line = lines.start;
+        let mut text = String::new();
+        let mut starts = Vec::new();
+
+        for y in lines {
+            if !starts.is_empty() {
+                text.push('\n');
+            }
+            starts.push(text.len());
+            text.push_str(doc.row(y).map_or("", |row| &row.content));
+        }
+
+        Self {
+            text,
+            first_line,
+            starts,
+        }
+    }
+
+    fn offset(&self, pos: &Position) -> usize {
+        self.starts[pos.y - self.first_line] + pos.x
+    }
+
+    fn position(&self, offset: usize) -> Position {
+        let row = self.starts.partition_point(|start| *start <= offset) - 1;
+        Position {
+            x: offset - self.starts[row],
+            y: self.first_line + row,
+        }
+    }
+
+    fn chars(&self, range: Range<usize>) -> ObjectRange {
+        ObjectRange::Chars(self.position(range.start), self.position(range.end))
+    }
+}
+
+impl Document {
+    /// Text `object` takes around `pos`, the inner one without the
+    /// surrounding blanks, quotes, brackets or tags. `count` takes more
+    /// words, sentences or paragraphs, or goes out of that many nested
+    /// brackets or tags.
+    pub fn text_object(
+        &self,
+        pos: &Position,
+        object: TextObject,
+        around: bool,
+        count: usize,
+    ) -> Option<ObjectRange> {
+        let count = cmp::max(count, 1);
+        match object {
+            TextObject::Word | TextObject::BigWord => {
+                self.word_object(pos, object == TextObject::BigWord, around, count)
+            }
+            TextObject::Sentence => self.sentence_object(pos, around, count),
+            TextObject::Paragraph => self.paragraph_object(pos, around, count),
+            TextObject::Quote(quote) => self.quote_object(pos, quote, around),
+            TextObject::Bracket(open, close) => {
+                self.bracket_object(pos, (open, close), around, count)
+            }
+            TextObject::Tag => self.tag_object(pos, around, count),
+        }
+    }
+
+    fn word_object(
+        &self,
+        pos: &Position,
+        big: bool,
+        around: bool,
+        count: usize,
+    ) -> Option<ObjectRange> {
+        let content = &self.row(pos.y)?.content;
+        let class = |c: char| match CharClass::from(c) {
+            CharClass::Punctuation if big => CharClass::Word,
+            class => class,
+        };
+
+        let mut runs: Vec<Run> = Vec::new();
+        let mut last_class = None;
+        for (x, c) in content.char_indices() {
+            let end = x + c.len_utf8();
+            let class = class(c);
+            match runs.last_mut() {
+                Some(run) if last_class.as_ref() == Some(&class) => run.range.end = end,
+                _ => runs.push(Run {
+                    range: x..end,
+                    blank: class == CharClass::Blank,
+                }),
+            }
+            last_class = Some(class);
+        }
+
+        let columns = select_runs(&runs, pos.x, count, around)?;
+        Some(ObjectRange::Chars(
+            Position {
+                x: columns.start,
+                y: pos.y,
+            },
+            Position {
+                x: columns.end,
+                y: pos.y,
+            },
+        ))
+    }
+
+    /// Sentences end with `.`, `!` or `?`, maybe followed by closing quotes
+    /// or brackets, before a blank. They don't go out of their paragraph.
+    fn sentence_object(&self, pos: &Position, around: bool, count: usize) -> Option<ObjectRange> {
+        let is_blank_row = |y: usize| self.row(y).is_none_or(|row| row.content.trim().is_empty());
+        if is_blank_row(pos.y) {
+            return None;
+        }
+
+        let first = (0..pos.y)
+            .rev()
+            .find(|y| is_blank_row(*y))
+            .map_or(0, |y| y + 1);
+        let last = (pos.y..self.lines_len())
+            .find(|y| is_blank_row(*y))
+            .unwrap_or(self.lines_len());
+        let rows = JoinedRows::new(self, first..last);
+
+        // blanks are part of the sentence unless they follow its end
+        let mut runs: Vec<Run> = Vec::new();
+        let mut ended = true;
+        for (i, c) in rows.text.char_indices() {
+            let end = i + c.len_utf8();
+            let gap = c.is_whitespace() && ended;
+            match runs.last_mut() {
+                Some(run) if run.blank == gap => run.range.end = end,
+                _ => runs.push(Run {
+                    range: i..end,
+                    blank: gap,
+                }),
+            }
+
+            if !c.is_whitespace() {
+                ended =
+                    matches!(c, '.' | '!' | '?') || (ended && matches!(c, ')' | ']' | '"' | '\''));
+            }
+        }
+
+        let range = select_runs(&runs, rows.offset(pos), count, around)?;
+        Some(rows.chars(range))
+    }
+
+    /// Paragraphs are separated by blank rows, which are a paragraph too.
+    fn paragraph_object(&self, pos: &Position, around: bool, count: usize) -> Option<ObjectRange> {
+        let mut runs: Vec<Run> = Vec::new();
+        for y in 0..self.lines_len() {
+            let blank = self.row(y).is_none_or(|row| row.content.trim().is_empty());
+            match runs.last_mut() {
+                Some(run) if run.blank == blank => run.range.end = y + 1,
+                _ => runs.push(Run {
+                    range: y..y + 1,
+                    blank,
+                }),
+            }
+        }
+
+        let lines = select_runs(&runs, pos.y, count, around)?;
+        Some(ObjectRange::Lines(lines.start, lines.end - 1))
+    }
+
+    /// Quotes are paired from the start of the row. Without quotes around
+    /// the cursor, the next quoted text of the row is taken.
+    fn quote_object(&self, pos: &Position, quote: char, around: bool) -> Option<ObjectRange> {
+        let content = &self.row(pos.y)?.content;
+
+        let mut quotes = Vec::new();
+        let mut escaped = false;
+        for (x, c) in content.char_indices() {
+            if c == quote && !escaped {
+                quotes.push(x);
+            }
+            escaped = c == '\\' && !escaped;
+        }
+
+        let before = quotes.iter().filter(|x| **x < pos.x).count();
+        let open = match quotes.get(before) {
+            // on a quote, opening or closing the quoted text
+            Some(x) if *x == pos.x && before % 2 == 0 => before,
+            Some(x) if *x == pos.x => before - 1,
+            _ if before % 2 == 1 => before - 1,
+            _ => before,
+        };
+        let (&open, &close) = (quotes.get(open)?, quotes.get(open + 1)?);
+
+        let (start, end) = if around {
+            // with the blanks after the quotes, or before them if none
+            let after = content[close + 1..]
+                .find(|c: char| !c.is_whitespace())
+                .map_or(content.len(), |x| close + 1 + x);
+            if after > close + 1 {
+                (open, after)
+            } else {
+                let before = content[..open].trim_end().len();
+                (before, close + 1)
+            }
+        } else {
+            (open + 1, close)
+        };
+
+        Some(ObjectRange::Chars(
+            Position { x: start, y: pos.y },
+            Position { x: end, y: pos.y },
+        ))
+    }
+
+    /// Brackets can be nested and go through rows. The inner text of
+    /// brackets on rows of their own is the rows between them.
+    fn bracket_object(
+        &self,
+        pos: &Position,
+        (open, close): (char, char),
+        around: bool,
+        count: usize,
+    ) -> Option<ObjectRange> {
+        // on a closing bracket, the brackets are the ones it closes
+        let mut from = if self.char_at(pos) == Some(close) {
+            self.previous_position(pos)?
+        } else {
+            *pos
+        };
+
+        let mut opening = self.find_unmatched(&from, open, close, false)?;
+        for _ in 1..count {
+            from = self.previous_position(&opening)?;
+            opening = self.find_unmatched(&from, open, close, false)?;
+        }
+        let after_opening = Position {
+            x: opening.x + open.len_utf8(),
+            y: opening.y,
+        };
+        let closing = self.find_unmatched(&after_opening, close, open, true)?;
+        let after_closing = Position {
+            x: closing.x + close.len_utf8(),
+            y: closing.y,
+        };
+
+        if around {
+            return Some(ObjectRange::Chars(opening, after_closing));
+        }
+
+        let opening_ends_row = self
+            .row(opening.y)
+            .is_some_and(|row| row.content.len() == after_opening.x);
+        let closing_starts_row = self
+            .row(closing.y)
+            .is_some_and(|row| row.content[..closing.x].trim().is_empty());
+        if closing.y > opening.y && opening_ends_row && closing_starts_row {
+            if closing.y - opening.y > 1 {
+                return Some(ObjectRange::Lines(opening.y + 1, closing.y - 1));
+            }
+
+            let end_of_row = Position {
+                x: self.row(opening.y)?.content.len(),
+                y: opening.y,
+            };
+            return Some(ObjectRange::Chars(end_of_row, end_of_row));
+        }
+
+        Some(ObjectRange::Chars(after_opening, closing))
+    }
+
+    /// First `wanted` bracket from `from` not matched by an `other` one,
+    /// looking forward or backward.
+    pub fn find_unmatched(
+        &self,
+        from: &Position,
+        wanted: char,
+        other: char,
+        forward: bool,
+    ) -> Option<Position> {
+        let mut depth = 0;
+        let mut current = *from;
+
+        loop {
+            match self.char_at(&current) {
+                Some(c) if c == wanted && depth == 0 => return Some(current),
+                Some(c) if c == wanted => depth -= 1,
+                Some(c) if c == other => depth += 1,
+                _ => {}
+            }
+
+            current = if forward {
+                self.next_position(&current)?
+            } else {
+                self.previous_position(&current)?
+            };
+        }
+    }
+
+    /// Tags are paired by name like in HTML, tags left open are ignored.
+    fn tag_object(&self, pos: &Position, around: bool, count: usize) -> Option<ObjectRange> {
+        let rows = JoinedRows::new(self, 0..self.lines_len());
+        let tag = Regex::new(r"<(/)?([^\s/>]+)[^>]*?(/)?>")
+            .unwrap_or_else(|err| panic!("tag pattern is not valid: {err}"));
+
+        // (name, start, end) of the tags not closed yet
+        let mut opened: Vec<(&str, Range<usize>)> = Vec::new();
+        // (opening tag, closing tag) of each element
+        let mut elements = Vec::new();
+        for captures in tag.captures_iter(&rows.text) {
+            let (Some(whole), Some(name)) = (captures.get(0), captures.get(2)) else {
+                continue;
+            };
+            if captures.get(3).is_some() {
+                continue;
+            }
+
+            if captures.get(1).is_none() {
+                opened.push((name.as_str(), whole.range()));
+            } else if let Some(i) = opened.iter().rposition(|(open, _)| *open == name.as_str()) {
+                // tags opened inside it and not closed are dropped
+                opened.truncate(i + 1);
+                if let Some((_, opening)) = opened.pop() {
+                    elements.push((opening, whole.range()));
+                }
+            }
+        }
+
+        let offset = rows.offset(pos);
+        let mut around_cursor: Vec<_> = elements
+            .into_iter()
+            .filter(|(opening, closing)| opening.start <= offset && offset < closing.end)
+            .collect();
+        // innermost first
+        around_cursor.sort_by_key(|(opening, _)| cmp::Reverse(opening.start));
+        let (opening, closing) = around_cursor.get(count - 1)?;
+
+        Some(if around {
+            rows.chars(opening.start..closing.end)
+        } else {
+            rows.chars(opening.end..closing.start)
+        })
+    }
+}
+
+/// Text of `count` runs from the one at `at`, blank ones included. Around
+/// objects count only the non blank runs and take the blank after the last
+/// one, or the one before the first if there is none after.
+fn select_runs(runs: &[Run], at: usize, count: usize, around: bool) -> Option<Range<usize>> {
+    let first = runs.iter().position(|run| run.range.contains(&at))?;
+    if !around {
+        let last = cmp::min(first + count - 1, runs.len() - 1);
+        return Some(runs[first].range.start..runs[last].range.end);
+    }
+
+    let mut last = first;
+    let mut taken = 0;
+    for (i, run) in runs.iter().enumerate().skip(first) {
+        last = i;
+        if !run.blank {
+            taken += 1;
+            if taken == count {
+                break;
+            }
+        }
+    }
+
+    let mut start = runs[first].range.start;
+    if !runs[first].blank {
+        match runs.get(last + 1) {
+            Some(run) if run.blank => last += 1,
+            _ if first > 0 && runs[first - 1].blank => start = runs[first - 1].range.start,
+            _ => {}
+        }
+    }
+
+    Some(start..runs[last].range.end)
+}
+
+mod test {
+    #[cfg(test)]
+    use super::ObjectRange;
+
+    /// Text object `c` around `x`, `y` of a document with brackets, quotes,
+    /// paragraphs, sentences and tags.
+    #[cfg(test)]
+    fn object(x: usize, y: usize, c: char, around: bool, count: usize) -> Option<ObjectRange> {
+        use super::TextObject;
+        use crate::{document::Document, utils::Position};
+
+        let doc = Document::from(vec![
+            "foo(bar, (baz)) \"qux\"",
+            "fn main() {",
+            "    call(a,",
+            "        b);",
+            "}",
+            "",
+            "One. Two <b>bold</b> end! Three",
+        ]);
+        doc.text_object(&Position { x, y }, TextObject::from_char(c)?, around, count)
+    }
+
+    #[cfg(test)]
+    fn chars(from: (usize, usize), to: (usize, usize)) -> ObjectRange {
+        use crate::utils::Position;
+
+        ObjectRange::Chars(
+            Position {
+                x: from.0,
+                y: from.1,
+            },
+            Position { x: to.0, y: to.1 },
+        )
+    }
+
+    #[test]
+    fn should_find_words() {
+        assert_eq!(object(5, 0, 'w', false, 1), Some(chars((4, 0), (7, 0))));
+        assert_eq!(object(5, 0, 'w', true, 1), Some(chars((4, 0), (7, 0))));
+        assert_eq!(object(5, 0, 'W', true, 1), Some(chars((0, 0), (9, 0))));
+        assert_eq!(object(0, 0, 'w', false, 3), Some(chars((0, 0), (7, 0))));
+    }
+
+    #[test]
+    fn should_find_nested_brackets() {
+        // from inside or on them
+        assert_eq!(object(11, 0, '(', false, 1), Some(chars((10, 0), (13, 0))));
+        assert_eq!(object(11, 0, ')', false, 2), Some(chars((4, 0), (14, 0))));
+        assert_eq!(object(14, 0, 'b', true, 1), Some(chars((3, 0), (15, 0))));
+    }
+
+    #[test]
+    fn should_find_quotes() {
+        assert_eq!(object(17, 0, '"', false, 1), Some(chars((17, 0), (20, 0))));
+        assert_eq!(object(1, 0, '"', true, 1), Some(chars((15, 0), (21, 0))));
+    }
+
+    #[test]
+    fn should_find_brackets_through_rows() {
+        // the rows between brackets on their own rows
+        assert_eq!(object(4, 3, '(', false, 1), Some(chars((9, 2), (9, 3))));
+        assert_eq!(object(4, 3, '{', false, 1), Some(ObjectRange::Lines(2, 3)));
+        assert_eq!(object(4, 3, '{', true, 1), Some(chars((10, 1), (1, 4))));
+    }
+
+    #[test]
+    fn should_find_paragraphs() {
+        assert_eq!(object(0, 2, 'p', false, 1), Some(ObjectRange::Lines(0, 4)));
+        assert_eq!(object(0, 2, 'p', true, 1), Some(ObjectRange::Lines(0, 5)));
+    }
+
+    #[test]
+    fn should_find_sentences() {
+        assert_eq!(object(6, 6, 's', false, 1), Some(chars((5, 6), (25, 6))));
+        assert_eq!(object(6, 6, 's', true, 1), Some(chars((5, 6), (26, 6))));
+    }
+
+    #[test]
+    fn should_find_tags() {
+        assert_eq!(object(14, 6, 't', false, 1), Some(chars((12, 6), (16, 6))));
+        assert_eq!(object(14, 6, 't', true, 1), Some(chars((9, 6), (20, 6))));
+    }
+}
@@ -0,0 +1,306 @@
+use std::{
+    env,
+    ffi::OsString,
+    io::{Error, ErrorKind, Write},
+    process::{Command, Stdio},
+};
+
+use crate::{
+    editor::{Register, SelectionKind},
+    terminal::Terminal,
+};
+
+/// External commands copying text read from stdin and pasting it to stdout.
+#[derive(Clone)]
+pub struct ClipboardTool {
+    pub name: String,
+    pub copy: Vec<String>,
+    pub paste: Vec<String>,
+}
+
+impl ClipboardTool {
+    fn new(name: &str, copy: &[&str], paste: &[&str]) -> Self {
+        Self {
+            name: name.to_string(),
+            copy: copy.iter().map(ToString::to_string).collect(),
+            paste: paste.iter().map(ToString::to_string).collect(),
+        }
+    }
+
+    /// Tools looked for, in order, when the clipboard is detected.
+    fn known() -> Vec<Self> {
+        vec![
+            Self::new("wl-copy", &["wl-copy"], &["wl-paste", "-n"]),
+            Self::new(
+                "xclip",
+                &["xclip", "-selection", "clipboard"],
+                &["xclip", "-selection", "clipboard", "-o"],
+            ),
+            Self::new("pbcopy", &["pbcopy"], &["pbpaste"]),
+        ]
+    }
+
+    /// Whether the tool is installed and its display server is running,
+    /// `var` reading the environment.
+    fn is_available(&self, var: &impl Fn(&str) -> Option<OsString>) -> bool {
+        let display = match self.name.as_str() {
+            "wl-copy" => Some("WAYLAND_DISPLAY"),
+            "xclip" => Some("DISPLAY"),
+            _ => None,
+        };
+        if display.is_some_and(|name| var(name).is_none()) {
+            return false;
+        }
+
+        let Some(program) = self.copy.first() else {
+            return false;
+        };
+
+        var("PATH")
+            .is_some_and(|paths| env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+    }
+
+    fn command(args: &[String]) -> Result<Command, Error> {
+        let (program, args) = args
+            .split_first()
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "empty clipboard command"))?;
+
+        let mut command = Command::new(program);
+        command.args(args).stderr(Stdio::null());
+        Ok(command)
+    }
+
+    fn copy(&self, text: &str) -> Result<(), Error> {
+        let mut child = Self::command(&self.copy)?
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes())?;
+        }
+        child.wait()?;
+
+        Ok(())
+    }
+
+    fn paste(&self) -> Result<String, Error> {
+        let output = Self::command(&self.paste)?.stdin(Stdio::null()).output()?;
+
+        String::from_utf8(output.stdout).map_err(|err| Error::new(ErrorKind::InvalidData, err))
+    }
+}
+
+/// The system clipboard behind the `+` and `*` registers.
+///
+/// Copies go through the terminal with OSC 52 or through an external tool,
+/// and pastes the same way. The terminal can't be read, so with OSC 52 or
+/// without a tool what was last copied is pasted back.
+#[derive(Default)]
+pub struct Clipboard {
+    osc52: bool,
+    tool: Option<ClipboardTool>,
+    copied: Option<Register>,
+}
+
+impl Clipboard {
+    pub fn new(osc52: bool, tool: Option<ClipboardTool>) -> Self {
+        Self {
+            osc52,
+            tool,
+            copied: None,
+        }
+    }
+
+    /// OSC 52 in SSH sessions, where local tools would copy on the wrong
+    /// machine, otherwise the first installed tool, and OSC 52 again when
+    /// there is none.
+    pub fn detect() -> Self {
+        Self::detect_with(|name| env::var_os(name))
+    }
+
+    /// Detects the clipboard with `var` reading the environment.
+    fn detect_with(var: impl Fn(&str) -> Option<OsString>) -> Self {
+        let remote = ["SSH_TTY", "SSH_CONNECTION"]
+            .iter()
+            .any(|name| var(name).is_some());
+        if remote {
+            return Self::new(true, None);
+        }
+
+        match ClipboardTool::known()
+            .into_iter()
+            .find(|tool| tool.is_available(&var))
+        {
+            Some(tool) => Self::new(false, Some(tool)),
+            None => Self::new(true, None),
+        }
+    }
+
+    /// Clipboard picked by name, `None` if the name is unknown.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "auto" => None,
+            "osc52" => Some(Self::new(true, None)),
+            "none" => Some(Self::default()),
+            _ => ClipboardTool::known()
+                .into_iter()
+                .find(|tool| tool.name == name)
+                .map(|tool| Self::new(false, Some(tool))),
+        }
+    }
+
+    pub fn copy(&mut self, register: &Register) -> Result<(), Error> {
+        self.copied = Some(register.clone());
+        let text = Self::register_text(register);
+
+        if self.osc52 {
+            Terminal::copy_to_clipboard(&text);
+            return Ok(());
+        }
+
+        match &self.tool {
+            Some(tool) => tool.copy(&text),
+            None => Ok(()),
+        }
+    }
+
+    pub fn paste(&self) -> Result<Option<Register>, Error> {
+        // pasted the way it was copied
+        let tool = match &self.tool {
+            Some(tool) if !self.osc52 => tool,
+            _ => return Ok(self.copied.clone()),
+        };
+
+        let text = tool.paste()?;
+        // keep how the text was copied if it comes back unchanged
+        if let Some(copied) = &self.copied {
+            if Self::register_text(copied) == text {
+                return Ok(Some(copied.clone()));
+            }
+        }
+
+        if text.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(match text.strip_suffix('\n') {
+            Some(lines) => Register {
+                lines: lines.split('\n').map(String::from).collect(),
+                kind: SelectionKind::Line,
+            },
+            None => Register::from_text(&text),
+        }))
+    }
+
+    /// Text of a register as other programs see it, lines taken whole end
+    /// with a line break.
+    fn register_text(register: &Register) -> String {
+        let mut text = register.lines.join("\n");
+        if register.kind == SelectionKind::Line {
+            text.push('\n');
+        }
+
+        text
+    }
+}
+
+mod test {
+    #[cfg(test)]
+    use std::ffi::OsString;
+
+    /// Environment with the variables `vars`, and `PATH` a directory with
+    /// the programs `programs` when there are some.
+    #[cfg(test)]
+    fn environment(
+        name: &str,
+        vars: &[&str],
+        programs: &[&str],
+    ) -> (impl Fn(&str) -> Option<OsString>, std::path::PathBuf) {
+        use std::{env, fs, process};
+
+        let dir = env::temp_dir().join(format!("text-editor-{name}-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for program in programs {
+            fs::write(dir.join(program), "").unwrap();
+        }
+
+        let vars: Vec<String> = vars.iter().map(ToString::to_string).collect();
+        let path = dir.clone().into_os_string();
+        let var = move |name: &str| match name {
+            "PATH" => Some(path.clone()),
+            _ if vars.iter().any(|var| var == name) => Some(OsString::from("1")),
+            _ => None,
+        };
+        (var, dir)
+    }
+
+    #[test]
+    fn should_use_an_installed_tool_locally() {
+        use super::Clipboard;
+
+        let (var, dir) = environment("clipboard-local", &["DISPLAY", "TMUX"], &["xclip"]);
+        let clipboard = Clipboard::detect_with(var);
+        std::fs::remove_dir_all(dir).unwrap();
+
+        assert!(!clipboard.osc52);
+        assert_eq!(
+            clipboard.tool.map(|tool| tool.name).as_deref(),
+            Some("xclip")
+        );
+    }
+
+    #[test]
+    fn should_skip_tools_without_their_display() {
+        use super::Clipboard;
+
+        let (var, dir) = environment("clipboard-display", &[], &["xclip", "wl-copy"]);
+        let clipboard = Clipboard::detect_with(var);
+        std::fs::remove_dir_all(dir).unwrap();
+
+        assert!(clipboard.osc52);
+        assert!(clipboard.tool.is_none());
+    }
+
+    #[test]
+    fn should_use_osc52_over_ssh() {
+        use super::Clipboard;
+
+        let (var, dir) = environment("clipboard-ssh", &["SSH_TTY", "DISPLAY"], &["xclip"]);
+        let clipboard = Clipboard::detect_with(var);
+        std::fs::remove_dir_all(dir).unwrap();
+
+        assert!(clipboard.osc52);
+        assert!(clipboard.tool.is_none());
+    }
+
+    #[test]
+    fn should_pick_clipboards_by_name() {
+        use super::Clipboard;
+
+        assert!(Clipboard::from_name("auto").is_none());
+        assert!(Clipboard::from_name("clippy").is_none());
+        assert!(Clipboard::from_name("osc52").is_some_and(|clipboard| clipboard.osc52));
+
+        let none = Clipboard::from_name("none").unwrap();
+        assert!(!none.osc52 && none.tool.is_none());
+        let xclip = Clipboard::from_name("xclip").unwrap();
+        assert!(!xclip.osc52);
+        assert_eq!(xclip.tool.map(|tool| tool.name).as_deref(), Some("xclip"));
+    }
+
+    #[test]
+    fn should_paste_what_was_copied_with_osc52() {
+        use super::{Clipboard, ClipboardTool};
+        use crate::editor::Register;
+
+        // the tool would paste something else
+        let tool = ClipboardTool::new("stub", &["true"], &["echo", "stale"]);
+        let mut clipboard = Clipboard::new(true, Some(tool));
+        let register = Register::from_text("fresh");
+        clipboard.copy(&register).unwrap();
+
+        let pasted = clipboard.paste().unwrap().unwrap();
+        assert_eq!(pasted.lines, register.lines);
+    }
+}
@@ -1,18 +1,99 @@
+mod command;
+mod fold;
+mod global;
+mod insert;
+mod macros;
+mod mapping;
+mod marks;
+mod mouse;
+mod operator;
+mod options;
+mod put;
+mod range;
+mod repeat;
+mod search;
+mod substitute;
+mod text_object;
+mod undo;
+mod visual;
+
 use std::cell::{Ref, RefCell, RefMut};
 use std::io::Error;
+use std::path::Path;
 use std::rc::Rc;
 
 use termion::event::Key;
 
-use crate::editor::{Mode, Window};
-use crate::terminal::Terminal;
-use crate::utils::{Direction, Position};
+use crate::document::{editorconfig, Document, Row, SearchDirection};
+use crate::editor::{
+    fold::Folds,
+    keymap::{Action, MapMode},
+    window_manager::Separator,
+    Clipboard, Keymap, Mode, Options, Registers, Selection, SelectionKind, Window,
+};
+use crate::syntax::SyntaxSet;
+use crate::terminal::{Input, Terminal};
+use crate::theme::{Theme, Themes};
+use crate::utils::{Alignment, Direction, Position};
+
+use insert::BlockInsert;
+use macros::MacroState;
+use mapping::MappingState;
+use marks::MarkState;
+use operator::Operator;
+use repeat::RepeatState;
+use search::SearchState;
+use substitute::Substitution;
+use undo::ChangeStart;
 
 pub struct Control<'a> {
     cursor_position: Position,
     mode: Mode,
     attached_window: Option<usize>,
     windows: &'a Rc<RefCell<Vec<Window>>>,
+    pending_keys: Vec<Key>,
+    /// typed before a command, like the `3` of `3j`
+    count: Option<usize>,
+    message: Option<String>,
+    /// shown instead of `message` when the last command failed
+    error: Option<String>,
+    visual_anchor: Position,
+    last_selection: Option<Selection>,
+    block_insert: Option<BlockInsert>,
+    registers: Registers,
+    clipboard: Clipboard,
+    /// register given with `"` for the next command
+    register_name: Option<char>,
+    /// text typed since entering insert mode, for the `.` register
+    inserted_text: String,
+    command_line: String,
+    should_quit: bool,
+    /// the change being made, to undo it
+    change_start: Option<ChangeStart>,
+    /// global values of the options
+    options: Options,
+    /// the left button was pressed on a window and is still down
+    dragging: bool,
+    /// the left button was pressed on a separator between windows and is
+    /// still down
+    resizing: Option<Separator>,
+    search: SearchState,
+    /// `:s///c` waiting for a match to be confirmed
+    substitution: Option<Substitution>,
+    /// `:global` is running its command on the marked lines
+    global_busy: bool,
+    /// `:normal` commands typing keys, nested ones included. The changes
+    /// of the keys are part of the command's.
+    replaying: usize,
+    macros: MacroState,
+    repeat: RepeatState,
+    marks: MarkState,
+    /// languages documents are highlighted with
+    syntaxes: SyntaxSet,
+    themes: Themes,
+    /// default bindings of normal mode, and the mappings of each mode
+    keymap: Keymap,
+    mapping: MappingState,
 }
 
 impl<'a> Control<'a> {
@@ -22,6 +103,34 @@ impl<'a> Control<'a> {
             mode: Mode::Normal,
             attached_window: None,
             windows,
+            pending_keys: Vec::new(),
+            count: None,
+            message: None,
+            error: None,
+            visual_anchor: Position::default(),
+            last_selection: None,
+            block_insert: None,
+            registers: Registers::default(),
+            clipboard: Clipboard::default(),
+            register_name: None,
+            inserted_text: String::new(),
+            command_line: String::new(),
+            should_quit: false,
+            change_start: None,
+            options: Options::default(),
+            dragging: false,
+            resizing: None,
+            search: SearchState::default(),
+            substitution: None,
+            global_busy: false,
+            replaying: 0,
+            macros: MacroState::default(),
+            repeat: RepeatState::default(),
+            marks: MarkState::default(),
+            syntaxes: SyntaxSet::default(),
+            themes: Themes::default(),
+            keymap: Keymap::default(),
+            mapping: MappingState::default(),
         }
     }
 
@@ -29,7 +138,72 @@ impl<'a> Control<'a> {
         &self.cursor_position
     }
 
+    pub fn mode(&self) -> &Mode {
+        &self.mode
+    }
+
+    /// Message for the last line of the screen, set by the last command.
+    pub fn message(&self) -> Option<&str> {
+        self.error.as_deref().or(self.message.as_deref())
+    }
+
+    /// Whether the message tells why the last command failed.
+    pub fn is_error(&self) -> bool {
+        self.error.is_some()
+    }
+
     //-------------- Window utilities --------------//
+    pub fn options(&self) -> &Options {
+        &self.options
+    }
+
+    pub fn set_clipboard(&mut self, clipboard: Clipboard) {
+        self.clipboard = clipboard;
+    }
+
+    /// Loads the grammar files of `dir`, telling which ones are malformed.
+    pub fn load_grammars(&mut self, dir: &Path) {
+        let errors = self.syntaxes.load_grammars(dir);
+        self.report_load_errors("grammars", &errors);
+    }
+
+    /// Loads the theme files of `dir`, telling which ones are malformed.
+    pub fn load_themes(&mut self, dir: &Path) {
+        let errors = self.themes.load_dir(dir);
+        self.report_load_errors("themes", &errors);
+    }
+
+    fn report_load_errors(&mut self, what: &str, errors: &[String]) {
+        if errors.is_empty() {
+            return;
+        }
+
+        let report = format!("Can't load {what}:\n{}", errors.join("\n"));
+        self.error = Some(match self.error.take() {
+            Some(error) => format!("{error}\n{report}"),
+            None => report,
+        });
+    }
+
+    pub fn theme(&self) -> &Theme {
+        self.themes.current()
+    }
+
+    /// Opens the file at `path`, highlighted in the language detected for it.
+    pub fn open_document(&self, path: &str) -> Result<Document, Error> {
+        let mut doc = Document::open(path)?;
+        let first_line = doc.row(0).map_or("", |row| row.content.as_str());
+        doc.set_syntax(self.syntaxes.detect(Some(path), first_line));
+        *doc.options_mut() = self.options.buffer.clone();
+        // editorconfig wins over what the rows tell
+        let properties = editorconfig::properties(Path::new(path));
+        if !editorconfig::apply(&properties, doc.options_mut()) {
+            doc.detect_indent();
+        }
+
+        Ok(doc)
+    }
+
     pub fn attach_window(&mut self, window_i: usize) {
         self.attached_window = Some(window_i);
     }
@@ -44,209 +218,748 @@ impl<'a> Control<'a> {
 
     pub fn scroll_attached_window(&self, direction: &Direction, length: usize) {
         if let Some(index) = self.attached_window {
-            self.borrow_mut_windows()[index].scroll(direction, length);
+            if let Some(window) = self.borrow_mut_windows().get_mut(index) {
+                window.scroll(direction, length);
+            }
         };
     }
 
     pub fn current_line_len(&self) -> Option<usize> {
-        let window_index = match self.attached_window {
-            Some(index) => index,
-            None => return None,
-        };
+        let window_index = self.attached_window?;
+        let windows = self.borrow_attached_windows();
+        let window = windows.get(window_index)?;
 
-        let line = match self.borrow_attached_windows()[window_index]
-            .document_line_number_from_cursor(self.cursor_position.y)
-        {
-            Some(l) => l,
-            None => return None,
-        };
+        let line = window.document_line_number_from_cursor(self.cursor_position.y)?;
 
         Some(
-            self.borrow_attached_windows()[window_index]
+            window
                 .document_row(line)
-                .expect(&format!("line {line} doesnt exists"))
+                .unwrap_or_else(|| panic!("line {line} doesnt exists"))
                 .content
                 .len(),
         )
     }
+
+    /// Cursor position inside the document of the attached window.
+    pub fn document_position(&self) -> Option<Position> {
+        let windows = self.borrow_attached_windows();
+        let window = windows.get(self.attached_window?)?;
+
+        window.document_position_from_cursor(&self.cursor_position)
+    }
+
+    /// Moves the cursor to `pos` in the attached window's document, clamped to
+    /// the document bounds, scrolling the window if it is out of view.
+    pub fn set_document_position(&mut self, pos: Position) {
+        let Some(window_index) = self.attached_window else {
+            return;
+        };
+
+        let cursor = {
+            let mut windows = self.borrow_mut_windows();
+            let Some(window) = windows.get_mut(window_index) else {
+                return;
+            };
+
+            let Some(last_line) = window.document_rows().checked_sub(1) else {
+                return;
+            };
+            // rows of a closed fold are all shown on its first one
+            let y = window.folds().visible_line(pos.y.min(last_line));
+            let x = window.document_row(y).map_or(0, |row| {
+                row.char_start(pos.x.min(self.max_column(row.content.len())))
+            });
+            let pos = Position { x, y };
+
+            window.scroll_into_view(&pos);
+            window.cursor_from_document_position(&pos)
+        };
+
+        if let Some(cursor) = cursor {
+            self.cursor_position = cursor;
+        }
+    }
+
+    /// Last column the cursor can be on, in insert mode that is right after
+    /// the last char.
+    fn max_column(&self, line_len: usize) -> usize {
+        match self.mode {
+            Mode::Insert => line_len,
+            _ => line_len.saturating_sub(1),
+        }
+    }
+
+    /// Runs `f` over the document of the attached window.
+    pub fn read_document<T>(&self, f: impl FnOnce(&Document) -> T) -> Option<T> {
+        let windows = self.borrow_attached_windows();
+        Some(f(windows.get(self.attached_window?)?.document()))
+    }
+
+    /// Runs `f` over the document of the attached window, allowing changes.
+    pub fn edit_document<T>(&self, f: impl FnOnce(&mut Document) -> T) -> Option<T> {
+        let mut windows = self.borrow_mut_windows();
+        let window = windows.get_mut(self.attached_window?)?;
+        let result = f(window.document_mut());
+        window.update_folds();
+        Some(result)
+    }
     //--------------------------------------------//
 
-    pub fn listen_key() -> Result<Key, Error> {
-        Terminal::read_key()
+    pub fn listen_input() -> Result<Input, Error> {
+        Terminal::read_input()
     }
 
+    /// Handles the next input. Keys that can start a longer mapping wait
+    /// for the rest of it until `timeoutlen` is over.
     pub fn process_key(&mut self) -> Result<(), Error> {
-        let key = Self::listen_key()?;
+        let input = match self.mapping_timeout() {
+            Some(timeout) => Terminal::read_input_timeout(timeout)?,
+            None => Some(Self::listen_input()?),
+        };
 
-        match self.mode {
-            Mode::Insert => todo!(),
-            Mode::Normal => self.process_key_in_normal_mode(key),
-            Mode::Command => todo!(),
+        match input {
+            Some(Input::Key(key)) => self.handle_key(key),
+            Some(Input::Paste(text)) => {
+                self.finish_mapping();
+                self.handle_paste(&text);
+            }
+            Some(Input::Mouse(event)) => {
+                self.finish_mapping();
+                self.handle_mouse(event);
+            }
+            None => self.finish_mapping(),
         }
 
         Ok(())
     }
 
-    pub fn process_key_in_normal_mode(&mut self, key: Key) {
-        match key {
-            Key::Backspace => todo!(),
-            // basic moves
-            Key::Char('h') => self.move_left(1),
-            Key::Char('j') => self.move_down(1),
-            Key::Char('k') => self.move_up(1),
-            Key::Char('l') => self.move_right(1),
+    /// Handles a typed key, through the mappings unless it is replayed.
+    pub fn handle_key(&mut self, key: Key) {
+        self.record_key(key);
+        if self.replaying > 0 {
+            self.run_key(key);
+        } else {
+            self.map_key(key);
+        }
+    }
 
-            Key::Char('A') => self.go_to_last_line_char(),
+    /// Runs `key` in the current mode, as it is.
+    fn run_key(&mut self, key: Key) {
+        self.message = None;
+        self.error = None;
+        self.start_change();
 
-            // big jumps
-            Key::Ctrl('d') => self.move_down_half_screen(),
-            Key::Ctrl('u') => self.move_up_half_screen(),
+        match self.mode {
+            Mode::Insert => {
+                self.record_insert_key(key);
+                self.process_key_in_insert_mode(key);
+                self.finish_insert_change();
+            }
+            Mode::Normal => self.process_key_in_normal_mode(key),
+            Mode::Command => self.process_key_in_command_mode(key),
+            Mode::Visual(_) => self.process_key_in_visual_mode(key),
+            Mode::Search(direction) => self.process_key_in_search_mode(key, direction),
+            Mode::Confirm => self.process_key_in_confirm_mode(key),
+        }
 
-            Key::Alt(_) => todo!(),
-            Key::Ctrl(_) => todo!(),
-            _ => todo!(),
+        // everything typed in insert mode, or replaced by a confirmed
+        // substitution, is undone at once
+        if !matches!(self.mode, Mode::Insert | Mode::Confirm) && self.replaying == 0 {
+            let modified = self.finish_change();
+            self.finish_repeated_change(modified);
         }
     }
 
-    //-------------- Movement --------------//
+    /// Pasted text is inserted at once, as a single change, without going
+    /// through the keys of any mode.
+    pub fn handle_paste(&mut self, text: &str) {
+        self.message = None;
+        self.error = None;
 
-    pub fn move_down(&mut self, length: usize) {
-        let next_pos = Position {
-            x: self.cursor_position.x,
-            y: self.cursor_position.y + length,
-        };
+        match self.mode {
+            Mode::Command => {
+                self.command_line
+                    .push_str(text.lines().next().unwrap_or_default());
+            }
+            Mode::Search(direction) => {
+                self.command_line
+                    .push_str(text.lines().next().unwrap_or_default());
+                self.incremental_search(direction);
+            }
+            Mode::Insert => {
+                // an insert in progress is its own change
+                self.finish_change();
+                self.start_change();
+                self.insert_pasted_text(text);
+                self.finish_change();
+            }
+            Mode::Confirm => {}
+            Mode::Normal | Mode::Visual(_) => {
+                self.exit_visual_mode();
+                self.start_change();
+                self.enter_insert_mode();
+                self.insert_pasted_text(text);
+                self.exit_insert_mode();
+                self.finish_change();
+            }
+        }
+    }
 
-        let window_index = match self.attached_window {
-            Some(index) => index,
-            None => return,
+    /// Takes the keys typed so far once they form a whole command.
+    fn take_pending_keys(&mut self, key: Key) -> Option<Vec<Key>> {
+        // a count comes before the command, a `0` alone goes to the line start
+        if let Key::Char(digit @ '0'..='9') = key {
+            if self.pending_keys.is_empty() && (digit != '0' || self.count.is_some()) {
+                let count = self.count.unwrap_or(0);
+                self.count = Some(
+                    count
+                        .saturating_mul(10)
+                        .saturating_add(digit as usize - '0' as usize),
+                );
+                return None;
+            }
+        }
+
+        self.pending_keys.push(key);
+
+        let mode = match self.mode {
+            Mode::Visual(_) => MapMode::Visual,
+            _ => MapMode::Normal,
+        };
+        if self.keymap.is_default_prefix(mode, &self.pending_keys) {
+            return None;
+        }
+        let Some((action, len)) = self.keymap.command(mode, &self.pending_keys) else {
+            return Some(std::mem::take(&mut self.pending_keys));
         };
 
-        let window_height = match self.borrow_attached_windows().get(window_index) {
-            Some(window) => window.size().height as usize,
-            None => return,
+        // wait for the arguments of the command
+        let args = &self.pending_keys[len..];
+        let pending = match action {
+            // `"x` picks the register used by the command that follows
+            Action::SelectRegister => {
+                if let [Key::Char(name)] = *args {
+                    if Registers::is_valid_name(name) {
+                        self.register_name = Some(name);
+                    } else {
+                        self.error = Some(format!("Invalid register name: '{name}'"));
+                    }
+                }
+                if !args.is_empty() {
+                    self.pending_keys.clear();
+                }
+                return None;
+            }
+            // `q` starts a recording only, while recording it stops it at once
+            Action::RecordMacro if self.recording().is_some() => false,
+            _ if action.takes_char() => args.is_empty(),
+            _ if action.is_operator() && mode == MapMode::Normal => self.is_motion_pending(args),
+            _ => false,
         };
+        if pending {
+            return None;
+        }
+
+        Some(std::mem::take(&mut self.pending_keys))
+    }
 
-        let rows_len = self.borrow_attached_windows()[window_index].document_rows();
+    /// Whether the keys typed after an operator are not a whole motion yet.
+    fn is_motion_pending(&self, keys: &[Key]) -> bool {
+        // `0` is a motion unless it is part of a count
+        let (_, motion) = split_count(keys);
+        if motion.is_empty() || self.keymap.is_default_prefix(MapMode::Operator, motion) {
+            return true;
+        }
+
+        match self.keymap.command(MapMode::Operator, motion) {
+            Some((action, len)) => action.takes_char() && motion.len() == len,
+            None => false,
+        }
+    }
 
-        if next_pos.y > rows_len {
+    pub fn process_key_in_normal_mode(&mut self, key: Key) {
+        let Some(keys) = self.take_pending_keys(key) else {
             return;
+        };
+
+        let count = self.count.take();
+        if let Some((action, len)) = self.keymap.command(MapMode::Normal, &keys) {
+            if action == Action::RepeatChange {
+                self.repeat_last_change(count);
+            } else if !self.process_counted_movement(action, &keys[len..], count) {
+                let register = self.register_name;
+                self.run_normal_command(action, keys.split_at(len), count.unwrap_or(1));
+                self.record_change(register, &keys, count);
+            }
         }
 
-        if (next_pos.y - 1 > window_height) & (next_pos.y < rows_len) {
-            self.scroll_attached_window(&Direction::Down, length);
-            self.correct_x();
+        // the register only applies to the command right after it
+        self.register_name = None;
+    }
+
+    /// Runs `action`, bound to the first keys of `keys`, the others being
+    /// its arguments.
+    fn run_normal_command(&mut self, action: Action, keys: (&[Key], &[Key]), count: usize) {
+        let (command, args) = keys;
+
+        // operators
+        if let Some(operator) = Operator::from_action(action) {
+            // a count after the operator multiplies the one before it
+            let (motion_count, motion) = split_count(args);
+            let count = count.saturating_mul(motion_count.unwrap_or(1));
+            // `dd`, `gcc`
+            if motion.len() == 1 && command.last() == motion.first() {
+                self.apply_operator_on_line(operator, count);
+                return;
+            }
+
+            match self.keymap.command(MapMode::Operator, motion) {
+                Some((object @ (Action::InnerObject | Action::AroundObject), len)) => {
+                    if let [Key::Char(name)] = motion[len..] {
+                        let around = object == Action::AroundObject;
+                        self.apply_operator_on_object(operator, name, around, count);
+                    }
+                }
+                Some((motion_action, len)) => {
+                    self.apply_operator(operator, motion_action, &motion[len..], count);
+                }
+                None => {}
+            }
             return;
         }
 
-        self.cursor_position = next_pos;
-        self.correct_x();
+        match (action, args) {
+            (Action::SetMark, [Key::Char(name)]) => self.set_mark(*name),
+
+            // macros
+            (Action::RecordMacro, []) => self.stop_recording(),
+            (Action::RecordMacro, [Key::Char(name)]) => self.start_recording(*name),
+            (Action::PlayMacro, [Key::Char(name)]) => self.play_macro(*name, count),
+
+            _ => self.run_action(action, count),
+        }
+    }
+
+    /// Runs the command `action` is, `count` times when it can be repeated.
+    fn run_action(&mut self, action: Action, count: usize) {
+        match action {
+            // insert
+            Action::Insert => self.enter_insert_mode(),
+            Action::Append => self.append(),
+            Action::InsertAtLineStart => self.insert_at_line_start(),
+            Action::AppendAtLineEnd => self.append_at_line_end(),
+            Action::OpenLineBelow => self.open_line(&Direction::Down),
+            Action::OpenLineAbove => self.open_line(&Direction::Up),
+
+            // visual
+            Action::VisualChar => self.enter_visual_mode(SelectionKind::Char),
+            Action::VisualLine => self.enter_visual_mode(SelectionKind::Line),
+            Action::VisualBlock => self.enter_visual_mode(SelectionKind::Block),
+            Action::Reselect => self.reselect(),
+
+            // registers
+            Action::PutAfter | Action::PutBefore => self.put(action == Action::PutBefore, count),
+            Action::DeleteChar => self.delete_chars(false, count),
+            Action::DeleteCharBefore => self.delete_chars(true, count),
+            Action::DeleteToLineEnd => {
+                self.apply_operator(Operator::Delete, Action::LineEnd, &[], count);
+            }
+            Action::ChangeToLineEnd => {
+                self.apply_operator(Operator::Change, Action::LineEnd, &[], count);
+            }
+            Action::YankLine => self.apply_operator_on_line(Operator::Yank, count),
+
+            Action::CommandLine => self.enter_command_mode(),
+            Action::SearchForward => self.enter_search_mode(SearchDirection::Forward),
+            Action::SearchBackward => self.enter_search_mode(SearchDirection::Backward),
+
+            // undo
+            Action::Undo => (0..count).for_each(|_| self.undo()),
+            Action::Redo => (0..count).for_each(|_| self.redo()),
+
+            // marks
+            Action::JumpBack => self.travel_jumps(true, count),
+            Action::JumpForward => self.travel_jumps(false, count),
+
+            // folds
+            Action::ToggleFold => self.edit_fold(Folds::toggle),
+            Action::OpenFold => self.edit_fold(Folds::open),
+            Action::CloseFold => self.edit_fold(Folds::close),
+            Action::OpenAllFolds => self.set_all_folds(false),
+            Action::CloseAllFolds => self.set_all_folds(true),
+
+            // `.` and movements are run before, with the count as typed
+            _ => {}
+        }
+    }
+
+    /// Runs `action` as a movement `count` times, `G` and `gg` go to line
+    /// `count` instead. Returns `false` if it is not a movement.
+    ///
+    /// Big jumps are kept in the jump list.
+    fn process_counted_movement(
+        &mut self,
+        action: Action,
+        args: &[Key],
+        count: Option<usize>,
+    ) -> bool {
+        let is_jump = matches!(
+            action,
+            Action::FirstLine
+                | Action::LastLine
+                | Action::MatchingBracket
+                | Action::SearchNext
+                | Action::SearchPrevious
+                | Action::SearchWordForward
+                | Action::SearchWordBackward
+                | Action::MarkLine
+                | Action::Mark
+        );
+        let origin = self.jump_origin();
+
+        let moved = self.run_counted_movement(action, args, count);
+        if moved && is_jump {
+            self.finish_jump(origin);
+        }
+
+        moved
+    }
+
+    fn run_counted_movement(&mut self, action: Action, args: &[Key], count: Option<usize>) -> bool {
+        if let (Some(count), Action::FirstLine | Action::LastLine) = (count, action) {
+            self.go_to_line(count.saturating_sub(1));
+            return true;
+        }
+
+        // `50%` goes to the middle of the document, past `100%` is an error
+        if let (Some(count), Action::MatchingBracket) = (count, action) {
+            if count > 100 {
+                self.fail_macro();
+                return false;
+            }
+            let lines = self.read_document(Document::lines_len).unwrap_or(0);
+            self.go_to_line((count * lines).div_ceil(100).saturating_sub(1));
+            return true;
+        }
+
+        // `3$` goes to the end of the line two lines down
+        if let (Some(count), Action::LineEnd) = (count, action) {
+            self.move_down(count - 1);
+            self.go_to_last_line_char();
+            return true;
+        }
+
+        for i in 0..count.unwrap_or(1) {
+            let before = self.document_position();
+            if !self.process_movement(action, args) {
+                return i > 0;
+            }
+
+            // stuck against the edge of the document
+            if action.must_move() && before == self.document_position() {
+                self.fail_macro();
+                break;
+            }
+        }
+
+        true
+    }
+
+    /// Runs `action` as a movement, returns `false` if it is not one.
+    fn process_movement(&mut self, action: Action, args: &[Key]) -> bool {
+        match action {
+            // marks
+            Action::MarkLine | Action::Mark => {
+                let [Key::Char(name)] = *args else {
+                    return false;
+                };
+                self.go_to_mark(name, action == Action::MarkLine);
+            }
+
+            // viewport
+            Action::AlignCenter => self.align_cursor_line(&Alignment::Center),
+            Action::AlignTop => self.align_cursor_line(&Alignment::Top),
+            Action::AlignBottom => self.align_cursor_line(&Alignment::Bottom),
+
+            // folds
+            Action::NextFold => self.move_to_fold(Folds::next_start),
+            Action::PreviousFold => self.move_to_fold(Folds::previous_end),
+
+            // screen lines
+            Action::ScreenLineDown => self.move_display_line(&Direction::Down, 1),
+            Action::ScreenLineUp => self.move_display_line(&Direction::Up, 1),
+
+            // basic moves
+            Action::Left => self.move_left(1),
+            Action::Down => self.move_down(1),
+            Action::Up => self.move_up(1),
+            Action::Right => self.move_right(1),
+
+            // inside the line
+            Action::LineStart => self.go_to_line_start(),
+            Action::FirstNonBlank => self.go_to_first_non_blank(),
+            Action::LineEnd => self.go_to_last_line_char(),
+
+            // words
+            Action::WordForward => self.move_to(Document::next_word_start),
+            Action::WordBackward => self.move_to(Document::previous_word_start),
+            Action::WordEnd => self.move_to(Document::word_end),
+
+            // big jumps
+            Action::FirstLine => self.go_to_line(0),
+            Action::LastLine => self.go_to_line(usize::MAX),
+            Action::MatchingBracket => self.go_to_matching_bracket(),
+
+            // search
+            Action::SearchNext => self.search_next(false),
+            Action::SearchPrevious => self.search_next(true),
+            Action::SearchWordForward => self.search_word_under_cursor(SearchDirection::Forward),
+            Action::SearchWordBackward => self.search_word_under_cursor(SearchDirection::Backward),
+            Action::HalfPageDown => self.move_down_half_screen(),
+            Action::HalfPageUp => self.move_up_half_screen(),
+            Action::PageDown => self.scroll_page(&Direction::Down),
+            Action::PageUp => self.scroll_page(&Direction::Up),
+            Action::ScrollDown => self.scroll_view(&Direction::Down, 1),
+            Action::ScrollUp => self.scroll_view(&Direction::Up, 1),
+
+            _ => return false,
+        }
+
+        true
+    }
+
+    //-------------- Movement --------------//
+
+    pub fn move_down(&mut self, length: usize) {
+        self.move_vertically(&Direction::Down, length);
     }
 
     pub fn move_up(&mut self, length: usize) {
-        let next_y: isize = self.cursor_position.y as isize - length as isize;
+        self.move_vertically(&Direction::Up, length);
+    }
 
-        if next_y < 0 {
-            self.scroll_attached_window(&Direction::Up, length);
-        } else {
-            self.cursor_position = Position {
-                x: self.cursor_position.x,
-                y: next_y as usize,
+    /// Moves `length` rows up or down, a closed fold being a single row.
+    fn move_vertically(&mut self, direction: &Direction, length: usize) {
+        let Some(pos) = self.document_position() else {
+            return;
+        };
+        let y = {
+            let windows = self.borrow_attached_windows();
+            let Some(window) = self.attached_window.and_then(|index| windows.get(index)) else {
+                return;
             };
-        }
+            window.step_lines(pos.y, direction, length)
+        };
 
-        self.correct_x();
+        self.set_document_position(Position { x: pos.x, y });
     }
 
     pub fn move_right(&mut self, length: usize) {
-        let next_pos = Position {
-            x: self.cursor_position.x + length,
-            y: self.cursor_position.y,
+        let Some(pos) = self.document_position() else {
+            return;
         };
 
-        let window_width = self.borrow_attached_windows()[self.attached_window.unwrap()]
-            .size()
-            .width as usize;
+        let x = self
+            .read_document(|doc| doc.row(pos.y).map(|row| row.next_char_index(pos.x, length)))
+            .flatten()
+            .unwrap_or(pos.x);
+        self.set_document_position(Position { x, y: pos.y });
+    }
 
-        let current_line_len = match self.current_line_len() {
-            Some(l) => l,
-            // if none means window is wrong
-            None => panic!("document_row does not exists"),
+    pub fn move_left(&mut self, length: usize) {
+        let Some(pos) = self.document_position() else {
+            return;
         };
 
-        if current_line_len < next_pos.x {
+        let x = self
+            .read_document(|doc| {
+                doc.row(pos.y)
+                    .map(|row| row.previous_char_index(pos.x, length))
+            })
+            .flatten()
+            .unwrap_or(pos.x);
+        self.set_document_position(Position { x, y: pos.y });
+    }
+
+    /// Moves by screen lines instead of document lines, they only differ
+    /// when rows are wrapped.
+    pub fn move_display_line(&mut self, direction: &Direction, length: usize) {
+        let Some(pos) = self.document_position() else {
             return;
-        }
+        };
 
-        if next_pos.x > window_width {
-            self.scroll_attached_window(&Direction::Right, length);
+        let Some(index) = self.attached_window else {
             return;
+        };
+
+        let next_pos =
+            self.borrow_attached_windows()[index].move_display_line(&pos, direction, length);
+
+        self.set_document_position(next_pos);
+    }
+
+    pub fn move_up_half_screen(&mut self) {
+        if let Some(steps) = self.scroll_lines() {
+            self.move_up(steps);
         }
+    }
 
-        self.cursor_position = next_pos;
+    pub fn move_down_half_screen(&mut self) {
+        if let Some(steps) = self.scroll_lines() {
+            self.move_down(steps);
+        }
     }
 
-    pub fn move_left(&mut self, length: usize) {
-        let next_x: isize = self.cursor_position.x as isize + length as isize;
+    /// Lines `Ctrl-u` and `Ctrl-d` move, from the `scroll` option.
+    fn scroll_lines(&self) -> Option<usize> {
+        let windows = self.borrow_attached_windows();
+        let window = windows.get(self.attached_window?)?;
 
-        if next_x < 0 {
-            self.scroll_attached_window(&Direction::Left, 1);
+        Some(match window.options().scroll {
+            0 => (window.size().height / 2) as usize,
+            lines => lines,
+        })
+    }
+
+    pub fn move_to(&mut self, motion: impl FnOnce(&Document, &Position) -> Position) {
+        let Some(pos) = self.document_position() else {
             return;
-        } else {
-            self.cursor_position = Position {
-                x: next_x as usize,
-                y: self.cursor_position.y,
-            }
+        };
+
+        if let Some(next_pos) = self.read_document(|doc| motion(doc, &pos)) {
+            self.set_document_position(next_pos);
         }
     }
 
-    pub fn move_up_half_screen(&mut self) {
-        let steps = (self.windows.borrow()[self.attached_window.unwrap()]
-            .size()
-            .height as f32
-            * 0.7) as usize;
+    /// Goes to the first non blank char of `line`, past the end goes to the
+    /// last line.
+    pub fn go_to_line(&mut self, line: usize) {
+        let Some(last_line) = self.read_document(|doc| doc.lines_len().saturating_sub(1)) else {
+            return;
+        };
 
-        self.move_up(steps);
+        self.set_document_position(Position {
+            x: 0,
+            y: line.min(last_line),
+        });
+        self.go_to_first_non_blank();
     }
 
-    pub fn move_down_half_screen(&mut self) {
-        let steps = (self.windows.borrow()[self.attached_window.unwrap()]
-            .size()
-            .height
-            / 2) as usize;
+    /// `%` goes to the bracket matching the one under the cursor, or the
+    /// next one on the line.
+    pub fn go_to_matching_bracket(&mut self) {
+        let Some(pos) = self.document_position() else {
+            return;
+        };
 
-        self.move_down(steps);
+        match self
+            .read_document(|doc| doc.matching_bracket(&pos))
+            .flatten()
+        {
+            Some(matching) => self.set_document_position(matching),
+            None => self.fail_macro(),
+        }
     }
 
-    pub fn correct_x(&mut self) {
-        let line_len = match self.current_line_len() {
-            Some(l) => l,
-            None => return,
+    pub fn go_to_line_start(&mut self) {
+        let Some(pos) = self.document_position() else {
+            return;
         };
 
-        if !(line_len < self.cursor_position.x) {
+        self.set_document_position(Position { x: 0, y: pos.y });
+    }
+
+    pub fn go_to_first_non_blank(&mut self) {
+        let Some(pos) = self.document_position() else {
             return;
-        }
+        };
+
+        let x = self
+            .read_document(|doc| doc.row(pos.y).map(Row::first_non_blank))
+            .flatten()
+            .unwrap_or_default();
 
-        self.go_to_last_line_char();
+        self.set_document_position(Position { x, y: pos.y });
     }
 
     pub fn go_to_last_line_char(&mut self) {
-        let window_index = match self.attached_window {
-            Some(index) => index,
-            None => return,
+        let Some(pos) = self.document_position() else {
+            return;
         };
 
-        let window_x_pos = self.borrow_attached_windows()[window_index].position().x;
+        let line_len = self.current_line_len().unwrap_or_default();
 
-        // get diffrence
-        let correction_len: isize = self.current_line_len().unwrap() as isize
-            - self.cursor_position.x as isize
-            + window_x_pos as isize;
+        self.set_document_position(Position {
+            x: self.max_column(line_len),
+            y: pos.y,
+        });
+    }
 
-        if correction_len > 0 {
-            return self.move_right(correction_len as usize);
-        } else {
-            return self.move_left(correction_len as usize);
+    //-------------- Viewport --------------//
+
+    /// Scrolls the attached window so the cursor line sits at the top, center
+    /// or bottom of it (`zt`, `zz`, `zb`).
+    pub fn align_cursor_line(&mut self, alignment: &Alignment) {
+        let Some(pos) = self.document_position() else {
+            return;
+        };
+
+        if let Some(index) = self.attached_window {
+            self.borrow_mut_windows()[index].align(pos.y, alignment);
         }
+
+        self.set_document_position(pos);
     }
+
+    /// Scrolls the attached window without moving the cursor, unless it would
+    /// leave the view (`Ctrl-e`, `Ctrl-y`).
+    pub fn scroll_view(&mut self, direction: &Direction, length: usize) {
+        let Some(pos) = self.document_position() else {
+            return;
+        };
+
+        let Some(index) = self.attached_window else {
+            return;
+        };
+
+        self.scroll_attached_window(direction, length);
+        let line = self.borrow_attached_windows()[index].clamp_line_to_view(pos.y);
+
+        self.set_document_position(Position { x: pos.x, y: line });
+    }
+
+    /// Scrolls a whole page keeping two lines of context (`Ctrl-f`, `Ctrl-b`).
+    pub fn scroll_page(&mut self, direction: &Direction) {
+        let Some(index) = self.attached_window else {
+            return;
+        };
+
+        let height = self.borrow_attached_windows()[index].size().height as usize;
+
+        self.scroll_view(direction, height.saturating_sub(2).max(1));
+    }
+}
+
+/// Splits the count at the start of `keys`, a `0` alone being a motion and
+/// not a count.
+fn split_count(keys: &[Key]) -> (Option<usize>, &[Key]) {
+    let digits = keys
+        .iter()
+        .take_while(|key| matches!(key, Key::Char('0'..='9')))
+        .count();
+    if digits == 0 || keys[0] == Key::Char('0') {
+        return (None, keys);
+    }
+
+    let count = keys[..digits]
+        .iter()
+        .filter_map(|key| match key {
+            Key::Char(digit) => digit.to_digit(10),
+            _ => None,
+        })
+        .fold(0usize, |count, digit| {
+            count.saturating_mul(10).saturating_add(digit as usize)
+        });
+
+    (Some(count), &keys[digits..])
 }
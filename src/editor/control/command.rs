@@ -0,0 +1,170 @@
+use termion::event::Key;
+
+use crate::{
+    document::SearchDirection,
+    editor::{
+        control::{mapping, range::LineRange},
+        Control, Mode, Selection, SelectionKind,
+    },
+    utils::Position,
+};
+
+impl Control<'_> {
+    pub fn process_key_in_command_mode(&mut self, key: Key) {
+        match key {
+            Key::Esc => self.exit_command_mode(),
+            Key::Char('\n') => {
+                let command = std::mem::take(&mut self.command_line);
+                self.exit_command_mode();
+                self.execute_command(&command);
+            }
+            Key::Char(c) => self.command_line.push(c),
+            // deleting past the `:` leaves command mode
+            Key::Backspace if self.command_line.is_empty() => self.exit_command_mode(),
+            Key::Backspace => {
+                self.command_line.pop();
+            }
+            _ => {}
+        }
+    }
+
+    pub fn enter_command_mode(&mut self) {
+        self.command_line.clear();
+        self.mode = Mode::Command;
+    }
+
+    /// `:` in visual mode, the command applies to the selected lines.
+    pub fn enter_command_mode_on_selection(&mut self) {
+        self.exit_visual_mode();
+        self.enter_command_mode();
+        self.command_line.push_str("'<,'>");
+    }
+
+    fn exit_command_mode(&mut self) {
+        self.command_line.clear();
+        self.mode = Mode::Normal;
+    }
+
+    /// What is being typed on the last line after its prompt, `:`, `/` or
+    /// `?`. `None` outside of command and search modes.
+    pub fn command_line(&self) -> Option<String> {
+        let prompt = match self.mode {
+            Mode::Command => ':',
+            Mode::Search(SearchDirection::Forward) => '/',
+            Mode::Search(SearchDirection::Backward) => '?',
+            _ => return None,
+        };
+
+        Some(format!("{prompt}{}", self.command_line))
+    }
+
+    pub fn should_quit(&self) -> bool {
+        self.should_quit
+    }
+
+    /// Runs a command line, what is typed after `:`.
+    pub fn execute_command(&mut self, command: &str) {
+        let command = command.trim();
+        if command.is_empty() {
+            return;
+        }
+
+        self.registers.set_last_command(command.to_string());
+
+        let (range, rest) = match self.parse_range(command) {
+            Ok(parsed) => parsed,
+            Err(message) => {
+                self.error = Some(message);
+                return;
+            }
+        };
+
+        let rest = rest.trim_start();
+        let name_len = rest
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(rest.len());
+        let (name, args) = rest.split_at(name_len);
+        let args = args.trim_start();
+        let current_line = LineRange::line(self.document_position().map_or(0, |pos| pos.y));
+
+        match name {
+            // a range alone moves to its last line
+            "" => {
+                if let Some(range) = range {
+                    let origin = self.jump_origin();
+                    self.go_to_line(range.end);
+                    self.go_to_first_non_blank();
+                    self.finish_jump(origin);
+                }
+            }
+            "s" | "substitute" => self.substitute(range.unwrap_or(current_line), args),
+            "g" | "global" if args.starts_with('!') => self.global(range, &args[1..], true),
+            "g" | "global" => self.global(range, args, false),
+            "v" | "vglobal" => self.global(range, args, true),
+            "norm" | "normal" => self.normal(range, args),
+            "d" | "delete" => self.delete_lines(range.unwrap_or(current_line), args),
+            "y" | "yank" => self.yank_lines(range.unwrap_or(current_line), args),
+            _ if range.is_some() => self.error = Some(String::from("No range allowed")),
+            "q" | "quit" => self.should_quit = true,
+            "se" | "set" => self.set_options(args, false),
+            "setl" | "setlocal" => self.set_options(args, true),
+            "noh" | "nohlsearch" => self.stop_search_highlight(),
+            "marks" => self.list_marks(),
+            "colo" | "colorscheme" => self.set_color_scheme(args),
+            _ if mapping::map_command_modes(name).is_some() => self.map_command(name, args),
+            _ if mapping::unmap_command_modes(name).is_some() => self.unmap_command(name, args),
+            "reg" | "registers" | "di" | "display" => {
+                let names: String = args.split_whitespace().collect();
+                self.list_registers(&names);
+            }
+            _ => self.error = Some(format!("Not an editor command: {command}")),
+        }
+    }
+
+    /// `:delete x`, the lines of `range` go to register `x` or the default
+    /// ones.
+    fn delete_lines(&mut self, range: LineRange, args: &str) {
+        self.register_name = args.chars().next();
+        self.delete_text(&Self::line_selection(range));
+        self.register_name = None;
+    }
+
+    /// `:yank x`
+    fn yank_lines(&mut self, range: LineRange, args: &str) {
+        let Some(pos) = self.document_position() else {
+            return;
+        };
+
+        self.register_name = args.chars().next();
+        self.yank_text(&Self::line_selection(range));
+        self.register_name = None;
+
+        // unlike `y`, the cursor stays where it is
+        self.set_document_position(pos);
+    }
+
+    fn line_selection(range: LineRange) -> Selection {
+        Selection {
+            anchor: Position {
+                x: 0,
+                y: range.start,
+            },
+            cursor: Position { x: 0, y: range.end },
+            kind: SelectionKind::Line,
+        }
+    }
+
+    /// `:colorscheme name` draws with theme `name`, `:colorscheme` tells the
+    /// one in use.
+    fn set_color_scheme(&mut self, name: &str) {
+        if name.is_empty() {
+            self.message = Some(self.themes.current().name().to_string());
+        } else if !self.themes.select(name) {
+            let known: Vec<&str> = self.themes.names().collect();
+            self.error = Some(format!(
+                "Cannot find color scheme '{name}', known ones: {}",
+                known.join(", ")
+            ));
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use std::cmp;
+
+use crate::{
+    editor::{
+        fold::{FoldMethod, Folds},
+        Control, Selection, Window,
+    },
+    utils::{Direction, Position},
+};
+
+impl Control<'_> {
+    /// Runs `f` over the folds of the attached window and its cursor line.
+    /// The cursor stays on the row showing that line.
+    fn with_folds<T>(&mut self, f: impl FnOnce(&mut Folds, usize) -> T) -> Option<T> {
+        let pos = self.document_position()?;
+        let result = {
+            let mut windows = self.borrow_mut_windows();
+            let window = windows.get_mut(self.attached_window?)?;
+            f(window.folds_mut(), pos.y)
+        };
+
+        self.set_document_position(pos);
+        Some(result)
+    }
+
+    /// `za`, `zo` and `zc` run `f` on the fold under the cursor.
+    pub fn edit_fold(&mut self, f: impl FnOnce(&mut Folds, usize) -> bool) {
+        if self.with_folds(f) == Some(false) {
+            self.error = Some(String::from("No fold found"));
+        }
+    }
+
+    /// `zR` opens every fold and `zM` closes them.
+    pub fn set_all_folds(&mut self, closed: bool) {
+        self.with_folds(|folds, _| folds.set_all(closed));
+    }
+
+    /// `zj` and `zk` go to the line `f` finds from the cursor line, the
+    /// column is kept.
+    pub fn move_to_fold(&mut self, f: impl FnOnce(&Folds, usize) -> Option<usize>) {
+        let Some(pos) = self.document_position() else {
+            return;
+        };
+
+        let line = self
+            .attached_window
+            .and_then(|index| f(self.borrow_attached_windows().get(index)?.folds(), pos.y));
+        if let Some(y) = line {
+            self.set_document_position(Position { x: pos.x, y });
+        }
+    }
+
+    /// Lines `start` and `end` moved out to the first and last rows of the
+    /// closed folds holding them, linewise operators take whole folds.
+    pub fn closed_fold_bounds(&self, start: usize, end: usize) -> (usize, usize) {
+        let windows = self.borrow_attached_windows();
+        let Some(folds) = self
+            .attached_window
+            .and_then(|index| windows.get(index))
+            .map(Window::folds)
+        else {
+            return (start, end);
+        };
+
+        (
+            folds.closed_at(start).map_or(start, |fold| fold.start),
+            folds.closed_at(end).map_or(end, |fold| fold.end),
+        )
+    }
+
+    /// Last line of the `count` rows shown from `line` on, a closed fold
+    /// being a single row.
+    pub fn last_shown_line(&self, line: usize, count: usize) -> usize {
+        let windows = self.borrow_attached_windows();
+        let last = self
+            .attached_window
+            .and_then(|index| windows.get(index))
+            .map(|window| window.step_lines(line, &Direction::Down, count - 1));
+        let last_line = self
+            .read_document(|doc| doc.lines_len().saturating_sub(1))
+            .unwrap_or(0);
+
+        cmp::min(last.unwrap_or(line + count - 1), last_line)
+    }
+
+    /// `zf` folds the selected rows, only when folds are made by hand.
+    pub fn create_fold(&mut self, selection: &Selection) {
+        let (start, end) = (selection.start(), selection.end());
+
+        let manual = self.attached_window.and_then(|index| {
+            let windows = self.borrow_attached_windows();
+            let method = &windows.get(index)?.options().foldmethod;
+            Some(FoldMethod::from_name(method) == Some(FoldMethod::Manual))
+        });
+        if manual == Some(false) {
+            self.error = Some(String::from("Cannot create fold with current 'foldmethod'"));
+            return;
+        }
+
+        self.with_folds(|folds, _| folds.add(start.y, end.y));
+        self.set_document_position(start);
+    }
+}
@@ -0,0 +1,177 @@
+use termion::event::Key;
+
+use crate::{
+    document::{Document, Pattern},
+    editor::{
+        control::{range::LineRange, substitute::split_delimited},
+        Control, Mode,
+    },
+};
+
+impl Control<'_> {
+    /// `:g/pattern/command` runs `command` on every line of `range` matching
+    /// `pattern`, `:v` (`invert`) on the lines not matching it.
+    ///
+    /// The lines are marked first and run one by one, so the command can
+    /// delete or insert lines without the others being lost.
+    pub fn global(&mut self, range: Option<LineRange>, args: &str, invert: bool) {
+        if self.global_busy {
+            self.error = Some(String::from("Cannot do :global recursively"));
+            return;
+        }
+
+        let mut chars = args.chars();
+        let Some(delimiter) = chars
+            .next()
+            .filter(|c| !c.is_alphanumeric() && !matches!(c, '\\' | '"' | '|' | ' '))
+        else {
+            self.error = Some(String::from("Usage: :g/pattern/command"));
+            return;
+        };
+
+        let (text, command) = split_delimited(chars.as_str(), delimiter);
+        let command = command.unwrap_or("").trim();
+
+        let pattern = if text.is_empty() {
+            self.last_search_pattern()
+                .ok_or_else(|| String::from("No previous search pattern"))
+        } else {
+            Pattern::new(&text).map_err(|_| format!("Invalid pattern: {text}"))
+        };
+        let pattern = match pattern {
+            Ok(pattern) => pattern,
+            Err(message) => {
+                self.error = Some(message);
+                return;
+            }
+        };
+
+        let last_line = self
+            .read_document(|doc| doc.lines_len().saturating_sub(1))
+            .unwrap_or(0);
+        let range = range.unwrap_or(LineRange {
+            start: 0,
+            end: last_line,
+        });
+
+        let marked = self
+            .edit_document(|doc| {
+                doc.clear_marks();
+                doc.mark_rows(range.start..range.end + 1, |row| {
+                    pattern.find_at(&row.content, 0).is_some() != invert
+                })
+            })
+            .unwrap_or(0);
+        self.remember_search(pattern.clone());
+
+        if marked == 0 {
+            self.error = Some(if invert {
+                format!("Pattern found in every line: {}", pattern.text)
+            } else {
+                format!("Pattern not found: {}", pattern.text)
+            });
+            return;
+        }
+
+        // without a command the lines are listed
+        if command.is_empty() {
+            self.list_marked_lines();
+            return;
+        }
+
+        self.global_busy = true;
+        while let Some(line) = self.take_marked_row() {
+            self.go_to_line(line);
+            self.execute_command(command);
+
+            if self.should_quit {
+                break;
+            }
+        }
+        self.global_busy = false;
+
+        self.edit_document(Document::clear_marks);
+    }
+
+    /// Unmarks the next marked row of the attached document and returns its
+    /// line. No text changes, so the folds are not made again.
+    fn take_marked_row(&self) -> Option<usize> {
+        let mut windows = self.borrow_mut_windows();
+        windows
+            .get_mut(self.attached_window?)?
+            .document_mut()
+            .take_marked_row()
+    }
+
+    fn list_marked_lines(&mut self) {
+        let mut lines = Vec::new();
+        while let Some(line) = self.take_marked_row() {
+            if let Some(content) = self
+                .read_document(|doc| doc.row(line).map(|row| row.content.clone()))
+                .flatten()
+            {
+                lines.push(format!("{:>3} {content}", line + 1));
+            }
+        }
+
+        self.message = Some(lines.join("\n"));
+    }
+
+    /// `:normal keys` types `keys` in normal mode on every line of `range`,
+    /// from the start of the line, or once where the cursor is.
+    pub fn normal(&mut self, range: Option<LineRange>, keys: &str) {
+        let keys: Vec<Key> = keys.chars().map(Key::Char).collect();
+        let Some(range) = range else {
+            self.replay_keys(&keys);
+            return;
+        };
+
+        // under `:global` the lines are already marked, they are only
+        // counted here
+        if self.global_busy {
+            for line in range.start..=range.end {
+                if self
+                    .read_document(|doc| line >= doc.lines_len())
+                    .unwrap_or(true)
+                {
+                    break;
+                }
+
+                self.go_to_line(line);
+                self.go_to_line_start();
+                self.replay_keys(&keys);
+            }
+            return;
+        }
+
+        self.edit_document(|doc| {
+            doc.clear_marks();
+            doc.mark_rows(range.start..range.end + 1, |_| true);
+        });
+        while let Some(line) = self.take_marked_row() {
+            self.go_to_line(line);
+            self.go_to_line_start();
+            self.replay_keys(&keys);
+        }
+    }
+
+    /// Runs `keys` as if they were typed in normal mode, leaving any mode
+    /// they started as `Esc` would. The changes are undone with the command
+    /// that replayed them.
+    pub fn replay_keys(&mut self, keys: &[Key]) {
+        self.replaying += 1;
+
+        for key in keys {
+            self.handle_key(*key);
+        }
+
+        // unfinished commands are dropped
+        self.pending_keys.clear();
+        self.register_name = None;
+        if !matches!(self.mode, Mode::Normal) {
+            self.handle_key(Key::Esc);
+        }
+
+        self.replaying -= 1;
+    }
+}
@@ -0,0 +1,294 @@
+use termion::event::Key;
+
+use crate::{
+    document::PairEdit,
+    editor::{
+        keymap::{Action, MapMode},
+        Control, Mode,
+    },
+    utils::{Direction, Position},
+};
+
+/// Text typed on the first row of a block is repeated on the other rows when
+/// leaving insert mode (`I` and `A` in visual block mode).
+pub struct BlockInsert {
+    pub top: usize,
+    pub bottom: usize,
+    pub column: usize,
+    /// rows shorter than `column` are padded with spaces instead of skipped
+    pub pad: bool,
+}
+
+impl Control<'_> {
+    pub fn process_key_in_insert_mode(&mut self, key: Key) {
+        match self.keymap.action(MapMode::Insert, &[key]) {
+            Some(Action::ExitInsert) => self.exit_insert_mode(),
+            Some(Action::NewLine) => {
+                self.inserted_text.push('\n');
+                self.insert_newline();
+            }
+            Some(Action::InsertTab) => {
+                self.inserted_text.push('\t');
+                self.insert_tab();
+            }
+            Some(Action::Backspace) => {
+                self.inserted_text.pop();
+                self.delete_pair_or_char();
+            }
+            Some(Action::DeleteChar) => self.delete_char_under_cursor(),
+            Some(Action::Left) => self.move_left(1),
+            Some(Action::Right) => self.move_right(1),
+            Some(Action::Up) => self.move_up(1),
+            Some(Action::Down) => self.move_down(1),
+            Some(_) => {}
+            // keys bound to nothing type their char
+            None => {
+                if let Key::Char(c) = key {
+                    self.inserted_text.push(c);
+                    self.type_char(c);
+                }
+            }
+        }
+    }
+
+    pub fn enter_insert_mode(&mut self) {
+        self.inserted_text.clear();
+        self.mode = Mode::Insert;
+    }
+
+    pub fn exit_insert_mode(&mut self) {
+        let inserted_text = std::mem::take(&mut self.inserted_text);
+        self.registers.set_last_insert(inserted_text);
+        self.finish_block_insert();
+        if let Some(pos) = self.document_position() {
+            self.edit_document(|doc| doc.set_mark('^', pos));
+        }
+        self.mode = Mode::Normal;
+
+        // back onto the last inserted char, like vim
+        self.move_left(1);
+    }
+
+    pub fn append(&mut self) {
+        self.enter_insert_mode();
+        self.move_right(1);
+    }
+
+    pub fn insert_at_line_start(&mut self) {
+        self.enter_insert_mode();
+        self.go_to_first_non_blank();
+    }
+
+    pub fn append_at_line_end(&mut self) {
+        self.enter_insert_mode();
+        self.go_to_last_line_char();
+    }
+
+    /// Opens a row below or above the cursor, indented as Enter would, and
+    /// starts inserting there (`o`, `O`).
+    pub fn open_line(&mut self, direction: &Direction) {
+        let Some(pos) = self.document_position() else {
+            return;
+        };
+
+        let above = matches!(direction, Direction::Up);
+        let Some(next_pos) = self.edit_document(|doc| doc.open_row(pos.y, above)) else {
+            return;
+        };
+        self.enter_insert_mode();
+        self.set_document_position(next_pos);
+    }
+
+    pub fn insert_char(&mut self, c: char) {
+        let Some(pos) = self.document_position() else {
+            return;
+        };
+
+        self.edit_document(|doc| doc.insert_char(&pos, c));
+        self.set_document_position(Position {
+            x: pos.x + c.len_utf8(),
+            y: pos.y,
+        });
+    }
+
+    /// Tab inserts a tab, or spaces up to the next level of indentation
+    /// with `expandtab`.
+    pub fn insert_tab(&mut self) {
+        let Some(pos) = self.document_position() else {
+            return;
+        };
+        let Some((options, column)) = self.read_document(|doc| {
+            let column = doc
+                .row(pos.y)
+                .map_or(0, |row| row.slice(0..pos.x).chars().count());
+            (doc.options().clone(), column)
+        }) else {
+            return;
+        };
+
+        if !options.expandtab {
+            self.insert_char('\t');
+            return;
+        }
+        let spaces = " ".repeat(options.shiftwidth - column % options.shiftwidth);
+        if let Some(end) = self.edit_document(|doc| doc.insert_text(&pos, &[spaces])) {
+            self.set_document_position(end);
+        }
+    }
+
+    /// Inserts text at the cursor as it is, the cursor ends up after it.
+    pub fn insert_pasted_text(&mut self, text: &str) {
+        let Some(pos) = self.document_position() else {
+            return;
+        };
+
+        let lines: Vec<String> = text.split('\n').map(String::from).collect();
+        if let Some(end) = self.edit_document(|doc| doc.insert_text(&pos, &lines)) {
+            self.set_document_position(end);
+        }
+
+        self.inserted_text.push_str(text);
+    }
+
+    /// Enter, the new row is indented from the one it was split from.
+    pub fn insert_newline(&mut self) {
+        let Some(pos) = self.document_position() else {
+            return;
+        };
+
+        if let Some(next_pos) = self.edit_document(|doc| doc.split_row(&pos)) {
+            self.set_document_position(next_pos);
+        }
+    }
+
+    /// A char typed in insert mode, with the closing char of a pair it
+    /// opens, or over the closing char already there.
+    fn type_char(&mut self, c: char) {
+        let Some(pos) = self.document_position() else {
+            return;
+        };
+
+        match self.read_document(|doc| doc.typed_pair(&pos, c)) {
+            Some(PairEdit::StepOver) => self.move_right(1),
+            Some(PairEdit::Close(close)) => {
+                self.edit_document(|doc| doc.insert_text(&pos, &[format!("{c}{close}")]));
+                self.set_document_position(Position {
+                    x: pos.x + c.len_utf8(),
+                    y: pos.y,
+                });
+            }
+            _ => {
+                self.insert_char(c);
+                self.reindent_typed();
+            }
+        }
+    }
+
+    /// Backspace, deleting both chars of an empty pair.
+    fn delete_pair_or_char(&mut self) {
+        let Some(pos) = self.document_position() else {
+            return;
+        };
+
+        if self.read_document(|doc| doc.is_in_empty_pair(&pos)) == Some(true) {
+            self.edit_document(|doc| doc.remove_char(&pos));
+        }
+        self.delete_char_before_cursor();
+    }
+
+    /// Dedents the row once a char typed closes a block.
+    fn reindent_typed(&mut self) {
+        let Some(pos) = self.document_position() else {
+            return;
+        };
+
+        if let Some(next_pos) = self.edit_document(|doc| doc.reindent_typed(&pos)) {
+            self.set_document_position(next_pos);
+        }
+    }
+
+    /// Backspace, at the start of a row it is joined to the one above.
+    pub fn delete_char_before_cursor(&mut self) {
+        let Some(pos) = self.document_position() else {
+            return;
+        };
+
+        let next_pos = self.edit_document(|doc| {
+            let previous = doc.previous_position(&pos)?;
+            doc.delete_range(&previous, &pos);
+            Some(previous)
+        });
+
+        if let Some(Some(next_pos)) = next_pos {
+            self.set_document_position(next_pos);
+        }
+    }
+
+    /// Delete, at the end of a row the next one is joined to it.
+    pub fn delete_char_under_cursor(&mut self) {
+        let Some(pos) = self.document_position() else {
+            return;
+        };
+
+        self.edit_document(|doc| {
+            let line_len = doc.row(pos.y).map_or(0, |row| row.content.len());
+
+            if pos.x < line_len {
+                doc.remove_char(&pos);
+            } else if let Some(next) = doc.next_position(&pos) {
+                doc.delete_range(&pos, &next);
+            }
+        });
+        self.set_document_position(pos);
+    }
+
+    pub fn start_block_insert(&mut self, block_insert: BlockInsert) {
+        let pos = Position {
+            x: block_insert.column,
+            y: block_insert.top,
+        };
+
+        self.block_insert = Some(block_insert);
+        self.enter_insert_mode();
+        self.set_document_position(pos);
+    }
+
+    fn finish_block_insert(&mut self) {
+        let Some(block) = self.block_insert.take() else {
+            return;
+        };
+
+        let Some(pos) = self.document_position() else {
+            return;
+        };
+
+        // only what was typed on the first row, without leaving it, is repeated
+        if pos.y != block.top || pos.x < block.column {
+            return;
+        }
+
+        self.edit_document(|doc| {
+            let text = doc
+                .row(block.top)
+                .map(|row| row.slice(block.column..pos.x).to_string())
+                .unwrap_or_default();
+
+            for y in block.top + 1..=block.bottom {
+                let line_len = doc.row(y).map_or(0, |row| row.content.len());
+                if line_len < block.column {
+                    if !block.pad {
+                        continue;
+                    }
+
+                    let padding = " ".repeat(block.column - line_len);
+                    doc.insert_text(&Position { x: line_len, y }, &[padding]);
+                }
+
+                doc.insert_text(
+                    &Position { x: block.column, y },
+                    std::slice::from_ref(&text),
+                );
+            }
+        });
+    }
+}
@@ -0,0 +1,135 @@
+use std::collections::VecDeque;
+
+use termion::event::Key;
+
+use crate::editor::{keys, Control, SelectionKind};
+
+/// Keys the macros played at once can type before they are stopped, a
+/// macro playing itself may never fail.
+const MAX_PLAYED_KEYS: usize = 100_000;
+
+/// Macros recorded with `q` and played with `@`.
+#[derive(Default)]
+pub struct MacroState {
+    /// register the typed keys are recorded into
+    recording: Option<char>,
+    recorded: Vec<Key>,
+    /// register played again by `@@`
+    last: Option<char>,
+    /// keys of the macros being played, the next one first
+    queue: VecDeque<Key>,
+    playing: bool,
+    /// a motion failed while playing, the macros stop
+    failed: bool,
+}
+
+impl Control<'_> {
+    /// Register a macro is being recorded into.
+    pub fn recording(&self) -> Option<char> {
+        self.macros.recording
+    }
+
+    /// Keeps `key` in the macro being recorded, unless it was not typed but
+    /// played by a macro or `:normal`.
+    pub fn record_key(&mut self, key: Key) {
+        if self.macros.recording.is_some() && !self.macros.playing && self.replaying == 0 {
+            self.macros.recorded.push(key);
+        }
+    }
+
+    /// `q{register}`, an uppercase register appends to the lowercase one.
+    pub fn start_recording(&mut self, name: char) {
+        if !name.is_ascii_alphanumeric() && name != '"' {
+            self.error = Some(format!("Invalid register name: '{name}'"));
+            return;
+        }
+
+        self.macros.recording = Some(name);
+        self.macros.recorded.clear();
+    }
+
+    /// `q` while recording, the keys are kept as text in the register.
+    pub fn stop_recording(&mut self) {
+        let Some(name) = self.macros.recording.take() else {
+            return;
+        };
+
+        let mut recorded = std::mem::take(&mut self.macros.recorded);
+        // the `q` stopping the recording
+        recorded.pop();
+        self.registers.record(name, &keys::to_notation(&recorded));
+    }
+
+    /// `@{register}` types the keys written in the register `count` times,
+    /// `@@` plays the last played register again and `@:` runs the last
+    /// command line.
+    pub fn play_macro(&mut self, name: char, count: usize) {
+        let name = match (name, self.macros.last) {
+            ('@', Some(last)) => last,
+            ('@', None) => {
+                self.error = Some(String::from("No previously used register"));
+                return;
+            }
+            _ => name,
+        };
+
+        let Some(register) = self.read_register(name) else {
+            if self.error.is_none() {
+                self.error = Some(format!("Nothing in register {name}"));
+            }
+            return;
+        };
+        self.macros.last = Some(name);
+
+        let mut text = register.lines.join("\n");
+        if register.kind == SelectionKind::Line || name == ':' {
+            text.push('\n');
+        }
+        if name == ':' {
+            text.insert(0, ':');
+        }
+
+        // a macro played by a macro runs before the rest of it, without
+        // growing the stack when the macro plays itself. Keys past the ones
+        // that can still be played before the macros are stopped never run.
+        let keys = keys::parse_notation(&text);
+        let copies = count.min((MAX_PLAYED_KEYS + 1).div_ceil(keys.len().max(1)));
+        for _ in 0..copies {
+            for key in keys.iter().rev() {
+                self.macros.queue.push_front(*key);
+            }
+        }
+        self.macros.queue.truncate(MAX_PLAYED_KEYS + 1);
+
+        if self.macros.playing {
+            return;
+        }
+
+        self.macros.playing = true;
+        self.macros.failed = false;
+        let mut played = 0;
+        while let Some(key) = self.macros.queue.pop_front() {
+            played += 1;
+            if played > MAX_PLAYED_KEYS {
+                self.error = Some(format!("Macro stopped after {MAX_PLAYED_KEYS} keys"));
+                self.macros.queue.clear();
+                break;
+            }
+            self.handle_key(key);
+
+            if self.macros.failed {
+                self.macros.queue.clear();
+            }
+        }
+        self.macros.playing = false;
+        self.macros.failed = false;
+    }
+
+    /// Stops the macros being played, when a motion could not move or a
+    /// search found nothing.
+    pub fn fail_macro(&mut self) {
+        if self.macros.playing {
+            self.macros.failed = true;
+        }
+    }
+}
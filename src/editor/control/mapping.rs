@@ -0,0 +1,211 @@
+use std::time::Duration;
+
+use termion::event::Key;
+
+use crate::editor::{
+    keymap::{self, Lookup, MapMode, Target},
+    Control, Mode,
+};
+
+/// Mappings expanded by one typed key at most, more are taken as a mapping
+/// of itself.
+const MAX_EXPANSIONS: usize = 1000;
+
+/// Keys typed while they can still be the start of a mapping.
+#[derive(Default)]
+pub struct MappingState {
+    typed: Vec<Key>,
+}
+
+/// What to do with the keys typed so far.
+enum Step {
+    Wait,
+    /// run the first key as it is
+    Key,
+    Mapping(MapMode, Target),
+}
+
+impl Control<'_> {
+    /// Mode the next key is mapped in, `None` while a command waits for
+    /// the rest of its keys.
+    fn map_mode(&self) -> Option<MapMode> {
+        match self.mode {
+            Mode::Normal if self.pending_keys.is_empty() => Some(MapMode::Normal),
+            Mode::Visual(_) if self.pending_keys.is_empty() => Some(MapMode::Visual),
+            Mode::Insert => Some(MapMode::Insert),
+            _ => None,
+        }
+    }
+
+    /// Runs `key` through the mappings of the mode.
+    pub fn map_key(&mut self, key: Key) {
+        self.mapping.typed.push(key);
+        self.resolve_mappings(false);
+    }
+
+    /// How long the keys typed wait for the rest of a longer mapping,
+    /// `None` when none are waiting.
+    pub fn mapping_timeout(&self) -> Option<Duration> {
+        let millis = u64::try_from(self.options.editor.timeoutlen).unwrap_or(u64::MAX);
+        (!self.mapping.typed.is_empty()).then_some(Duration::from_millis(millis))
+    }
+
+    /// No key came before the timeout, the keys typed are what they map to
+    /// or themselves.
+    pub fn finish_mapping(&mut self) {
+        self.resolve_mappings(true);
+    }
+
+    /// Runs the keys typed once they can't be the start of a longer
+    /// mapping, or all of them once `complete`.
+    fn resolve_mappings(&mut self, complete: bool) {
+        let mut expansions = 0;
+
+        while !self.mapping.typed.is_empty() {
+            let step = match self.map_mode() {
+                Some(mode) => match self.keymap.lookup(mode, &self.mapping.typed) {
+                    Lookup::Prefix(_) if !complete => Step::Wait,
+                    Lookup::Prefix(Some(target)) | Lookup::Exact(target) => {
+                        Step::Mapping(mode, target.clone())
+                    }
+                    Lookup::Prefix(None) | Lookup::None => Step::Key,
+                },
+                None => Step::Key,
+            };
+
+            match step {
+                Step::Wait => return,
+                Step::Key => {
+                    let key = self.mapping.typed.remove(0);
+                    self.run_key(key);
+                }
+                Step::Mapping(..) if expansions == MAX_EXPANSIONS => {
+                    self.mapping.typed.clear();
+                    self.error = Some(String::from("Recursive mapping"));
+                }
+                Step::Mapping(mode, target) => {
+                    expansions += 1;
+                    let lhs = std::mem::take(&mut self.mapping.typed);
+                    self.run_mapping(mode, &lhs, target);
+                }
+            }
+        }
+    }
+
+    /// Runs what `lhs` is mapped to. Keys mapped again are typed back, but
+    /// when they start with `lhs` it is run as it is, as `nmap n nzz` does.
+    fn run_mapping(&mut self, mode: MapMode, lhs: &[Key], target: Target) {
+        let (keys, remap) = match target {
+            Target::Action(action) => (self.keymap.keys(mode, action), false),
+            Target::Keys { keys, noremap } => (keys, !noremap),
+        };
+        if !remap {
+            for key in keys {
+                self.run_key(key);
+            }
+            return;
+        }
+
+        let rest = match keys.strip_prefix(lhs) {
+            Some(rest) => {
+                for key in lhs {
+                    self.run_key(*key);
+                }
+                rest
+            }
+            None => &keys,
+        };
+        self.mapping.typed.extend_from_slice(rest);
+    }
+
+    /// `:map lhs rhs` and the like, for the modes of the command `name`.
+    /// Without `rhs` the mappings starting with `lhs` are listed, and all of
+    /// them without arguments.
+    pub fn map_command(&mut self, name: &str, args: &str) {
+        let Some((modes, noremap)) = map_command_modes(name) else {
+            return;
+        };
+        let (lhs, rhs) = match args.split_once(char::is_whitespace) {
+            Some((lhs, rhs)) => (lhs, rhs.trim_start()),
+            None => (args, ""),
+        };
+
+        if rhs.is_empty() {
+            let lhs = keymap::parse_keys(lhs, &self.options.editor.mapleader);
+            let lines = self.keymap.list(modes, &lhs);
+            if lines.is_empty() {
+                self.message = Some(String::from("No mapping found"));
+            } else {
+                self.message = Some(lines.join("\n"));
+            }
+        } else if let Err(err) = self.map_keys(modes, lhs, rhs, noremap) {
+            self.error = Some(err);
+        }
+    }
+
+    /// Maps the keys written `lhs` to `rhs` in `modes`.
+    pub fn map_keys(
+        &mut self,
+        modes: &[MapMode],
+        lhs: &str,
+        rhs: &str,
+        noremap: bool,
+    ) -> Result<(), String> {
+        let leader = &self.options.editor.mapleader;
+        let keys = keymap::parse_keys(lhs, leader);
+        if keys.is_empty() {
+            return Err(String::from("Argument required"));
+        }
+        let target = Target::parse(rhs, leader, noremap)?;
+
+        for mode in modes {
+            self.keymap.map(*mode, keys.clone(), target.clone());
+        }
+        Ok(())
+    }
+
+    /// `:unmap lhs` and the like.
+    pub fn unmap_command(&mut self, name: &str, args: &str) {
+        let Some(modes) = unmap_command_modes(name) else {
+            return;
+        };
+        let keys = keymap::parse_keys(args.trim(), &self.options.editor.mapleader);
+        let mut found = false;
+        for mode in modes {
+            found |= self.keymap.unmap(*mode, &keys);
+        }
+
+        if !found {
+            self.error = Some(String::from("No such mapping"));
+        }
+    }
+}
+
+/// Modes of a mapping command like `nmap`, and whether it is a `noremap`
+/// one. `None` for other commands.
+pub fn map_command_modes(name: &str) -> Option<(&'static [MapMode], bool)> {
+    const NORMAL_VISUAL: &[MapMode] = &[MapMode::Normal, MapMode::Visual];
+
+    match name {
+        "map" => Some((NORMAL_VISUAL, false)),
+        "nm" | "nmap" => Some((&[MapMode::Normal], false)),
+        "im" | "imap" => Some((&[MapMode::Insert], false)),
+        "vm" | "vmap" => Some((&[MapMode::Visual], false)),
+        "no" | "noremap" => Some((NORMAL_VISUAL, true)),
+        "nn" | "nnoremap" => Some((&[MapMode::Normal], true)),
+        "ino" | "inoremap" => Some((&[MapMode::Insert], true)),
+        "vn" | "vnoremap" => Some((&[MapMode::Visual], true)),
+        _ => None,
+    }
+}
+
+/// Modes of an unmapping command like `nunmap`.
+pub fn unmap_command_modes(name: &str) -> Option<&'static [MapMode]> {
+    match name {
+        "unm" | "unmap" => Some(&[MapMode::Normal, MapMode::Visual]),
+        "nun" | "nunmap" => Some(&[MapMode::Normal]),
+        "iu" | "iunmap" => Some(&[MapMode::Insert]),
+        "vu" | "vunmap" => Some(&[MapMode::Visual]),
+        _ => None,
+    }
+}
@@ -0,0 +1,270 @@
+use std::collections::HashMap;
+
+use crate::{
+    document::RowChange,
+    editor::{Control, Window},
+    utils::{Position, Size},
+};
+
+/// Jumps kept by the jump list, older ones are forgotten.
+const JUMP_LIST_LEN: usize = 100;
+
+/// Uppercase mark, it can be jumped to from any file.
+struct GlobalMark {
+    file: Option<String>,
+    /// where the mark was set, used when the file is no longer open
+    pos: Position,
+}
+
+/// Position the cursor jumped from, in a window.
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Jump {
+    window: usize,
+    pos: Position,
+}
+
+/// Global marks and the jump list. Other marks are kept by the documents,
+/// so that they follow their text.
+#[derive(Default)]
+pub struct MarkState {
+    global: HashMap<char, GlobalMark>,
+    jumps: Vec<Jump>,
+    /// jump `Ctrl-o` and `Ctrl-i` went to, `jumps.len()` when they are not
+    /// being used
+    jump_index: usize,
+}
+
+impl Control<'_> {
+    /// `m{name}` marks the cursor position. Uppercase marks remember the
+    /// file too.
+    pub fn set_mark(&mut self, name: char) {
+        let Some(pos) = self.document_position() else {
+            return;
+        };
+
+        let name = match name {
+            'a'..='z' | 'A'..='Z' | '[' | ']' | '<' | '>' => name,
+            '\'' | '`' => '\'',
+            _ => {
+                self.error = Some(String::from("Invalid mark name"));
+                return;
+            }
+        };
+
+        let file = self
+            .edit_document(|doc| {
+                doc.set_mark(name, pos);
+                doc.file_name().map(String::from)
+            })
+            .flatten();
+        if name.is_ascii_uppercase() {
+            self.marks.global.insert(name, GlobalMark { file, pos });
+        }
+    }
+
+    /// Position of mark `name` in the document of the attached window, an
+    /// uppercase mark only if it was set in this document.
+    pub fn mark_position(&self, name: char) -> Option<Position> {
+        let name = if name == '`' { '\'' } else { name };
+
+        if name.is_ascii_uppercase() {
+            let file = &self.marks.global.get(&name)?.file;
+            if self.read_document(|doc| doc.file_name() != file.as_deref())? {
+                return None;
+            }
+        }
+
+        self.read_document(|doc| doc.mark(name)).flatten()
+    }
+
+    /// Goes to mark `name` (`` `a ``), or to the first non blank char of its
+    /// line (`'a`). An uppercase mark set in another file goes to the window
+    /// showing it, opening one if there is none. Returns `false` if the mark
+    /// is not set.
+    pub fn go_to_mark(&mut self, name: char, linewise: bool) -> bool {
+        if name.is_ascii_uppercase() && self.mark_position(name).is_none() {
+            if let Some(global) = self.marks.global.get(&name) {
+                let (file, pos) = (global.file.clone(), global.pos);
+                if !self.show_file(file.as_deref()) {
+                    return false;
+                }
+
+                let pos = self.mark_position(name).unwrap_or(pos);
+                self.move_to_mark(pos, linewise);
+                return true;
+            }
+        }
+
+        let Some(pos) = self.mark_position(name) else {
+            self.error = Some(String::from("Mark not set"));
+            self.fail_macro();
+            return false;
+        };
+
+        self.move_to_mark(pos, linewise);
+        true
+    }
+
+    fn move_to_mark(&mut self, pos: Position, linewise: bool) {
+        if linewise {
+            self.go_to_line(pos.y);
+        } else {
+            self.set_document_position(pos);
+        }
+    }
+
+    /// Attaches the window showing `file`, opening it in a new window over
+    /// the attached one if none does.
+    fn show_file(&mut self, file: Option<&str>) -> bool {
+        let shown = self
+            .borrow_attached_windows()
+            .iter()
+            .position(|window| window.document().file_name() == file);
+        if let Some(index) = shown {
+            self.attach_window(index);
+            return true;
+        }
+
+        let Some(path) = file else {
+            self.error = Some(String::from("Mark not set"));
+            return false;
+        };
+        let doc = match self.open_document(path) {
+            Ok(doc) => doc,
+            Err(err) => {
+                self.error = Some(format!("Can't open file {path}: {err}"));
+                return false;
+            }
+        };
+
+        let index = {
+            let mut windows = self.borrow_mut_windows();
+            let Some(current) = self.attached_window.and_then(|index| windows.get(index)) else {
+                return false;
+            };
+
+            let size = Size {
+                width: current.size().width,
+                height: current.size().height,
+            };
+            let mut window = Window::new(doc, size, current.position());
+            *window.options_mut() = self.options.window.for_file(path);
+            window.update_folds();
+            windows.push(window);
+            windows.len() - 1
+        };
+        self.attach_window(index);
+
+        true
+    }
+
+    /// Where the cursor is, to know if a motion jumped away from it.
+    pub fn jump_origin(&self) -> Option<(usize, Position)> {
+        self.attached_window.zip(self.document_position())
+    }
+
+    /// Keeps `origin` in the jump list if the cursor left it, and as the
+    /// `'` mark of its document.
+    pub fn finish_jump(&mut self, origin: Option<(usize, Position)>) {
+        let Some((window, pos)) = origin else {
+            return;
+        };
+        if self.jump_origin() == origin {
+            return;
+        }
+
+        if let Some(window) = self.borrow_mut_windows().get_mut(window) {
+            window.document_mut().set_mark('\'', pos);
+        }
+        self.push_jump(Jump { window, pos });
+        self.marks.jump_index = self.marks.jumps.len();
+    }
+
+    /// Adds `jump` to the jump list, once per line.
+    fn push_jump(&mut self, jump: Jump) {
+        let jumps = &mut self.marks.jumps;
+        jumps.retain(|kept| kept.window != jump.window || kept.pos.y != jump.pos.y);
+        jumps.push(jump);
+        if jumps.len() > JUMP_LIST_LEN {
+            jumps.remove(0);
+        }
+    }
+
+    /// Goes `count` jumps back (`Ctrl-o`) or forward (`Ctrl-i`) in the jump
+    /// list.
+    pub fn travel_jumps(&mut self, back: bool, count: usize) {
+        // the position left is kept, to come back to it with `Ctrl-i`
+        if back && self.marks.jump_index >= self.marks.jumps.len() {
+            if let Some((window, pos)) = self.jump_origin() {
+                self.push_jump(Jump { window, pos });
+                self.marks.jump_index = self.marks.jumps.len() - 1;
+            }
+        }
+
+        let index = if back {
+            self.marks.jump_index.checked_sub(count)
+        } else {
+            Some(self.marks.jump_index + count)
+        };
+        let Some(&jump) = index.and_then(|index| self.marks.jumps.get(index)) else {
+            self.fail_macro();
+            return;
+        };
+        self.marks.jump_index = index.unwrap_or_default();
+
+        if self.attached_window != Some(jump.window) {
+            self.attach_window(jump.window);
+        }
+        self.set_document_position(jump.pos);
+    }
+
+    /// Sets the `[`, `]` and `.` marks around the text `change` made.
+    pub fn mark_change(&mut self, change: &RowChange) {
+        self.edit_document(|doc| {
+            if let Some((start, end)) = change.changed_range(doc) {
+                doc.set_mark('[', start);
+                doc.set_mark(']', end);
+                doc.set_mark('.', end);
+            }
+        });
+    }
+
+    /// `:marks` lists the marks with their line and column.
+    pub fn list_marks(&mut self) {
+        let file = self
+            .read_document(|doc| doc.file_name().map(String::from))
+            .flatten();
+
+        let mut lines = vec![String::from("mark line  col file/text")];
+        let local = self
+            .read_document(|doc| {
+                doc.marks()
+                    .iter()
+                    .filter(|(name, _)| !name.is_ascii_uppercase())
+                    .map(|(name, pos)| {
+                        let text = doc.row(pos.y).map_or("", |row| row.content.trim());
+                        format!(" {name} {:>6} {:>4} {text}", pos.y + 1, pos.x)
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        lines.extend(local);
+
+        let mut global: Vec<_> = self.marks.global.keys().copied().collect();
+        global.sort_unstable();
+        for name in global {
+            let Some(mark) = self.marks.global.get(&name) else {
+                continue;
+            };
+            let pos = if mark.file == file {
+                self.mark_position(name).unwrap_or(mark.pos)
+            } else {
+                mark.pos
+            };
+            let file = mark.file.as_deref().unwrap_or("[No Name]");
+            lines.push(format!(" {name} {:>6} {:>4} {file}", pos.y + 1, pos.x));
+        }
+
+        self.message = Some(lines.join("\n"));
+    }
+}
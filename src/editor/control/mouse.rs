@@ -0,0 +1,149 @@
+use termion::event::{MouseButton, MouseEvent};
+
+use crate::{
+    editor::{
+        window_manager::{move_separator, separator_at, Separator},
+        Control, Mode, SelectionKind,
+    },
+    utils::{Direction, Position},
+};
+
+/// Lines scrolled by one step of the mouse wheel.
+const WHEEL_LINES: usize = 3;
+
+impl Control<'_> {
+    pub fn handle_mouse(&mut self, event: MouseEvent) {
+        if !self.options.editor.mouse
+            || matches!(self.mode, Mode::Command | Mode::Search(_) | Mode::Confirm)
+        {
+            return;
+        }
+
+        self.message = None;
+        self.error = None;
+
+        match event {
+            MouseEvent::Press(button, x, y) => {
+                let screen = Self::screen_position(x, y);
+                match button {
+                    MouseButton::Left => self.click(&screen),
+                    MouseButton::WheelUp => self.scroll_window_at(&screen, &Direction::Up),
+                    MouseButton::WheelDown => self.scroll_window_at(&screen, &Direction::Down),
+                    _ => {}
+                }
+            }
+            MouseEvent::Hold(x, y) => self.drag(&Self::screen_position(x, y)),
+            MouseEvent::Release(..) => {
+                self.dragging = false;
+                self.resizing = None;
+            }
+        }
+    }
+
+    /// Terminals count mouse positions from 1.
+    fn screen_position(x: u16, y: u16) -> Position {
+        Position {
+            x: usize::from(x.saturating_sub(1)),
+            y: usize::from(y.saturating_sub(1)),
+        }
+    }
+
+    fn window_at(&self, screen: &Position) -> Option<usize> {
+        self.borrow_attached_windows()
+            .iter()
+            .position(|window| window.contains(screen))
+    }
+
+    /// Focuses the window under the pointer and moves the cursor there, or
+    /// grabs the separator under it.
+    fn click(&mut self, screen: &Position) {
+        let Some(index) = self.window_at(screen) else {
+            let separator = separator_at(&self.borrow_attached_windows(), screen);
+            self.resizing = separator;
+            return;
+        };
+
+        if let Mode::Visual(_) = self.mode {
+            self.exit_visual_mode();
+        }
+
+        let pos = self.borrow_attached_windows()[index].nearest_document_position(screen);
+        if let Some(pos) = pos {
+            self.attach_window(index);
+            self.set_document_position(pos);
+            self.dragging = true;
+        }
+    }
+
+    /// Selects from where the button was pressed to the pointer, or moves
+    /// the separator grabbed there.
+    fn drag(&mut self, screen: &Position) {
+        if let Some(separator) = self.resizing {
+            self.resize_windows(separator, screen);
+            return;
+        }
+        if !self.dragging || matches!(self.mode, Mode::Insert) {
+            return;
+        }
+
+        let Some(index) = self.attached_window else {
+            return;
+        };
+
+        // the pointer can leave the window while dragging
+        let pos = {
+            let windows = self.borrow_attached_windows();
+            let Some(window) = windows.get(index) else {
+                return;
+            };
+
+            let top_left = window.position();
+            let size = window.size();
+            window.nearest_document_position(&Position {
+                x: screen.x.clamp(
+                    top_left.x,
+                    (top_left.x + size.width as usize).saturating_sub(1),
+                ),
+                y: screen.y.clamp(
+                    top_left.y,
+                    (top_left.y + size.height as usize).saturating_sub(1),
+                ),
+            })
+        };
+
+        let Some(pos) = pos else {
+            return;
+        };
+
+        if !matches!(self.mode, Mode::Visual(_)) {
+            self.enter_visual_mode(SelectionKind::Char);
+        }
+        self.set_document_position(pos);
+        self.sync_selection();
+    }
+
+    /// Moves `separator` to the pointer, the cursor is kept in the attached
+    /// window as it shrinks.
+    fn resize_windows(&mut self, separator: Separator, screen: &Position) {
+        let moved = move_separator(&mut self.borrow_mut_windows(), separator, screen);
+        self.resizing = Some(moved);
+
+        if let Some(pos) = self.document_position() {
+            self.set_document_position(pos);
+        }
+    }
+
+    fn scroll_window_at(&mut self, screen: &Position, direction: &Direction) {
+        let Some(index) = self.window_at(screen) else {
+            return;
+        };
+
+        if Some(index) == self.attached_window {
+            // keeps the cursor in the view
+            self.scroll_view(direction, WHEEL_LINES);
+            self.sync_selection();
+        } else if let Some(window) = self.borrow_mut_windows().get_mut(index) {
+            window.scroll(direction, WHEEL_LINES);
+        }
+    }
+}
@@ -0,0 +1,509 @@
+use std::cmp;
+
+use termion::event::Key;
+
+use super::insert::BlockInsert;
+use crate::{
+    document::Document,
+    editor::{keymap::Action, Control, Register, Registers, Selection, SelectionKind},
+    utils::{Direction, Position},
+};
+
+/// Commands that act on the text a motion moves over (`dw`, `yj`, `c$`) or
+/// on a visual selection.
+#[derive(Clone, Copy)]
+pub enum Operator {
+    Delete,
+    Yank,
+    Change,
+    /// `>`, a level of indentation more on every row
+    Indent,
+    /// `<`, a level of indentation less on every row
+    Dedent,
+    /// `gc`, comments out the rows or uncomments them
+    Comment,
+    /// `zf`, makes a closed fold of the rows
+    Fold,
+}
+
+impl Operator {
+    pub fn from_action(action: Action) -> Option<Self> {
+        match action {
+            Action::Delete => Some(Self::Delete),
+            Action::Yank => Some(Self::Yank),
+            Action::Change => Some(Self::Change),
+            Action::Indent => Some(Self::Indent),
+            Action::Dedent => Some(Self::Dedent),
+            Action::Comment => Some(Self::Comment),
+            Action::CreateFold => Some(Self::Fold),
+            _ => None,
+        }
+    }
+}
+
+/// How much of the text a motion moves over is taken by an operator.
+enum MotionKind {
+    /// whole lines, `j`, `G`
+    Line,
+    /// up to and including the char the motion stops on, `e`, `$`
+    Inclusive,
+    /// up to the char the motion stops on, `w`, `h`
+    Exclusive,
+}
+
+impl Control<'_> {
+    /// Runs `operator` over the text between the cursor and where `motion`
+    /// moves it `count` times with the arguments `args`, like the mark of
+    /// `'`. Does nothing if `motion` is not a movement.
+    pub fn apply_operator(
+        &mut self,
+        operator: Operator,
+        motion: Action,
+        args: &[Key],
+        count: usize,
+    ) {
+        let Some(start) = self.document_position() else {
+            return;
+        };
+
+        let Some((target, kind)) = self.operator_target(operator, &start, (motion, args), count)
+        else {
+            return;
+        };
+
+        let (from, to) = (cmp::min(start, target), cmp::max(start, target));
+        if let MotionKind::Line = kind {
+            let (first, last) = self.closed_fold_bounds(from.y, to.y);
+            self.run_operator(
+                operator,
+                &Selection {
+                    anchor: Position {
+                        x: from.x,
+                        y: first,
+                    },
+                    cursor: Position { x: to.x, y: last },
+                    kind: SelectionKind::Line,
+                },
+            );
+            return;
+        }
+
+        let end = self
+            .read_document(|doc| {
+                let line_len = doc.row(to.y).map_or(0, |row| row.content.len());
+                match kind {
+                    // nothing to take on an empty row
+                    MotionKind::Inclusive if from == to && to.x >= line_len => None,
+                    MotionKind::Inclusive => Some(to),
+                    _ if from == to => None,
+                    _ => Some(Self::exclusive_end(doc, &from, &to)),
+                }
+            })
+            .flatten();
+
+        if let Some(end) = end {
+            self.run_operator(
+                operator,
+                &Selection {
+                    anchor: from,
+                    cursor: end,
+                    kind: SelectionKind::Char,
+                },
+            );
+        } else {
+            self.set_document_position(start);
+            if let Operator::Change = operator {
+                self.enter_insert_mode();
+            } else {
+                // like `D` on an empty row
+                self.fail_macro();
+            }
+        }
+    }
+
+    /// Where `motion` goes from `start` when repeated `count` times, and how
+    /// much text it covers. `None` when it can't move.
+    fn operator_target(
+        &mut self,
+        operator: Operator,
+        start: &Position,
+        motion: (Action, &[Key]),
+        count: usize,
+    ) -> Option<(Position, MotionKind)> {
+        let (motion, args) = motion;
+        let on_blank = self
+            .read_document(|doc| doc.char_at(start).is_none_or(char::is_whitespace))
+            .unwrap_or(true);
+
+        // word motions are computed on the document, the cursor can't go past
+        // the last char of a row like they can
+        let word_step = |doc: &Document, from: &Position| match motion {
+            // `cw` changes up to the end of the word, like `ce`
+            Action::WordForward if matches!(operator, Operator::Change) && !on_blank => {
+                let before = doc.previous_position(from).unwrap_or(*from);
+                let end = doc.word_end(&before);
+                Some((cmp::max(end, *from), MotionKind::Inclusive))
+            }
+            Action::WordForward => {
+                let next = doc.next_word_start(from);
+                let line_len = doc.row(from.y).map_or(0, |row| row.content.len());
+
+                // the last word of a row is taken without the line break
+                if next.y > from.y && line_len > from.x {
+                    Some((
+                        Position {
+                            x: line_len,
+                            y: from.y,
+                        },
+                        MotionKind::Exclusive,
+                    ))
+                } else {
+                    Some((next, MotionKind::Exclusive))
+                }
+            }
+            Action::WordBackward => Some((doc.previous_word_start(from), MotionKind::Exclusive)),
+            Action::WordEnd => Some((doc.word_end(from), MotionKind::Inclusive)),
+            _ => None,
+        };
+
+        let word_motion = self
+            .read_document(|doc| {
+                let mut target = word_step(doc, start)?;
+                for _ in 1..count {
+                    // `ce` and `cw` go on from the next char, not to the same end
+                    let from = match target.1 {
+                        MotionKind::Inclusive => doc.next_position(&target.0)?,
+                        _ => target.0,
+                    };
+                    target = word_step(doc, &from)?;
+                }
+                Some(target)
+            })
+            .flatten();
+
+        if word_motion.is_some() {
+            return word_motion;
+        }
+
+        // `dl` takes the last char of a row too, the cursor can't go past it
+        if let Action::Right = motion {
+            let x = self
+                .read_document(|doc| {
+                    doc.row(start.y)
+                        .map(|row| row.next_char_index(start.x, count))
+                })
+                .flatten()?;
+            return Some((Position { x, y: start.y }, MotionKind::Exclusive));
+        }
+
+        // motions that can't move take nothing, instead of the cursor line
+        // or char
+        let stuck = match (motion, args) {
+            (Action::MarkLine | Action::Mark, [Key::Char(name)])
+                if self.mark_position(*name).is_none() =>
+            {
+                self.error = Some(String::from("Mark not set"));
+                true
+            }
+            (Action::MatchingBracket, _) => self
+                .read_document(|doc| doc.matching_bracket(start).is_none())
+                .unwrap_or(true),
+            _ => false,
+        };
+        if stuck {
+            self.fail_macro();
+            return None;
+        }
+
+        let kind = match motion {
+            Action::Down
+            | Action::Up
+            | Action::FirstLine
+            | Action::LastLine
+            | Action::HalfPageDown
+            | Action::HalfPageUp
+            | Action::PageDown
+            | Action::PageUp
+            | Action::MarkLine => MotionKind::Line,
+            Action::LineEnd | Action::MatchingBracket => MotionKind::Inclusive,
+            _ => MotionKind::Exclusive,
+        };
+
+        if !self.process_counted_movement(motion, args, Some(count)) {
+            return None;
+        }
+        let target = self.document_position()?;
+
+        // `dj` on the last line takes nothing, the movement failed already
+        if matches!(kind, MotionKind::Line) && motion.must_move() && target.y == start.y {
+            return None;
+        }
+
+        Some((target, kind))
+    }
+
+    /// Last char taken by an exclusive motion from `from` to `to`, when `to`
+    /// is at the start of a row the previous line break is left out too.
+    fn exclusive_end(doc: &Document, from: &Position, to: &Position) -> Position {
+        if to.x == 0 && to.y > from.y {
+            let line_len = doc.row(to.y - 1).map_or(0, |row| row.content.len());
+            return Position {
+                x: line_len.saturating_sub(1),
+                y: to.y - 1,
+            };
+        }
+
+        doc.previous_position(to).unwrap_or(*from)
+    }
+
+    /// Runs `operator` on `count` lines from the cursor line (`dd`, `yy`,
+    /// `cc`).
+    pub fn apply_operator_on_line(&mut self, operator: Operator, count: usize) {
+        let Some(pos) = self.document_position() else {
+            return;
+        };
+        let last = self.last_shown_line(pos.y, count);
+        let (first, last) = self.closed_fold_bounds(pos.y, last);
+
+        self.run_operator(
+            operator,
+            &Selection {
+                anchor: Position { x: pos.x, y: first },
+                cursor: Position { x: pos.x, y: last },
+                kind: SelectionKind::Line,
+            },
+        );
+    }
+
+    /// Deletes `count` chars under and after (`x`) or before (`X`) the
+    /// cursor, within the line.
+    pub fn delete_chars(&mut self, before: bool, count: usize) {
+        let Some(pos) = self.document_position() else {
+            return;
+        };
+
+        let line_len = self.current_line_len().unwrap_or(0);
+        if line_len == 0 || (before && pos.x == 0) {
+            self.fail_macro();
+            return;
+        }
+
+        let Some((from, to)) = self
+            .read_document(|doc| {
+                let row = doc.row(pos.y)?;
+                Some(if before {
+                    (
+                        row.previous_char_index(pos.x, count),
+                        row.previous_char_index(pos.x, 1),
+                    )
+                } else {
+                    let end = row.next_char_index(pos.x, count);
+                    (pos.x, row.previous_char_index(end, 1))
+                })
+            })
+            .flatten()
+        else {
+            return;
+        };
+        self.run_operator(
+            Operator::Delete,
+            &Selection {
+                anchor: Position { x: from, y: pos.y },
+                cursor: Position { x: to, y: pos.y },
+                kind: SelectionKind::Char,
+            },
+        );
+    }
+
+    pub fn run_operator(&mut self, operator: Operator, selection: &Selection) {
+        match operator {
+            Operator::Delete => self.delete_text(selection),
+            Operator::Yank => self.yank_text(selection),
+            Operator::Change => self.change_text(selection),
+            Operator::Indent => self.shift_rows(selection, &Direction::Right),
+            Operator::Dedent => self.shift_rows(selection, &Direction::Left),
+            Operator::Comment => self.toggle_comments(selection),
+            Operator::Fold => self.create_fold(selection),
+        }
+    }
+
+    pub fn delete_text(&mut self, selection: &Selection) {
+        if !self.can_use_register(true) {
+            return;
+        }
+        let name = self.register_name.take();
+
+        let register = self.selection_register(selection);
+        self.store_register(name, register, true);
+        self.remove_selected_text(selection);
+    }
+
+    pub fn yank_text(&mut self, selection: &Selection) {
+        if !self.can_use_register(true) {
+            return;
+        }
+        let name = self.register_name.take();
+
+        let register = self.selection_register(selection);
+        self.store_register(name, register, false);
+        self.edit_document(|doc| {
+            doc.set_mark('[', selection.start());
+            doc.set_mark(']', selection.end());
+        });
+
+        self.set_document_position(Self::selection_corner(selection));
+        self.report_lines(selection.end().y - selection.start().y + 1, "lines yanked");
+    }
+
+    /// Deletes the text and starts inserting in its place, for blocks the
+    /// text is inserted on every row.
+    pub fn change_text(&mut self, selection: &Selection) {
+        if !self.can_use_register(true) {
+            return;
+        }
+        let name = self.register_name.take();
+
+        let (start, end) = (selection.start(), selection.end());
+        let register = self.selection_register(selection);
+        self.store_register(name, register, true);
+
+        match selection.kind {
+            SelectionKind::Char => {
+                self.remove_selected_text(selection);
+                self.enter_insert_mode();
+                self.set_document_position(start);
+            }
+            SelectionKind::Line => {
+                // a single empty row is left in place of the selected ones
+                self.edit_document(|doc| {
+                    doc.insert_rows(end.y + 1, &[String::new()]);
+                    doc.delete_rows(start.y..end.y + 1);
+                });
+                self.enter_insert_mode();
+                self.set_document_position(Position { x: 0, y: start.y });
+            }
+            SelectionKind::Block => {
+                self.remove_selected_text(selection);
+                self.start_block_insert(BlockInsert {
+                    top: start.y,
+                    bottom: end.y,
+                    column: selection.block_columns().0,
+                    pad: false,
+                });
+            }
+        }
+    }
+
+    /// Whether the register given with `"`, if any, can be used by the next
+    /// command, shows a message when it can't.
+    pub fn can_use_register(&mut self, write: bool) -> bool {
+        let Some(name) = self.register_name else {
+            return true;
+        };
+
+        let valid = if write {
+            Registers::is_writable(name)
+        } else {
+            Registers::is_valid_name(name)
+        };
+
+        if !valid {
+            self.error = Some(format!("Invalid register name: '{name}'"));
+        }
+
+        valid
+    }
+
+    /// Where a characterwise selection stops, right after its last char or
+    /// after the line break when it ends on the end of a row.
+    pub fn char_selection_end(&self, selection: &Selection) -> Position {
+        let end = selection.end();
+
+        self.read_document(|doc| {
+            let line_len = doc.row(end.y).map_or(0, |row| row.content.len());
+
+            if end.x >= line_len && end.y + 1 < doc.lines_len() {
+                Position { x: 0, y: end.y + 1 }
+            } else {
+                Position {
+                    x: doc
+                        .row(end.y)
+                        .map_or(0, |row| row.next_char_index(end.x, 1)),
+                    y: end.y,
+                }
+            }
+        })
+        .unwrap_or(end)
+    }
+
+    /// Top left corner of a selection, where the cursor goes after an
+    /// operator.
+    pub fn selection_corner(selection: &Selection) -> Position {
+        match selection.kind {
+            SelectionKind::Block => Position {
+                x: selection.block_columns().0,
+                y: selection.start().y,
+            },
+            _ => selection.start(),
+        }
+    }
+
+    fn selection_register(&self, selection: &Selection) -> Register {
+        let (start, end) = (selection.start(), selection.end());
+        let char_end = self.char_selection_end(selection);
+        let (left, right) = selection.block_columns();
+
+        let lines = self
+            .read_document(|doc| match selection.kind {
+                SelectionKind::Char => doc.text_range(&start, &char_end),
+                SelectionKind::Line => (start.y..=end.y)
+                    .filter_map(|y| doc.row(y))
+                    .map(|row| row.content.clone())
+                    .collect(),
+                SelectionKind::Block => (start.y..=end.y)
+                    .filter_map(|y| doc.row(y))
+                    .map(|row| row.slice(left..right + 1).to_string())
+                    .collect(),
+            })
+            .unwrap_or_default();
+
+        Register {
+            lines,
+            kind: selection.kind,
+        }
+    }
+
+    /// Tells how many lines an operator went through, like vim only when
+    /// there are more than two.
+    pub fn report_lines(&mut self, count: usize, what: &str) {
+        if count > 2 {
+            self.message = Some(format!("{count} {what}"));
+        }
+    }
+
+    fn remove_selected_text(&mut self, selection: &Selection) {
+        let (start, end) = (selection.start(), selection.end());
+
+        match selection.kind {
+            SelectionKind::Char => {
+                let char_end = self.char_selection_end(selection);
+                self.edit_document(|doc| doc.delete_range(&start, &char_end));
+                self.set_document_position(start);
+            }
+            SelectionKind::Line => {
+                self.edit_document(|doc| doc.delete_rows(start.y..end.y + 1));
+                self.go_to_line(start.y);
+                self.report_lines(end.y - start.y + 1, "fewer lines");
+            }
+            SelectionKind::Block => {
+                let (left, right) = selection.block_columns();
+                self.edit_document(|doc| {
+                    for y in start.y..=end.y {
+                        doc.delete_range(&Position { x: left, y }, &Position { x: right + 1, y });
+                    }
+                });
+                self.set_document_position(Self::selection_corner(selection));
+            }
+        }
+    }
+}
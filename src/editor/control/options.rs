@@ -0,0 +1,251 @@
+use std::{fs, path::Path};
+
+use toml::{Table, Value};
+
+use crate::{
+    editor::{
+        control::mapping,
+        keys,
+        options::{OptionInfo, OptionKind, OptionScope, OptionValue, OPTIONS},
+        Clipboard, Control,
+    },
+    utils::lines::{line_at, line_of_key},
+};
+
+impl Control<'_> {
+    /// `:set` and `:setlocal` arguments: `opt=value`, `opt` and `noopt` for
+    /// on and off options, `opt!` toggling them, and `opt?` telling the
+    /// value. Without arguments every option is listed.
+    pub fn set_options(&mut self, args: &str, local: bool) {
+        if args.trim().is_empty() {
+            let values: Vec<String> = OPTIONS.iter().map(|info| self.show_option(info)).collect();
+            self.message = Some(values.join("\n"));
+            return;
+        }
+
+        let mut shown = Vec::new();
+        for arg in args.split_whitespace() {
+            match self.set_option_arg(arg, local) {
+                Ok(Some(value)) => shown.push(value),
+                Ok(None) => {}
+                Err(err) => {
+                    self.error = Some(err);
+                    return;
+                }
+            }
+        }
+        if !shown.is_empty() {
+            self.message = Some(shown.join("  "));
+        }
+    }
+
+    /// Runs one `:set` argument, returning the value to show when asked.
+    fn set_option_arg(&mut self, arg: &str, local: bool) -> Result<Option<String>, String> {
+        let unknown = || format!("Unknown option: {arg}");
+
+        if let Some((name, text)) = arg.split_once('=') {
+            let info = OptionInfo::find(name).ok_or_else(unknown)?;
+            let value = info.parse(text)?;
+            self.set_option(info, value, local)?;
+            return Ok(None);
+        }
+        if let Some(name) = arg.strip_suffix('?') {
+            let info = OptionInfo::find(name).ok_or_else(unknown)?;
+            return Ok(Some(self.show_option(info)));
+        }
+
+        let (name, value) = match OptionInfo::find(arg) {
+            Some(info) if info.kind != OptionKind::Bool => {
+                return Ok(Some(self.show_option(info)));
+            }
+            Some(_) => (arg, Some(true)),
+            None => match (arg.strip_prefix("no"), arg.strip_suffix('!')) {
+                (Some(name), _) => (name, Some(false)),
+                (None, Some(name)) => (name, None),
+                (None, None) => return Err(unknown()),
+            },
+        };
+        let info = OptionInfo::find(name)
+            .filter(|info| info.kind == OptionKind::Bool)
+            .ok_or_else(unknown)?;
+        let value = match value {
+            Some(value) => value,
+            None => self.option_value(info) != OptionValue::Bool(true),
+        };
+        self.set_option(info, OptionValue::Bool(value), local)?;
+
+        Ok(None)
+    }
+
+    /// `opt=value`, or `opt` and `noopt` for on and off options.
+    fn show_option(&self, info: &OptionInfo) -> String {
+        match self.option_value(info) {
+            OptionValue::Bool(true) => info.name.to_string(),
+            OptionValue::Bool(false) => format!("no{}", info.name),
+            value => format!("{}={value}", info.name),
+        }
+    }
+
+    /// Value of the option for the attached window and its document.
+    pub fn option_value(&self, info: &OptionInfo) -> OptionValue {
+        let value = match info.scope {
+            OptionScope::Global => self.options.editor.get(info.name),
+            OptionScope::Buffer => self
+                .read_document(|doc| doc.options().get(info.name))
+                .unwrap_or_else(|| self.options.buffer.get(info.name)),
+            OptionScope::Window => {
+                let windows = self.borrow_attached_windows();
+                match self.attached_window.and_then(|index| windows.get(index)) {
+                    Some(window) => window.options().get(info.name),
+                    None => self.options.window.get(info.name),
+                }
+            }
+        };
+
+        value.unwrap_or(OptionValue::Bool(false))
+    }
+
+    /// Sets the option of the attached window or its document, and the
+    /// global value unless `local`.
+    pub fn set_option(
+        &mut self,
+        info: &OptionInfo,
+        value: OptionValue,
+        local: bool,
+    ) -> Result<(), String> {
+        match info.scope {
+            OptionScope::Global => {
+                self.options.editor.set(info.name, value)?;
+                if info.name == "clipboard" {
+                    let clipboard = Clipboard::from_name(&self.options.editor.clipboard);
+                    self.set_clipboard(clipboard.unwrap_or_else(Clipboard::detect));
+                }
+            }
+            OptionScope::Buffer => {
+                if !local {
+                    self.options.buffer.set(info.name, value.clone())?;
+                }
+                if let Some(result) =
+                    self.edit_document(|doc| doc.options_mut().set(info.name, value))
+                {
+                    result?;
+                }
+            }
+            OptionScope::Window => {
+                if !local {
+                    self.options.window.set(info.name, value.clone())?;
+                }
+                let mut windows = self.borrow_mut_windows();
+                if let Some(window) = self
+                    .attached_window
+                    .and_then(|index| windows.get_mut(index))
+                {
+                    window.options_mut().set(info.name, value)?;
+                    if info.name == "foldmethod" {
+                        window.update_folds();
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads the options of the config file at `path`, a missing file
+    /// leaving the defaults. Mistakes are reported with their line.
+    pub fn load_config(&mut self, path: &Path) {
+        let Ok(text) = fs::read_to_string(path) else {
+            return;
+        };
+
+        let errors: Vec<String> = self
+            .apply_config(&text)
+            .into_iter()
+            .map(|(line, message)| format!("{}:{line}: {message}", path.display()))
+            .collect();
+        self.report_load_errors("config", &errors);
+    }
+
+    /// Sets the options of the config `text`, `colorscheme` picking the
+    /// theme, maps the keys of tables named after mapping commands like
+    /// `[nnoremap]` and writes the macros of `[registers]`. Returns the
+    /// lines that could not be applied.
+    fn apply_config(&mut self, text: &str) -> Vec<(usize, String)> {
+        let table = match text.parse::<Table>() {
+            Ok(table) => table,
+            Err(err) => {
+                let line = err.span().map_or(1, |span| line_at(text, span.start));
+                return vec![(line, err.message().to_string())];
+            }
+        };
+
+        let mut errors = Vec::new();
+        // options first, as `mapleader` changes the mappings
+        let (maps, options): (Vec<_>, Vec<_>) =
+            table.iter().partition(|(_, value)| value.is_table());
+        for (key, value) in options {
+            if let Err(message) = self.apply_config_option(key, value) {
+                errors.push((line_of_key(text, &[key]), message));
+            }
+        }
+
+        for (name, maps) in maps {
+            if name == "registers" {
+                for (register, value) in maps.as_table().into_iter().flatten() {
+                    if let Err(message) = self.apply_config_register(register, value) {
+                        errors.push((line_of_key(text, &[name, register]), message));
+                    }
+                }
+                continue;
+            }
+
+            let Some((modes, noremap)) = mapping::map_command_modes(name) else {
+                errors.push((line_of_key(text, &[name]), format!("Unknown table: {name}")));
+                continue;
+            };
+            for (lhs, rhs) in maps.as_table().into_iter().flatten() {
+                let result = rhs
+                    .as_str()
+                    .ok_or(format!("{lhs} is mapped to a string"))
+                    .and_then(|rhs| self.map_keys(modes, lhs, rhs, noremap));
+                if let Err(message) = result {
+                    errors.push((line_of_key(text, &[name, lhs]), message));
+                }
+            }
+        }
+
+        errors.sort_by_key(|(line, _)| *line);
+        errors
+    }
+
+    fn apply_config_option(&mut self, key: &str, value: &Value) -> Result<(), String> {
+        if key == "colorscheme" {
+            return match value.as_str() {
+                Some(name) if self.themes.select(name) => Ok(()),
+                Some(name) => Err(format!("Cannot find color scheme '{name}'")),
+                None => Err(String::from("colorscheme is a string")),
+            };
+        }
+
+        let info = OptionInfo::find(key).ok_or(format!("Unknown option: {key}"))?;
+        let value = info.parse_toml(value)?;
+        self.set_option(info, value, false)
+    }
+
+    /// Writes the keys `value` into register `name`, as if they were
+    /// recorded with `q{name}`.
+    fn apply_config_register(&mut self, name: &str, value: &Value) -> Result<(), String> {
+        let mut chars = name.chars();
+        let register = match (chars.next(), chars.next()) {
+            (Some(c), None) if c.is_ascii_alphanumeric() || c == '"' => c,
+            _ => return Err(format!("Invalid register name: '{name}'")),
+        };
+        let text = value
+            .as_str()
+            .ok_or(format!("register {name} is a string"))?;
+
+        let keys = keys::to_notation(&keys::parse_notation(text));
+        self.registers.record(register, &keys);
+        Ok(())
+    }
+}
@@ -0,0 +1,200 @@
+use crate::{
+    editor::{Control, Register, SelectionKind},
+    utils::Position,
+};
+
+/// Bytes a counted put can add at once, `99999p` would fill the memory.
+const MAX_PUT_BYTES: usize = 10_000_000;
+
+impl Control<'_> {
+    /// Contents of register `name`, `%` being the name of the current file
+    /// and `+` and `*` the system clipboard.
+    pub fn read_register(&mut self, name: char) -> Option<Register> {
+        match name {
+            '%' => self
+                .read_document(|doc| doc.file_name().map(Register::from_text))
+                .flatten(),
+            '+' | '*' => match self.clipboard.paste() {
+                Ok(register) => register,
+                Err(err) => {
+                    self.error = Some(format!("Can't read the clipboard: {err}"));
+                    None
+                }
+            },
+            _ => self.registers.get(name),
+        }
+    }
+
+    /// Keeps yanked or deleted text in register `name`, or the default ones
+    /// when it is `None`.
+    pub fn store_register(&mut self, name: Option<char>, register: Register, deleted: bool) {
+        if let Some('+' | '*') = name {
+            if let Err(err) = self.clipboard.copy(&register) {
+                self.error = Some(format!("Can't copy to the clipboard: {err}"));
+            }
+        }
+
+        if deleted {
+            self.registers.delete(name, register);
+        } else {
+            self.registers.yank(name, register);
+        }
+    }
+
+    /// Puts the text of the register given with `"`, or the unnamed one,
+    /// `count` times after the cursor (`p`) or before it (`P`).
+    pub fn put(&mut self, before: bool, count: usize) {
+        if !self.can_use_register(false) {
+            return;
+        }
+
+        let name = self.register_name.take().unwrap_or('"');
+        let Some(register) = self.read_register(name) else {
+            if self.error.is_none() {
+                self.error = Some(format!("Nothing in register {name}"));
+            }
+            return;
+        };
+
+        let Some(register) = repeat_register(register, count) else {
+            self.error = Some(String::from("Too much text to put"));
+            return;
+        };
+
+        let Some(pos) = self.document_position() else {
+            return;
+        };
+
+        let column = if before {
+            pos.x
+        } else {
+            self.read_document(|doc| doc.row(pos.y).map(|row| row.next_char_index(pos.x, 1)))
+                .flatten()
+                .unwrap_or(pos.x)
+        };
+
+        match register.kind {
+            SelectionKind::Char => {
+                let at = Position {
+                    x: column,
+                    y: pos.y,
+                };
+                let end = self.edit_document(|doc| doc.insert_text(&at, &register.lines));
+
+                // on the last put char, or the first one if it spans lines
+                if let Some(end) = end {
+                    if register.lines.len() > 1 {
+                        self.set_document_position(at);
+                    } else {
+                        let x = self
+                            .read_document(|doc| {
+                                doc.row(end.y).map(|row| row.previous_char_index(end.x, 1))
+                            })
+                            .flatten()
+                            .unwrap_or(end.x);
+                        self.set_document_position(Position { x, y: end.y });
+                    }
+                }
+            }
+            SelectionKind::Line => {
+                let line = if before { pos.y } else { pos.y + 1 };
+                self.edit_document(|doc| doc.insert_rows(line, &register.lines));
+                self.go_to_line(line);
+                self.go_to_first_non_blank();
+            }
+            SelectionKind::Block => {
+                self.edit_document(|doc| {
+                    for (i, text) in register.lines.iter().enumerate() {
+                        let y = pos.y + i;
+                        if y >= doc.lines_len() {
+                            doc.insert_rows(y, &[String::new()]);
+                        }
+
+                        let line_len = doc.row(y).map_or(0, |row| row.content.len());
+                        if line_len < column {
+                            let padding = " ".repeat(column - line_len);
+                            doc.insert_text(&Position { x: line_len, y }, &[padding]);
+                        }
+
+                        doc.insert_text(&Position { x: column, y }, std::slice::from_ref(text));
+                    }
+                });
+                self.set_document_position(Position {
+                    x: column,
+                    y: pos.y,
+                });
+            }
+        }
+    }
+
+    /// Lists the registers holding something (`:registers`), only the ones
+    /// in `names` when it is not empty.
+    pub fn list_registers(&mut self, names: &str) {
+        let mut filled = self.registers.filled();
+        for name in ['%', '+', '*'] {
+            if let Some(register) = self.read_register(name) {
+                filled.push((name, register));
+            }
+        }
+
+        let mut lines = vec![String::from("Type Name Content")];
+        for (name, register) in filled {
+            if !names.is_empty() && !names.contains(name) {
+                continue;
+            }
+
+            let kind = match register.kind {
+                SelectionKind::Char => 'c',
+                SelectionKind::Line => 'l',
+                SelectionKind::Block => 'b',
+            };
+            lines.push(format!("  {kind}  \"{name}   {}", register.preview()));
+        }
+
+        self.message = Some(lines.join("\n"));
+    }
+}
+
+/// The text of `register` put `count` times in a row: chars follow each
+/// other, lines are below each other and blocks are side by side. `None`
+/// when it would be more than `MAX_PUT_BYTES`.
+fn repeat_register(register: Register, count: usize) -> Option<Register> {
+    let size: usize = register.lines.iter().map(|line| line.len() + 1).sum();
+    if size.checked_mul(count)? > MAX_PUT_BYTES {
+        return None;
+    }
+    if count <= 1 {
+        return Some(register);
+    }
+
+    let lines = match register.kind {
+        SelectionKind::Char => register
+            .lines
+            .join("\n")
+            .repeat(count)
+            .split('\n')
+            .map(String::from)
+            .collect(),
+        SelectionKind::Line => register
+            .lines
+            .iter()
+            .cycle()
+            .take(register.lines.len() * count)
+            .cloned()
+            .collect(),
+        SelectionKind::Block => {
+            // short lines are padded so the copies line up
+            let width = register.lines.iter().map(|line| line.chars().count()).max();
+            let width = width.unwrap_or(0);
+            register
+                .lines
+                .iter()
+                .map(|line| format!("{line:width$}").repeat(count - 1) + line)
+                .collect()
+        }
+    };
+    Some(Register {
+        lines,
+        kind: register.kind,
+    })
+}
@@ -0,0 +1,138 @@
+use std::cmp;
+
+use crate::editor::Control;
+
+/// Lines an ex command applies to, both ends included.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct LineRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl LineRange {
+    pub fn line(line: usize) -> Self {
+        Self {
+            start: line,
+            end: line,
+        }
+    }
+}
+
+impl Control<'_> {
+    /// Reads the range at the start of an ex command, like `%`, `.,+5` or
+    /// `'<,'>`. Returns it, if there is one, with the rest of the command.
+    pub fn parse_range<'c>(
+        &self,
+        command: &'c str,
+    ) -> Result<(Option<LineRange>, &'c str), String> {
+        let last_line = self
+            .read_document(|doc| doc.lines_len().saturating_sub(1))
+            .unwrap_or(0);
+
+        if let Some(rest) = command.strip_prefix('%') {
+            return Ok((
+                Some(LineRange {
+                    start: 0,
+                    end: last_line,
+                }),
+                rest,
+            ));
+        }
+
+        let current = self.document_position().map_or(0, |pos| pos.y);
+        let (start, rest) = self.parse_address(command, current)?;
+        let Some(start) = start else {
+            return Ok((None, rest));
+        };
+
+        // after `;` the second address counts from the first one
+        let (end, rest) = match rest.chars().next() {
+            Some(',') => self.parse_address(&rest[1..], current)?,
+            Some(';') => self.parse_address(&rest[1..], start)?,
+            _ => (None, rest),
+        };
+        let end = end.unwrap_or(start);
+
+        if cmp::max(start, end) > last_line {
+            return Err(String::from("Invalid range"));
+        }
+
+        Ok((
+            Some(LineRange {
+                start: cmp::min(start, end),
+                end: cmp::max(start, end),
+            }),
+            rest,
+        ))
+    }
+
+    /// One line of a range: `.`, `$`, a line number or a mark, followed by
+    /// `+n` and `-n` offsets.
+    fn parse_address<'c>(
+        &self,
+        text: &'c str,
+        current: usize,
+    ) -> Result<(Option<usize>, &'c str), String> {
+        let mut chars = text.chars();
+        let (mut line, mut rest) = match chars.next() {
+            Some('.') => (Some(current), chars.as_str()),
+            Some('$') => (
+                self.read_document(|doc| doc.lines_len().saturating_sub(1)),
+                chars.as_str(),
+            ),
+            Some('\'') => {
+                let name = chars.next().ok_or("Invalid range")?;
+                let line = self
+                    .mark_line(name)
+                    .ok_or_else(|| format!("Mark not set: {name}"))?;
+                (Some(line), chars.as_str())
+            }
+            Some('0'..='9') => {
+                let (number, rest) = split_number(text)?;
+                // lines are counted from 1
+                (number.map(|number| number.saturating_sub(1)), rest)
+            }
+            // a lone offset counts from the current line
+            Some('+' | '-') => (Some(current), text),
+            _ => (None, text),
+        };
+
+        while let Some(sign @ ('+' | '-')) = rest.chars().next() {
+            let (number, after) = split_number(&rest[1..])?;
+            let offset = number.unwrap_or(1);
+
+            let base = line.unwrap_or(current);
+            let line_number = if sign == '+' {
+                base.checked_add(offset)
+            } else {
+                base.checked_sub(offset)
+            };
+            line = Some(line_number.ok_or("Invalid range")?);
+            rest = after;
+        }
+
+        Ok((line, rest))
+    }
+
+    /// Line of mark `name`, `<` and `>` being the start and the end of the
+    /// last visual selection.
+    fn mark_line(&self, name: char) -> Option<usize> {
+        self.mark_position(name).map(|pos| pos.y)
+    }
+}
+
+/// Splits the number at the start of `text`, if it starts with digits, from
+/// what follows it.
+fn split_number(text: &str) -> Result<(Option<usize>, &str), String> {
+    let end = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    if end == 0 {
+        return Ok((None, text));
+    }
+
+    let number = text[..end]
+        .parse()
+        .map_err(|_| String::from("Invalid range"))?;
+    Ok((Some(number), &text[end..]))
+}
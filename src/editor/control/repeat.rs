@@ -0,0 +1,212 @@
+use termion::event::Key;
+
+use crate::editor::{
+    keymap::{Action, MapMode},
+    Control, Mode, Selection, SelectionKind,
+};
+
+/// A change, as the keys typing it again.
+#[derive(Clone)]
+struct Change {
+    /// keys of the command, with the text inserted after it and the `Esc`
+    /// leaving insert mode
+    keys: Vec<Key>,
+    count: Option<usize>,
+}
+
+/// Last change, repeated by `.`.
+#[derive(Default)]
+pub struct RepeatState {
+    /// change whose inserted text is being typed
+    inserting: Option<Change>,
+    /// command just run, kept once it is known to have changed the document
+    pending: Option<Change>,
+    last: Option<Change>,
+}
+
+/// Whether `action` is repeated by `.`, the commands that change the
+/// document are.
+fn is_change(action: Action) -> bool {
+    match action {
+        Action::Yank | Action::CreateFold => false,
+        _ if action.is_operator() => true,
+        _ => matches!(
+            action,
+            Action::Insert
+                | Action::Append
+                | Action::InsertAtLineStart
+                | Action::AppendAtLineEnd
+                | Action::OpenLineBelow
+                | Action::OpenLineAbove
+                | Action::PutAfter
+                | Action::PutBefore
+                | Action::DeleteChar
+                | Action::DeleteCharBefore
+                | Action::DeleteToLineEnd
+                | Action::ChangeToLineEnd
+                | Action::ToggleCase
+                | Action::Lowercase
+                | Action::Uppercase
+                | Action::BlockInsert
+                | Action::BlockAppend
+        ),
+    }
+}
+
+impl Control<'_> {
+    /// Keeps the command `keys` run with `count` in normal mode as the
+    /// change repeated by `.`, once `finish_repeated_change` tells it
+    /// changed the document. A command entering insert mode is kept once
+    /// insert mode is left, with the keys typed in it.
+    pub fn record_change(&mut self, register: Option<char>, keys: &[Key], count: Option<usize>) {
+        let Some((action, len)) = self.keymap.command(MapMode::Normal, keys) else {
+            return;
+        };
+        if !is_change(action) {
+            return;
+        }
+
+        let (keys, count) = if action.is_operator() {
+            // the count of the motion is merged with the command's one, so
+            // that a new count replaces both
+            let (operator_keys, motion) = keys.split_at(len);
+            let (motion_count, motion) = super::split_count(motion);
+            let count = match (count, motion_count) {
+                (None, None) => None,
+                (count, motion_count) => {
+                    Some(count.unwrap_or(1).saturating_mul(motion_count.unwrap_or(1)))
+                }
+            };
+            (operator_keys.iter().chain(motion).copied().collect(), count)
+        } else {
+            (keys.to_vec(), count)
+        };
+
+        let keys = [self.register_keys(register), keys].concat();
+        self.keep_change(Change { keys, count });
+    }
+
+    /// Keeps `action`, run with the keys `command` on `selection` in visual
+    /// mode, as the change repeated by `.`. It is repeated on as many lines
+    /// and chars from the cursor.
+    pub fn record_visual_change(
+        &mut self,
+        register: Option<char>,
+        selection: &Selection,
+        action: Action,
+        command: &[Key],
+    ) {
+        if !is_change(action) {
+            return;
+        }
+
+        let keys = [
+            self.reselect_keys(selection),
+            self.register_keys(register),
+            command.to_vec(),
+        ]
+        .concat();
+        self.keep_change(Change { keys, count: None });
+    }
+
+    fn keep_change(&mut self, change: Change) {
+        if matches!(self.mode, Mode::Insert) {
+            self.repeat.inserting = Some(change);
+        } else {
+            self.repeat.pending = Some(change);
+        }
+    }
+
+    /// Keys giving the register `register` to the next command, none for
+    /// the default one.
+    fn register_keys(&self, register: Option<char>) -> Vec<Key> {
+        let Some(name) = register else {
+            return Vec::new();
+        };
+
+        let mut keys = self.keymap.keys(MapMode::Normal, Action::SelectRegister);
+        keys.push(Key::Char(name));
+        keys
+    }
+
+    /// Keys selecting from the cursor as many lines as `selection`, and as
+    /// many chars on its last line or columns for a block.
+    fn reselect_keys(&self, selection: &Selection) -> Vec<Key> {
+        let (start, end) = (selection.start(), selection.end());
+        let chars = |y: usize, from: usize, to: usize| {
+            self.read_document(|doc| {
+                doc.row(y)
+                    .map_or(0, |row| row.slice(from..to).chars().count())
+            })
+            .unwrap_or(0)
+        };
+        let (kind, columns) = match selection.kind {
+            SelectionKind::Line => (Action::VisualLine, 0),
+            SelectionKind::Char if start.y == end.y => {
+                (Action::VisualChar, chars(end.y, start.x, end.x))
+            }
+            SelectionKind::Char => (Action::VisualChar, chars(end.y, 0, end.x)),
+            SelectionKind::Block => {
+                let (left, right) = selection.block_columns();
+                (Action::VisualBlock, chars(selection.cursor.y, left, right))
+            }
+        };
+
+        let counted = |count: usize, action: Action| {
+            if count == 0 {
+                return Vec::new();
+            }
+            let digits = count.to_string().chars().map(Key::Char).collect::<Vec<_>>();
+            [digits, self.keymap.keys(MapMode::Visual, action)].concat()
+        };
+
+        let mut keys = self.keymap.keys(MapMode::Normal, kind);
+        keys.extend(counted(end.y - start.y, Action::Down));
+        // a characterwise selection over several lines ends on a column of
+        // its last line
+        if selection.kind == SelectionKind::Char && end.y > start.y {
+            keys.extend(self.keymap.keys(MapMode::Visual, Action::LineStart));
+        }
+        keys.extend(counted(columns, Action::Right));
+        keys
+    }
+
+    /// Keeps the command just run as the change repeated by `.` if it
+    /// `modified` the document.
+    pub fn finish_repeated_change(&mut self, modified: bool) {
+        let pending = self.repeat.pending.take();
+        if modified && pending.is_some() {
+            self.repeat.last = pending;
+        }
+    }
+
+    /// Adds `key`, typed in insert mode, to the change that entered it.
+    pub fn record_insert_key(&mut self, key: Key) {
+        if let Some(change) = &mut self.repeat.inserting {
+            change.keys.push(key);
+        }
+    }
+
+    /// Keeps the change that entered insert mode once it is left.
+    pub fn finish_insert_change(&mut self) {
+        if !matches!(self.mode, Mode::Insert) {
+            if let Some(change) = self.repeat.inserting.take() {
+                self.repeat.last = Some(change);
+            }
+        }
+    }
+
+    /// `.` makes the last change again, `count` replacing its count. It is
+    /// undone at once.
+    pub fn repeat_last_change(&mut self, count: Option<usize>) {
+        let Some(change) = self.repeat.last.clone() else {
+            return;
+        };
+
+        let count = count.or(change.count);
+        let count = count.map(|count| count.to_string()).unwrap_or_default();
+        let keys: Vec<Key> = count.chars().map(Key::Char).chain(change.keys).collect();
+
+        self.replay_keys(&keys);
+    }
+}
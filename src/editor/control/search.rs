@@ -0,0 +1,228 @@
+use termion::event::Key;
+
+use crate::{
+    document::{Pattern, SearchDirection},
+    editor::{Control, Mode},
+    utils::Position,
+};
+
+/// Searches typed after `/` and `?`, and the last one repeated by `n` and `N`.
+#[derive(Default)]
+pub struct SearchState {
+    last: Option<(Pattern, SearchDirection)>,
+    /// cursor when the search prompt was opened, where typed searches start
+    start: Option<Position>,
+    history: Vec<String>,
+    /// entry of `history` shown on the prompt while browsing it
+    history_index: Option<usize>,
+    /// matches of the last search are highlighted, until `:nohlsearch`
+    highlight: bool,
+}
+
+impl Control<'_> {
+    pub fn process_key_in_search_mode(&mut self, key: Key, direction: SearchDirection) {
+        match key {
+            Key::Esc => self.cancel_search(),
+            Key::Char('\n') => self.accept_search(direction),
+            Key::Char(c) => {
+                self.command_line.push(c);
+                self.incremental_search(direction);
+            }
+            // deleting past the `/` leaves search mode
+            Key::Backspace if self.command_line.is_empty() => self.cancel_search(),
+            Key::Backspace => {
+                self.command_line.pop();
+                self.incremental_search(direction);
+            }
+            Key::Up => self.browse_search_history(true, direction),
+            Key::Down => self.browse_search_history(false, direction),
+            _ => {}
+        }
+    }
+
+    pub fn enter_search_mode(&mut self, direction: SearchDirection) {
+        self.command_line.clear();
+        self.search.start = self.document_position();
+        self.search.history_index = None;
+        self.mode = Mode::Search(direction);
+    }
+
+    fn exit_search_mode(&mut self) {
+        self.command_line.clear();
+        self.mode = Mode::Normal;
+        self.sync_search_highlight();
+    }
+
+    /// Puts the cursor back where it was before the search was typed.
+    fn cancel_search(&mut self) {
+        if let Some(start) = self.search.start.take() {
+            self.set_document_position(start);
+        }
+        self.exit_search_mode();
+    }
+
+    /// Searches the typed pattern, or the last one if nothing was typed.
+    fn accept_search(&mut self, direction: SearchDirection) {
+        let text = std::mem::take(&mut self.command_line);
+        if let Some(start) = self.search.start.take() {
+            self.set_document_position(start);
+        }
+        let origin = self.jump_origin();
+
+        if !text.is_empty() {
+            self.search.history.retain(|entry| *entry != text);
+            self.search.history.push(text.clone());
+
+            let Ok(pattern) = Pattern::new(&text) else {
+                self.exit_search_mode();
+                self.error = Some(format!("Invalid pattern: {text}"));
+                return;
+            };
+            self.search.last = Some((pattern, direction));
+        } else if let Some((_, last_direction)) = &mut self.search.last {
+            *last_direction = direction;
+        }
+
+        self.exit_search_mode();
+        self.search_next(false);
+        self.finish_jump(origin);
+    }
+
+    /// Moves to the first match of what is typed so far, while it is typed.
+    pub fn incremental_search(&mut self, direction: SearchDirection) {
+        let Some(start) = self.search.start else {
+            return;
+        };
+
+        let found = Pattern::new(&self.command_line).ok().and_then(|pattern| {
+            self.read_document(|doc| doc.search(&pattern, &start, direction))
+                .flatten()
+        });
+        self.set_document_position(found.map_or(start, |found| found.position));
+        self.sync_search_highlight();
+    }
+
+    /// Shows an older (`Up`) or a newer (`Down`) search on the prompt.
+    fn browse_search_history(&mut self, older: bool, direction: SearchDirection) {
+        let len = self.search.history.len();
+        let index = match (self.search.history_index, older) {
+            (None, true) => len.checked_sub(1),
+            (Some(index), true) => Some(index.saturating_sub(1)),
+            (Some(index), false) if index + 1 < len => Some(index + 1),
+            _ => None,
+        };
+
+        self.search.history_index = index;
+        self.command_line = index
+            .and_then(|index| self.search.history.get(index))
+            .cloned()
+            .unwrap_or_default();
+        self.incremental_search(direction);
+    }
+
+    /// Repeats the last search in the same direction (`n`) or the other one
+    /// (`N`).
+    pub fn search_next(&mut self, reverse: bool) {
+        let Some((pattern, direction)) = self.search.last.clone() else {
+            self.error = Some(String::from("No previous search pattern"));
+            return;
+        };
+        let direction = if reverse {
+            direction.reversed()
+        } else {
+            direction
+        };
+
+        self.search.highlight = true;
+        self.sync_search_highlight();
+
+        let Some(pos) = self.document_position() else {
+            return;
+        };
+
+        let found = self
+            .read_document(|doc| doc.search(&pattern, &pos, direction))
+            .flatten();
+        let Some(found) = found else {
+            self.error = Some(format!("Pattern not found: {}", pattern.text));
+            self.fail_macro();
+            return;
+        };
+
+        self.set_document_position(found.position);
+        if found.wrapped {
+            self.message = Some(String::from(match direction {
+                SearchDirection::Forward => "search hit BOTTOM, continuing at TOP",
+                SearchDirection::Backward => "search hit TOP, continuing at BOTTOM",
+            }));
+        }
+    }
+
+    /// Searches the word under the cursor as a whole word (`*` and `#`).
+    pub fn search_word_under_cursor(&mut self, direction: SearchDirection) {
+        let Some(pos) = self.document_position() else {
+            return;
+        };
+
+        let word = self
+            .read_document(|doc| {
+                let columns = doc.word_at(&pos)?;
+                let word = doc.row(pos.y)?.content.get(columns.clone())?.to_string();
+                Some((columns.start, word))
+            })
+            .flatten();
+        let Some((start, word)) = word else {
+            self.error = Some(String::from("No string under cursor"));
+            return;
+        };
+
+        let pattern = Pattern::word(&word, self.options.editor.ignorecase);
+        self.search.history.retain(|entry| *entry != pattern.text);
+        self.search.history.push(pattern.text.clone());
+        self.search.last = Some((pattern, direction));
+
+        // from the start of the word, so that `#` skips it
+        self.set_document_position(Position { x: start, y: pos.y });
+        self.search_next(false);
+    }
+
+    pub fn last_search_pattern(&self) -> Option<Pattern> {
+        self.search
+            .last
+            .as_ref()
+            .map(|(pattern, _)| pattern.clone())
+    }
+
+    /// Makes `pattern` the one repeated by `n`, keeping the direction of the
+    /// last search.
+    pub fn remember_search(&mut self, pattern: Pattern) {
+        let direction = self
+            .search
+            .last
+            .as_ref()
+            .map_or(SearchDirection::Forward, |(_, direction)| *direction);
+        self.search.last = Some((pattern, direction));
+        self.search.highlight = true;
+        self.sync_search_highlight();
+    }
+
+    /// `:nohlsearch`, matches are highlighted again by the next search.
+    pub fn stop_search_highlight(&mut self) {
+        self.search.highlight = false;
+        self.sync_search_highlight();
+    }
+
+    /// Highlights in every window what is being typed on the search prompt,
+    /// or else the last search.
+    fn sync_search_highlight(&mut self) {
+        let pattern = match self.mode {
+            Mode::Search(_) => Pattern::new(&self.command_line).ok(),
+            _ if self.search.highlight => self.search.last.as_ref().map(|(p, _)| p.clone()),
+            _ => None,
+        };
+
+        for window in self.borrow_mut_windows().iter_mut() {
+            window.set_search_pattern(pattern.clone());
+        }
+    }
+}
@@ -0,0 +1,288 @@
+use termion::event::Key;
+
+use crate::{
+    document::{Document, Pattern, Replacement},
+    editor::{control::range::LineRange, Control, Mode},
+    utils::Position,
+};
+
+/// A `:substitute` going through the matches in its range.
+pub struct Substitution {
+    pattern: Pattern,
+    replacement: Replacement,
+    /// every match of a line is replaced, not only the first one (`g`)
+    global: bool,
+    /// each replacement is confirmed first (`c`)
+    confirm: bool,
+    last_line: usize,
+    /// where the next match is looked for, or the match being confirmed
+    next: Position,
+    /// `next` is right after a replaced match
+    after_match: bool,
+    /// matches found, replaced or not
+    matches: usize,
+    count: usize,
+    lines: usize,
+    last_changed_line: Option<usize>,
+}
+
+impl Substitution {
+    /// Start of the next match in the range, from `next`.
+    fn find_next(&self, doc: &Document) -> Option<Position> {
+        (self.next.y..=self.last_line).find_map(|y| {
+            let line = &doc.row(y)?.content;
+            let from = if y == self.next.y { self.next.x } else { 0 };
+            let found = self.pattern.find_at(line, from)?;
+
+            // no empty match right where the last match ended
+            let found = if self.after_match && y == self.next.y && found == (from..from) {
+                let skipped = line[from..].chars().next()?.len_utf8();
+                self.pattern.find_at(line, from + skipped)?
+            } else {
+                found
+            };
+
+            Some(Position { x: found.start, y })
+        })
+    }
+
+    /// Replaces the match at `next` and looks for the following one after
+    /// the replacement.
+    fn replace_next(&mut self, doc: &mut Document) {
+        let Position { x, y } = self.next;
+        let Some(found) = doc
+            .row(y)
+            .and_then(|row| self.pattern.find_at(&row.content, x))
+        else {
+            return;
+        };
+        let Some(replaced) = doc.replace_match(y, x, &self.pattern, &self.replacement) else {
+            return;
+        };
+
+        self.count += 1;
+        if self.last_changed_line != Some(y) {
+            self.lines += 1;
+            self.last_changed_line = Some(y);
+        }
+
+        if let Some(row) = doc.row(y) {
+            self.advance(y, replaced.end, found.is_empty(), &row.content);
+        }
+    }
+
+    /// Leaves the match at `next` as it is.
+    fn skip_next(&mut self, doc: &Document) {
+        let Position { x, y } = self.next;
+        let Some(line) = doc.row(y).map(|row| &row.content) else {
+            return;
+        };
+
+        if let Some(found) = self.pattern.find_at(line, x) {
+            self.advance(y, found.end, found.is_empty(), line);
+        }
+    }
+
+    /// Moves `next` to `end` in row `y`, one char further after an empty
+    /// match so that it is not found again, or to the next row when only the
+    /// first match of a line is replaced.
+    fn advance(&mut self, y: usize, end: usize, empty: bool, line: &str) {
+        let x = if empty {
+            line[end..].chars().next().map(|c| end + c.len_utf8())
+        } else {
+            Some(end)
+        };
+
+        self.after_match = !empty;
+        self.next = match x {
+            Some(x) if self.global => Position { x, y },
+            _ => Position { x: 0, y: y + 1 },
+        };
+    }
+}
+
+impl Control<'_> {
+    /// `:s/pattern/replacement/flags` over the lines of `range`.
+    pub fn substitute(&mut self, range: LineRange, args: &str) {
+        let mut chars = args.chars();
+        let Some(delimiter) = chars
+            .next()
+            .filter(|c| !c.is_alphanumeric() && !matches!(c, '\\' | '"' | '|' | ' '))
+        else {
+            self.error = Some(String::from("Usage: :s/pattern/replacement/flags"));
+            return;
+        };
+
+        let (pattern, rest) = split_delimited(chars.as_str(), delimiter);
+        let (replacement, flags) = rest.map_or((String::new(), ""), |rest| {
+            let (replacement, flags) = split_delimited(rest, delimiter);
+            (replacement, flags.unwrap_or(""))
+        });
+
+        let mut ignore_case = None;
+        let mut global = false;
+        let mut confirm = false;
+        for flag in flags.trim().chars() {
+            match flag {
+                'g' => global = true,
+                'c' => confirm = true,
+                'i' => ignore_case = Some(true),
+                'I' => ignore_case = Some(false),
+                _ => {
+                    self.error = Some(format!("Trailing characters: {flags}"));
+                    return;
+                }
+            }
+        }
+
+        // an empty pattern is the last one searched
+        let text = if pattern.is_empty() {
+            let Some(last) = self.last_search_pattern() else {
+                self.error = Some(String::from("No previous search pattern"));
+                return;
+            };
+            last.text
+        } else {
+            pattern
+        };
+        let pattern = match ignore_case {
+            Some(ignore_case) => Pattern::with_case(&text, ignore_case),
+            None => Pattern::new(&text),
+        };
+        let Ok(pattern) = pattern else {
+            self.error = Some(format!("Invalid pattern: {text}"));
+            return;
+        };
+
+        self.remember_search(pattern.clone());
+        self.continue_substitution(Substitution {
+            pattern,
+            replacement: Replacement::parse(&replacement),
+            global,
+            // `:global` moves on to the next line without waiting for answers
+            confirm: confirm && !self.global_busy,
+            last_line: range.end,
+            next: Position {
+                x: 0,
+                y: range.start,
+            },
+            after_match: false,
+            matches: 0,
+            count: 0,
+            lines: 0,
+            last_changed_line: None,
+        });
+    }
+
+    /// Answers whether to replace the match under the cursor, `y` for yes,
+    /// `n` for no, `a` for this one and all the next ones, `l` for this last
+    /// one and `q` to stop.
+    pub fn process_key_in_confirm_mode(&mut self, key: Key) {
+        let Some(mut substitution) = self.substitution.take() else {
+            self.mode = Mode::Normal;
+            return;
+        };
+
+        match key {
+            Key::Char('y') => {
+                self.edit_document(|doc| substitution.replace_next(doc));
+                self.continue_substitution(substitution);
+            }
+            Key::Char('l') => {
+                self.edit_document(|doc| substitution.replace_next(doc));
+                self.finish_substitution(&substitution);
+            }
+            Key::Char('a') => {
+                substitution.confirm = false;
+                self.edit_document(|doc| substitution.replace_next(doc));
+                self.continue_substitution(substitution);
+            }
+            Key::Char('n') => {
+                self.read_document(|doc| substitution.skip_next(doc));
+                self.continue_substitution(substitution);
+            }
+            Key::Char('q') | Key::Esc => self.finish_substitution(&substitution),
+            _ => {
+                self.substitution = Some(substitution);
+                self.ask_confirmation();
+            }
+        }
+    }
+
+    /// Replaces matches until one needs to be confirmed or there are no more.
+    fn continue_substitution(&mut self, mut substitution: Substitution) {
+        loop {
+            let found = self
+                .read_document(|doc| substitution.find_next(doc))
+                .flatten();
+            let Some(found) = found else {
+                self.finish_substitution(&substitution);
+                return;
+            };
+
+            substitution.matches += 1;
+            substitution.next = found;
+            if substitution.confirm {
+                self.mode = Mode::Confirm;
+                self.set_document_position(found);
+                self.substitution = Some(substitution);
+                self.ask_confirmation();
+                return;
+            }
+
+            self.edit_document(|doc| substitution.replace_next(doc));
+        }
+    }
+
+    fn ask_confirmation(&mut self) {
+        self.message = Some(String::from("replace this match? (y/n/a/q/l)"));
+    }
+
+    /// Leaves the cursor on the last changed line and reports what changed.
+    fn finish_substitution(&mut self, substitution: &Substitution) {
+        self.mode = Mode::Normal;
+        self.message = None;
+
+        if let Some(line) = substitution.last_changed_line {
+            self.go_to_line(line);
+            self.go_to_first_non_blank();
+        }
+
+        if substitution.matches == 0 {
+            self.error = Some(format!("Pattern not found: {}", substitution.pattern.text));
+        } else if substitution.count > 0 {
+            self.message = Some(format!(
+                "{} substitution{} on {} line{}",
+                substitution.count,
+                if substitution.count == 1 { "" } else { "s" },
+                substitution.lines,
+                if substitution.lines == 1 { "" } else { "s" },
+            ));
+        }
+    }
+}
+
+/// Splits `text` at the first `delimiter` not escaped by a backslash.
+/// Returns the text before it, without the escaping backslashes, and what
+/// follows it if there is a delimiter.
+pub fn split_delimited(text: &str, delimiter: char) -> (String, Option<&str>) {
+    let mut part = String::new();
+    let mut chars = text.char_indices();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some((_, escaped)) if escaped == delimiter => part.push(escaped),
+                Some((_, escaped)) => {
+                    part.push('\\');
+                    part.push(escaped);
+                }
+                None => part.push('\\'),
+            },
+            _ if c == delimiter => return (part, Some(&text[i + c.len_utf8()..])),
+            _ => part.push(c),
+        }
+    }
+
+    (part, None)
+}
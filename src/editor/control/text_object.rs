@@ -0,0 +1,82 @@
+use crate::{
+    document::{ObjectRange, TextObject},
+    editor::{control::operator::Operator, Control, Mode, Selection, SelectionKind},
+    utils::Position,
+};
+
+impl Control<'_> {
+    /// Text of object `name` typed after `i` or `a` (`around`) from the
+    /// cursor.
+    fn object_range(&self, name: char, around: bool, count: usize) -> Option<ObjectRange> {
+        let object = TextObject::from_char(name)?;
+        let pos = self.document_position()?;
+
+        self.read_document(|doc| doc.text_object(&pos, object, around, count))
+            .flatten()
+    }
+
+    /// Selection of the text in `range`, `None` if it is empty like the
+    /// inside of `()`.
+    fn object_selection(&self, range: ObjectRange) -> Option<Selection> {
+        match range {
+            ObjectRange::Lines(start, end) => Some(Selection {
+                anchor: Position { x: 0, y: start },
+                cursor: Position { x: 0, y: end },
+                kind: SelectionKind::Line,
+            }),
+            ObjectRange::Chars(start, end) if start == end => None,
+            ObjectRange::Chars(start, end) => Some(Selection {
+                anchor: start,
+                cursor: self
+                    .read_document(|doc| doc.previous_position(&end))
+                    .flatten()
+                    .unwrap_or(start),
+                kind: SelectionKind::Char,
+            }),
+        }
+    }
+
+    /// Runs `operator` on text object `name` (`diw`, `ca(`, `yit`).
+    pub fn apply_operator_on_object(
+        &mut self,
+        operator: Operator,
+        name: char,
+        around: bool,
+        count: usize,
+    ) {
+        let Some(range) = self.object_range(name, around, count) else {
+            self.fail_macro();
+            return;
+        };
+
+        match (self.object_selection(range), range) {
+            (Some(selection), _) => self.run_operator(operator, &selection),
+            // changing an empty object inserts where it is
+            (None, ObjectRange::Chars(start, _)) => {
+                if let Operator::Change = operator {
+                    self.set_document_position(start);
+                    self.enter_insert_mode();
+                }
+            }
+            (None, ObjectRange::Lines(..)) => {}
+        }
+    }
+
+    /// Selects text object `name` in visual mode (`viw`, `vap`), a linewise
+    /// object making the selection linewise.
+    pub fn select_object(&mut self, name: char, around: bool, count: usize) {
+        let Some(selection) = self
+            .object_range(name, around, count)
+            .and_then(|range| self.object_selection(range))
+        else {
+            self.fail_macro();
+            return;
+        };
+
+        if selection.kind == SelectionKind::Line {
+            self.mode = Mode::Visual(SelectionKind::Line);
+        }
+        self.visual_anchor = selection.anchor;
+        self.set_document_position(selection.cursor);
+    }
+}
@@ -0,0 +1,106 @@
+use crate::{
+    document::Document,
+    editor::{Control, Snapshot},
+    utils::Position,
+};
+
+/// Start of the change being made, the document keeps the rows it edits.
+pub struct ChangeStart {
+    cursor: Position,
+}
+
+impl Control<'_> {
+    /// Starts a change where the cursor is, unless one is in progress.
+    pub fn start_change(&mut self) {
+        if self.change_start.is_some() {
+            return;
+        }
+        self.change_start = self
+            .document_position()
+            .map(|cursor| ChangeStart { cursor });
+
+        // edits made outside of a change are not undone with this one
+        if let Some(index) = self.attached_window {
+            if let Some(window) = self.borrow_mut_windows().get_mut(index) {
+                window.document_mut().take_change();
+            }
+        }
+    }
+
+    /// Ends the change in progress, it can be undone if the document was
+    /// modified. Returns whether it was.
+    pub fn finish_change(&mut self) -> bool {
+        let Some(ChangeStart { cursor }) = self.change_start.take() else {
+            return false;
+        };
+
+        let Some(change) = self.edit_document(Document::take_change).flatten() else {
+            return false;
+        };
+        self.mark_change(&change);
+
+        if let Some(index) = self.attached_window {
+            if let Some(window) = self.borrow_mut_windows().get_mut(index) {
+                window.history_mut().record(Snapshot { change, cursor });
+            }
+        }
+        true
+    }
+
+    /// `u`
+    pub fn undo(&mut self) {
+        self.travel_history(true);
+    }
+
+    /// `Ctrl-r`
+    pub fn redo(&mut self) {
+        self.travel_history(false);
+    }
+
+    fn travel_history(&mut self, back: bool) {
+        // undoing is not a change by itself
+        self.change_start = None;
+
+        let Some(current) = self.document_position() else {
+            return;
+        };
+
+        let cursor = self.attached_window.and_then(|index| {
+            let mut windows = self.borrow_mut_windows();
+            let window = windows.get_mut(index)?;
+
+            let snapshot = if back {
+                window.history_mut().undo()
+            } else {
+                window.history_mut().redo()
+            }?;
+
+            let doc = window.document_mut();
+            doc.take_change();
+            let change = doc.undo_change(snapshot.change);
+            let replaced = Snapshot {
+                change,
+                cursor: current,
+            };
+            if back {
+                window.history_mut().undone(replaced);
+            } else {
+                window.history_mut().redone(replaced);
+            }
+            window.update_folds();
+
+            Some(snapshot.cursor)
+        });
+
+        let Some(cursor) = cursor else {
+            self.message = Some(String::from(if back {
+                "Already at oldest change"
+            } else {
+                "Already at newest change"
+            }));
+            return;
+        };
+
+        self.set_document_position(cursor);
+    }
+}
@@ -0,0 +1,270 @@
+use std::cmp;
+
+use termion::event::Key;
+
+use super::{insert::BlockInsert, Operator};
+use crate::{
+    editor::{
+        keymap::{Action, MapMode},
+        Control, Mode, Selection, SelectionKind,
+    },
+    utils::{Direction, Position},
+};
+
+/// Case change applied to a selection.
+pub enum Case {
+    Toggle,
+    Lower,
+    Upper,
+}
+
+impl Case {
+    fn apply(&self, text: &str) -> String {
+        match self {
+            Self::Lower => text.to_lowercase(),
+            Self::Upper => text.to_uppercase(),
+            Self::Toggle => text
+                .chars()
+                .map(|c| {
+                    if c.is_lowercase() {
+                        c.to_uppercase().to_string()
+                    } else {
+                        c.to_lowercase().to_string()
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Control<'_> {
+    pub fn process_key_in_visual_mode(&mut self, key: Key) {
+        let Some(keys) = self.take_pending_keys(key) else {
+            return;
+        };
+
+        let count = self.count.take();
+        if let Some((action, len)) = self.keymap.command(MapMode::Visual, &keys) {
+            let args = &keys[len..];
+            if !self.process_counted_movement(action, args, count) {
+                let register = self.register_name;
+                let selection = self.selection();
+                self.run_visual_command(action, args, count.unwrap_or(1));
+                if let Some(selection) = selection {
+                    self.record_visual_change(register, &selection, action, &keys[..len]);
+                }
+            }
+        }
+
+        self.sync_selection();
+    }
+
+    fn run_visual_command(&mut self, action: Action, args: &[Key], count: usize) {
+        // operators act on the selection
+        if let Some(operator) = Operator::from_action(action) {
+            if let Some(selection) = self.take_selection() {
+                self.run_operator(operator, &selection);
+            }
+            return;
+        }
+
+        match (action, args) {
+            (Action::ExitVisual, _) => self.exit_visual_mode(),
+            (Action::VisualChar, _) => self.switch_visual_mode(SelectionKind::Char),
+            (Action::VisualLine, _) => self.switch_visual_mode(SelectionKind::Line),
+            (Action::VisualBlock, _) => self.switch_visual_mode(SelectionKind::Block),
+            (Action::SwapSelectionEnds, _) => self.swap_selection_ends(),
+            (Action::CommandLine, _) => self.enter_command_mode_on_selection(),
+
+            (Action::ToggleCase, _) => self.change_selection_case(&Case::Toggle),
+            (Action::Lowercase, _) => self.change_selection_case(&Case::Lower),
+            (Action::Uppercase, _) => self.change_selection_case(&Case::Upper),
+            (Action::BlockInsert, _) => self.insert_in_block(false),
+            (Action::BlockAppend, _) => self.insert_in_block(true),
+
+            // text objects
+            (Action::InnerObject | Action::AroundObject, [Key::Char(name)]) => {
+                self.select_object(*name, action == Action::AroundObject, count);
+            }
+
+            _ => {}
+        }
+    }
+
+    /// Text selected in visual mode, `None` in any other mode.
+    pub fn selection(&self) -> Option<Selection> {
+        let Mode::Visual(kind) = self.mode else {
+            return None;
+        };
+
+        Some(Selection {
+            anchor: self.visual_anchor,
+            cursor: self.document_position()?,
+            kind,
+        })
+    }
+
+    pub fn enter_visual_mode(&mut self, kind: SelectionKind) {
+        let Some(pos) = self.document_position() else {
+            return;
+        };
+
+        self.visual_anchor = pos;
+        self.mode = Mode::Visual(kind);
+        self.sync_selection();
+    }
+
+    /// Changes the kind of selection, or leaves visual mode when it already
+    /// is of that kind.
+    fn switch_visual_mode(&mut self, kind: SelectionKind) {
+        match self.mode {
+            Mode::Visual(current) if current == kind => self.exit_visual_mode(),
+            _ => self.mode = Mode::Visual(kind),
+        }
+    }
+
+    pub fn exit_visual_mode(&mut self) {
+        if let Some(selection) = self.selection() {
+            self.edit_document(|doc| {
+                doc.set_mark('<', selection.start());
+                doc.set_mark('>', selection.end());
+            });
+        }
+        self.last_selection = self.selection().or(self.last_selection);
+        self.mode = Mode::Normal;
+        self.sync_selection();
+    }
+
+    /// Selects again what was selected the last time visual mode was used
+    /// (`gv`).
+    pub fn reselect(&mut self) {
+        let Some(selection) = self.last_selection else {
+            return;
+        };
+
+        self.visual_anchor = selection.anchor;
+        self.mode = Mode::Visual(selection.kind);
+        self.set_document_position(selection.cursor);
+        self.sync_selection();
+    }
+
+    fn swap_selection_ends(&mut self) {
+        let Some(selection) = self.selection() else {
+            return;
+        };
+
+        self.visual_anchor = selection.cursor;
+        self.set_document_position(selection.anchor);
+    }
+
+    /// Lets the attached window know what to highlight.
+    pub fn sync_selection(&self) {
+        let selection = self.selection();
+
+        if let Some(index) = self.attached_window {
+            if let Some(window) = self.borrow_mut_windows().get_mut(index) {
+                window.set_selection(selection);
+            }
+        }
+    }
+
+    /// Leaves visual mode returning what was selected.
+    fn take_selection(&mut self) -> Option<Selection> {
+        let selection = self.selection()?;
+        self.exit_visual_mode();
+
+        Some(selection)
+    }
+
+    /// Indents or dedents the rows of `selection` by one level of the
+    /// document's indentation, `>>` and `<<` too.
+    pub fn shift_rows(&mut self, selection: &Selection, direction: &Direction) {
+        let lines = selection.start().y..selection.end().y + 1;
+        let count = lines.len();
+
+        self.edit_document(|doc| match direction {
+            Direction::Left => doc.dedent_rows(lines),
+            _ => doc.indent_rows(lines),
+        });
+        self.go_to_line(selection.start().y);
+        self.report_lines(count, "lines shifted");
+    }
+
+    /// Comments out the rows of `selection` with the language's comments,
+    /// or uncomments them (`gc`).
+    pub fn toggle_comments(&mut self, selection: &Selection) {
+        let lines = selection.start().y..selection.end().y + 1;
+
+        if self.edit_document(|doc| doc.toggle_comments(lines)) == Some(false) {
+            self.error = Some(String::from("No comments in this file type"));
+            return;
+        }
+        self.go_to_line(selection.start().y);
+    }
+
+    pub fn change_selection_case(&mut self, case: &Case) {
+        let Some(selection) = self.take_selection() else {
+            return;
+        };
+
+        self.edit_document(|doc| {
+            for y in selection.start().y..=selection.end().y {
+                let line_len = doc.row(y).map_or(0, |row| row.content.len());
+                let Some(columns) = selection.columns(y, line_len) else {
+                    continue;
+                };
+
+                let (from, to) = (columns.start, cmp::min(columns.end, line_len));
+                let text = doc
+                    .row(y)
+                    .map(|row| case.apply(row.slice(from..to)))
+                    .unwrap_or_default();
+
+                doc.delete_range(&Position { x: from, y }, &Position { x: to, y });
+                doc.insert_text(&Position { x: from, y }, &[text]);
+            }
+        });
+        self.set_document_position(Self::selection_corner(&selection));
+    }
+
+    /// Starts inserting before (`I`) or after (`A`) a block, what is typed
+    /// is repeated on every row of it.
+    pub fn insert_in_block(&mut self, append: bool) {
+        let Some(selection) = self.selection() else {
+            return;
+        };
+
+        if selection.kind != SelectionKind::Block {
+            return;
+        }
+
+        self.exit_visual_mode();
+
+        let (left, right) = selection.block_columns();
+        let column = if append { right + 1 } else { left };
+        let top = selection.start().y;
+
+        if append {
+            self.edit_document(|doc| {
+                let line_len = doc.row(top).map_or(0, |row| row.content.len());
+                if line_len < column {
+                    let padding = " ".repeat(column - line_len);
+                    doc.insert_text(
+                        &Position {
+                            x: line_len,
+                            y: top,
+                        },
+                        &[padding],
+                    );
+                }
+            });
+        }
+
+        self.start_block_insert(BlockInsert {
+            top,
+            bottom: selection.end().y,
+            column,
+            pad: append,
+        });
+    }
+}
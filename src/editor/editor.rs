@@ -1,14 +1,12 @@
 use crate::{
-    document::Document,
-    editor::Window,
+    document::BufferOptions,
+    editor::{Clipboard, Highlight, ScreenHighlight, Window},
+    syntax::{Scope, Span},
     terminal::Terminal,
-    utils::{Position, Size},
-};
-use std::{
-    cell::RefCell,
-    io::{Error, Read},
-    rc::Rc,
+    theme::Face,
+    utils::{paths, Position, Size},
 };
+use std::{cell::RefCell, io::Error, rc::Rc};
 
 use super::{Control, WindowManager};
 
@@ -16,6 +14,8 @@ const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 pub struct Editor<'a> {
     should_quit: bool,
+    /// whether the terminal reports the mouse, follows the `mouse` option
+    mouse_enabled: bool,
     terminal: Terminal,
     windows_manager: WindowManager<'a>,
     control: &'a mut Control<'a>,
@@ -25,8 +25,17 @@ impl<'a> Editor<'a> {
     pub fn new(
         control: &'a mut Control<'a>,
         windows: &'a Rc<RefCell<Vec<Window>>>,
-        terminal: Terminal,
-    ) -> Result<Self, Error> {
+        mut terminal: Terminal,
+    ) -> Self {
+        control.attach_window(0);
+        control.set_clipboard(Clipboard::detect());
+        if let Some(dir) = paths::config_dir() {
+            control.load_grammars(&dir.join("syntaxes"));
+            control.load_themes(&dir.join("themes"));
+            control.load_config(&dir.join("config.toml"));
+        }
+        terminal.reserve_rows(control.options().editor.reservedrows);
+
         let windows_manager = WindowManager::new(
             windows,
             Size {
@@ -35,15 +44,13 @@ impl<'a> Editor<'a> {
             },
         );
 
-        let res = Self {
+        Self {
             terminal,
             should_quit: false,
+            mouse_enabled: false,
             windows_manager,
             control,
-        };
-        res.control.attach_window(0);
-
-        Ok(res)
+        }
     }
 
     fn die(e: &Error) {
@@ -62,27 +69,53 @@ impl<'a> Editor<'a> {
 
             if let Err(err) = self.control.process_key() {
                 Self::die(&err);
-            };
+            }
+            self.should_quit = self.control.should_quit();
         }
     }
 
-    fn refresh_screen(&self) -> Result<(), Error> {
+    fn refresh_screen(&mut self) -> Result<(), Error> {
         Terminal::hide_cursor();
 
+        let options = &self.control.options().editor;
+        if options.mouse != self.mouse_enabled {
+            self.mouse_enabled = options.mouse;
+            if self.mouse_enabled {
+                Terminal::enable_mouse();
+            } else {
+                Terminal::disable_mouse();
+            }
+        }
+        let height = self.terminal.size().height;
+        self.terminal.reserve_rows(options.reservedrows);
+        if self.terminal.size().height != height {
+            Terminal::clear_screen();
+            self.windows_manager.set_size(Size {
+                width: self.terminal.size().width,
+                height: self.terminal.size().height,
+            });
+        }
+
         if self.should_quit {
             Terminal::clear_screen();
             Terminal::cursor_position(&Position { x: 0, y: 0 });
         } else {
             self.draw_windows();
-            Terminal::cursor_position(self.control.position());
-
-            Terminal::cursor_position(self.control.position());
-            println!("{:?}", self.control.position());
+            self.draw_message_line();
 
             // if has no windows render whelcome message
             if self.windows_manager.is_empty() {
                 self.draw_welcome_message();
             }
+
+            // while typing a command the cursor is on the command line
+            match self.control.command_line() {
+                Some(command_line) => Terminal::cursor_position(&Position {
+                    x: command_line.len(),
+                    y: self.message_line(),
+                }),
+                None => Terminal::cursor_position(self.control.position()),
+            }
         }
 
         Terminal::show_cursor();
@@ -91,18 +124,133 @@ impl<'a> Editor<'a> {
 
     pub fn draw_windows(&self) {
         let visible_buff = self.windows_manager.visible_area_buff();
+        let highlights = self.windows_manager.visible_highlights();
 
-        for line in 0..visible_buff.len() - 1 {
+        for (line, spans) in visible_buff.iter().enumerate().take(visible_buff.len() - 1) {
             Terminal::cursor_position(&Position { x: 0, y: line });
             Terminal::clear_current_line();
-            print!("{}\r", visible_buff[line]);
 
-            Terminal::cursor_position(&self.control.position());
+            let line_highlights: Vec<&ScreenHighlight> = highlights
+                .iter()
+                .filter(|highlight| highlight.position.y == line)
+                .collect();
+            print!("{}\r", self.highlight_line(spans, &line_highlights));
+        }
+    }
+
+    /// Draws the spans of a screen line with the theme style of their scope,
+    /// and the highlighted cells over them.
+    fn highlight_line(&self, spans: &[Span], highlights: &[&ScreenHighlight]) -> String {
+        let mut cells: Vec<(char, Option<Scope>, Option<Highlight>)> = Span::cells(spans)
+            .into_iter()
+            .map(|(c, scope)| (c, scope, None))
+            .collect();
+        // highlights can go past the text, over the end of a row
+        // and the background of the theme goes to the end of the screen
+        let end = highlights
+            .iter()
+            .map(|h| h.position.x + h.len)
+            .chain([self.terminal.size().width as usize])
+            .max()
+            .unwrap_or_default();
+        if cells.len() < end {
+            cells.resize(end, (' ', None, None));
+        }
+
+        let mut sorted = highlights.to_vec();
+        sorted.sort_by_key(|highlight| highlight.position.x);
+        for highlight in sorted {
+            let end = highlight.position.x + highlight.len;
+            for cell in &mut cells[highlight.position.x..end] {
+                cell.2 = cell.2.or(Some(highlight.highlight));
+            }
+        }
+
+        let theme = self.control.theme();
+        let mut line = String::new();
+        for run in cells.chunk_by(|a, b| (a.1, a.2) == (b.1, b.2)) {
+            let text: String = run.iter().map(|cell| cell.0).collect();
+            let mut style = match run[0].1 {
+                Some(scope) => theme.style(Face::Syntax(scope)),
+                None => theme.style(Face::Normal),
+            };
+            match run[0].2 {
+                Some(Highlight::Selection) => style = theme.style(Face::Selection).over(style),
+                Some(Highlight::Search) => style = theme.style(Face::Search).over(style),
+                None => {}
+            }
+            line.push_str(&self.terminal.paint(&text, style));
+        }
+
+        line
+    }
+
+    fn message_line(&self) -> usize {
+        (self.terminal.size().height as usize).saturating_sub(1)
+    }
+
+    /// Last line of the screen, with the command being typed, the message of
+    /// the last command or the current mode, and the cursor position on the
+    /// right. Messages of many lines are drawn over the windows.
+    fn draw_message_line(&self) {
+        let width = self.terminal.size().width as usize;
+
+        if let Some(command_line) = self.control.command_line() {
+            Terminal::cursor_position(&Position {
+                x: 0,
+                y: self.message_line(),
+            });
+            Terminal::clear_current_line();
+            print!("{command_line}");
+            return;
+        }
+
+        let indicator = self.control.mode().indicator();
+        let status = match self.control.recording() {
+            Some(name) => format!("{indicator}recording @{name}"),
+            None => indicator.to_string(),
+        };
+        let message = self.control.message().unwrap_or(&status);
+        let lines: Vec<&str> = message.split('\n').collect();
+        let top = (self.message_line() + 1).saturating_sub(lines.len());
+
+        for (i, text) in lines.iter().enumerate() {
+            let ruler = if lines.len() == 1 {
+                let file_info = self
+                    .control
+                    .read_document(|doc| {
+                        let language = doc
+                            .syntax()
+                            .map(|syntax| format!("{}  ", syntax.name()))
+                            .unwrap_or_default();
+                        format!("{language}{}  ", indent_info(doc.options()))
+                    })
+                    .unwrap_or_default();
+                self.control
+                    .document_position()
+                    .map(|pos| format!("{file_info}{},{}", pos.y + 1, pos.x + 1))
+                    .unwrap_or_default()
+            } else {
+                String::new()
+            };
+            let line = fit_line(text, &ruler, width);
+            let face = if self.control.is_error() {
+                Face::Error
+            } else if self.control.message().is_none() {
+                Face::StatusLine
+            } else {
+                Face::Normal
+            };
+            let style = self.control.theme().style(face);
+
+            Terminal::cursor_position(&Position { x: 0, y: top + i });
+            Terminal::clear_current_line();
+            print!("{}", self.terminal.paint(&line, style));
         }
     }
 
     fn draw_welcome_message(&self) {
-        let mut welcome_message = format!("Hecto editor --version {}", VERSION);
+        let mut welcome_message = format!("Hecto editor --version {VERSION}");
         let width = self.terminal.size().width as usize;
         let len = welcome_message.len();
         let padding = width.saturating_sub(len) / 2;
@@ -120,8 +268,47 @@ impl<'a> Editor<'a> {
     }
 
     pub fn open_document(&mut self, path: &str) -> Result<(), Error> {
-        let doc = Document::open(path)?;
-        self.windows_manager.new_window(doc);
+        let doc = self.control.open_document(path)?;
+        let options = self.control.options();
+        self.windows_manager.new_window(
+            doc,
+            options.window.for_file(path),
+            options.editor.winwidth,
+            options.editor.winheight,
+        );
         Ok(())
     }
 }
+
+/// Indentation of the status line, like `spaces:4` or `tabs:8`.
+fn indent_info(options: &BufferOptions) -> String {
+    if options.expandtab {
+        format!("spaces:{}", options.shiftwidth)
+    } else {
+        format!("tabs:{}", options.tabstop)
+    }
+}
+
+/// `text` on the left of a line `width` columns wide and `ruler` on its
+/// right, cut at the width. Columns are counted in chars.
+fn fit_line(text: &str, ruler: &str, width: usize) -> String {
+    let padding = width.saturating_sub(ruler.chars().count());
+    format!("{text:<padding$}{ruler}")
+        .chars()
+        .chain(std::iter::repeat(' '))
+        .take(width)
+        .collect()
+}
+
+mod test {
+    #[test]
+    fn should_fit_message_line_to_width_in_chars() {
+        use super::fit_line;
+
+        assert_eq!(fit_line("é", "1,1", 6), "é  1,1");
+        assert_eq!(fit_line("ééé", "", 2), "éé");
+        assert_eq!(fit_line("a", "", 3), "a  ");
+        // a long message pushes the ruler out
+        assert_eq!(fit_line("abcdef", "1,1", 5), "abcde");
+    }
+}
@@ -0,0 +1,469 @@
+use std::ops::Range;
+
+use crate::document::{Document, LineEdit};
+
+const OPEN_MARKER: &str = "{{{";
+const CLOSE_MARKER: &str = "}}}";
+
+/// How the folds of a window are made, from the `foldmethod` option.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FoldMethod {
+    /// made with `zf`
+    Manual,
+    /// rows more indented than the one above them
+    Indent,
+    /// rows from one with `{{{` to one with `}}}`
+    Marker,
+}
+
+impl FoldMethod {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "manual" => Some(Self::Manual),
+            "indent" => Some(Self::Indent),
+            "marker" => Some(Self::Marker),
+            _ => None,
+        }
+    }
+}
+
+/// Rows `start` to `end`, both included, that can be shown as a single
+/// summary line.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Fold {
+    pub start: usize,
+    pub end: usize,
+    pub closed: bool,
+}
+
+impl Fold {
+    fn contains(&self, line: usize) -> bool {
+        self.start <= line && line <= self.end
+    }
+
+    fn len(&self) -> usize {
+        self.end - self.start + 1
+    }
+}
+
+/// Folds of a window, nested ones included. Outer folds come before the
+/// folds they contain.
+#[derive(Clone, Default)]
+pub struct Folds {
+    folds: Vec<Fold>,
+}
+
+impl Folds {
+    /// Moves the folds with the lines inserted and deleted in the document,
+    /// those whose rows were all deleted are dropped.
+    pub fn follow(&mut self, edit: &LineEdit) {
+        match edit {
+            LineEdit::Inserted { line, count } => self.lines_inserted(*line, *count),
+            LineEdit::Deleted(lines) => self.lines_deleted(lines),
+        }
+    }
+
+    /// `count` lines were inserted before `line`.
+    fn lines_inserted(&mut self, line: usize, count: usize) {
+        for fold in &mut self.folds {
+            if fold.start >= line {
+                fold.start += count;
+            }
+            if fold.end >= line {
+                fold.end += count;
+            }
+        }
+    }
+
+    /// The lines in `lines` were deleted, a fold loses the rows it had
+    /// there.
+    fn lines_deleted(&mut self, lines: &Range<usize>) {
+        let shift = |line: usize| {
+            if line >= lines.end {
+                line - lines.len()
+            } else {
+                line.min(lines.start)
+            }
+        };
+        for fold in &mut self.folds {
+            // an end deleted goes to the line above the deleted ones
+            let end = if lines.contains(&fold.end) {
+                lines.start.checked_sub(1)
+            } else {
+                Some(shift(fold.end))
+            };
+            fold.start = shift(fold.start);
+            fold.end = end.unwrap_or(fold.start);
+        }
+        self.folds.retain(|fold| fold.start < fold.end);
+    }
+
+    /// Makes the folds of `method` again after `doc` changed, those that
+    /// still start on the row a closed one moved to stay closed. Manual
+    /// folds are kept within the document.
+    pub fn update(&mut self, doc: &Document, method: FoldMethod) {
+        let ranges = match method {
+            FoldMethod::Manual => {
+                let last_line = doc.lines_len().saturating_sub(1);
+                for fold in &mut self.folds {
+                    fold.end = fold.end.min(last_line);
+                }
+                self.folds.retain(|fold| fold.start < fold.end);
+                return;
+            }
+            FoldMethod::Indent => indent_folds(doc),
+            FoldMethod::Marker => marker_folds(doc),
+        };
+
+        let closed: Vec<usize> = self
+            .folds
+            .iter()
+            .filter(|fold| fold.closed)
+            .map(|fold| fold.start)
+            .collect();
+        self.folds = ranges
+            .into_iter()
+            .filter(|(start, end)| start < end)
+            .map(|(start, end)| Fold {
+                start,
+                end,
+                closed: closed.contains(&start),
+            })
+            .collect();
+        self.sort();
+    }
+
+    /// Adds a closed fold over the rows `start` to `end` (`zf`).
+    pub fn add(&mut self, start: usize, end: usize) {
+        if start < end {
+            self.folds.push(Fold {
+                start,
+                end,
+                closed: true,
+            });
+            self.sort();
+        }
+    }
+
+    /// Outermost closed fold holding `line`, the rows shown as its summary.
+    pub fn closed_at(&self, line: usize) -> Option<Fold> {
+        self.folds
+            .iter()
+            .find(|fold| fold.closed && fold.contains(line))
+            .copied()
+    }
+
+    pub fn has_closed(&self) -> bool {
+        self.folds.iter().any(|fold| fold.closed)
+    }
+
+    /// Row shown for `line`, the first one of the closed fold hiding it.
+    pub fn visible_line(&self, line: usize) -> usize {
+        self.closed_at(line).map_or(line, |fold| fold.start)
+    }
+
+    /// First row shown after the one of `line`, past the fold hiding it.
+    pub fn next_visible_line(&self, line: usize) -> usize {
+        self.closed_at(line).map_or(line, |fold| fold.end) + 1
+    }
+
+    /// Last row shown before the one of `line`.
+    pub fn previous_visible_line(&self, line: usize) -> Option<usize> {
+        let start = self.visible_line(line);
+        Some(self.visible_line(start.checked_sub(1)?))
+    }
+
+    /// Nesting of `fold`, 1 for the outermost ones.
+    fn level(&self, fold: &Fold) -> usize {
+        self.folds
+            .iter()
+            .filter(|outer| outer.start <= fold.start && fold.end <= outer.end)
+            .count()
+    }
+
+    /// Opens the closed fold hiding `line` (`zo`). Returns `false` when no
+    /// fold holds it.
+    pub fn open(&mut self, line: usize) -> bool {
+        match self
+            .folds
+            .iter_mut()
+            .find(|fold| fold.closed && fold.contains(line))
+        {
+            Some(fold) => fold.closed = false,
+            None => return self.folds.iter().any(|fold| fold.contains(line)),
+        }
+        true
+    }
+
+    /// Closes the innermost open fold holding `line` and what is closed in
+    /// it (`zc`). Returns `false` when no fold holds it.
+    pub fn close(&mut self, line: usize) -> bool {
+        let closed = self.closed_at(line);
+        let candidate = self.folds.iter_mut().rev().find(|fold| {
+            !fold.closed
+                && fold.contains(line)
+                && closed.is_none_or(|closed| fold.start <= closed.start && closed.end <= fold.end)
+        });
+
+        match candidate {
+            Some(fold) => fold.closed = true,
+            None => return self.folds.iter().any(|fold| fold.contains(line)),
+        }
+        true
+    }
+
+    /// Opens the fold holding `line` if it is closed, else closes it (`za`).
+    pub fn toggle(&mut self, line: usize) -> bool {
+        if self.closed_at(line).is_some() {
+            self.open(line)
+        } else {
+            self.close(line)
+        }
+    }
+
+    /// `zR` and `zM`.
+    pub fn set_all(&mut self, closed: bool) {
+        for fold in &mut self.folds {
+            fold.closed = closed;
+        }
+    }
+
+    /// First row of the next fold below `line` (`zj`).
+    pub fn next_start(&self, line: usize) -> Option<usize> {
+        self.folds
+            .iter()
+            .map(|fold| fold.start)
+            .filter(|start| *start > line)
+            .min()
+    }
+
+    /// Last row of the previous fold above `line` (`zk`).
+    pub fn previous_end(&self, line: usize) -> Option<usize> {
+        self.folds
+            .iter()
+            .map(|fold| fold.end)
+            .filter(|end| *end < line)
+            .max()
+    }
+
+    /// Summary line of the closed fold starting on `line`, like
+    /// `+--  4 lines: fn main() {`.
+    pub fn summary(&self, doc: &Document, line: usize) -> Option<String> {
+        let fold = self.closed_at(line).filter(|fold| fold.start == line)?;
+        let text = doc.row(line).map_or("", |row| row.content.as_str());
+        let text = text.replace(OPEN_MARKER, "");
+
+        Some(format!(
+            "+-{}{:>3} lines: {}",
+            "-".repeat(self.level(&fold)),
+            fold.len(),
+            text.trim()
+        ))
+    }
+
+    fn sort(&mut self) {
+        self.folds
+            .sort_by(|a, b| a.start.cmp(&b.start).then(b.end.cmp(&a.end)));
+    }
+}
+
+/// Folds of the rows indented more than the rows around them, blank rows
+/// taking the indentation of the less indented row around them.
+fn indent_folds(doc: &Document) -> Vec<(usize, usize)> {
+    let shiftwidth = doc.options().shiftwidth.max(1);
+    let tabstop = doc.options().tabstop.max(1);
+    let indent_level = |line: usize| {
+        let row = doc.row(line)?;
+        if row.content.trim().is_empty() {
+            return None;
+        }
+        let width: usize = doc
+            .row_indent(line)
+            .chars()
+            .map(|c| if c == '\t' { tabstop } else { 1 })
+            .sum();
+        Some(width / shiftwidth)
+    };
+
+    let known: Vec<Option<usize>> = (0..doc.lines_len()).map(indent_level).collect();
+    let levels: Vec<usize> = (0..known.len())
+        .map(|line| {
+            known[line].unwrap_or_else(|| {
+                let above = known[..line].iter().rev().find_map(|level| *level);
+                let below = known[line + 1..].iter().find_map(|level| *level);
+                above.unwrap_or(0).min(below.unwrap_or(0))
+            })
+        })
+        .collect();
+
+    let mut folds = Vec::new();
+    // rows where a fold of each level started
+    let mut starts: Vec<usize> = Vec::new();
+    for (line, level) in levels.iter().chain([&0]).enumerate() {
+        while starts.len() > *level {
+            if let Some(start) = starts.pop() {
+                folds.push((start, line - 1));
+            }
+        }
+        while starts.len() < *level {
+            starts.push(line);
+        }
+    }
+
+    folds
+}
+
+/// Folds from the rows with `{{{` to the rows with `}}}`, those left open
+/// go to the end of the document.
+fn marker_folds(doc: &Document) -> Vec<(usize, usize)> {
+    let mut folds = Vec::new();
+    let mut starts = Vec::new();
+
+    for line in 0..doc.lines_len() {
+        let content = doc.row(line).map_or("", |row| row.content.as_str());
+        let mut markers: Vec<(usize, bool)> = content
+            .match_indices(OPEN_MARKER)
+            .map(|(i, _)| (i, true))
+            .chain(content.match_indices(CLOSE_MARKER).map(|(i, _)| (i, false)))
+            .collect();
+        markers.sort_unstable();
+
+        for (_, open) in markers {
+            if open {
+                starts.push(line);
+            } else if let Some(start) = starts.pop() {
+                folds.push((start, line));
+            }
+        }
+    }
+
+    let last_line = doc.lines_len().saturating_sub(1);
+    folds.extend(starts.into_iter().map(|start| (start, last_line)));
+    folds
+}
+
+mod test {
+    #[cfg(test)]
+    fn indented_doc() -> crate::document::Document {
+        crate::document::Document::from(vec![
+            "fn a() {",
+            "    if b {",
+            "        c();",
+            "",
+            "        d();",
+            "    }",
+            "}",
+            "",
+            "e",
+        ])
+    }
+
+    #[test]
+    fn should_fold_indented_rows() {
+        use super::{FoldMethod, Folds};
+
+        let mut folds = Folds::default();
+        folds.update(&indented_doc(), FoldMethod::Indent);
+
+        assert_eq!(folds.next_start(0), Some(1));
+        assert_eq!(folds.next_start(1), Some(2));
+        assert_eq!(folds.previous_end(8), Some(5));
+    }
+
+    #[test]
+    fn should_close_inner_fold_first() {
+        use super::{FoldMethod, Folds};
+
+        let doc = indented_doc();
+        let mut folds = Folds::default();
+        folds.update(&doc, FoldMethod::Indent);
+
+        assert!(folds.close(3));
+        assert_eq!(folds.visible_line(3), 2);
+        assert_eq!(folds.next_visible_line(2), 5);
+        assert!(folds.close(3));
+        assert_eq!(folds.next_visible_line(1), 6);
+        assert_eq!(folds.previous_visible_line(6), Some(1));
+        assert_eq!(
+            folds.summary(&doc, 1).as_deref(),
+            Some("+--  5 lines: if b {")
+        );
+    }
+
+    #[test]
+    fn should_open_folds() {
+        use super::{FoldMethod, Folds};
+
+        let mut folds = Folds::default();
+        folds.update(&indented_doc(), FoldMethod::Indent);
+        folds.close(3);
+        folds.close(3);
+
+        assert!(folds.open(3));
+        assert_eq!(folds.next_visible_line(1), 2);
+        assert!(!folds.toggle(8));
+        folds.set_all(false);
+        assert!(!folds.has_closed());
+    }
+
+    #[test]
+    fn should_keep_closed_folds_closed_on_update() {
+        use super::{FoldMethod, Folds};
+
+        let doc = indented_doc();
+        let mut folds = Folds::default();
+        folds.update(&doc, FoldMethod::Indent);
+        folds.close(1);
+        folds.update(&doc, FoldMethod::Indent);
+
+        assert_eq!(folds.closed_at(4).map(|fold| fold.start), Some(1));
+    }
+
+    #[test]
+    fn should_fold_marked_rows() {
+        use super::{FoldMethod, Folds};
+        use crate::document::Document;
+
+        let doc = Document::from(vec!["a {{{", "b {{{1", "c", "}}}", "d", "}}}", "e {{{"]);
+        let mut folds = Folds::default();
+        folds.update(&doc, FoldMethod::Marker);
+        folds.set_all(true);
+
+        assert_eq!(folds.next_visible_line(0), 6);
+        assert_eq!(folds.summary(&doc, 0).as_deref(), Some("+--  6 lines: a"));
+        assert!(folds.open(2));
+        assert_eq!(
+            folds.summary(&doc, 1).as_deref(),
+            Some("+---  3 lines: b 1")
+        );
+    }
+
+    #[test]
+    fn should_keep_manual_folds_within_document() {
+        use super::{FoldMethod, Folds};
+        use crate::document::Document;
+
+        let mut folds = Folds::default();
+        folds.add(4, 6);
+        let doc = Document::from(vec!["a", "b", "c", "d", "e"]);
+        folds.update(&doc, FoldMethod::Manual);
+
+        assert_eq!(folds.closed_at(4), None);
+    }
+
+    #[test]
+    fn should_move_manual_folds_with_edited_lines() {
+        use super::Folds;
+        use crate::document::LineEdit;
+
+        let mut folds = Folds::default();
+        folds.add(1, 3);
+
+        folds.follow(&LineEdit::Inserted { line: 0, count: 2 });
+        assert_eq!(folds.closed_at(5).map(|fold| fold.start), Some(3));
+        folds.follow(&LineEdit::Deleted(4..5));
+        assert_eq!(folds.closed_at(3).map(|fold| fold.end), Some(4));
+        folds.follow(&LineEdit::Deleted(1..4));
+        assert_eq!(folds.closed_at(1), None);
+    }
+}
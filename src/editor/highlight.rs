@@ -0,0 +1,15 @@
+use crate::utils::Position;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Highlight {
+    Selection,
+    Search,
+}
+
+/// A run of screen cells drawn with a highlight.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ScreenHighlight {
+    pub position: Position,
+    pub len: usize,
+    pub highlight: Highlight,
+}
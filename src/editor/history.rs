@@ -0,0 +1,55 @@
+use crate::{document::RowChange, utils::Position};
+
+/// Changes that can be undone, older ones are forgotten.
+const UNDO_LEVELS: usize = 1000;
+
+/// Rows as they were around a change, with the cursor where it was.
+#[derive(Clone)]
+pub struct Snapshot {
+    pub change: RowChange,
+    pub cursor: Position,
+}
+
+/// Undo and redo stacks of a window, each change keeps only the rows it
+/// replaced.
+#[derive(Default)]
+pub struct History {
+    undo: Vec<Snapshot>,
+    redo: Vec<Snapshot>,
+}
+
+impl History {
+    /// Keeps the rows before a change, what was undone can't be redone
+    /// anymore.
+    pub fn record(&mut self, before: Snapshot) {
+        self.push_undo(before);
+        self.redo.clear();
+    }
+
+    /// Last change, to be undone.
+    pub fn undo(&mut self) -> Option<Snapshot> {
+        self.undo.pop()
+    }
+
+    /// Last undone change, to be made again.
+    pub fn redo(&mut self) -> Option<Snapshot> {
+        self.redo.pop()
+    }
+
+    /// Keeps what an undo replaced to redo it.
+    pub fn undone(&mut self, current: Snapshot) {
+        self.redo.push(current);
+    }
+
+    /// Keeps what a redo replaced to undo it again.
+    pub fn redone(&mut self, current: Snapshot) {
+        self.push_undo(current);
+    }
+
+    fn push_undo(&mut self, snapshot: Snapshot) {
+        self.undo.push(snapshot);
+        if self.undo.len() > UNDO_LEVELS {
+            self.undo.remove(0);
+        }
+    }
+}
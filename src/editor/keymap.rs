@@ -0,0 +1,642 @@
+use termion::event::Key;
+
+use crate::editor::keys;
+
+/// Commands that keys are bound to. Commands taking an argument read it
+/// from the keys typed after theirs, like the register of `q` or the motion
+/// of an operator.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Action {
+    Insert,
+    Append,
+    InsertAtLineStart,
+    AppendAtLineEnd,
+    OpenLineBelow,
+    OpenLineAbove,
+    VisualChar,
+    VisualLine,
+    VisualBlock,
+    Reselect,
+    PutAfter,
+    PutBefore,
+    DeleteChar,
+    DeleteCharBefore,
+    DeleteToLineEnd,
+    ChangeToLineEnd,
+    YankLine,
+    CommandLine,
+    SearchForward,
+    SearchBackward,
+    Undo,
+    Redo,
+    JumpBack,
+    JumpForward,
+    RepeatChange,
+    ToggleFold,
+    OpenFold,
+    CloseFold,
+    OpenAllFolds,
+    CloseAllFolds,
+    // taking a register or a mark
+    SelectRegister,
+    RecordMacro,
+    PlayMacro,
+    SetMark,
+    // operators, taking a motion in normal mode
+    Delete,
+    Yank,
+    Change,
+    Indent,
+    Dedent,
+    Comment,
+    CreateFold,
+    // text objects, taking their name
+    InnerObject,
+    AroundObject,
+    // visual mode
+    ExitVisual,
+    SwapSelectionEnds,
+    ToggleCase,
+    Lowercase,
+    Uppercase,
+    BlockInsert,
+    BlockAppend,
+    // insert mode
+    ExitInsert,
+    NewLine,
+    InsertTab,
+    Backspace,
+    // movements, which take a count and are the motions of operators
+    AlignCenter,
+    AlignTop,
+    AlignBottom,
+    NextFold,
+    PreviousFold,
+    ScreenLineDown,
+    ScreenLineUp,
+    Left,
+    Down,
+    Up,
+    Right,
+    LineStart,
+    FirstNonBlank,
+    LineEnd,
+    WordForward,
+    WordBackward,
+    WordEnd,
+    FirstLine,
+    LastLine,
+    MatchingBracket,
+    SearchNext,
+    SearchPrevious,
+    SearchWordForward,
+    SearchWordBackward,
+    HalfPageDown,
+    HalfPageUp,
+    PageDown,
+    PageUp,
+    ScrollDown,
+    ScrollUp,
+    /// `'` goes to the line of a mark
+    MarkLine,
+    /// `` ` `` goes to the position of a mark
+    Mark,
+}
+
+/// Default bindings of the movements, in normal and visual mode and after
+/// an operator. An action can have several.
+const MOTIONS: [(&str, Action, &str); 38] = [
+    ("zz", Action::AlignCenter, "align_center"),
+    ("zt", Action::AlignTop, "align_top"),
+    ("zb", Action::AlignBottom, "align_bottom"),
+    ("zj", Action::NextFold, "next_fold"),
+    ("zk", Action::PreviousFold, "previous_fold"),
+    ("gj", Action::ScreenLineDown, "screen_line_down"),
+    ("gk", Action::ScreenLineUp, "screen_line_up"),
+    ("h", Action::Left, "left"),
+    ("<Left>", Action::Left, "left"),
+    ("j", Action::Down, "down"),
+    ("<Down>", Action::Down, "down"),
+    ("k", Action::Up, "up"),
+    ("<Up>", Action::Up, "up"),
+    ("l", Action::Right, "right"),
+    ("<Right>", Action::Right, "right"),
+    ("0", Action::LineStart, "line_start"),
+    ("<Home>", Action::LineStart, "line_start"),
+    ("^", Action::FirstNonBlank, "first_non_blank"),
+    ("$", Action::LineEnd, "line_end"),
+    ("<End>", Action::LineEnd, "line_end"),
+    ("w", Action::WordForward, "word_forward"),
+    ("b", Action::WordBackward, "word_backward"),
+    ("e", Action::WordEnd, "word_end"),
+    ("gg", Action::FirstLine, "first_line"),
+    ("G", Action::LastLine, "last_line"),
+    ("%", Action::MatchingBracket, "matching_bracket"),
+    ("n", Action::SearchNext, "search_next"),
+    ("N", Action::SearchPrevious, "search_previous"),
+    ("*", Action::SearchWordForward, "search_word_forward"),
+    ("#", Action::SearchWordBackward, "search_word_backward"),
+    ("<C-d>", Action::HalfPageDown, "half_page_down"),
+    ("<C-u>", Action::HalfPageUp, "half_page_up"),
+    ("<C-f>", Action::PageDown, "page_down"),
+    ("<C-b>", Action::PageUp, "page_up"),
+    ("<C-e>", Action::ScrollDown, "scroll_down"),
+    ("<C-y>", Action::ScrollUp, "scroll_up"),
+    ("'", Action::MarkLine, "mark_line"),
+    ("`", Action::Mark, "mark"),
+];
+
+/// Default bindings of normal mode, besides the movements.
+const NORMAL: [(&str, Action, &str); 43] = [
+    ("i", Action::Insert, "insert"),
+    ("a", Action::Append, "append"),
+    ("I", Action::InsertAtLineStart, "insert_at_line_start"),
+    ("A", Action::AppendAtLineEnd, "append_at_line_end"),
+    ("o", Action::OpenLineBelow, "open_line_below"),
+    ("O", Action::OpenLineAbove, "open_line_above"),
+    ("v", Action::VisualChar, "visual"),
+    ("V", Action::VisualLine, "visual_line"),
+    ("<C-v>", Action::VisualBlock, "visual_block"),
+    ("gv", Action::Reselect, "reselect"),
+    ("p", Action::PutAfter, "put_after"),
+    ("P", Action::PutBefore, "put_before"),
+    ("x", Action::DeleteChar, "delete_char"),
+    ("<Del>", Action::DeleteChar, "delete_char"),
+    ("X", Action::DeleteCharBefore, "delete_char_before"),
+    ("D", Action::DeleteToLineEnd, "delete_to_line_end"),
+    ("C", Action::ChangeToLineEnd, "change_to_line_end"),
+    ("Y", Action::YankLine, "yank_line"),
+    (":", Action::CommandLine, "command_line"),
+    ("/", Action::SearchForward, "search_forward"),
+    ("?", Action::SearchBackward, "search_backward"),
+    ("u", Action::Undo, "undo"),
+    ("<C-r>", Action::Redo, "redo"),
+    ("<C-o>", Action::JumpBack, "jump_back"),
+    ("<Tab>", Action::JumpForward, "jump_forward"),
+    ("<C-i>", Action::JumpForward, "jump_forward"),
+    (".", Action::RepeatChange, "repeat_change"),
+    ("za", Action::ToggleFold, "toggle_fold"),
+    ("zo", Action::OpenFold, "open_fold"),
+    ("zc", Action::CloseFold, "close_fold"),
+    ("zR", Action::OpenAllFolds, "open_all_folds"),
+    ("zM", Action::CloseAllFolds, "close_all_folds"),
+    ("\"", Action::SelectRegister, "select_register"),
+    ("q", Action::RecordMacro, "record_macro"),
+    ("@", Action::PlayMacro, "play_macro"),
+    ("m", Action::SetMark, "set_mark"),
+    ("d", Action::Delete, "delete"),
+    ("y", Action::Yank, "yank"),
+    ("c", Action::Change, "change"),
+    (">", Action::Indent, "indent"),
+    ("<lt>", Action::Dedent, "dedent"),
+    ("gc", Action::Comment, "comment"),
+    ("zf", Action::CreateFold, "create_fold"),
+];
+
+/// Default bindings of visual mode, besides the movements. Operators act on
+/// the selection.
+const VISUAL: [(&str, Action, &str); 24] = [
+    ("<Esc>", Action::ExitVisual, "exit_visual"),
+    ("v", Action::VisualChar, "visual"),
+    ("V", Action::VisualLine, "visual_line"),
+    ("<C-v>", Action::VisualBlock, "visual_block"),
+    ("o", Action::SwapSelectionEnds, "swap_selection_ends"),
+    (":", Action::CommandLine, "command_line"),
+    ("\"", Action::SelectRegister, "select_register"),
+    ("d", Action::Delete, "delete"),
+    ("x", Action::Delete, "delete"),
+    ("<Del>", Action::Delete, "delete"),
+    ("y", Action::Yank, "yank"),
+    ("c", Action::Change, "change"),
+    ("s", Action::Change, "change"),
+    (">", Action::Indent, "indent"),
+    ("<lt>", Action::Dedent, "dedent"),
+    ("gc", Action::Comment, "comment"),
+    ("zf", Action::CreateFold, "create_fold"),
+    ("~", Action::ToggleCase, "toggle_case"),
+    ("u", Action::Lowercase, "lowercase"),
+    ("U", Action::Uppercase, "uppercase"),
+    ("I", Action::BlockInsert, "block_insert"),
+    ("A", Action::BlockAppend, "block_append"),
+    ("i", Action::InnerObject, "inner_object"),
+    ("a", Action::AroundObject, "around_object"),
+];
+
+/// Default bindings after an operator, besides the movements.
+const OPERATOR: [(&str, Action, &str); 2] = [
+    ("i", Action::InnerObject, "inner_object"),
+    ("a", Action::AroundObject, "around_object"),
+];
+
+/// Default bindings of insert mode, other keys type their char.
+const INSERT: [(&str, Action, &str); 9] = [
+    ("<Esc>", Action::ExitInsert, "exit_insert"),
+    ("<CR>", Action::NewLine, "new_line"),
+    ("<Tab>", Action::InsertTab, "insert_tab"),
+    ("<BS>", Action::Backspace, "backspace"),
+    ("<Del>", Action::DeleteChar, "delete_char"),
+    ("<Left>", Action::Left, "left"),
+    ("<Right>", Action::Right, "right"),
+    ("<Up>", Action::Up, "up"),
+    ("<Down>", Action::Down, "down"),
+];
+
+/// Written before an action name to map keys to it, as in
+/// `:nmap H <Plug>first_non_blank`.
+const PLUG: &str = "<Plug>";
+
+/// Default bindings of `mode`, the movements first.
+fn default_bindings(
+    mode: MapMode,
+) -> impl Iterator<Item = &'static (&'static str, Action, &'static str)> {
+    let (motions, bindings): (&[_], &[_]) = match mode {
+        MapMode::Normal => (&MOTIONS, &NORMAL),
+        MapMode::Visual => (&MOTIONS, &VISUAL),
+        MapMode::Operator => (&MOTIONS, &OPERATOR),
+        MapMode::Insert => (&[], &INSERT),
+    };
+    motions.iter().chain(bindings)
+}
+
+impl Action {
+    pub fn name(self) -> &'static str {
+        Self::bindings()
+            .find(|(_, action, _)| *action == self)
+            .map_or("", |(_, _, name)| name)
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        Self::bindings()
+            .find(|(_, _, known)| *known == name)
+            .map(|(_, action, _)| *action)
+    }
+
+    fn bindings() -> impl Iterator<Item = &'static (&'static str, Action, &'static str)> {
+        MOTIONS
+            .iter()
+            .chain(&NORMAL)
+            .chain(&VISUAL)
+            .chain(&OPERATOR)
+            .chain(&INSERT)
+    }
+
+    /// Operators run on the text a motion moves over in normal mode, or on
+    /// the selection in visual mode.
+    pub fn is_operator(self) -> bool {
+        matches!(
+            self,
+            Self::Delete
+                | Self::Yank
+                | Self::Change
+                | Self::Indent
+                | Self::Dedent
+                | Self::Comment
+                | Self::CreateFold
+        )
+    }
+
+    /// Movements that fail when they are stuck against an edge of the
+    /// document.
+    pub fn must_move(self) -> bool {
+        matches!(
+            self,
+            Self::Left
+                | Self::Down
+                | Self::Up
+                | Self::Right
+                | Self::WordForward
+                | Self::WordBackward
+                | Self::WordEnd
+                | Self::ScreenLineDown
+                | Self::ScreenLineUp
+                | Self::NextFold
+                | Self::PreviousFold
+        )
+    }
+
+    /// Actions taking the char typed after their keys, like a register or
+    /// a mark name.
+    pub fn takes_char(self) -> bool {
+        matches!(
+            self,
+            Self::SelectRegister
+                | Self::RecordMacro
+                | Self::PlayMacro
+                | Self::SetMark
+                | Self::MarkLine
+                | Self::Mark
+                | Self::InnerObject
+                | Self::AroundObject
+        )
+    }
+}
+
+/// Modes keys can be mapped in.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MapMode {
+    Normal,
+    Insert,
+    Visual,
+    /// after an operator, for its motion, keys are not mapped in it
+    Operator,
+}
+
+impl MapMode {
+    /// Letter of the mode in `:map` listings and command names like `:nmap`.
+    pub fn letter(self) -> char {
+        match self {
+            Self::Normal => 'n',
+            Self::Insert => 'i',
+            Self::Visual => 'v',
+            Self::Operator => 'o',
+        }
+    }
+}
+
+/// What mapped keys are replaced with.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Target {
+    /// typed keys, mapped again unless `noremap`
+    Keys {
+        keys: Vec<Key>,
+        noremap: bool,
+    },
+    Action(Action),
+}
+
+impl Target {
+    /// Reads the right side of a mapping, `<leader>` being `leader`.
+    pub fn parse(text: &str, leader: &str, noremap: bool) -> Result<Target, String> {
+        match text.strip_prefix(PLUG) {
+            Some(name) => Action::from_name(name)
+                .map(Target::Action)
+                .ok_or(format!("Unknown action: {name}")),
+            None => Ok(Target::Keys {
+                keys: parse_keys(text, leader),
+                noremap,
+            }),
+        }
+    }
+}
+
+/// How keys typed so far match the mappings.
+#[derive(PartialEq, Eq, Debug)]
+pub enum Lookup<'a> {
+    /// no mapping starts with them
+    None,
+    /// longer mappings start with them, one may be them
+    Prefix(Option<&'a Target>),
+    Exact(&'a Target),
+}
+
+/// Keys bound to actions by default, and the mappings, in each mode.
+pub struct Keymap {
+    defaults: Vec<(MapMode, Vec<Key>, Action)>,
+    maps: Vec<(MapMode, Vec<Key>, Target)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let modes = [
+            MapMode::Normal,
+            MapMode::Insert,
+            MapMode::Visual,
+            MapMode::Operator,
+        ];
+        Self {
+            defaults: modes
+                .into_iter()
+                .flat_map(|mode| {
+                    default_bindings(mode)
+                        .map(move |(keys, action, _)| (mode, keys::parse_notation(keys), *action))
+                })
+                .collect(),
+            maps: Vec::new(),
+        }
+    }
+}
+
+impl Keymap {
+    /// Action bound to `keys` by default in `mode`.
+    pub fn action(&self, mode: MapMode, keys: &[Key]) -> Option<Action> {
+        self.defaults
+            .iter()
+            .find(|(bound_mode, bound, _)| *bound_mode == mode && bound == keys)
+            .map(|(_, _, action)| *action)
+    }
+
+    /// Action bound by default in `mode` to the keys `keys` start with, and
+    /// how many keys they are. The rest are its arguments.
+    pub fn command(&self, mode: MapMode, keys: &[Key]) -> Option<(Action, usize)> {
+        self.defaults
+            .iter()
+            .find(|(bound_mode, bound, _)| *bound_mode == mode && keys.starts_with(bound))
+            .map(|(_, bound, action)| (*action, bound.len()))
+    }
+
+    /// Whether longer default bindings of `mode` start with `keys`, like `g`
+    /// for `gg`.
+    pub fn is_default_prefix(&self, mode: MapMode, keys: &[Key]) -> bool {
+        self.defaults.iter().any(|(bound_mode, bound, _)| {
+            *bound_mode == mode && bound.len() > keys.len() && bound.starts_with(keys)
+        })
+    }
+
+    /// Keys `action` is bound to by default in `mode`, the first ones when
+    /// there are several.
+    pub fn keys(&self, mode: MapMode, action: Action) -> Vec<Key> {
+        self.defaults
+            .iter()
+            .find(|(bound_mode, _, bound)| *bound_mode == mode && *bound == action)
+            .map(|(_, keys, _)| keys.clone())
+            .unwrap_or_default()
+    }
+
+    /// Maps `keys` to `target` in `mode`, replacing the mapping they had.
+    pub fn map(&mut self, mode: MapMode, keys: Vec<Key>, target: Target) {
+        self.unmap(mode, &keys);
+        self.maps.push((mode, keys, target));
+    }
+
+    /// Removes the mapping of `keys`, returns whether there was one.
+    pub fn unmap(&mut self, mode: MapMode, keys: &[Key]) -> bool {
+        let len = self.maps.len();
+        self.maps
+            .retain(|(mapped, bound, _)| *mapped != mode || bound != keys);
+        self.maps.len() != len
+    }
+
+    pub fn lookup(&self, mode: MapMode, keys: &[Key]) -> Lookup<'_> {
+        let mut exact = None;
+        let mut longer = false;
+        for (_, bound, target) in self.maps.iter().filter(|(mapped, ..)| *mapped == mode) {
+            if bound == keys {
+                exact = Some(target);
+            } else if bound.starts_with(keys) {
+                longer = true;
+            }
+        }
+
+        match (exact, longer) {
+            (_, true) => Lookup::Prefix(exact),
+            (Some(target), false) => Lookup::Exact(target),
+            (None, false) => Lookup::None,
+        }
+    }
+
+    /// Lines listing the mappings of `modes` starting with `prefix`, as
+    /// `n  lhs  rhs`.
+    pub fn list(&self, modes: &[MapMode], prefix: &[Key]) -> Vec<String> {
+        self.maps
+            .iter()
+            .filter(|(mode, keys, _)| modes.contains(mode) && keys.starts_with(prefix))
+            .map(|(mode, keys, target)| {
+                let target = match target {
+                    Target::Keys { keys, noremap } => {
+                        let star = if *noremap { "*" } else { " " };
+                        format!("{star} {}", notation(keys))
+                    }
+                    Target::Action(action) => format!("  {PLUG}{}", action.name()),
+                };
+                format!("{}  {:<10}{target}", mode.letter(), notation(keys))
+            })
+            .collect()
+    }
+}
+
+/// Keys written in notation, with spaces shown as `<Space>`.
+fn notation(keys: &[Key]) -> String {
+    keys::to_notation(keys).replace(' ', "<Space>")
+}
+
+/// Reads keys written in notation, `<leader>` standing for the keys of
+/// `leader`.
+pub fn parse_keys(text: &str, leader: &str) -> Vec<Key> {
+    let leader = keys::parse_notation(leader);
+    let mut parsed = Vec::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.to_ascii_lowercase().find("<leader>") {
+        parsed.extend(keys::parse_notation(&rest[..start]));
+        parsed.extend(leader.iter().copied());
+        rest = &rest[start + "<leader>".len()..];
+    }
+    parsed.extend(keys::parse_notation(rest));
+
+    parsed
+}
+
+mod test {
+    #[test]
+    fn should_bind_default_keys_per_mode() {
+        use super::{Action, Keymap, MapMode};
+        use termion::event::Key;
+
+        let keymap = Keymap::default();
+        assert_eq!(
+            keymap.action(MapMode::Normal, &[Key::Char('g'), Key::Char('g')]),
+            Some(Action::FirstLine)
+        );
+        assert_eq!(
+            keymap.action(MapMode::Visual, &[Key::Char('u')]),
+            Some(Action::Lowercase)
+        );
+        assert_eq!(
+            keymap.action(MapMode::Operator, &[Key::Char('i')]),
+            Some(Action::InnerObject)
+        );
+        assert_eq!(keymap.action(MapMode::Insert, &[Key::Char('i')]), None);
+    }
+
+    #[test]
+    fn should_find_commands_with_arguments() {
+        use super::{Action, Keymap, MapMode};
+        use termion::event::Key;
+
+        let keymap = Keymap::default();
+        assert!(keymap.is_default_prefix(MapMode::Normal, &[Key::Char('g')]));
+        assert_eq!(
+            keymap.command(MapMode::Normal, &[Key::Char('q'), Key::Char('a')]),
+            Some((Action::RecordMacro, 1))
+        );
+    }
+
+    #[test]
+    fn should_name_actions() {
+        use super::{Action, Keymap, MapMode};
+        use termion::event::Key;
+
+        assert_eq!(Action::from_name("undo"), Some(Action::Undo));
+        assert_eq!(Action::from_name("exit_insert"), Some(Action::ExitInsert));
+        assert_eq!(Action::FirstNonBlank.name(), "first_non_blank");
+        assert_eq!(
+            Keymap::default().keys(MapMode::Normal, Action::Redo),
+            vec![Key::Ctrl('r')]
+        );
+    }
+
+    #[test]
+    fn should_parse_leader() {
+        use super::parse_keys;
+        use termion::event::Key;
+
+        let keys = parse_keys("<Leader>w", "<Space>");
+        assert_eq!(keys, vec![Key::Char(' '), Key::Char('w')]);
+    }
+
+    #[test]
+    fn should_look_mappings_up() {
+        use super::{Keymap, Lookup, MapMode, Target};
+        use termion::event::Key;
+
+        let mut keymap = Keymap::default();
+        let keys = vec![Key::Char(' '), Key::Char('w')];
+        let save = Target::parse(":w<CR>", " ", true).unwrap();
+        keymap.map(MapMode::Normal, keys.clone(), save.clone());
+
+        assert_eq!(keymap.lookup(MapMode::Normal, &keys), Lookup::Exact(&save));
+        assert_eq!(keymap.lookup(MapMode::Insert, &keys), Lookup::None);
+        assert_eq!(
+            keymap.lookup(MapMode::Normal, &keys[..1]),
+            Lookup::Prefix(None)
+        );
+        assert!(keymap.unmap(MapMode::Normal, &keys));
+        assert_eq!(keymap.lookup(MapMode::Normal, &keys), Lookup::None);
+    }
+
+    #[test]
+    fn should_list_mappings() {
+        use super::{Action, Keymap, Lookup, MapMode, Target};
+        use termion::event::Key;
+
+        let mut keymap = Keymap::default();
+        let keys = vec![Key::Char(' '), Key::Char('w')];
+        keymap.map(
+            MapMode::Normal,
+            keys.clone(),
+            Target::parse(":w<CR>", " ", true).unwrap(),
+        );
+        let first = Target::parse("<Plug>first_non_blank", " ", false).unwrap();
+        assert_eq!(first, Target::Action(Action::FirstNonBlank));
+        keymap.map(MapMode::Normal, keys[..1].to_vec(), first.clone());
+
+        assert_eq!(
+            keymap.lookup(MapMode::Normal, &keys[..1]),
+            Lookup::Prefix(Some(&first))
+        );
+        assert_eq!(
+            keymap.list(&[MapMode::Normal], &[]),
+            vec![
+                String::from("n  <Space>w  * :w<CR>"),
+                String::from("n  <Space>     <Plug>first_non_blank"),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_not_parse_unknown_actions() {
+        use super::Target;
+
+        assert!(Target::parse("<Plug>fly", " ", false).is_err());
+    }
+}
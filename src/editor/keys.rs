@@ -0,0 +1,154 @@
+use termion::event::Key;
+
+/// Names of the keys written between `<` and `>`.
+const NAMED_KEYS: [(&str, Key); 17] = [
+    ("CR", Key::Char('\n')),
+    ("Tab", Key::Char('\t')),
+    ("lt", Key::Char('<')),
+    ("Esc", Key::Esc),
+    ("BS", Key::Backspace),
+    ("Del", Key::Delete),
+    ("Insert", Key::Insert),
+    ("Left", Key::Left),
+    ("Right", Key::Right),
+    ("Up", Key::Up),
+    ("Down", Key::Down),
+    ("Home", Key::Home),
+    ("End", Key::End),
+    ("PageUp", Key::PageUp),
+    ("PageDown", Key::PageDown),
+    ("S-Tab", Key::BackTab),
+    ("Nul", Key::Null),
+];
+
+/// Writes keys as text, like `dd<Esc>`, special keys being named between
+/// `<` and `>` as vim does.
+pub fn to_notation(keys: &[Key]) -> String {
+    keys.iter().filter_map(|key| key_notation(*key)).collect()
+}
+
+fn key_notation(key: Key) -> Option<String> {
+    if let Some((name, _)) = NAMED_KEYS.iter().find(|(_, named)| *named == key) {
+        return Some(format!("<{name}>"));
+    }
+
+    match key {
+        Key::Char(c) => Some(c.to_string()),
+        Key::Ctrl(c) => Some(format!("<C-{c}>")),
+        Key::Alt(c) => Some(format!("<M-{c}>")),
+        Key::F(n) => Some(format!("<F{n}>")),
+        _ => None,
+    }
+}
+
+/// Reads keys written by `to_notation`. A `<` not starting a known key name
+/// is taken as it is, line breaks are `Enter`.
+pub fn parse_notation(text: &str) -> Vec<Key> {
+    let mut keys = Vec::new();
+    let mut rest = text;
+
+    while let Some(c) = rest.chars().next() {
+        let named = (c == '<')
+            .then(|| rest[1..].split_once('>'))
+            .flatten()
+            .and_then(|(name, after)| Some((parse_key_name(name)?, after)));
+
+        let (key, after) = named.unwrap_or((Key::Char(c), &rest[c.len_utf8()..]));
+        keys.push(key);
+        rest = after;
+    }
+
+    keys
+}
+
+/// Key named `name` in `<name>`, ignoring case.
+fn parse_key_name(name: &str) -> Option<Key> {
+    // only read, a space is written as it is
+    if name.eq_ignore_ascii_case("Space") {
+        return Some(Key::Char(' '));
+    }
+    if let Some((_, key)) = NAMED_KEYS
+        .iter()
+        .find(|(named, _)| named.eq_ignore_ascii_case(name))
+    {
+        return Some(*key);
+    }
+
+    let modified = |prefix: &str| {
+        let rest = name
+            .get(..prefix.len())?
+            .eq_ignore_ascii_case(prefix)
+            .then(|| &name[prefix.len()..])?;
+        let mut chars = rest.chars();
+        let c = chars.next()?;
+        chars.next().is_none().then_some(c)
+    };
+
+    if let Some(c) = modified("C-") {
+        return Some(Key::Ctrl(c.to_ascii_lowercase()));
+    }
+    if let Some(c) = modified("M-").or_else(|| modified("A-")) {
+        return Some(Key::Alt(c));
+    }
+
+    let number = name.strip_prefix(['F', 'f'])?.parse().ok()?;
+    (1..=12).contains(&number).then_some(Key::F(number))
+}
+
+mod test {
+    #[test]
+    fn should_write_key_notation() {
+        use super::to_notation;
+        use termion::event::Key;
+
+        let keys = [
+            Key::Char('d'),
+            Key::Char('<'),
+            Key::Esc,
+            Key::Ctrl('r'),
+            Key::Char('\n'),
+            Key::F(5),
+            Key::Alt('x'),
+            Key::Up,
+        ];
+        assert_eq!(to_notation(&keys), "d<lt><Esc><C-r><CR><F5><M-x><Up>");
+    }
+
+    #[test]
+    fn should_read_key_notation() {
+        use super::parse_notation;
+        use termion::event::Key;
+
+        assert_eq!(
+            parse_notation("d<lt><Esc><C-r><CR><F5><M-x><Up>"),
+            vec![
+                Key::Char('d'),
+                Key::Char('<'),
+                Key::Esc,
+                Key::Ctrl('r'),
+                Key::Char('\n'),
+                Key::F(5),
+                Key::Alt('x'),
+                Key::Up,
+            ]
+        );
+    }
+
+    #[test]
+    fn should_type_unknown_names_as_they_are() {
+        use super::parse_notation;
+        use termion::event::Key;
+
+        assert_eq!(
+            parse_notation("a<b><esc>\n"),
+            vec![
+                Key::Char('a'),
+                Key::Char('<'),
+                Key::Char('b'),
+                Key::Char('>'),
+                Key::Esc,
+                Key::Char('\n')
+            ]
+        );
+    }
+}
@@ -1,12 +1,30 @@
+pub mod clipboard;
 pub mod control;
+#[allow(clippy::module_inception)]
 pub mod editor;
+pub mod fold;
+pub mod highlight;
+pub mod history;
+pub mod keymap;
+pub mod keys;
 pub mod mode;
+pub mod options;
+pub mod register;
+pub mod selection;
+#[cfg(test)]
 pub mod test;
 pub mod window;
 pub mod window_manager;
 
+pub use clipboard::Clipboard;
 pub use control::Control;
 pub use editor::Editor;
+pub use highlight::{Highlight, ScreenHighlight};
+pub use history::{History, Snapshot};
+pub use keymap::Keymap;
 pub use mode::Mode;
+pub use options::{Options, WindowOptions};
+pub use register::{Register, Registers};
+pub use selection::{Selection, SelectionKind};
 pub use window::Window;
 pub use window_manager::WindowManager;
@@ -1,5 +1,25 @@
+use crate::{document::SearchDirection, editor::SelectionKind};
+
 pub enum Mode {
     Insert,
     Normal,
     Command,
+    Visual(SelectionKind),
+    /// typing a search after `/` or `?`
+    Search(SearchDirection),
+    /// answering whether to replace a match of `:s///c`
+    Confirm,
+}
+
+impl Mode {
+    /// Shown on the message line while the mode is active.
+    pub fn indicator(&self) -> &'static str {
+        match self {
+            Self::Insert => "-- INSERT --",
+            Self::Visual(SelectionKind::Char) => "-- VISUAL --",
+            Self::Visual(SelectionKind::Line) => "-- VISUAL LINE --",
+            Self::Visual(SelectionKind::Block) => "-- VISUAL BLOCK --",
+            Self::Normal | Self::Command | Self::Search(_) | Self::Confirm => "",
+        }
+    }
 }
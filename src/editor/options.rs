@@ -0,0 +1,417 @@
+use std::{fmt, path::Path};
+
+use crate::{
+    document::BufferOptions,
+    editor::{fold::FoldMethod, Clipboard},
+};
+
+/// Where the value of an option is kept.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum OptionScope {
+    /// one value for the whole editor
+    Global,
+    /// a value for each document, `:set` also changing the one new
+    /// documents get
+    Buffer,
+    /// a value for each window, `:set` also changing the one new windows get
+    Window,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum OptionKind {
+    Bool,
+    Number,
+    Text,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum OptionValue {
+    Bool(bool),
+    Number(usize),
+    Text(String),
+}
+
+impl fmt::Display for OptionValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bool(value) => write!(f, "{value}"),
+            Self::Number(value) => write!(f, "{value}"),
+            Self::Text(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+/// An option of the registry, with its short name like Vim's `so` for
+/// `scrolloff`.
+#[derive(Debug)]
+pub struct OptionInfo {
+    pub name: &'static str,
+    pub short: &'static str,
+    pub scope: OptionScope,
+    pub kind: OptionKind,
+}
+
+/// Every option `:set` and the config file know.
+pub const OPTIONS: [OptionInfo; 24] = [
+    option("mouse", "", OptionScope::Global, OptionKind::Bool),
+    option("clipboard", "cb", OptionScope::Global, OptionKind::Text),
+    option("winwidth", "wiw", OptionScope::Global, OptionKind::Number),
+    option("winheight", "wh", OptionScope::Global, OptionKind::Number),
+    option(
+        "reservedrows",
+        "rr",
+        OptionScope::Global,
+        OptionKind::Number,
+    ),
+    option("mapleader", "", OptionScope::Global, OptionKind::Text),
+    option("timeoutlen", "tm", OptionScope::Global, OptionKind::Number),
+    option("ignorecase", "ic", OptionScope::Global, OptionKind::Bool),
+    option("shiftwidth", "sw", OptionScope::Buffer, OptionKind::Number),
+    option("expandtab", "et", OptionScope::Buffer, OptionKind::Bool),
+    option("tabstop", "ts", OptionScope::Buffer, OptionKind::Number),
+    option("fileformat", "ff", OptionScope::Buffer, OptionKind::Text),
+    option(
+        "fileencoding",
+        "fenc",
+        OptionScope::Buffer,
+        OptionKind::Text,
+    ),
+    option("trimwhitespace", "", OptionScope::Buffer, OptionKind::Bool),
+    option(
+        "fixendofline",
+        "fixeol",
+        OptionScope::Buffer,
+        OptionKind::Bool,
+    ),
+    option("textwidth", "tw", OptionScope::Buffer, OptionKind::Number),
+    option("autopairs", "ap", OptionScope::Buffer, OptionKind::Bool),
+    option("scrolloff", "so", OptionScope::Window, OptionKind::Number),
+    option(
+        "sidescrolloff",
+        "siso",
+        OptionScope::Window,
+        OptionKind::Number,
+    ),
+    option("wrap", "", OptionScope::Window, OptionKind::Bool),
+    option("linebreak", "lbr", OptionScope::Window, OptionKind::Bool),
+    option("showbreak", "sbr", OptionScope::Window, OptionKind::Text),
+    option("scroll", "scr", OptionScope::Window, OptionKind::Number),
+    option("foldmethod", "fdm", OptionScope::Window, OptionKind::Text),
+];
+
+const fn option(
+    name: &'static str,
+    short: &'static str,
+    scope: OptionScope,
+    kind: OptionKind,
+) -> OptionInfo {
+    OptionInfo {
+        name,
+        short,
+        scope,
+        kind,
+    }
+}
+
+impl OptionInfo {
+    /// Option called `name`, or by its short name.
+    pub fn find(name: &str) -> Option<&'static OptionInfo> {
+        OPTIONS
+            .iter()
+            .find(|info| info.name == name || (!info.short.is_empty() && info.short == name))
+    }
+
+    /// Value of the option written as `text` after `:set name=`.
+    pub fn parse(&self, text: &str) -> Result<OptionValue, String> {
+        match self.kind {
+            OptionKind::Bool => match text {
+                "true" | "on" | "1" => Ok(OptionValue::Bool(true)),
+                "false" | "off" | "0" => Ok(OptionValue::Bool(false)),
+                _ => Err(format!("Invalid value for {}: {text}", self.name)),
+            },
+            OptionKind::Number => text
+                .parse()
+                .map(OptionValue::Number)
+                .map_err(|_| format!("Number required for {}: {text}", self.name)),
+            OptionKind::Text => Ok(OptionValue::Text(text.to_string())),
+        }
+    }
+
+    /// Value of the option set to `value` in the config file.
+    pub fn parse_toml(&self, value: &toml::Value) -> Result<OptionValue, String> {
+        match (self.kind, value) {
+            (OptionKind::Bool, toml::Value::Boolean(value)) => Ok(OptionValue::Bool(*value)),
+            (OptionKind::Number, toml::Value::Integer(value)) => usize::try_from(*value)
+                .map(OptionValue::Number)
+                .map_err(|_| format!("{} can't be negative", self.name)),
+            (OptionKind::Text, toml::Value::String(value)) => Ok(OptionValue::Text(value.clone())),
+            (OptionKind::Bool, _) => Err(format!("{} is true or false", self.name)),
+            (OptionKind::Number, _) => Err(format!("{} is a number", self.name)),
+            (OptionKind::Text, _) => Err(format!("{} is a string", self.name)),
+        }
+    }
+}
+
+/// Options of the whole editor.
+#[derive(Clone)]
+pub struct EditorOptions {
+    /// Use the mouse to place the cursor, select and scroll. Without it the
+    /// terminal handles the mouse, to select text on the screen.
+    pub mouse: bool,
+    /// Clipboard `"+` copies to: `auto`, `osc52`, `none` or a tool name like
+    /// `xclip`.
+    pub clipboard: String,
+    /// Columns of new windows, all the screen when 0.
+    pub winwidth: usize,
+    /// Lines of new windows, all the screen above the message line when 0.
+    pub winheight: usize,
+    /// Rows at the bottom of the terminal left out of the screen, for a
+    /// terminal multiplexer hiding them.
+    pub reservedrows: usize,
+    /// Keys `<leader>` stands for in mappings, when they are made.
+    pub mapleader: String,
+    /// Milliseconds keys wait for the rest of a longer mapping.
+    pub timeoutlen: usize,
+    /// Ignore case in the word searches of `*` and `#`, the searches typed
+    /// after `/` and `?` being smart-case.
+    pub ignorecase: bool,
+}
+
+impl EditorOptions {
+    pub fn get(&self, name: &str) -> Option<OptionValue> {
+        match name {
+            "mouse" => Some(OptionValue::Bool(self.mouse)),
+            "clipboard" => Some(OptionValue::Text(self.clipboard.clone())),
+            "winwidth" => Some(OptionValue::Number(self.winwidth)),
+            "winheight" => Some(OptionValue::Number(self.winheight)),
+            "reservedrows" => Some(OptionValue::Number(self.reservedrows)),
+            "mapleader" => Some(OptionValue::Text(self.mapleader.clone())),
+            "timeoutlen" => Some(OptionValue::Number(self.timeoutlen)),
+            "ignorecase" => Some(OptionValue::Bool(self.ignorecase)),
+            _ => None,
+        }
+    }
+
+    pub fn set(&mut self, name: &str, value: OptionValue) -> Result<(), String> {
+        match (name, value) {
+            ("mouse", OptionValue::Bool(value)) => self.mouse = value,
+            ("clipboard", OptionValue::Text(value)) => {
+                if value != "auto" && Clipboard::from_name(&value).is_none() {
+                    return Err(format!("Unknown clipboard: {value}"));
+                }
+                self.clipboard = value;
+            }
+            ("winwidth", OptionValue::Number(value)) => self.winwidth = value,
+            ("winheight", OptionValue::Number(value)) => self.winheight = value,
+            ("reservedrows", OptionValue::Number(value)) => self.reservedrows = value,
+            ("mapleader", OptionValue::Text(value)) => self.mapleader = value,
+            ("timeoutlen", OptionValue::Number(value)) => self.timeoutlen = value,
+            ("ignorecase", OptionValue::Bool(value)) => self.ignorecase = value,
+            (name, _) => return Err(format!("Unknown option: {name}")),
+        }
+        Ok(())
+    }
+}
+
+impl Default for EditorOptions {
+    fn default() -> Self {
+        Self {
+            mouse: false,
+            clipboard: String::from("auto"),
+            winwidth: 0,
+            winheight: 0,
+            reservedrows: 0,
+            mapleader: String::from("\\"),
+            timeoutlen: 1000,
+            ignorecase: false,
+        }
+    }
+}
+
+impl BufferOptions {
+    pub fn get(&self, name: &str) -> Option<OptionValue> {
+        match name {
+            "shiftwidth" => Some(OptionValue::Number(self.shiftwidth)),
+            "expandtab" => Some(OptionValue::Bool(self.expandtab)),
+            "tabstop" => Some(OptionValue::Number(self.tabstop)),
+            "fileformat" => Some(OptionValue::Text(self.fileformat.clone())),
+            "fileencoding" => Some(OptionValue::Text(self.fileencoding.clone())),
+            "trimwhitespace" => Some(OptionValue::Bool(self.trimwhitespace)),
+            "fixendofline" => Some(OptionValue::Bool(self.fixendofline)),
+            "textwidth" => Some(OptionValue::Number(self.textwidth)),
+            "autopairs" => Some(OptionValue::Bool(self.autopairs)),
+            _ => None,
+        }
+    }
+
+    pub fn set(&mut self, name: &str, value: OptionValue) -> Result<(), String> {
+        match (name, value) {
+            ("shiftwidth", OptionValue::Number(0)) => {
+                return Err(String::from("shiftwidth can't be 0"));
+            }
+            ("shiftwidth", OptionValue::Number(value)) => self.shiftwidth = value,
+            ("expandtab", OptionValue::Bool(value)) => self.expandtab = value,
+            ("tabstop", OptionValue::Number(0)) => {
+                return Err(String::from("tabstop can't be 0"));
+            }
+            ("tabstop", OptionValue::Number(value)) => self.tabstop = value,
+            ("fileformat", OptionValue::Text(value)) => {
+                if !matches!(value.as_str(), "unix" | "dos" | "mac") {
+                    return Err(format!("Unknown file format: {value}"));
+                }
+                self.fileformat = value;
+            }
+            ("fileencoding", OptionValue::Text(value)) => self.fileencoding = value,
+            ("trimwhitespace", OptionValue::Bool(value)) => self.trimwhitespace = value,
+            ("fixendofline", OptionValue::Bool(value)) => self.fixendofline = value,
+            ("textwidth", OptionValue::Number(value)) => self.textwidth = value,
+            ("autopairs", OptionValue::Bool(value)) => self.autopairs = value,
+            (name, _) => return Err(format!("Unknown option: {name}")),
+        }
+        Ok(())
+    }
+}
+
+/// Options that can differ from one window to another.
+#[derive(Clone)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct WindowOptions {
+    /// Lines of context kept above and below the cursor.
+    pub scrolloff: usize,
+    /// Columns of context kept to the left and right of the cursor.
+    pub sidescrolloff: usize,
+    /// Break long rows into several screen lines instead of scrolling
+    /// horizontally.
+    pub wrap: bool,
+    /// When wrapping, break rows at the last space that fits.
+    pub linebreak: bool,
+    /// Shown at the start of screen lines that continue a wrapped row.
+    pub showbreak: String,
+    /// Lines `Ctrl-u` and `Ctrl-d` move, half the window when 0.
+    pub scroll: usize,
+    /// How folds are made: `manual`, `indent` or `marker`.
+    pub foldmethod: String,
+    /// `wrap` and `linebreak` were set, prose files keep the values given
+    wrap_set: bool,
+    linebreak_set: bool,
+}
+
+impl WindowOptions {
+    /// Options for a window showing the file at `path`, prose is wrapped
+    /// unless `wrap` or `linebreak` were set.
+    pub fn for_file(&self, path: &str) -> Self {
+        let is_prose = Path::new(path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| matches!(ext, "md" | "markdown" | "txt" | "rst"));
+
+        Self {
+            wrap: if self.wrap_set { self.wrap } else { is_prose },
+            linebreak: if self.linebreak_set {
+                self.linebreak
+            } else {
+                is_prose
+            },
+            ..self.clone()
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<OptionValue> {
+        match name {
+            "scrolloff" => Some(OptionValue::Number(self.scrolloff)),
+            "sidescrolloff" => Some(OptionValue::Number(self.sidescrolloff)),
+            "wrap" => Some(OptionValue::Bool(self.wrap)),
+            "linebreak" => Some(OptionValue::Bool(self.linebreak)),
+            "showbreak" => Some(OptionValue::Text(self.showbreak.clone())),
+            "scroll" => Some(OptionValue::Number(self.scroll)),
+            "foldmethod" => Some(OptionValue::Text(self.foldmethod.clone())),
+            _ => None,
+        }
+    }
+
+    pub fn set(&mut self, name: &str, value: OptionValue) -> Result<(), String> {
+        match (name, value) {
+            ("scrolloff", OptionValue::Number(value)) => self.scrolloff = value,
+            ("sidescrolloff", OptionValue::Number(value)) => self.sidescrolloff = value,
+            ("wrap", OptionValue::Bool(value)) => (self.wrap, self.wrap_set) = (value, true),
+            ("linebreak", OptionValue::Bool(value)) => {
+                (self.linebreak, self.linebreak_set) = (value, true);
+            }
+            ("showbreak", OptionValue::Text(value)) => self.showbreak = value,
+            ("scroll", OptionValue::Number(value)) => self.scroll = value,
+            ("foldmethod", OptionValue::Text(value)) => {
+                if FoldMethod::from_name(&value).is_none() {
+                    return Err(format!("Unknown fold method: {value}"));
+                }
+                self.foldmethod = value;
+            }
+            (name, _) => return Err(format!("Unknown option: {name}")),
+        }
+        Ok(())
+    }
+}
+
+impl Default for WindowOptions {
+    fn default() -> Self {
+        Self {
+            scrolloff: 5,
+            sidescrolloff: 0,
+            wrap: false,
+            linebreak: false,
+            showbreak: String::new(),
+            scroll: 0,
+            foldmethod: String::from("manual"),
+            wrap_set: false,
+            linebreak_set: false,
+        }
+    }
+}
+
+/// Global values of the options, the local ones of new documents and
+/// windows start from them.
+#[derive(Clone, Default)]
+pub struct Options {
+    pub editor: EditorOptions,
+    pub buffer: BufferOptions,
+    pub window: WindowOptions,
+}
+
+mod test {
+    #[test]
+    fn should_parse_option_values() {
+        use super::{OptionInfo, OptionScope, OptionValue};
+
+        let scrolloff = OptionInfo::find("so").unwrap();
+        assert_eq!(scrolloff.name, "scrolloff");
+        assert_eq!(scrolloff.scope, OptionScope::Window);
+        assert_eq!(scrolloff.parse("3"), Ok(OptionValue::Number(3)));
+        assert!(scrolloff.parse("-3").is_err());
+        assert!(scrolloff
+            .parse_toml(&toml::Value::String(String::from("3")))
+            .is_err());
+
+        let wrap = OptionInfo::find("wrap").unwrap();
+        assert_eq!(wrap.parse("off"), Ok(OptionValue::Bool(false)));
+        assert_eq!(
+            wrap.parse_toml(&toml::Value::Boolean(true)),
+            Ok(OptionValue::Bool(true))
+        );
+        assert!(OptionInfo::find("nope").is_none());
+    }
+
+    #[test]
+    fn should_wrap_prose_unless_wrap_is_set() {
+        use super::{OptionValue, WindowOptions};
+
+        let mut options = WindowOptions::default();
+        assert!(options.for_file("notes.md").wrap);
+        assert!(options.for_file("notes.md").linebreak);
+        assert!(!options.for_file("main.rs").wrap);
+
+        options.set("wrap", OptionValue::Bool(false)).unwrap();
+        assert!(!options.for_file("notes.md").wrap);
+        assert!(options.for_file("notes.md").linebreak);
+        options.set("wrap", OptionValue::Bool(true)).unwrap();
+        assert!(options.for_file("main.rs").wrap);
+    }
+}
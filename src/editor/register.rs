@@ -0,0 +1,194 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::editor::SelectionKind;
+
+/// Deletes kept in the numbered registers `1` to `9`.
+const DELETE_HISTORY: usize = 9;
+
+/// Text taken from a document by yanking or deleting, one item per line.
+#[derive(Clone)]
+pub struct Register {
+    pub lines: Vec<String>,
+    pub kind: SelectionKind,
+}
+
+impl Register {
+    /// Characterwise register from text where lines are split by `\n`.
+    pub fn from_text(text: &str) -> Self {
+        Self {
+            lines: text.split('\n').map(String::from).collect(),
+            kind: SelectionKind::Char,
+        }
+    }
+
+    /// Adds `other` at the end, used by the uppercase named registers.
+    fn append(&mut self, other: Register) {
+        if self.kind == SelectionKind::Char && other.kind == SelectionKind::Char {
+            let mut lines = other.lines.into_iter();
+            if let (Some(last), Some(first)) = (self.lines.last_mut(), lines.next()) {
+                last.push_str(&first);
+            }
+            self.lines.extend(lines);
+        } else {
+            if other.kind == SelectionKind::Line {
+                self.kind = SelectionKind::Line;
+            }
+            self.lines.extend(other.lines);
+        }
+    }
+
+    /// Contents in one line, with line breaks shown as `^J`.
+    pub fn preview(&self) -> String {
+        let mut text = self.lines.join("^J");
+        if self.kind == SelectionKind::Line {
+            text.push_str("^J");
+        }
+
+        text
+    }
+}
+
+/// Every register text can be yanked into, deleted into and put from.
+///
+/// - `"` the unnamed register, holds the last yanked or deleted text
+/// - `0` the last yank without a register name
+/// - `1` to `9` the last deletes spanning lines, `1` being the newest
+/// - `-` the last delete within a line
+/// - `a` to `z` named registers, `A` to `Z` append to them
+/// - `_` the black hole, writing to it keeps every other register untouched
+/// - `.` the last inserted text and `:` the last command line, read-only
+///
+/// The current file name (`%`) and the system clipboard (`+` and `*`) are not
+/// stored here, they come from the document and the clipboard.
+#[derive(Default)]
+pub struct Registers {
+    unnamed: Option<Register>,
+    yanked: Option<Register>,
+    deleted: VecDeque<Register>,
+    small_delete: Option<Register>,
+    named: HashMap<char, Register>,
+    last_insert: Option<String>,
+    last_command: Option<String>,
+}
+
+impl Registers {
+    /// Whether `name` can be given with `"` before a put.
+    pub fn is_valid_name(name: char) -> bool {
+        Self::is_writable(name) || matches!(name, '.' | ':' | '%')
+    }
+
+    /// Whether `name` can be given with `"` before a yank or delete.
+    pub fn is_writable(name: char) -> bool {
+        name.is_ascii_alphanumeric() || matches!(name, '"' | '-' | '_' | '+' | '*')
+    }
+
+    pub fn get(&self, name: char) -> Option<Register> {
+        match name {
+            '"' => self.unnamed.clone(),
+            '0' => self.yanked.clone(),
+            '1'..='9' => {
+                let index = name as usize - '1' as usize;
+                self.deleted.get(index).cloned()
+            }
+            '-' => self.small_delete.clone(),
+            '.' => self.last_insert.as_deref().map(Register::from_text),
+            ':' => self.last_command.as_deref().map(Register::from_text),
+            _ if name.is_ascii_alphabetic() => self.named.get(&name.to_ascii_lowercase()).cloned(),
+            _ => None,
+        }
+    }
+
+    /// Stores yanked text, in `0` when no register is given.
+    pub fn yank(&mut self, name: Option<char>, register: Register) {
+        match name {
+            None | Some('"') => {
+                self.yanked = Some(register.clone());
+                self.unnamed = Some(register);
+            }
+            Some(name) => self.write(name, register),
+        }
+    }
+
+    /// Stores deleted text, in `1` shifting the older deletes or in `-` when
+    /// it is within a line and no register is given.
+    pub fn delete(&mut self, name: Option<char>, register: Register) {
+        match name {
+            None | Some('"') => {
+                if register.kind == SelectionKind::Char && register.lines.len() == 1 {
+                    self.small_delete = Some(register.clone());
+                } else {
+                    self.deleted.push_front(register.clone());
+                    self.deleted.truncate(DELETE_HISTORY);
+                }
+                self.unnamed = Some(register);
+            }
+            Some(name) => self.write(name, register),
+        }
+    }
+
+    fn write(&mut self, name: char, register: Register) {
+        match name {
+            '0' => self.yanked = Some(register.clone()),
+            '1'..='9' => {
+                let index = name as usize - '1' as usize;
+                self.deleted
+                    .resize(self.deleted.len().max(index + 1), Register::from_text(""));
+                self.deleted[index] = register.clone();
+            }
+            '-' => self.small_delete = Some(register.clone()),
+            'A'..='Z' => {
+                let lower = name.to_ascii_lowercase();
+                let appended = match self.named.remove(&lower) {
+                    Some(mut current) => {
+                        current.append(register);
+                        current
+                    }
+                    None => register,
+                };
+                self.named.insert(lower, appended.clone());
+                self.unnamed = Some(appended);
+                return;
+            }
+            'a'..='z' => {
+                self.named.insert(name, register.clone());
+            }
+            // the clipboard is written by the caller, the unnamed register
+            // just below
+            '"' | '+' | '*' => {}
+            // the black hole `_` keeps nothing
+            _ => return,
+        }
+
+        self.unnamed = Some(register);
+    }
+
+    /// Stores the keys of a macro recorded with `q`, unlike a yank the
+    /// unnamed register is left as it is unless it is the one recorded.
+    pub fn record(&mut self, name: char, keys: &str) {
+        let unnamed = self.unnamed.take();
+        self.write(name, Register::from_text(keys));
+        if name != '"' {
+            self.unnamed = unnamed;
+        }
+    }
+
+    pub fn set_last_insert(&mut self, text: String) {
+        self.last_insert = Some(text);
+    }
+
+    pub fn set_last_command(&mut self, text: String) {
+        self.last_command = Some(text);
+    }
+
+    /// Every register holding something, in the order `:registers` shows
+    /// them.
+    pub fn filled(&self) -> Vec<(char, Register)> {
+        ['"']
+            .into_iter()
+            .chain('0'..='9')
+            .chain('a'..='z')
+            .chain(['-', '.', ':'])
+            .filter_map(|name| self.get(name).map(|register| (name, register)))
+            .collect()
+    }
+}
@@ -0,0 +1,65 @@
+use crate::utils::Position;
+use std::{cmp, ops::Range};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SelectionKind {
+    Char,
+    Line,
+    Block,
+}
+
+/// Text between an anchor and the cursor, as selected in visual mode.
+#[derive(Clone, Copy, Debug)]
+pub struct Selection {
+    pub anchor: Position,
+    pub cursor: Position,
+    pub kind: SelectionKind,
+}
+
+impl Selection {
+    pub fn start(&self) -> Position {
+        cmp::min(self.anchor, self.cursor)
+    }
+
+    pub fn end(&self) -> Position {
+        cmp::max(self.anchor, self.cursor)
+    }
+
+    /// Leftmost and rightmost selected columns of a block, both included.
+    pub fn block_columns(&self) -> (usize, usize) {
+        (
+            cmp::min(self.anchor.x, self.cursor.x),
+            cmp::max(self.anchor.x, self.cursor.x),
+        )
+    }
+
+    /// Columns selected on `line`, a row `line_len` long. The end of a row
+    /// counts as one more column so line breaks and empty rows can be
+    /// selected too.
+    pub fn columns(&self, line: usize, line_len: usize) -> Option<Range<usize>> {
+        let (start, end) = (self.start(), self.end());
+        if line < start.y || line > end.y {
+            return None;
+        }
+
+        let columns = match self.kind {
+            SelectionKind::Line => 0..line_len + 1,
+            SelectionKind::Char => {
+                let from = if line == start.y { start.x } else { 0 };
+                let to = if line == end.y {
+                    end.x + 1
+                } else {
+                    line_len + 1
+                };
+
+                from..cmp::min(to, line_len + 1)
+            }
+            SelectionKind::Block => {
+                let (left, right) = self.block_columns();
+                left..cmp::min(right + 1, line_len)
+            }
+        };
+
+        Some(columns)
+    }
+}
@@ -1,5 +1,15 @@
 mod test {
-    use crate::document::Document;
+    use std::{cell::RefCell, rc::Rc};
+
+    use termion::event::Key;
+
+    use crate::{
+        document::Document,
+        editor::{Control, Window},
+        utils::{Position, Size},
+    };
+
+    type Windows = Rc<RefCell<Vec<Window>>>;
 
     fn default_doc(value: Option<Vec<&str>>) -> Document {
         match value {
@@ -8,6 +18,22 @@ mod test {
         }
     }
 
+    /// A `width` × `height` window showing `doc` at the top left corner.
+    fn window(doc: Document, width: u16, height: u16) -> Window {
+        Window::new(doc, Size { width, height }, Position { x: 0, y: 0 })
+    }
+
+    fn single_window(doc: Document, width: u16, height: u16) -> Windows {
+        Rc::new(RefCell::new(vec![window(doc, width, height)]))
+    }
+
+    /// Control of `windows`, attached to the first one.
+    fn attached_control(windows: &Windows) -> Control<'_> {
+        let mut control = Control::new(windows);
+        control.attach_window(0);
+        control
+    }
+
     #[test]
     fn test_create_new_control() {
         use crate::editor::Control;
@@ -17,7 +43,6 @@ mod test {
         let _ = Control::new(&windows);
     }
 
-    #[ignore]
     #[test]
     fn test_should_not_panic_when_has_no_windows() {
         use crate::editor::Control;
@@ -78,4 +103,1422 @@ mod test {
 
         assert_eq!(control.position().clone(), Position { x: 0, y: 1 });
     }
+
+    fn numbered_doc(lines: usize) -> Document {
+        let rows: Vec<String> = (0..lines).map(|i| format!("line{i}")).collect();
+        Document::from(rows.iter().map(String::as_str).collect::<Vec<&str>>())
+    }
+
+    #[test]
+    fn test_should_clamp_move_down_at_document_end() {
+        let windows = single_window(numbered_doc(30), 10, 10);
+        let mut control = attached_control(&windows);
+        control.move_down(100);
+
+        assert_eq!(control.document_position(), Some(Position { x: 0, y: 29 }));
+        assert_eq!(control.position().clone(), Position { x: 0, y: 9 });
+    }
+
+    #[test]
+    fn test_should_center_cursor_line() {
+        let windows = single_window(numbered_doc(30), 10, 10);
+        let mut control = attached_control(&windows);
+        control.move_down(15);
+
+        control.process_key_in_normal_mode(Key::Char('z'));
+        control.process_key_in_normal_mode(Key::Char('z'));
+        assert_eq!(control.position().clone(), Position { x: 0, y: 5 });
+
+        control.process_key_in_normal_mode(Key::Char('z'));
+        control.process_key_in_normal_mode(Key::Char('t'));
+        // scrolloff keeps some lines above the cursor
+        assert_eq!(control.position().clone(), Position { x: 0, y: 4 });
+
+        control.process_key_in_normal_mode(Key::Char('z'));
+        control.process_key_in_normal_mode(Key::Char('b'));
+        assert_eq!(control.position().clone(), Position { x: 0, y: 5 });
+        assert_eq!(control.document_position(), Some(Position { x: 0, y: 15 }));
+    }
+
+    #[test]
+    fn test_should_keep_cursor_visible_when_scrolling_view() {
+        let windows = single_window(numbered_doc(30), 10, 10);
+        let mut control = attached_control(&windows);
+
+        control.process_key_in_normal_mode(Key::Ctrl('e'));
+        // the first visible line is now 1, plus 4 lines of scrolloff
+        assert_eq!(control.document_position(), Some(Position { x: 0, y: 5 }));
+
+        control.process_key_in_normal_mode(Key::Ctrl('y'));
+        assert_eq!(control.document_position(), Some(Position { x: 0, y: 5 }));
+    }
+
+    #[test]
+    fn test_should_scroll_pages() {
+        let windows = single_window(numbered_doc(30), 10, 10);
+        let mut control = attached_control(&windows);
+
+        control.process_key_in_normal_mode(Key::Ctrl('f'));
+        assert_eq!(control.document_position(), Some(Position { x: 0, y: 12 }));
+
+        control.process_key_in_normal_mode(Key::Ctrl('f'));
+        control.process_key_in_normal_mode(Key::Ctrl('f'));
+        // the window stops at the end of the document
+        assert_eq!(control.position().clone(), Position { x: 0, y: 4 });
+        assert_eq!(control.document_position(), Some(Position { x: 0, y: 24 }));
+
+        control.process_key_in_normal_mode(Key::Ctrl('b'));
+        control.process_key_in_normal_mode(Key::Ctrl('b'));
+        control.process_key_in_normal_mode(Key::Ctrl('b'));
+        assert_eq!(control.document_position(), Some(Position { x: 0, y: 5 }));
+    }
+
+    #[test]
+    fn test_should_move_by_logical_and_display_lines() {
+        let windows = single_window(default_doc(Some(vec!["0123456789", "abc"])), 4, 4);
+        windows.borrow_mut()[0].options_mut().wrap = true;
+        let mut control = attached_control(&windows);
+        control.move_right(1);
+
+        control.process_key_in_normal_mode(Key::Char('g'));
+        control.process_key_in_normal_mode(Key::Char('j'));
+        assert_eq!(control.document_position(), Some(Position { x: 5, y: 0 }));
+        assert_eq!(control.position().clone(), Position { x: 1, y: 1 });
+
+        control.process_key_in_normal_mode(Key::Char('j'));
+        assert_eq!(control.document_position(), Some(Position { x: 2, y: 1 }));
+        assert_eq!(control.position().clone(), Position { x: 2, y: 3 });
+    }
+
+    fn handle_keys(control: &mut Control, keys: &[Key]) {
+        for key in keys {
+            control.handle_key(*key);
+        }
+    }
+
+    /// Types `command` after `:`, like `execute_command` but as one change.
+    fn run_command(control: &mut Control, command: &str) {
+        handle_keys(control, &keys(&format!(":{command}\n")));
+    }
+
+    fn document_lines(control: &Control) -> Vec<String> {
+        control
+            .read_document(|doc| {
+                (0..doc.lines_len())
+                    .filter_map(|y| doc.row(y).map(|row| row.content.clone()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn test_should_delete_char_selection() {
+        let windows = single_window(default_doc(Some(vec!["Hello World", "foo bar"])), 20, 10);
+        let mut control = attached_control(&windows);
+
+        // select from "World" to "foo " across the line break
+        handle_keys(
+            &mut control,
+            &[
+                Key::Char('w'),
+                Key::Char('v'),
+                Key::Char('j'),
+                Key::Char('h'),
+                Key::Char('h'),
+                Key::Char('d'),
+            ],
+        );
+
+        assert_eq!(document_lines(&control), vec!["Hello ar"]);
+        assert_eq!(control.document_position(), Some(Position { x: 6, y: 0 }));
+        assert!(control.selection().is_none());
+    }
+
+    #[test]
+    fn test_should_yank_and_put_lines() {
+        let windows = single_window(default_doc(Some(vec!["a", "b", "c"])), 20, 10);
+        let mut control = attached_control(&windows);
+
+        handle_keys(
+            &mut control,
+            &[
+                Key::Char('V'),
+                Key::Char('j'),
+                Key::Char('y'),
+                Key::Char('G'),
+                Key::Char('p'),
+            ],
+        );
+
+        assert_eq!(document_lines(&control), vec!["a", "b", "c", "a", "b"]);
+        assert_eq!(control.document_position(), Some(Position { x: 0, y: 3 }));
+    }
+
+    #[test]
+    fn test_should_insert_text_in_block() {
+        let windows = single_window(default_doc(Some(vec!["abc", "def", "ghi"])), 20, 10);
+        let mut control = attached_control(&windows);
+
+        handle_keys(
+            &mut control,
+            &[
+                Key::Char('l'),
+                Key::Ctrl('v'),
+                Key::Char('j'),
+                Key::Char('I'),
+                Key::Char('-'),
+                Key::Esc,
+            ],
+        );
+
+        assert_eq!(document_lines(&control), vec!["a-bc", "d-ef", "ghi"]);
+    }
+
+    #[test]
+    fn test_should_shift_and_change_case_of_lines() {
+        let windows = single_window(default_doc(Some(vec!["one", "two", "three"])), 20, 10);
+        let mut control = attached_control(&windows);
+
+        handle_keys(
+            &mut control,
+            &[Key::Char('V'), Key::Char('j'), Key::Char('>')],
+        );
+        assert_eq!(
+            document_lines(&control),
+            vec!["    one", "    two", "three"]
+        );
+
+        // gv selects the same lines again
+        handle_keys(
+            &mut control,
+            &[Key::Char('g'), Key::Char('v'), Key::Char('U')],
+        );
+        assert_eq!(
+            document_lines(&control),
+            vec!["    ONE", "    TWO", "three"]
+        );
+    }
+
+    fn keys(text: &str) -> Vec<Key> {
+        text.chars().map(Key::Char).collect()
+    }
+
+    #[test]
+    fn test_should_delete_and_put_with_motions() {
+        let windows = single_window(default_doc(Some(vec!["one two three", "four"])), 20, 10);
+        let mut control = attached_control(&windows);
+
+        handle_keys(&mut control, &keys("dw"));
+        assert_eq!(document_lines(&control), vec!["two three", "four"]);
+
+        handle_keys(&mut control, &keys("$P"));
+        assert_eq!(document_lines(&control), vec!["two threone e", "four"]);
+
+        handle_keys(&mut control, &keys("ddp"));
+        assert_eq!(document_lines(&control), vec!["four", "two threone e"]);
+        assert_eq!(control.document_position(), Some(Position { x: 0, y: 1 }));
+
+        // the last word of a row is deleted without the line break
+        handle_keys(&mut control, &keys("wwdw"));
+        assert_eq!(document_lines(&control), vec!["four", "two threone "]);
+    }
+
+    #[test]
+    fn test_should_put_a_register_count_times() {
+        let windows = single_window(default_doc(Some(vec!["ab", "c"])), 20, 10);
+        let mut control = attached_control(&windows);
+
+        handle_keys(&mut control, &keys("yl3p"));
+        assert_eq!(document_lines(&control), vec!["aaaab", "c"]);
+        assert_eq!(control.document_position(), Some(Position { x: 3, y: 0 }));
+
+        handle_keys(&mut control, &keys("jyy2P"));
+        assert_eq!(document_lines(&control), vec!["aaaab", "c", "c", "c"]);
+        assert_eq!(control.document_position(), Some(Position { x: 0, y: 1 }));
+
+        // a count too large is refused at once
+        handle_keys(&mut control, &keys("yy99999999p"));
+        assert_eq!(document_lines(&control), vec!["aaaab", "c", "c", "c"]);
+        assert!(control.is_error());
+    }
+
+    #[test]
+    fn test_should_take_the_last_char_of_a_row_with_l() {
+        let windows = single_window(default_doc(Some(vec!["abc", "abcd"])), 20, 10);
+        let mut control = attached_control(&windows);
+
+        handle_keys(&mut control, &keys("$dl"));
+        assert_eq!(document_lines(&control)[0], "ab");
+
+        handle_keys(&mut control, &keys("$ylP"));
+        assert_eq!(document_lines(&control)[0], "abb");
+
+        handle_keys(&mut control, &keys("$clX"));
+        handle_keys(&mut control, &[Key::Esc]);
+        assert_eq!(document_lines(&control)[0], "abX");
+
+        // the count stops at the end of the row
+        handle_keys(&mut control, &keys("j02l3dl"));
+        assert_eq!(document_lines(&control)[1], "ab");
+    }
+
+    #[test]
+    fn test_should_not_delete_lines_with_stuck_line_motions() {
+        let windows = single_window(default_doc(Some(vec!["a", "b", "c"])), 20, 10);
+        let mut control = attached_control(&windows);
+
+        handle_keys(&mut control, &keys("dk"));
+        assert_eq!(document_lines(&control), vec!["a", "b", "c"]);
+        handle_keys(&mut control, &keys("Gdj"));
+        assert_eq!(document_lines(&control), vec!["a", "b", "c"]);
+
+        // arrows are the same motions as `j` and `k`
+        handle_keys(&mut control, &keys("gg"));
+        handle_keys(&mut control, &[Key::Char('d'), Key::Down]);
+        assert_eq!(document_lines(&control), vec!["c"]);
+    }
+
+    #[test]
+    fn test_should_keep_named_and_numbered_registers() {
+        let windows = single_window(default_doc(Some(vec!["a", "b", "c", "d"])), 20, 10);
+        let mut control = attached_control(&windows);
+
+        // yank into a, append to it, then delete two lines
+        handle_keys(&mut control, &keys("\"ayyj\"Ayyddddj"));
+        assert_eq!(document_lines(&control), vec!["a", "d"]);
+
+        let lines = |control: &mut Control, name| {
+            control
+                .read_register(name)
+                .map(|register| register.lines)
+                .unwrap_or_default()
+        };
+        assert_eq!(lines(&mut control, 'a'), vec!["a", "b"]);
+        assert_eq!(lines(&mut control, '1'), vec!["c"]);
+        assert_eq!(lines(&mut control, '2'), vec!["b"]);
+        assert_eq!(lines(&mut control, '"'), vec!["c"]);
+
+        // the black hole keeps the other registers untouched
+        handle_keys(&mut control, &keys("\"_dd\"ap"));
+        assert_eq!(document_lines(&control), vec!["a", "a", "b"]);
+        assert_eq!(lines(&mut control, '"'), vec!["c"]);
+    }
+
+    #[test]
+    fn test_should_list_registers() {
+        let windows = single_window(default_doc(Some(vec!["one two"])), 20, 10);
+        let mut control = attached_control(&windows);
+
+        handle_keys(&mut control, &keys("yeixy"));
+        handle_keys(&mut control, &[Key::Esc]);
+        handle_keys(&mut control, &keys(":reg 0.\n"));
+
+        assert_eq!(
+            control.message(),
+            Some("Type Name Content\n  c  \"0   one\n  c  \".   xy")
+        );
+    }
+
+    #[test]
+    fn test_should_copy_and_paste_through_clipboard_tool() {
+        use crate::editor::{clipboard::ClipboardTool, Clipboard};
+        use std::{env, fs, process};
+
+        let file = env::temp_dir().join(format!("clipboard-{}", process::id()));
+        let file = file.display();
+        let tool = ClipboardTool {
+            name: String::from("stub"),
+            copy: vec!["sh".into(), "-c".into(), format!("cat > {file}")],
+            paste: vec!["cat".into(), file.to_string()],
+        };
+
+        let windows = single_window(default_doc(Some(vec!["one", "two"])), 20, 10);
+        let mut control = attached_control(&windows);
+        control.set_clipboard(Clipboard::new(false, Some(tool)));
+
+        handle_keys(&mut control, &keys("\"+yy"));
+        assert_eq!(fs::read_to_string(file.to_string()).unwrap(), "one\n");
+
+        // text copied by another program is pasted as it is
+        fs::write(file.to_string(), "x").unwrap();
+        handle_keys(&mut control, &keys("j\"*p"));
+        assert_eq!(document_lines(&control), vec!["one", "txwo"]);
+
+        fs::remove_file(file.to_string()).unwrap();
+    }
+
+    #[test]
+    fn test_should_undo_and_redo_changes() {
+        let windows = single_window(default_doc(Some(vec!["one", "two"])), 20, 10);
+        let mut control = attached_control(&windows);
+
+        // a whole insert is undone at once
+        handle_keys(&mut control, &keys("Afoo"));
+        handle_keys(&mut control, &[Key::Esc]);
+        handle_keys(&mut control, &keys("jdd"));
+        assert_eq!(document_lines(&control), vec!["onefoo"]);
+
+        handle_keys(&mut control, &keys("u"));
+        assert_eq!(document_lines(&control), vec!["onefoo", "two"]);
+        handle_keys(&mut control, &keys("u"));
+        assert_eq!(document_lines(&control), vec!["one", "two"]);
+        handle_keys(&mut control, &keys("u"));
+        assert_eq!(control.message(), Some("Already at oldest change"));
+
+        handle_keys(&mut control, &[Key::Ctrl('r')]);
+        assert_eq!(document_lines(&control), vec!["onefoo", "two"]);
+    }
+
+    #[test]
+    fn test_should_paste_text_as_one_change() {
+        use crate::editor::Mode;
+
+        let windows = single_window(default_doc(Some(vec!["ab"])), 20, 10);
+        let mut control = attached_control(&windows);
+
+        // pasted in normal mode the text is not taken as commands
+        control.handle_paste("dd\nx");
+        assert_eq!(document_lines(&control), vec!["dd", "xab"]);
+        assert!(matches!(control.mode(), Mode::Normal));
+        assert_eq!(control.document_position(), Some(Position { x: 0, y: 1 }));
+
+        handle_keys(&mut control, &keys("u"));
+        assert_eq!(document_lines(&control), vec!["ab"]);
+
+        // in insert mode the paste is undone apart from what is typed
+        handle_keys(&mut control, &keys("iz"));
+        control.handle_paste("yy");
+        handle_keys(&mut control, &keys("w"));
+        handle_keys(&mut control, &[Key::Esc]);
+        assert_eq!(document_lines(&control), vec!["zyywab"]);
+
+        handle_keys(&mut control, &keys("u"));
+        assert_eq!(document_lines(&control), vec!["zyyab"]);
+        handle_keys(&mut control, &keys("u"));
+        assert_eq!(document_lines(&control), vec!["zab"]);
+    }
+
+    #[test]
+    fn test_should_click_drag_and_scroll_with_mouse() {
+        use crate::editor::{Mode, SelectionKind};
+        use termion::event::{MouseButton, MouseEvent};
+
+        let size = || Size {
+            width: 10,
+            height: 5,
+        };
+        let windows = Rc::new(RefCell::new(vec![
+            Window::new(numbered_doc(3), size(), Position { x: 0, y: 0 }),
+            Window::new(numbered_doc(30), size(), Position { x: 0, y: 5 }),
+        ]));
+        let mut control = attached_control(&windows);
+        control.execute_command("set mouse");
+
+        // terminals count from 1, this is the second row of the second window
+        control.handle_mouse(MouseEvent::Press(MouseButton::Left, 2, 7));
+        assert_eq!(control.document_position(), Some(Position { x: 1, y: 1 }));
+        assert_eq!(control.position().clone(), Position { x: 1, y: 6 });
+
+        control.handle_mouse(MouseEvent::Hold(1, 8));
+        control.handle_mouse(MouseEvent::Release(1, 8));
+        assert!(matches!(control.mode(), Mode::Visual(SelectionKind::Char)));
+        let selection = control.selection().unwrap();
+        assert_eq!(selection.start(), Position { x: 1, y: 1 });
+        assert_eq!(selection.end(), Position { x: 0, y: 2 });
+
+        control.handle_mouse(MouseEvent::Press(MouseButton::WheelDown, 1, 6));
+        assert_eq!(
+            windows.borrow()[1].document_line_number_from_cursor(5),
+            Some(3)
+        );
+
+        let cursor = *control.position();
+        control.execute_command("set nomouse");
+        control.handle_mouse(MouseEvent::Press(MouseButton::Left, 1, 1));
+        assert_eq!(control.position().clone(), cursor);
+    }
+
+    #[test]
+    fn test_should_move_the_cursor_where_a_sgr_click_points() {
+        use crate::terminal::{Input, Terminal};
+
+        let windows = single_window(numbered_doc(30), 10, 5);
+        let mut control = attached_control(&windows);
+        control.execute_command("set mouse");
+        control.move_down(10);
+
+        // the fourth column of the third row, counted from 1 by the terminal
+        let inputs =
+            Terminal::parse_inputs(b"\x1b[<0;4;3M\x1b[<0;4;3m", std::iter::empty()).unwrap();
+        for input in inputs {
+            let Input::Mouse(event) = input else {
+                panic!("not a mouse input");
+            };
+            control.handle_mouse(event);
+        }
+
+        assert_eq!(control.position().clone(), Position { x: 3, y: 2 });
+        let top = windows.borrow()[0]
+            .document_line_number_from_cursor(0)
+            .unwrap();
+        assert_eq!(
+            control.document_position(),
+            Some(Position { x: 3, y: top + 2 })
+        );
+    }
+
+    #[test]
+    fn test_should_resize_windows_by_dragging_separators() {
+        use termion::event::{MouseButton, MouseEvent};
+
+        let size = |width, height| Size { width, height };
+        // a column between the windows on top, a line above the bottom one
+        let windows = Rc::new(RefCell::new(vec![
+            Window::new(numbered_doc(3), size(10, 4), Position { x: 0, y: 0 }),
+            Window::new(numbered_doc(3), size(9, 4), Position { x: 11, y: 0 }),
+            Window::new(numbered_doc(3), size(20, 4), Position { x: 0, y: 5 }),
+        ]));
+        let mut control = attached_control(&windows);
+        control.execute_command("set mouse");
+        let area = |index: usize| {
+            let windows = windows.borrow();
+            let window = &windows[index];
+            (window.position(), window.size().width, window.size().height)
+        };
+
+        control.handle_mouse(MouseEvent::Press(MouseButton::Left, 11, 2));
+        control.handle_mouse(MouseEvent::Hold(7, 2));
+        control.handle_mouse(MouseEvent::Release(7, 2));
+        assert_eq!(area(0), (Position { x: 0, y: 0 }, 6, 4));
+        assert_eq!(area(1), (Position { x: 7, y: 0 }, 13, 4));
+
+        // the windows keep a column
+        control.handle_mouse(MouseEvent::Press(MouseButton::Left, 7, 1));
+        control.handle_mouse(MouseEvent::Hold(30, 1));
+        assert_eq!(area(1), (Position { x: 19, y: 0 }, 1, 4));
+        control.handle_mouse(MouseEvent::Release(30, 1));
+
+        control.handle_mouse(MouseEvent::Press(MouseButton::Left, 3, 5));
+        control.handle_mouse(MouseEvent::Hold(3, 3));
+        assert_eq!(area(0), (Position { x: 0, y: 0 }, 18, 2));
+        assert_eq!(area(2), (Position { x: 0, y: 3 }, 20, 6));
+        control.handle_mouse(MouseEvent::Release(3, 3));
+
+        // dragging in a window selects again
+        control.handle_mouse(MouseEvent::Hold(1, 2));
+        assert_eq!(area(0), (Position { x: 0, y: 0 }, 18, 2));
+    }
+
+    #[test]
+    fn test_should_search_incrementally_and_repeat() {
+        use crate::editor::{Highlight, Mode};
+
+        let doc = default_doc(Some(vec!["foo bar", "Foo foobar", "bar foo"]));
+        let windows = single_window(doc, 20, 5);
+        let mut control = attached_control(&windows);
+
+        // the cursor follows what is typed, smart-case ignores case here
+        handle_keys(&mut control, &keys("/fo"));
+        assert!(matches!(control.mode(), Mode::Search(_)));
+        assert_eq!(control.command_line().as_deref(), Some("/fo"));
+        assert_eq!(control.document_position(), Some(Position { x: 0, y: 1 }));
+        handle_keys(&mut control, &[Key::Esc]);
+        assert_eq!(control.document_position(), Some(Position { x: 0, y: 0 }));
+
+        handle_keys(&mut control, &keys("/foob\n"));
+        assert_eq!(control.document_position(), Some(Position { x: 4, y: 1 }));
+        let highlights = windows.borrow()[0].visible_highlights();
+        assert_eq!(highlights.len(), 1);
+        assert_eq!(highlights[0].highlight, Highlight::Search);
+
+        // uppercase letters make the search case sensitive
+        handle_keys(&mut control, &keys("/Foo\n"));
+        assert_eq!(control.document_position(), Some(Position { x: 0, y: 1 }));
+        handle_keys(&mut control, &keys("n"));
+        assert_eq!(control.document_position(), Some(Position { x: 0, y: 1 }));
+        assert_eq!(
+            control.message(),
+            Some("search hit BOTTOM, continuing at TOP")
+        );
+
+        // `?` with nothing typed repeats the last search backward
+        handle_keys(&mut control, &keys("gg/foo\n?\n"));
+        assert_eq!(control.document_position(), Some(Position { x: 0, y: 0 }));
+        handle_keys(&mut control, &keys("N"));
+        assert_eq!(control.document_position(), Some(Position { x: 0, y: 1 }));
+
+        // `*` searches the whole word with its case, skipping `Foo` and `foobar`
+        handle_keys(&mut control, &keys("gg*"));
+        assert_eq!(control.document_position(), Some(Position { x: 4, y: 2 }));
+        handle_keys(&mut control, &keys("*"));
+        assert_eq!(control.document_position(), Some(Position { x: 0, y: 0 }));
+        handle_keys(&mut control, &keys("#"));
+        assert_eq!(control.document_position(), Some(Position { x: 4, y: 2 }));
+
+        // history is browsed with the arrows, it has the searches of `*`
+        handle_keys(&mut control, &keys("/"));
+        handle_keys(&mut control, &[Key::Up]);
+        assert_eq!(control.command_line().as_deref(), Some(r"/\bfoo\b"));
+        handle_keys(&mut control, &[Key::Esc]);
+
+        handle_keys(&mut control, &keys(":noh\n"));
+        assert!(windows.borrow()[0].visible_highlights().is_empty());
+
+        handle_keys(&mut control, &keys("/xyz\n"));
+        assert_eq!(control.message(), Some("Pattern not found: xyz"));
+    }
+
+    #[test]
+    fn test_should_search_the_word_under_the_cursor_with_its_case() {
+        let doc = default_doc(Some(vec!["foo", "Foo", "foo"]));
+        let windows = single_window(doc, 20, 5);
+        let mut control = attached_control(&windows);
+
+        handle_keys(&mut control, &keys("*"));
+        assert_eq!(control.document_position(), Some(Position { x: 0, y: 2 }));
+
+        run_command(&mut control, "set ignorecase");
+        handle_keys(&mut control, &keys("gg*"));
+        assert_eq!(control.document_position(), Some(Position { x: 0, y: 1 }));
+    }
+
+    #[test]
+    fn test_should_substitute_over_ranges() {
+        use crate::editor::Mode;
+
+        let doc = default_doc(Some(vec![
+            "let first_name = 1;",
+            "let last_name = 2;",
+            "foo foo foo",
+            "foo foo foo",
+        ]));
+        let windows = single_window(doc, 30, 5);
+        let mut control = attached_control(&windows);
+
+        // groups and case modifiers, on the current line by default
+        run_command(&mut control, r"s/(\w+)_(\w+)/\u\1\U\2/");
+        assert_eq!(document_lines(&control)[0], "let FirstNAME = 1;");
+        assert_eq!(control.message(), Some("1 substitution on 1 line"));
+
+        run_command(&mut control, r"2,$s/foo|let/X/g");
+        assert_eq!(
+            document_lines(&control)[1..],
+            vec!["X last_name = 2;", "X X X", "X X X"]
+        );
+        assert_eq!(control.message(), Some("7 substitutions on 3 lines"));
+        assert_eq!(control.document_position(), Some(Position { x: 0, y: 3 }));
+
+        // the whole command is one change
+        handle_keys(&mut control, &keys("u"));
+        assert_eq!(document_lines(&control)[2..], vec!["foo foo foo"; 2]);
+
+        run_command(&mut control, "%s/FOO/bar/");
+        assert_eq!(control.message(), Some("Pattern not found: FOO"));
+        run_command(&mut control, "%s/FOO/bar/i");
+        assert_eq!(document_lines(&control)[2..], vec!["bar foo foo"; 2]);
+        run_command(&mut control, "9s/a/b/");
+        assert_eq!(control.message(), Some("Invalid range"));
+
+        // the selected lines, asking for each match
+        handle_keys(&mut control, &keys("ggjjVj:s/foo/baz/gc\n"));
+        assert!(matches!(control.mode(), Mode::Confirm));
+        assert_eq!(control.document_position(), Some(Position { x: 4, y: 2 }));
+        handle_keys(&mut control, &keys("nyl"));
+        assert!(matches!(control.mode(), Mode::Normal));
+        assert_eq!(
+            document_lines(&control)[2..],
+            vec!["bar foo baz", "bar baz foo"]
+        );
+        assert_eq!(control.message(), Some("2 substitutions on 2 lines"));
+
+        handle_keys(&mut control, &keys("u"));
+        assert_eq!(document_lines(&control)[2..], vec!["bar foo foo"; 2]);
+
+        // `a` replaces the rest, `.,+1` is the cursor line and the next
+        handle_keys(&mut control, &keys("gg"));
+        run_command(&mut control, r".,+1s/\d/N/gc");
+        handle_keys(&mut control, &[Key::Char('a')]);
+        assert_eq!(
+            document_lines(&control)[..2],
+            vec!["let FirstNAME = N;", "let last_name = N;"]
+        );
+    }
+
+    #[test]
+    fn test_should_reject_line_numbers_too_large_for_a_range() {
+        let windows = single_window(numbered_doc(3), 20, 10);
+        let mut control = attached_control(&windows);
+
+        for range in [
+            "1,99999999999999999999",
+            "$+18446744073709551615",
+            ".+1+99999999999999999999",
+        ] {
+            run_command(&mut control, &format!("{range}d"));
+            assert_eq!(control.message(), Some("Invalid range"));
+        }
+        assert_eq!(document_lines(&control), vec!["line0", "line1", "line2"]);
+    }
+
+    #[test]
+    fn test_should_run_commands_on_matching_lines() {
+        let doc = default_doc(Some(vec!["a1", "b1", "a2", "a3", "b2"]));
+        let windows = single_window(doc, 20, 5);
+        let mut control = attached_control(&windows);
+
+        // deleting a line does not skip the next marked one
+        run_command(&mut control, "g/a/d");
+        assert_eq!(document_lines(&control), vec!["b1", "b2"]);
+        handle_keys(&mut control, &keys("u"));
+        assert_eq!(document_lines(&control), vec!["a1", "b1", "a2", "a3", "b2"]);
+
+        // nor does opening a line below the marked ones
+        run_command(&mut control, "v/a/normal oadded");
+        assert_eq!(
+            document_lines(&control),
+            vec!["a1", "b1", "added", "a2", "a3", "b2", "added"]
+        );
+        handle_keys(&mut control, &keys("u"));
+
+        // or deleting the line above the one it runs on
+        run_command(&mut control, "g/a/normal kdd");
+        assert_eq!(document_lines(&control), vec!["a3", "b2"]);
+        handle_keys(&mut control, &keys("u"));
+
+        run_command(&mut control, "g!/b/s/a/c/");
+        assert_eq!(document_lines(&control), vec!["c1", "b1", "c2", "c3", "b2"]);
+        handle_keys(&mut control, &keys("u"));
+
+        run_command(&mut control, "2,4g/a/normal Ax");
+        assert_eq!(
+            document_lines(&control),
+            vec!["a1", "b1", "a2x", "a3x", "b2"]
+        );
+        handle_keys(&mut control, &keys("u"));
+
+        // without a range `:normal` runs where the cursor is
+        run_command(&mut control, "%normal I-");
+        run_command(&mut control, "normal $x");
+        assert_eq!(
+            document_lines(&control),
+            vec!["-a1", "-b1", "-a2", "-a3", "-b"]
+        );
+        handle_keys(&mut control, &keys("uu"));
+        assert_eq!(document_lines(&control), vec!["a1", "b1", "a2", "a3", "b2"]);
+
+        run_command(&mut control, "g/b");
+        assert_eq!(control.message(), Some("  2 b1\n  5 b2"));
+        run_command(&mut control, "g/z/d");
+        assert_eq!(control.message(), Some("Pattern not found: z"));
+    }
+
+    #[test]
+    fn test_should_record_and_play_macros() {
+        use std::{env, fs, process};
+
+        let windows = single_window(numbered_doc(8), 20, 10);
+        let mut control = attached_control(&windows);
+
+        handle_keys(&mut control, &keys("qaA!"));
+        handle_keys(&mut control, &[Key::Esc]);
+        handle_keys(&mut control, &keys("jq0"));
+        assert_eq!(control.recording(), None);
+        assert_eq!(control.document_position(), Some(Position { x: 0, y: 1 }));
+
+        // the keys are kept as text
+        handle_keys(&mut control, &keys("\"ap"));
+        assert_eq!(document_lines(&control)[1], "lA!<Esc>jine1");
+        handle_keys(&mut control, &keys("u"));
+
+        handle_keys(&mut control, &keys("2@a@@"));
+        assert_eq!(
+            document_lines(&control)[..5],
+            vec!["line0!", "line1!", "line2!", "line3!", "line4"]
+        );
+
+        // a macro playing itself stops when `j` can't go further down
+        handle_keys(&mut control, &keys("qbx"));
+        handle_keys(&mut control, &keys("j@bq"));
+        handle_keys(&mut control, &keys("u3G"));
+        assert_eq!(control.document_position(), Some(Position { x: 0, y: 2 }));
+        handle_keys(&mut control, &keys("@b"));
+        assert_eq!(
+            document_lines(&control)[2..],
+            vec!["ine2!", "ine3!", "ine4", "ine5", "ine6", "ine7"]
+        );
+        assert_eq!(control.document_position(), Some(Position { x: 0, y: 7 }));
+
+        // or when `x` has nothing left to delete
+        handle_keys(&mut control, &keys("qcqqcx@cq"));
+        handle_keys(&mut control, &keys("@c"));
+        assert_eq!(document_lines(&control)[7], "");
+
+        // a macro that never fails is stopped
+        handle_keys(&mut control, &keys("qdqqd0@dq@d"));
+        assert_eq!(control.message(), Some("Macro stopped after 100000 keys"));
+
+        // a count moves that many times
+        handle_keys(&mut control, &keys("gg3j"));
+        assert_eq!(control.document_position(), Some(Position { x: 0, y: 3 }));
+
+        // the unnamed register keeps a macro too
+        handle_keys(&mut control, &keys("q\"xq@\""));
+        assert_eq!(document_lines(&control)[3], "e3!");
+        assert_eq!(control.message(), None);
+
+        // macros written in the config file, mistakes tell their line
+        let dir = env::temp_dir().join(format!("text-editor-macros-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        fs::write(&path, "[registers]\nw = \"A?<Esc>j\"\n\"+\" = \"x\"\n").unwrap();
+        control.load_config(&path);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(control
+            .message()
+            .unwrap()
+            .ends_with("config.toml:3: Invalid register name: '+'"));
+        handle_keys(&mut control, &keys("2@w"));
+        assert_eq!(document_lines(&control)[3..5], vec!["e3!?", "ine4?"]);
+    }
+
+    #[test]
+    fn test_should_stop_macros_played_a_huge_count_of_times() {
+        let windows = single_window(numbered_doc(2), 20, 10);
+        let mut control = attached_control(&windows);
+
+        handle_keys(&mut control, &keys("qa0q99999999999@a"));
+        assert_eq!(control.message(), Some("Macro stopped after 100000 keys"));
+    }
+
+    #[test]
+    fn test_should_repeat_visual_changes() {
+        let windows = single_window(
+            default_doc(Some(vec![
+                "abcdef", "abcdef", "one", "two", "three", "four",
+            ])),
+            20,
+            10,
+        );
+        let mut control = attached_control(&windows);
+
+        // as many chars from the cursor
+        handle_keys(&mut control, &keys("vlldj0."));
+        assert_eq!(document_lines(&control)[..2], ["def", "def"]);
+
+        // with the text inserted after it
+        handle_keys(&mut control, &keys("ggvcX"));
+        handle_keys(&mut control, &[Key::Esc]);
+        handle_keys(&mut control, &keys("j0."));
+        assert_eq!(document_lines(&control)[..2], ["Xef", "Xef"]);
+
+        // as many lines
+        handle_keys(&mut control, &keys("jVjd."));
+        assert_eq!(document_lines(&control), vec!["Xef", "Xef"]);
+
+        // as many columns of a block
+        handle_keys(&mut control, &keys("gg"));
+        handle_keys(
+            &mut control,
+            &[Key::Ctrl('v'), Key::Char('l'), Key::Char('U')],
+        );
+        handle_keys(&mut control, &keys("l."));
+        assert_eq!(document_lines(&control), vec!["XEF", "Xef"]);
+    }
+
+    #[test]
+    fn test_should_repeat_last_change() {
+        let windows = single_window(numbered_doc(8), 20, 10);
+        let mut control = attached_control(&windows);
+
+        // the inserted text is repeated with the command
+        handle_keys(&mut control, &keys("cwfoo"));
+        handle_keys(&mut control, &[Key::Esc]);
+        handle_keys(&mut control, &keys("j0."));
+        assert_eq!(document_lines(&control)[..3], vec!["foo", "foo", "line2"]);
+
+        // as a single change
+        handle_keys(&mut control, &keys("u"));
+        assert_eq!(document_lines(&control)[..3], vec!["foo", "line1", "line2"]);
+
+        // with its count, or a new one
+        handle_keys(&mut control, &keys("j03xj0."));
+        assert_eq!(document_lines(&control)[2..4], vec!["e2", "e3"]);
+        handle_keys(&mut control, &keys("j01."));
+        assert_eq!(document_lines(&control)[4], "ine4");
+
+        // the count of the motion is replaced too
+        handle_keys(&mut control, &keys("5Gd2dj."));
+        assert_eq!(
+            document_lines(&control),
+            vec!["foo", "line1", "e2", "e3", "line6"]
+        );
+        handle_keys(&mut control, &keys("gg3."));
+        assert_eq!(document_lines(&control), vec!["e3", "line6"]);
+
+        // moving doesn't change what is repeated
+        handle_keys(&mut control, &keys("A!"));
+        handle_keys(&mut control, &[Key::Esc]);
+        handle_keys(&mut control, &keys("jwb."));
+        assert_eq!(document_lines(&control), vec!["e3!", "line6!"]);
+
+        // nor does a command that changes nothing
+        handle_keys(&mut control, &keys("\"zpdfZ."));
+        assert_eq!(document_lines(&control), vec!["e3!", "line6!!"]);
+        handle_keys(&mut control, &keys("o"));
+        handle_keys(&mut control, &[Key::Esc]);
+        handle_keys(&mut control, &keys("xk."));
+        assert_eq!(document_lines(&control), vec!["e3!", "line6!!", "", ""]);
+
+        // counts too large to multiply are kept as large as they can be
+        handle_keys(&mut control, &keys("gg9999999999d9999999999j"));
+        assert_eq!(document_lines(&control), vec![""]);
+    }
+
+    #[test]
+    fn test_should_operate_on_text_objects() {
+        let doc = default_doc(Some(vec![
+            "let x = call(a, (b),",
+            "    c);",
+            "<p>say \"hi there\"</p>",
+            "",
+            "last one",
+        ]));
+        let windows = single_window(doc, 40, 10);
+        let mut control = attached_control(&windows);
+
+        // brackets going through rows, from inside nested ones
+        handle_keys(&mut control, &keys("$hhci("));
+        handle_keys(&mut control, &keys("z"));
+        handle_keys(&mut control, &[Key::Esc]);
+        assert_eq!(document_lines(&control)[0], "let x = call(a, (z),");
+        handle_keys(&mut control, &keys("c2i("));
+        handle_keys(&mut control, &keys("args"));
+        handle_keys(&mut control, &[Key::Esc]);
+        assert_eq!(
+            document_lines(&control)[..2],
+            vec!["let x = call(args);", "<p>say \"hi there\"</p>"]
+        );
+
+        handle_keys(&mut control, &keys("0wdaw"));
+        assert_eq!(document_lines(&control)[0], "let = call(args);");
+
+        // quotes and tags
+        handle_keys(&mut control, &keys("j0yi\"P"));
+        assert_eq!(
+            document_lines(&control)[1],
+            "<p>say \"hi therehi there\"</p>"
+        );
+        handle_keys(&mut control, &keys("udit"));
+        assert_eq!(document_lines(&control)[1], "<p></p>");
+
+        // a paragraph in visual mode is taken linewise
+        handle_keys(&mut control, &keys("Gvipd"));
+        assert_eq!(
+            document_lines(&control),
+            vec!["let = call(args);", "<p></p>", ""]
+        );
+        handle_keys(&mut control, &keys("gg$hhvi(y"));
+        assert_eq!(control.document_position(), Some(Position { x: 11, y: 0 }));
+    }
+
+    #[test]
+    fn test_should_jump_to_marks_and_through_jump_list() {
+        let windows = single_window(numbered_doc(10), 20, 10);
+        let mut control = attached_control(&windows);
+
+        // jumps come back in order, `Ctrl-i` goes forward again
+        handle_keys(&mut control, &keys("5GG"));
+        handle_keys(&mut control, &[Key::Ctrl('o')]);
+        assert_eq!(control.document_position(), Some(Position { x: 0, y: 4 }));
+        handle_keys(&mut control, &[Key::Ctrl('o')]);
+        assert_eq!(control.document_position(), Some(Position { x: 0, y: 0 }));
+        handle_keys(&mut control, &[Key::Char('\t')]);
+        assert_eq!(control.document_position(), Some(Position { x: 0, y: 4 }));
+
+        // `''` goes back to where the last jump started
+        handle_keys(&mut control, &keys("3Gllmagg`a"));
+        assert_eq!(control.document_position(), Some(Position { x: 2, y: 2 }));
+        handle_keys(&mut control, &keys("''"));
+        assert_eq!(control.document_position(), Some(Position { x: 0, y: 0 }));
+
+        // marks follow their line when lines are added or removed above it
+        handle_keys(&mut control, &keys("Onew"));
+        handle_keys(&mut control, &[Key::Esc]);
+        handle_keys(&mut control, &keys("'a"));
+        assert_eq!(control.document_position(), Some(Position { x: 0, y: 3 }));
+        handle_keys(&mut control, &keys("`."));
+        assert_eq!(control.document_position(), Some(Position { x: 2, y: 0 }));
+        handle_keys(&mut control, &keys("ggjdd`a"));
+        assert_eq!(control.document_position(), Some(Position { x: 2, y: 2 }));
+
+        // as motions and ranges
+        handle_keys(&mut control, &keys("ggd'a"));
+        assert_eq!(document_lines(&control)[0], "line3");
+        handle_keys(&mut control, &keys("u"));
+        handle_keys(&mut control, &keys("ggmb"));
+        run_command(&mut control, "'b,'ad");
+        assert_eq!(document_lines(&control)[0], "line3");
+
+        // a mark on a deleted line is gone
+        handle_keys(&mut control, &keys("'a"));
+        assert_eq!(control.message(), Some("Mark not set"));
+    }
+
+    #[test]
+    fn test_should_go_to_a_percentage_of_the_document() {
+        let windows = single_window(numbered_doc(10), 20, 10);
+        let mut control = attached_control(&windows);
+
+        handle_keys(&mut control, &keys("50%"));
+        assert_eq!(control.document_position(), Some(Position { x: 0, y: 4 }));
+
+        // past 100% the cursor stays, and operators take nothing
+        handle_keys(&mut control, &keys("101%99999999999999999999%d200%"));
+        assert_eq!(control.document_position(), Some(Position { x: 0, y: 4 }));
+        assert_eq!(document_lines(&control).len(), 10);
+    }
+
+    #[test]
+    fn test_should_switch_color_scheme() {
+        use std::{env, fs, process};
+
+        let windows = Rc::new(RefCell::new(Vec::new()));
+        let mut control = Control::new(&windows);
+        assert_eq!(control.theme().name(), "default");
+
+        run_command(&mut control, "colorscheme gruvbox");
+        assert_eq!(control.theme().name(), "gruvbox");
+        run_command(&mut control, "colorscheme");
+        assert_eq!(control.message(), Some("gruvbox"));
+
+        run_command(&mut control, "colorscheme nope");
+        assert!(control.is_error());
+        assert_eq!(control.theme().name(), "gruvbox");
+
+        // themes of the config directory, their errors tell the line
+        let dir = env::temp_dir().join(format!("text-editor-themes-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("light.toml"), "normal = { bg = \"white\" }\n").unwrap();
+        fs::write(dir.join("broken.toml"), "string = \"green\"\nkeyword = 3\n").unwrap();
+        control.load_themes(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(control
+            .message()
+            .unwrap()
+            .contains("broken.toml:2: Colors are strings"));
+        run_command(&mut control, "colo light");
+        assert_eq!(control.theme().name(), "light");
+    }
+
+    #[test]
+    fn test_should_set_options_in_their_scope() {
+        use std::{env, fs, process};
+
+        let windows = Rc::new(RefCell::new(vec![
+            window(numbered_doc(10), 20, 10),
+            window(numbered_doc(10), 20, 10),
+        ]));
+        let mut control = attached_control(&windows);
+
+        run_command(&mut control, "set so?");
+        assert_eq!(control.message(), Some("scrolloff=5"));
+
+        // `:setlocal` leaves the global value and the other windows
+        run_command(&mut control, "setlocal so=1 wrap");
+        run_command(&mut control, "set so? wrap?");
+        assert_eq!(control.message(), Some("scrolloff=1  wrap"));
+        assert_eq!(control.options().window.scrolloff, 5);
+        control.attach_window(1);
+        run_command(&mut control, "set scrolloff wrap?");
+        assert_eq!(control.message(), Some("scrolloff=5  nowrap"));
+
+        run_command(&mut control, "set wrap!");
+        assert!(windows.borrow()[1].options().wrap);
+        assert!(control.options().window.wrap);
+
+        // buffer options go to the document
+        run_command(&mut control, "set sw=2");
+        handle_keys(&mut control, &keys("V>"));
+        assert_eq!(document_lines(&control)[0], "  line0");
+        run_command(&mut control, "set noet");
+        handle_keys(&mut control, &keys("V>"));
+        assert_eq!(document_lines(&control)[0], "\t  line0");
+
+        // undoing a change keeps the options set after it
+        run_command(&mut control, "set sw=2");
+        handle_keys(&mut control, &keys("u"));
+        assert_eq!(document_lines(&control)[0], "  line0");
+        assert_eq!(
+            control.read_document(|doc| doc.options().shiftwidth),
+            Some(2)
+        );
+        handle_keys(&mut control, &[Key::Ctrl('r')]);
+
+        run_command(&mut control, "set scroll=3");
+        handle_keys(&mut control, &[Key::Ctrl('d')]);
+        assert_eq!(control.document_position(), Some(Position { x: 3, y: 3 }));
+
+        run_command(&mut control, "set sw=two");
+        assert_eq!(
+            control.message(),
+            Some("Number required for shiftwidth: two")
+        );
+        run_command(&mut control, "set nope");
+        assert_eq!(control.message(), Some("Unknown option: nope"));
+        run_command(&mut control, "set clipboard=paper");
+        assert_eq!(control.message(), Some("Unknown clipboard: paper"));
+
+        // the config file sets the global values, mistakes tell their line
+        let dir = env::temp_dir().join(format!("text-editor-config-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        fs::write(
+            &path,
+            "mouse = true\nscrolloff = \"3\"\ncolorscheme = \"gruvbox\"\nshiftwidth = 8\n",
+        )
+        .unwrap();
+        control.load_config(&path);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(control
+            .message()
+            .unwrap()
+            .ends_with("config.toml:2: scrolloff is a number"));
+        assert!(control.options().editor.mouse);
+        assert_eq!(control.options().buffer.shiftwidth, 8);
+        assert_eq!(control.theme().name(), "gruvbox");
+    }
+
+    #[test]
+    fn test_should_run_mapped_keys() {
+        use crate::editor::Mode;
+        use std::{env, fs, process};
+
+        let windows = single_window(numbered_doc(10), 20, 10);
+        let mut control = attached_control(&windows);
+
+        // the default leader is `\`
+        run_command(&mut control, "nnoremap <leader>d dd");
+        handle_keys(&mut control, &keys("\\d"));
+        assert_eq!(document_lines(&control)[0], "line1");
+
+        // keys wait for the rest of the mapping, or run as they are
+        run_command(&mut control, "inoremap jk <Esc>");
+        handle_keys(&mut control, &keys("ijax"));
+        assert!(matches!(control.mode(), Mode::Insert));
+        handle_keys(&mut control, &keys("jkx"));
+        assert_eq!(document_lines(&control)[0], "jaline1");
+        handle_keys(&mut control, &keys("ij"));
+        control.finish_mapping();
+        handle_keys(&mut control, &[Key::Esc]);
+        assert_eq!(document_lines(&control)[0], "jajline1");
+
+        // mapped keys are mapped again, unless they start with the mapping
+        run_command(&mut control, "nmap Q <Plug>line_end");
+        run_command(&mut control, "nmap E Q");
+        handle_keys(&mut control, &keys("0E"));
+        assert_eq!(control.document_position(), Some(Position { x: 7, y: 0 }));
+        run_command(&mut control, "nmap a b");
+        run_command(&mut control, "nmap b a");
+        handle_keys(&mut control, &keys("a"));
+        assert_eq!(control.message(), Some("Recursive mapping"));
+
+        run_command(&mut control, "nmap E");
+        assert_eq!(control.message(), Some("n  E           Q"));
+        run_command(&mut control, "nunmap E");
+        run_command(&mut control, "nunmap E");
+        assert_eq!(control.message(), Some("No such mapping"));
+        run_command(&mut control, "nmap x <Plug>jump");
+        assert_eq!(control.message(), Some("Unknown action: jump"));
+
+        // from the config file, with its leader, mistakes tell the line in
+        // their table
+        let dir = env::temp_dir().join(format!("text-editor-keymap-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        fs::write(
+            &path,
+            "mapleader = \"<Space>\"\n\n[nnoremap]\nkj = \"j\"\n\"<leader>j\" = \"3j\"\n\n[imap]\nkj = 1\n",
+        )
+        .unwrap();
+        control.load_config(&path);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(control
+            .message()
+            .unwrap()
+            .ends_with("config.toml:8: kj is mapped to a string"));
+        handle_keys(&mut control, &keys("gg j"));
+        assert_eq!(control.document_position(), Some(Position { x: 0, y: 3 }));
+    }
+
+    #[test]
+    fn test_should_rebind_the_commands_of_every_mode() {
+        let windows = single_window(numbered_doc(10), 20, 10);
+        let mut control = attached_control(&windows);
+
+        // registers and macros
+        run_command(&mut control, "nnoremap Q <Plug>record_macro");
+        run_command(&mut control, "nnoremap R <Plug>select_register");
+        handle_keys(&mut control, &keys("QaRbddQ@a\"bp"));
+        assert_eq!(document_lines(&control)[..2], ["line2", "line1"]);
+
+        // visual mode
+        run_command(&mut control, "vnoremap L <Plug>uppercase");
+        handle_keys(&mut control, &keys("ggvL"));
+        assert_eq!(document_lines(&control)[0], "Line2");
+
+        // insert mode
+        run_command(&mut control, "inoremap <C-h> <Plug>backspace");
+        handle_keys(&mut control, &keys("A"));
+        handle_keys(&mut control, &[Key::Ctrl('h'), Key::Esc]);
+        assert_eq!(document_lines(&control)[0], "Line");
+    }
+
+    #[test]
+    fn test_should_indent_as_the_opened_file() {
+        use std::{env, fs, process};
+
+        let windows = Rc::new(RefCell::new(Vec::new()));
+        let mut control = Control::new(&windows);
+        let path = env::temp_dir().join(format!("text-editor-indent-{}.c", process::id()));
+        fs::write(&path, "int a() {\n\tif (b) {\n\t\tc();\n\t}\n}\n").unwrap();
+        let doc = control.open_document(&path.display().to_string()).unwrap();
+        fs::remove_file(&path).unwrap();
+        windows.borrow_mut().push(window(doc, 20, 10));
+        control.attach_window(0);
+
+        run_command(&mut control, "set et? ts?");
+        assert_eq!(control.message(), Some("noexpandtab  tabstop=8"));
+        handle_keys(&mut control, &keys(">>"));
+        assert_eq!(document_lines(&control)[0], "\tint a() {");
+        handle_keys(&mut control, &keys("jI\t"));
+        handle_keys(&mut control, &[Key::Esc]);
+        assert_eq!(document_lines(&control)[1], "\t\tif (b) {");
+
+        // `:set` overrides what was detected, Tab fills up to the next level
+        run_command(&mut control, "setlocal et sw=3");
+        handle_keys(&mut control, &keys("ox\t"));
+        handle_keys(&mut control, &[Key::Esc]);
+        assert_eq!(document_lines(&control)[2], "\t\t   x   ");
+        handle_keys(&mut control, &keys("k<<"));
+        assert_eq!(document_lines(&control)[1], "\tif (b) {");
+    }
+
+    #[test]
+    fn test_should_type_pairs_in_insert_mode() {
+        let windows = single_window(Document::from(vec![""]), 20, 10);
+        let mut control = attached_control(&windows);
+
+        handle_keys(&mut control, &keys("if(a[\"b"));
+        assert_eq!(document_lines(&control), vec!["f(a[\"b\"])"]);
+        handle_keys(&mut control, &keys("\"])"));
+        assert_eq!(document_lines(&control), vec!["f(a[\"b\"])"]);
+        assert_eq!(control.document_position(), Some(Position { x: 9, y: 0 }));
+
+        // Backspace takes both chars of an empty pair, Enter opens a block
+        handle_keys(&mut control, &keys(" {"));
+        handle_keys(&mut control, &[Key::Backspace]);
+        assert_eq!(document_lines(&control), vec!["f(a[\"b\"]) "]);
+        handle_keys(&mut control, &keys("{\nc"));
+        handle_keys(&mut control, &[Key::Esc]);
+        assert_eq!(
+            document_lines(&control),
+            vec!["f(a[\"b\"]) {", "    c", "}"]
+        );
+    }
+
+    #[test]
+    fn test_should_toggle_comments() {
+        use crate::syntax::languages::RUST;
+
+        let mut doc = Document::from(vec!["fn a() {", "    b();", "", "    c();", "}"]);
+        doc.set_syntax(Some(Rc::new(RUST)));
+        let windows = single_window(doc, 20, 10);
+        let mut control = attached_control(&windows);
+
+        handle_keys(&mut control, &keys("jgcc"));
+        assert_eq!(document_lines(&control)[1], "    // b();");
+        handle_keys(&mut control, &keys("gcc"));
+        assert_eq!(document_lines(&control)[1], "    b();");
+        handle_keys(&mut control, &keys("gc2j"));
+        assert_eq!(
+            document_lines(&control)[1..4],
+            ["    // b();", "", "    // c();"]
+        );
+
+        // the whole toggle is undone at once, `.` does it again
+        handle_keys(&mut control, &keys("u"));
+        assert_eq!(document_lines(&control)[1..4], ["    b();", "", "    c();"]);
+        handle_keys(&mut control, &keys("."));
+        assert_eq!(document_lines(&control)[3], "    // c();");
+
+        // some rows not commented yet, all of them are
+        handle_keys(&mut control, &keys("ggVjgc"));
+        assert_eq!(
+            document_lines(&control)[..2],
+            ["// fn a() {", "//     // b();"]
+        );
+    }
+
+    #[test]
+    fn test_should_fold_rows() {
+        let doc = Document::from(vec![
+            "fn a() {", "    b();", "    c();", "}", "d", "e {", "    f();", "    g();", "}",
+        ]);
+        let windows = single_window(doc, 20, 10);
+        let mut control = attached_control(&windows);
+        let line = |control: &Control| control.document_position().map(|pos| pos.y);
+
+        run_command(&mut control, "set fdm=indent");
+        handle_keys(&mut control, &keys("zjzc"));
+        assert_eq!(line(&control), Some(1));
+        handle_keys(&mut control, &keys("j"));
+        assert_eq!(line(&control), Some(3));
+        handle_keys(&mut control, &keys("zjzk"));
+        assert_eq!(line(&control), Some(1));
+
+        // `j` and `k` step over closed folds
+        handle_keys(&mut control, &keys("zMgg4j"));
+        assert_eq!(line(&control), Some(5));
+        handle_keys(&mut control, &keys("jj"));
+        assert_eq!(line(&control), Some(8));
+        assert_eq!(
+            windows.borrow()[0].visible_area_buff(),
+            vec![
+                "fn a() {",
+                "+--  2 lines: b();",
+                "}",
+                "d",
+                "e {",
+                "+--  2 lines: f();",
+                "}"
+            ]
+        );
+
+        handle_keys(&mut control, &keys("kza"));
+        assert_eq!(windows.borrow()[0].visible_area_buff()[6], "    g();");
+        handle_keys(&mut control, &keys("zRggzo"));
+        assert_eq!(control.message(), Some("No fold found"));
+
+        // `zf` only makes folds when they are made by hand
+        handle_keys(&mut control, &keys("zfj"));
+        assert_eq!(
+            control.message(),
+            Some("Cannot create fold with current 'foldmethod'")
+        );
+        run_command(&mut control, "set fdm=manual");
+        handle_keys(&mut control, &keys("4Gzfjgg3j"));
+        assert_eq!(line(&control), Some(3));
+        handle_keys(&mut control, &keys("j"));
+        assert_eq!(line(&control), Some(5));
+    }
+
+    #[test]
+    fn test_should_move_folds_with_their_rows() {
+        let windows = single_window(numbered_doc(6), 20, 10);
+        let mut control = attached_control(&windows);
+
+        // a fold made by hand stays on its rows when a line above is deleted
+        handle_keys(&mut control, &keys("3Gzfjggdd"));
+        assert_eq!(
+            windows.borrow()[0].visible_area_buff()[..3],
+            ["line1", "+--  2 lines: line2", "line4"]
+        );
+
+        // a closed indent fold stays closed when a line is put above it
+        let doc = default_doc(Some(vec!["fn a() {", "    b();", "    c();", "}"]));
+        windows.borrow_mut()[0] = window(doc, 20, 10);
+        run_command(&mut control, "set fdm=indent");
+        handle_keys(&mut control, &keys("2GzcggOx"));
+        handle_keys(&mut control, &[Key::Esc]);
+        assert_eq!(
+            windows.borrow()[0].visible_area_buff(),
+            ["x", "fn a() {", "+--  2 lines: b();", "}"]
+        );
+    }
+
+    #[test]
+    fn test_should_take_whole_closed_folds_with_linewise_operators() {
+        let doc = default_doc(Some(vec!["fn a() {", "    x", "    y", "}", "z"]));
+        let windows = single_window(doc, 20, 10);
+        let mut control = attached_control(&windows);
+
+        run_command(&mut control, "set fdm=indent");
+        handle_keys(&mut control, &keys("jzcdd"));
+        assert_eq!(document_lines(&control), ["fn a() {", "}", "z"]);
+
+        handle_keys(&mut control, &keys("uggjzc2dd"));
+        assert_eq!(document_lines(&control), ["fn a() {", "z"]);
+
+        handle_keys(&mut control, &keys("uggjzcggdj"));
+        assert_eq!(document_lines(&control), ["}", "z"]);
+    }
+
+    #[test]
+    fn test_should_ignore_unbound_keys_in_normal_mode() {
+        let windows = single_window(default_doc(Some(vec!["abc", "def"])), 10, 10);
+        let mut control = attached_control(&windows);
+
+        handle_keys(
+            &mut control,
+            &[
+                Key::Esc,
+                Key::Backspace,
+                Key::Ctrl('x'),
+                Key::Alt('x'),
+                Key::Char('Q'),
+                Key::Char('J'),
+                Key::Char('z'),
+                Key::Char('Z'),
+            ],
+        );
+        handle_keys(&mut control, &keys("jl"));
+        assert_eq!(document_lines(&control), vec!["abc", "def"]);
+        assert_eq!(control.position().clone(), Position { x: 1, y: 1 });
+    }
+
+    #[test]
+    fn test_should_replay_unbound_keys() {
+        let windows = single_window(default_doc(Some(vec!["abcdef"])), 10, 10);
+        let mut control = attached_control(&windows);
+
+        // `Q` and `Z` are bound to nothing, the keys after them still run
+        run_command(&mut control, "normal QZx");
+        assert_eq!(document_lines(&control), vec!["bcdef"]);
+
+        handle_keys(&mut control, &keys("qaQxq@a"));
+        assert_eq!(document_lines(&control), vec!["def"]);
+
+        run_command(&mut control, "nnoremap K Qx");
+        handle_keys(&mut control, &keys("K"));
+        assert_eq!(document_lines(&control), vec!["ef"]);
+    }
+
+    #[test]
+    fn test_should_move_and_edit_by_chars_not_bytes() {
+        let windows = single_window(default_doc(Some(vec!["éab", "ñ"])), 10, 10);
+        let mut control = attached_control(&windows);
+
+        handle_keys(&mut control, &keys("lx"));
+        assert_eq!(document_lines(&control)[0], "éb");
+        handle_keys(&mut control, &keys("0liZ"));
+        handle_keys(&mut control, &[Key::Esc]);
+        assert_eq!(document_lines(&control)[0], "éZb");
+        assert_eq!(control.document_position(), Some(Position { x: 2, y: 0 }));
+
+        handle_keys(&mut control, &keys("0xp"));
+        assert_eq!(document_lines(&control)[0], "Zéb");
+        assert_eq!(control.document_position(), Some(Position { x: 1, y: 0 }));
+        handle_keys(&mut control, &keys("$hX"));
+        assert_eq!(document_lines(&control)[0], "éb");
+        handle_keys(&mut control, &keys("$2X"));
+        assert_eq!(document_lines(&control)[0], "b");
+
+        handle_keys(&mut control, &keys("jax"));
+        handle_keys(&mut control, &[Key::Backspace, Key::Backspace]);
+        assert_eq!(document_lines(&control)[1], "");
+    }
 }
@@ -1,17 +1,40 @@
 use crate::{
-    document::{Document, Row},
-    utils::{Direction, Position, Size},
+    document::{Document, Pattern, Row},
+    editor::{
+        fold::{FoldMethod, Folds},
+        Highlight, History, ScreenHighlight, Selection, WindowOptions,
+    },
+    syntax::Span,
+    utils::{Alignment, Direction, Position, Size},
 };
-use std::ops::Range;
+use std::{cmp, iter, ops::Range};
 
 struct VisibleArea {
     x_range: (usize, usize),
     y_range: (usize, usize),
 }
 
-impl VisibleArea {
-    pub fn y_range(&self) -> Range<usize> {
-        self.y_range.0..self.y_range.1
+/// Part of a document row that is shown on a single screen line.
+#[derive(Clone, Copy)]
+#[allow(clippy::struct_excessive_bools)]
+struct DisplayLine {
+    line: usize,
+    start: usize,
+    end: usize,
+    /// continues the screen line above, so it is prefixed with `showbreak`
+    wrapped: bool,
+    /// last piece of the row
+    last: bool,
+    /// the cursor can sit anywhere after `start`, used by the last piece of
+    /// wrapped rows
+    open_end: bool,
+    /// summary of a closed fold starting at `line`
+    folded: bool,
+}
+
+impl DisplayLine {
+    fn contains(&self, x: usize) -> bool {
+        self.start <= x && (x < self.end || self.open_end)
     }
 }
 
@@ -20,6 +43,12 @@ pub struct Window {
     size: Size,
     visible_area: VisibleArea,
     position: Position,
+    options: WindowOptions,
+    selection: Option<Selection>,
+    /// matches of this pattern are highlighted
+    search_pattern: Option<Pattern>,
+    history: History,
+    folds: Folds,
 }
 
 impl Window {
@@ -32,95 +61,669 @@ impl Window {
             },
             size,
             position,
+            options: WindowOptions::default(),
+            selection: None,
+            search_pattern: None,
+            history: History::default(),
+            folds: Folds::default(),
+        }
+    }
+
+    pub fn options(&self) -> &WindowOptions {
+        &self.options
+    }
+
+    pub fn options_mut(&mut self) -> &mut WindowOptions {
+        &mut self.options
+    }
+
+    pub fn folds(&self) -> &Folds {
+        &self.folds
+    }
+
+    pub fn folds_mut(&mut self) -> &mut Folds {
+        &mut self.folds
+    }
+
+    /// Makes the folds again from the document and the `foldmethod` option,
+    /// after either changed. Folds first move with the lines the document
+    /// inserted and deleted.
+    pub fn update_folds(&mut self) {
+        let method = FoldMethod::from_name(&self.options.foldmethod).unwrap_or(FoldMethod::Manual);
+        for edit in self.doc.take_line_edits() {
+            self.folds.follow(&edit);
+        }
+        self.folds.update(&self.doc, method);
+        self.scroll_to(self.visible_area.y_range.0);
+    }
+
+    /// Line `length` shown rows above or below `line`, a closed fold being a
+    /// single row. It is not clamped to the end of the document.
+    pub fn step_lines(&self, line: usize, direction: &Direction, length: usize) -> usize {
+        if !self.folds.has_closed() {
+            return match direction {
+                Direction::Up => line.saturating_sub(length),
+                _ => line.saturating_add(length),
+            };
         }
+
+        let mut line = self.folds.visible_line(line);
+        for _ in 0..length {
+            let next = match direction {
+                Direction::Up => self.folds.previous_visible_line(line),
+                _ => Some(self.folds.next_visible_line(line))
+                    .filter(|next| *next < self.doc.lines_len()),
+            };
+            match next {
+                Some(next) => line = next,
+                None => break,
+            }
+        }
+        line
+    }
+
+    /// Lines shown from `from` on, skipping those hidden in closed folds.
+    fn visible_lines(&self, from: usize) -> impl Iterator<Item = usize> + '_ {
+        iter::successors(Some(self.folds.visible_line(from)), |line| {
+            Some(self.folds.next_visible_line(*line))
+        })
+        .take_while(|line| *line < self.doc.lines_len())
     }
 
+    /// Shows `y0` at the top of the window, or the fold hiding it.
+    fn scroll_to(&mut self, y0: usize) {
+        let y0 = self.folds.visible_line(y0);
+        self.visible_area.y_range = (y0, y0 + self.size.height as usize);
+    }
+
+    /// Moves the visible area, clamping it so it never goes past the
+    /// beginning or the end of the document.
     pub fn scroll(&mut self, direction: &Direction, length: usize) {
+        let width = self.size.width as usize;
+
         match direction {
             Direction::Up => {
-                let initial_space = self.visible_area.y_range.1 - self.visible_area.y_range.0;
-                let y0 = self.visible_area.y_range.0.saturating_sub(length);
-                let y1 = self.visible_area.y_range.1.saturating_sub(length);
-
-                if initial_space == (y1 - y0) {
-                    self.visible_area.y_range.0 = y0;
-                    self.visible_area.y_range.1 = y1;
-                }
+                self.scroll_to(self.step_lines(self.visible_area.y_range.0, direction, length));
             }
             Direction::Down => {
-                // let initial_space = //self.visible_area.y_range.1 - self.visible_area.y_range.0;
-                let mut y0 = self.visible_area.y_range.0.saturating_add(length);
-                let mut y1 = self.visible_area.y_range.1.saturating_add(length);
-                let line_len = self.doc.lines_len();
-
-                // if doc has no more lines got to last line
-                if line_len < y1 {
-                    y1 = y1 - line_len;
-                    y0 = y0 - y1;
+                // the last line of the document is the lowest the window can go
+                let max_y0 = self.last_first_line();
+                let y0 = self.step_lines(self.visible_area.y_range.0, direction, length);
+                self.scroll_to(cmp::min(y0, max_y0));
+            }
+            // wrapped rows are always shown from their first column
+            Direction::Left | Direction::Right if self.options.wrap => {}
+            Direction::Left => {
+                let x0 = self.visible_area.x_range.0.saturating_sub(length);
+                self.visible_area.x_range = (x0, x0 + width);
+            }
+            Direction::Right => {
+                // leave room for the cursor right after the longest row
+                let max_x0 = (self.longest_row_len() + 1).saturating_sub(width);
+                let x0 = cmp::min(self.visible_area.x_range.0.saturating_add(length), max_x0);
+                self.visible_area.x_range = (x0, x0 + width);
+            }
+        }
+    }
+
+    /// Scrolls just enough to show `pos` with `scrolloff` lines and
+    /// `sidescrolloff` columns of context around it.
+    pub fn scroll_into_view(&mut self, pos: &Position) {
+        let so = self.scrolloff();
+        let height = self.size.height as usize;
+        let y0 = self.visible_area.y_range.0;
+        let line = self.folds.visible_line(pos.y);
+
+        let top = self.step_lines(line, &Direction::Up, so);
+        if top < y0 {
+            self.scroll_to(top);
+        } else {
+            // get close first so far jumps don't go screen line by screen line
+            let bottom_y0 = self.step_lines(line, &Direction::Up, height.saturating_sub(1));
+            if bottom_y0 > y0 {
+                self.scroll_to(cmp::min(bottom_y0, self.last_first_line()));
+            }
+
+            let below = self.display_rows_after(pos, so);
+            while self.visible_area.y_range.0 < pos.y
+                && self.display_rows_until(self.visible_area.y_range.0, pos) + below > height
+            {
+                let y0 = self.visible_area.y_range.0;
+                self.scroll(&Direction::Down, 1);
+
+                if self.visible_area.y_range.0 == y0 {
+                    break;
                 }
+            }
+        }
+
+        let sso = self.sidescrolloff();
+        let (x0, x1) = self.visible_area.x_range;
+
+        if pos.x < x0 + sso {
+            self.scroll(&Direction::Left, x0 + sso - pos.x);
+        } else if pos.x + sso >= x1 {
+            self.scroll(&Direction::Right, pos.x + sso + 1 - x1);
+        }
+    }
+
+    /// Scrolls so `line` ends up at the top, center or bottom of the window.
+    pub fn align(&mut self, line: usize, alignment: &Alignment) {
+        let height = self.size.height as usize;
+        let so = self.scrolloff();
+
+        let above = match alignment {
+            Alignment::Top => so,
+            Alignment::Center => height / 2,
+            Alignment::Bottom => height.saturating_sub(so + 1),
+        };
+        let y0 = self.step_lines(line, &Direction::Up, above);
+
+        if y0 > self.visible_area.y_range.0 {
+            self.scroll_to(cmp::min(y0, self.last_first_line()));
+        } else {
+            self.scroll_to(y0);
+        }
+    }
+
+    /// Returns the line closest to `line` that is visible while respecting
+    /// `scrolloff`, the ends of the document are always reachable.
+    pub fn clamp_line_to_view(&self, line: usize) -> usize {
+        let so = self.scrolloff();
+        let y0 = self.visible_area.y_range.0;
+        let last_line = self.doc.lines_len().saturating_sub(1);
+        let display_lines = self.display_lines();
+
+        let top = if y0 == 0 {
+            0
+        } else {
+            self.step_lines(y0, &Direction::Down, so)
+        };
+        let bottom = match display_lines.last() {
+            Some(display_line) if display_line.line == last_line && display_line.last => last_line,
+            _ => display_lines
+                .len()
+                .checked_sub(so + 1)
+                .map_or(top, |i| display_lines[i].line),
+        };
 
-                if self.size().height as usize == (y1.saturating_sub(y0)) {
-                    self.visible_area.y_range.0 = y0;
-                    self.visible_area.y_range.1 = y1;
+        line.clamp(top, cmp::max(top, bottom))
+    }
+
+    /// Position `length` screen lines above or below `pos`, keeping the
+    /// screen column (`gj`, `gk`).
+    pub fn move_display_line(
+        &self,
+        pos: &Position,
+        direction: &Direction,
+        length: usize,
+    ) -> Position {
+        let mut line = pos.y;
+        let mut display_lines = self.row_display_lines(line);
+        let Some(mut index) = display_lines.iter().position(|dl| dl.contains(pos.x)).or(
+            // without wrap the cursor may be scrolled out of the only piece
+            (!display_lines.is_empty()).then_some(0),
+        ) else {
+            return *pos;
+        };
+        let column = self.screen_column(&display_lines[index], pos.x);
+
+        for _ in 0..length {
+            match direction {
+                Direction::Down if index + 1 < display_lines.len() => index += 1,
+                Direction::Down if self.folds.next_visible_line(line) < self.doc.lines_len() => {
+                    line = self.folds.next_visible_line(line);
+                    display_lines = self.row_display_lines(line);
+                    index = 0;
                 }
+                Direction::Up if index > 0 => index -= 1,
+                Direction::Up if self.folds.previous_visible_line(line).is_some() => {
+                    line = self.folds.previous_visible_line(line).unwrap_or_default();
+                    display_lines = self.row_display_lines(line);
+                    index = display_lines.len() - 1;
+                }
+                _ => break,
             }
-            // TODO: LEFT AND RIGHT ARE NOT TESTED
-            Direction::Left => {
-                let initial_space = self.visible_area.x_range.1 - self.visible_area.x_range.0;
-                let x0 = self.visible_area.x_range.0.saturating_sub(length);
-                let x1 = self.visible_area.x_range.1.saturating_sub(length);
+        }
+
+        let display_line = display_lines[index];
+        let x = self.document_column(&display_line, column);
 
-                if initial_space == (x1 - x0) {
-                    self.visible_area.x_range.0 = x0;
-                    self.visible_area.x_range.1 = x1;
+        Position {
+            // stay on the same screen line
+            x: match self.doc.row(display_line.line) {
+                Some(row) if !display_line.last && x >= display_line.end => {
+                    row.previous_char_index(display_line.end, 1)
                 }
+                _ => x,
+            },
+            y: display_line.line,
+        }
+    }
+
+    fn scrolloff(&self) -> usize {
+        cmp::min(
+            self.options.scrolloff,
+            (self.size.height as usize).saturating_sub(1) / 2,
+        )
+    }
+
+    fn sidescrolloff(&self) -> usize {
+        cmp::min(
+            self.options.sidescrolloff,
+            (self.size.width as usize).saturating_sub(1) / 2,
+        )
+    }
+
+    fn longest_row_len(&self) -> usize {
+        (0..self.doc.lines_len())
+            .filter_map(|i| self.doc.row(i))
+            .map(|row| row.content.len())
+            .max()
+            .unwrap_or_default()
+    }
+
+    fn prefix_width(&self, display_line: &DisplayLine) -> usize {
+        if display_line.wrapped {
+            self.options.showbreak.chars().count()
+        } else {
+            0
+        }
+    }
+
+    /// Screen column of the document column `x`, counted in chars from the
+    /// start of `display_line`.
+    fn screen_column(&self, display_line: &DisplayLine, x: usize) -> usize {
+        let shown = match self.doc.row(display_line.line) {
+            Some(row) => {
+                row.slice(display_line.start..x).chars().count()
+                    + x.saturating_sub(row.content.len())
             }
-            Direction::Right => {
-                let initial_space = self.size.width as usize; //self.visible_area.x_range.1 - self.visible_area.x_range.0;
-                let x0 = self.visible_area.x_range.0.saturating_add(length);
-                let x1 = self.visible_area.x_range.1.saturating_add(length);
-                if initial_space == (x1 - x0) {
-                    self.visible_area.x_range.0 = x0;
-                    self.visible_area.x_range.1 = x1;
+            None => x.saturating_sub(display_line.start),
+        };
+        self.prefix_width(display_line) + shown
+    }
+
+    /// Document column shown on screen column `column` of `display_line`,
+    /// past the end of the row when the column is.
+    fn document_column(&self, display_line: &DisplayLine, column: usize) -> usize {
+        let column = column.saturating_sub(self.prefix_width(display_line));
+        let Some(row) = self.doc.row(display_line.line) else {
+            return display_line.start + column;
+        };
+
+        let rest = row.slice(display_line.start..row.content.len());
+        match rest.char_indices().nth(column) {
+            Some((i, _)) => display_line.start + i,
+            None => row.content.len() + column - rest.chars().count(),
+        }
+    }
+
+    /// Splits a row into the pieces shown on each screen line, without wrap
+    /// that is only the horizontally visible part of it. A closed fold is a
+    /// single screen line holding any column of its rows.
+    fn row_display_lines(&self, line: usize) -> Vec<DisplayLine> {
+        let Some(row) = self.doc.row(line) else {
+            return Vec::new();
+        };
+
+        if let Some(fold) = self.folds.closed_at(line) {
+            return vec![DisplayLine {
+                line: fold.start,
+                start: 0,
+                end: 0,
+                wrapped: false,
+                last: true,
+                open_end: true,
+                folded: true,
+            }];
+        }
+
+        if !self.options.wrap {
+            let (start, end) = self.visible_area.x_range;
+            return vec![DisplayLine {
+                line,
+                start,
+                end,
+                wrapped: false,
+                last: true,
+                open_end: false,
+                folded: false,
+            }];
+        }
+
+        let content = row.content.as_str();
+        let width = self.size.width as usize;
+        let showbreak = self.options.showbreak.chars().count();
+
+        let mut display_lines = Vec::new();
+        let mut start = 0;
+        loop {
+            let wrapped = start > 0;
+            let available = if wrapped {
+                width.saturating_sub(showbreak)
+            } else {
+                width
+            }
+            .max(1);
+
+            // the row is split after `available` chars
+            let Some((split, _)) = content[start..].char_indices().nth(available) else {
+                display_lines.push(DisplayLine {
+                    line,
+                    start,
+                    end: content.len(),
+                    wrapped,
+                    last: true,
+                    open_end: true,
+                    folded: false,
+                });
+                return display_lines;
+            };
+
+            let mut end = start + split;
+            if self.options.linebreak {
+                if let Some(space) = content[start..end].rfind(' ') {
+                    end = start + space + 1;
                 }
             }
+
+            display_lines.push(DisplayLine {
+                line,
+                start,
+                end,
+                wrapped,
+                last: false,
+                open_end: false,
+                folded: false,
+            });
+            start = end;
         }
     }
 
-    pub fn visible_area_buff(&self) -> Vec<String> {
-        // take all rows in range
-        let visible_text_y: Vec<&Row> = self
-            .visible_area
-            .y_range()
-            .map(|i| self.doc.row(i))
-            .take_while(|opt| opt.is_some())
-            .map(|opt| opt.unwrap())
-            .collect();
-
-        let visible_text_x: Vec<String> = visible_text_y
+    /// Screen lines from the top of the window down to its bottom.
+    fn display_lines(&self) -> Vec<DisplayLine> {
+        self.visible_lines(self.visible_area.y_range.0)
+            .flat_map(|line| self.row_display_lines(line))
+            .take(self.size.height as usize)
+            .collect()
+    }
+
+    /// Screen lines needed to show everything from the start of `from` down
+    /// to the screen line holding `pos`.
+    fn display_rows_until(&self, from: usize, pos: &Position) -> usize {
+        let line = self.folds.visible_line(pos.y);
+        let above: usize = self
+            .visible_lines(from)
+            .take_while(|visible| *visible < line)
+            .map(|line| self.row_display_lines(line).len())
+            .sum();
+        let within = self
+            .row_display_lines(pos.y)
             .iter()
-            .map(|row| row.render(self.visible_area.x_range.0, self.visible_area.x_range.1))
-            .collect();
+            .position(|dl| dl.contains(pos.x))
+            .unwrap_or_default();
 
-        visible_text_x
+        above + within + 1
+    }
+
+    /// Screen lines after the one holding `pos`, up to `limit`.
+    fn display_rows_after(&self, pos: &Position, limit: usize) -> usize {
+        let display_lines = self.row_display_lines(pos.y);
+        let within = display_lines
+            .iter()
+            .position(|dl| dl.contains(pos.x))
+            .unwrap_or_default();
+
+        let mut rows = display_lines.len().saturating_sub(within + 1);
+        for line in self.visible_lines(self.folds.next_visible_line(pos.y)) {
+            if rows >= limit {
+                break;
+            }
+            rows += self.row_display_lines(line).len();
+        }
+
+        cmp::min(rows, limit)
+    }
+
+    /// Lowest line that can be at the top of the window while still filling
+    /// it with the end of the document.
+    fn last_first_line(&self) -> usize {
+        let height = self.size.height as usize;
+
+        let last_line = self.doc.lines_len().saturating_sub(1);
+
+        let mut rows = 0;
+        let mut first = None;
+        let mut line = Some(self.folds.visible_line(last_line));
+        while let Some(current) = line {
+            rows += self.row_display_lines(current).len();
+            if rows > height {
+                break;
+            }
+            first = Some(current);
+            line = self.folds.previous_visible_line(current);
+        }
+
+        first.unwrap_or(self.folds.visible_line(last_line))
+    }
+
+    /// Screen lines of the window, as spans styled by the highlighting of
+    /// the document.
+    pub fn visible_area_spans(&self) -> Vec<Vec<Span>> {
+        self.display_lines()
+            .iter()
+            .map(|display_line| {
+                let mut spans = Vec::new();
+                if display_line.folded {
+                    let summary = self
+                        .folds
+                        .summary(&self.doc, display_line.line)
+                        .unwrap_or_default();
+                    let summary: String = summary.chars().take(self.size.width as usize).collect();
+                    spans.push(Span::plain(&summary));
+                    return spans;
+                }
+                if display_line.wrapped {
+                    spans.push(Span::plain(&self.options.showbreak));
+                }
+                if let Some(row) = self.doc.row(display_line.line) {
+                    let tokens = self.doc.row_tokens(display_line.line);
+                    spans.extend(row.render(display_line.start, display_line.end, &tokens));
+                }
+
+                spans
+            })
+            .collect()
+    }
+
+    /// Text of the screen lines of the window.
+    #[cfg(test)]
+    pub fn visible_area_buff(&self) -> Vec<String> {
+        self.visible_area_spans()
+            .iter()
+            .map(|spans| Span::text(spans))
+            .collect()
     }
 
     pub fn position(&self) -> Position {
         self.position
     }
 
+    /// Moves the window to `position` on the screen and resizes it, the
+    /// first line shown stays at the top.
+    pub fn set_area(&mut self, position: Position, size: Size) {
+        let x0 = self.visible_area.x_range.0;
+        self.visible_area.x_range = (x0, x0 + size.width as usize);
+        self.position = position;
+        self.size = size;
+        self.scroll_to(self.visible_area.y_range.0);
+    }
+
     pub fn size(&self) -> &Size {
         &self.size
     }
 
     pub fn document_line_number_from_cursor(&self, pos_y: usize) -> Option<usize> {
-        if pos_y < self.position().y as usize
-            || pos_y > self.position().y + self.size().height as usize
+        self.display_lines()
+            .get(pos_y.checked_sub(self.position().y)?)
+            .map(|display_line| display_line.line)
+    }
+
+    pub fn contains(&self, screen: &Position) -> bool {
+        let Position { x, y } = self.position();
+
+        (x..x + self.size().width as usize).contains(&screen.x)
+            && (y..y + self.size().height as usize).contains(&screen.y)
+    }
+
+    /// Document position shown closest to a screen position inside the
+    /// window, the screen lines below the last row map to it.
+    pub fn nearest_document_position(&self, screen: &Position) -> Option<Position> {
+        if !self.contains(screen) {
+            return None;
+        }
+
+        let last = self.display_lines().len().checked_sub(1)?;
+        let row = cmp::min(screen.y - self.position().y, last);
+
+        self.document_position_from_cursor(&Position {
+            x: screen.x,
+            y: self.position().y + row,
+        })
+    }
+
+    /// Maps a screen position inside the window to the document.
+    pub fn document_position_from_cursor(&self, cursor: &Position) -> Option<Position> {
+        if cursor.x < self.position().x
+            || cursor.x >= self.position().x + self.size().width as usize
         {
             return None;
         }
 
-        Some(self.visible_area.y_range.0 + pos_y)
+        let display_line = *self
+            .display_lines()
+            .get(cursor.y.checked_sub(self.position().y)?)?;
+
+        Some(Position {
+            x: self.document_column(&display_line, cursor.x - self.position().x),
+            y: display_line.line,
+        })
+    }
+
+    /// Maps a document position to the screen, `None` if it is not visible.
+    pub fn cursor_from_document_position(&self, pos: &Position) -> Option<Position> {
+        let (row, display_line) = self
+            .display_lines()
+            .into_iter()
+            .enumerate()
+            .find(|(_, dl)| dl.line == pos.y && dl.contains(pos.x))?;
+
+        Some(Position {
+            x: self.position().x
+                + cmp::min(
+                    self.screen_column(&display_line, pos.x),
+                    (self.size().width as usize).saturating_sub(1),
+                ),
+            y: self.position().y + row,
+        })
+    }
+
+    pub fn set_selection(&mut self, selection: Option<Selection>) {
+        self.selection = selection;
+    }
+
+    pub fn set_search_pattern(&mut self, pattern: Option<Pattern>) {
+        self.search_pattern = pattern;
+    }
+
+    /// Screen cells to highlight, the visual mode selection and the matches
+    /// of the last search.
+    pub fn visible_highlights(&self) -> Vec<ScreenHighlight> {
+        let mut highlights = Vec::new();
+
+        for (row, display_line) in self.display_lines().iter().enumerate() {
+            if display_line.folded {
+                continue;
+            }
+            let content = self
+                .doc
+                .row(display_line.line)
+                .map_or("", |row| row.content.as_str());
+
+            if let Some(selection) = &self.selection {
+                if let Some(columns) = selection.columns(display_line.line, content.len()) {
+                    highlights.extend(self.screen_highlight(
+                        row,
+                        display_line,
+                        columns,
+                        Highlight::Selection,
+                    ));
+                }
+            }
+
+            if let Some(pattern) = &self.search_pattern {
+                for columns in pattern.find_all(content) {
+                    highlights.extend(self.screen_highlight(
+                        row,
+                        display_line,
+                        columns,
+                        Highlight::Search,
+                    ));
+                }
+            }
+        }
+
+        highlights
+    }
+
+    /// Part of the document `columns` shown on screen line `row`.
+    fn screen_highlight(
+        &self,
+        row: usize,
+        display_line: &DisplayLine,
+        columns: Range<usize>,
+        highlight: Highlight,
+    ) -> Option<ScreenHighlight> {
+        let width = self.size().width as usize;
+
+        // the cell after the row only shows up after its last piece
+        let piece_end = if display_line.open_end {
+            display_line.end + 1
+        } else {
+            display_line.end
+        };
+        let from = cmp::max(columns.start, display_line.start);
+        let to = cmp::min(columns.end, piece_end);
+        if from >= to {
+            return None;
+        }
+
+        let x = self.screen_column(display_line, from);
+        if x >= width {
+            return None;
+        }
+
+        Some(ScreenHighlight {
+            position: Position {
+                x: self.position().x + x,
+                y: self.position().y + row,
+            },
+            len: cmp::min(self.screen_column(display_line, to) - x, width - x),
+            highlight,
+        })
+    }
+
+    pub fn document(&self) -> &Document {
+        &self.doc
+    }
+
+    pub fn document_mut(&mut self) -> &mut Document {
+        &mut self.doc
+    }
+
+    pub fn history_mut(&mut self) -> &mut History {
+        &mut self.history
     }
 
     pub fn document_row(&self, line: usize) -> Option<&Row> {
@@ -283,4 +886,455 @@ mod test {
         assert_eq!(buff[0].len(), text_buff[0].len());
         assert_eq!(buff, text_buff[0..text_buff.len() - 1]);
     }
+
+    #[test]
+    fn should_clamp_scroll_down_at_document_end() {
+        use crate::{
+            document::Document,
+            editor::window::Window,
+            utils::{Direction, Position, Size},
+        };
+        let text_buff = vec!["line1", "line2", "line3", "line4", "line5"];
+
+        let mut w = Window::new(
+            Document::from(text_buff.clone()),
+            Size {
+                width: 6,
+                height: 3,
+            },
+            Position::default(),
+        );
+
+        w.scroll(&Direction::Down, 10);
+
+        let buff = w.visible_area_buff();
+        assert_eq!(buff, text_buff[2..text_buff.len()]);
+    }
+
+    #[test]
+    fn should_clamp_scroll_up_at_document_start() {
+        use crate::{
+            document::Document,
+            editor::window::Window,
+            utils::{Direction, Position, Size},
+        };
+        let text_buff = vec!["line1", "line2", "line3", "line4", "line5"];
+
+        let mut w = Window::new(
+            Document::from(text_buff.clone()),
+            Size {
+                width: 6,
+                height: 3,
+            },
+            Position::default(),
+        );
+
+        w.scroll(&Direction::Down, 1);
+        w.scroll(&Direction::Up, 10);
+
+        let buff = w.visible_area_buff();
+        assert_eq!(buff, text_buff[0..3]);
+    }
+
+    #[test]
+    fn should_scroll_right_and_left() {
+        use crate::{
+            document::Document,
+            editor::window::Window,
+            utils::{Direction, Position, Size},
+        };
+
+        let mut w = Window::new(
+            Document::from(vec!["0123456789", "abc"]),
+            Size {
+                width: 4,
+                height: 2,
+            },
+            Position::default(),
+        );
+
+        w.scroll(&Direction::Right, 2);
+        assert_eq!(w.visible_area_buff(), vec!["2345", "c"]);
+
+        w.scroll(&Direction::Left, 1);
+        assert_eq!(w.visible_area_buff(), vec!["1234", "bc"]);
+
+        w.scroll(&Direction::Left, 10);
+        assert_eq!(w.visible_area_buff(), vec!["0123", "abc"]);
+    }
+
+    #[test]
+    fn should_clamp_scroll_right_at_longest_row() {
+        use crate::{
+            document::Document,
+            editor::window::Window,
+            utils::{Direction, Position, Size},
+        };
+
+        let mut w = Window::new(
+            Document::from(vec!["0123456789", "abc"]),
+            Size {
+                width: 4,
+                height: 2,
+            },
+            Position::default(),
+        );
+
+        // one column is kept free after the last char for the cursor
+        w.scroll(&Direction::Right, 100);
+        assert_eq!(w.visible_area_buff(), vec!["789", ""]);
+    }
+
+    #[test]
+    fn should_keep_scrolloff_lines_around_cursor() {
+        use crate::{
+            document::Document,
+            editor::window::Window,
+            utils::{Position, Size},
+        };
+        let text_buff: Vec<String> = (0..30).map(|i| format!("line{i}")).collect();
+
+        let mut w = Window::new(
+            Document::from(text_buff.iter().map(String::as_str).collect::<Vec<&str>>()),
+            Size {
+                width: 10,
+                height: 12,
+            },
+            Position::default(),
+        );
+
+        // default scrolloff is 5, so line 6 is the last one reachable
+        // without scrolling
+        w.scroll_into_view(&Position { x: 0, y: 6 });
+        assert_eq!(w.document_line_number_from_cursor(0), Some(0));
+
+        w.scroll_into_view(&Position { x: 0, y: 10 });
+        assert_eq!(w.document_line_number_from_cursor(0), Some(4));
+        assert_eq!(
+            w.cursor_from_document_position(&Position { x: 0, y: 10 }),
+            Some(Position { x: 0, y: 6 })
+        );
+
+        w.scroll_into_view(&Position { x: 0, y: 29 });
+        assert_eq!(w.document_line_number_from_cursor(11), Some(29));
+    }
+
+    #[test]
+    fn should_align_line() {
+        use crate::{
+            document::Document,
+            editor::window::Window,
+            utils::{Alignment, Position, Size},
+        };
+        let text_buff: Vec<String> = (0..30).map(|i| format!("line{i}")).collect();
+
+        let mut w = Window::new(
+            Document::from(text_buff.iter().map(String::as_str).collect::<Vec<&str>>()),
+            Size {
+                width: 10,
+                height: 12,
+            },
+            Position::default(),
+        );
+
+        w.align(15, &Alignment::Center);
+        assert_eq!(w.document_line_number_from_cursor(0), Some(9));
+
+        w.align(15, &Alignment::Top);
+        assert_eq!(w.document_line_number_from_cursor(0), Some(10));
+
+        w.align(15, &Alignment::Bottom);
+        assert_eq!(w.document_line_number_from_cursor(11), Some(20));
+
+        // cant go past the end of the document
+        w.align(28, &Alignment::Top);
+        assert_eq!(w.document_line_number_from_cursor(11), Some(29));
+    }
+
+    #[test]
+    fn should_wrap_long_rows() {
+        use crate::{
+            document::Document,
+            editor::window::Window,
+            utils::{Position, Size},
+        };
+
+        let mut w = Window::new(
+            Document::from(vec!["0123456789", "abc", "def"]),
+            Size {
+                width: 4,
+                height: 4,
+            },
+            Position::default(),
+        );
+        w.options_mut().wrap = true;
+
+        assert_eq!(w.visible_area_buff(), vec!["0123", "4567", "89", "abc"]);
+        assert_eq!(w.document_line_number_from_cursor(2), Some(0));
+        assert_eq!(w.document_line_number_from_cursor(3), Some(1));
+        assert_eq!(
+            w.document_position_from_cursor(&Position { x: 1, y: 2 }),
+            Some(Position { x: 9, y: 0 })
+        );
+        assert_eq!(
+            w.cursor_from_document_position(&Position { x: 5, y: 0 }),
+            Some(Position { x: 1, y: 1 })
+        );
+        assert_eq!(
+            w.cursor_from_document_position(&Position { x: 0, y: 2 }),
+            None
+        );
+    }
+
+    #[test]
+    fn should_wrap_at_word_boundaries_with_showbreak() {
+        use crate::{
+            document::Document,
+            editor::window::Window,
+            utils::{Position, Size},
+        };
+
+        let mut w = Window::new(
+            Document::from(vec!["the quick brown fox"]),
+            Size {
+                width: 8,
+                height: 4,
+            },
+            Position::default(),
+        );
+        w.options_mut().wrap = true;
+        w.options_mut().linebreak = true;
+        w.options_mut().showbreak = String::from("> ");
+
+        assert_eq!(
+            w.visible_area_buff(),
+            vec!["the ", "> quick ", "> brown ", "> fox"]
+        );
+        assert_eq!(
+            w.document_position_from_cursor(&Position { x: 2, y: 1 }),
+            Some(Position { x: 4, y: 0 })
+        );
+        assert_eq!(
+            w.cursor_from_document_position(&Position { x: 16, y: 0 }),
+            Some(Position { x: 2, y: 3 })
+        );
+    }
+
+    #[test]
+    fn should_wrap_rows_by_chars() {
+        use crate::{
+            document::Document,
+            editor::window::Window,
+            utils::{Direction, Position, Size},
+        };
+
+        let mut w = Window::new(
+            Document::from(vec!["caféxyzw"]),
+            Size {
+                width: 4,
+                height: 4,
+            },
+            Position::default(),
+        );
+        w.options_mut().wrap = true;
+
+        assert_eq!(w.visible_area_buff(), vec!["café", "xyzw"]);
+        // `x` starts at byte 5, after the two bytes of `é`
+        let x = Position { x: 5, y: 0 };
+        assert_eq!(
+            w.cursor_from_document_position(&x),
+            Some(Position { x: 0, y: 1 })
+        );
+        assert_eq!(
+            w.document_position_from_cursor(&Position { x: 0, y: 1 }),
+            Some(x)
+        );
+        assert_eq!(
+            w.move_display_line(&Position { x: 6, y: 0 }, &Direction::Up, 1),
+            Position { x: 1, y: 0 }
+        );
+        assert_eq!(
+            w.move_display_line(&Position { x: 3, y: 0 }, &Direction::Down, 1),
+            Position { x: 8, y: 0 }
+        );
+    }
+
+    #[test]
+    fn should_move_by_display_lines() {
+        use crate::{
+            document::Document,
+            editor::window::Window,
+            utils::{Direction, Position, Size},
+        };
+
+        let mut w = Window::new(
+            Document::from(vec!["0123456789", "abc"]),
+            Size {
+                width: 4,
+                height: 4,
+            },
+            Position::default(),
+        );
+        w.options_mut().wrap = true;
+
+        let pos = Position { x: 1, y: 0 };
+        let pos = w.move_display_line(&pos, &Direction::Down, 1);
+        assert_eq!(pos, Position { x: 5, y: 0 });
+
+        let pos = w.move_display_line(&pos, &Direction::Down, 2);
+        assert_eq!(pos, Position { x: 1, y: 1 });
+
+        let pos = w.move_display_line(&pos, &Direction::Up, 1);
+        assert_eq!(pos, Position { x: 9, y: 0 });
+    }
+
+    #[test]
+    fn should_scroll_wrapped_rows_into_view() {
+        use crate::{
+            document::Document,
+            editor::window::Window,
+            utils::{Direction, Position, Size},
+        };
+
+        let mut w = Window::new(
+            Document::from(vec!["0123456789", "abcdefgh", "x", "y"]),
+            Size {
+                width: 4,
+                height: 3,
+            },
+            Position::default(),
+        );
+        w.options_mut().wrap = true;
+        w.options_mut().scrolloff = 0;
+
+        w.scroll_into_view(&Position { x: 5, y: 1 });
+        assert_eq!(w.visible_area_buff(), vec!["abcd", "efgh", "x"]);
+
+        // rows are never cut at the top, so the wrapped row can't stay
+        w.scroll(&Direction::Down, 10);
+        assert_eq!(w.visible_area_buff(), vec!["x", "y"]);
+    }
+
+    #[test]
+    fn should_highlight_selection_on_screen() {
+        use crate::{
+            document::Document,
+            editor::{window::Window, Highlight, ScreenHighlight, Selection, SelectionKind},
+            utils::{Position, Size},
+        };
+
+        let mut w = Window::new(
+            Document::from(vec!["abcdef", "gh"]),
+            Size {
+                width: 4,
+                height: 3,
+            },
+            Position { x: 2, y: 1 },
+        );
+        w.set_selection(Some(Selection {
+            anchor: Position { x: 2, y: 0 },
+            cursor: Position { x: 1, y: 1 },
+            kind: SelectionKind::Char,
+        }));
+
+        // the first row is cut by the window width
+        assert_eq!(
+            w.visible_highlights(),
+            vec![
+                ScreenHighlight {
+                    position: Position { x: 4, y: 1 },
+                    len: 2,
+                    highlight: Highlight::Selection,
+                },
+                ScreenHighlight {
+                    position: Position { x: 2, y: 2 },
+                    len: 2,
+                    highlight: Highlight::Selection,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn should_render_wrapped_rows_in_styled_spans() {
+        use crate::{
+            document::Document,
+            editor::window::Window,
+            syntax::{languages::RUST, Scope, Span},
+            utils::{Position, Size},
+        };
+        use std::rc::Rc;
+
+        let mut doc = Document::from(vec!["let x = \"abcdef\";"]);
+        doc.set_syntax(Some(Rc::new(RUST)));
+        let mut w = Window::new(
+            doc,
+            Size {
+                width: 12,
+                height: 2,
+            },
+            Position::default(),
+        );
+        w.options_mut().wrap = true;
+        w.options_mut().showbreak = String::from(">");
+
+        let string = |text: &str| Span {
+            text: text.to_string(),
+            scope: Some(Scope::String),
+        };
+        assert_eq!(
+            w.visible_area_spans(),
+            vec![
+                vec![
+                    Span {
+                        text: String::from("let"),
+                        scope: Some(Scope::Keyword),
+                    },
+                    Span::plain(" x = "),
+                    string("\"abc"),
+                ],
+                vec![Span::plain(">"), string("def\""), Span::plain(";")],
+            ]
+        );
+    }
+
+    #[test]
+    fn should_show_closed_folds_as_a_single_line() {
+        use crate::{
+            document::Document,
+            editor::window::Window,
+            utils::{Direction, Position, Size},
+        };
+
+        let mut w = Window::new(
+            Document::from(vec!["a", "b {{{", "c", "d }}}", "e", "f"]),
+            Size {
+                width: 20,
+                height: 4,
+            },
+            Position::default(),
+        );
+        w.options_mut().foldmethod = String::from("marker");
+        w.update_folds();
+        w.folds_mut().set_all(true);
+
+        assert_eq!(
+            w.visible_area_buff(),
+            vec!["a", "+--  3 lines: b", "e", "f"]
+        );
+        assert_eq!(w.document_line_number_from_cursor(1), Some(1));
+        assert_eq!(w.document_line_number_from_cursor(2), Some(4));
+
+        let pos = w.move_display_line(&Position { x: 0, y: 0 }, &Direction::Down, 2);
+        assert_eq!(pos, Position { x: 0, y: 4 });
+        let pos = w.move_display_line(&pos, &Direction::Up, 1);
+        assert_eq!(pos, Position { x: 0, y: 1 });
+
+        // the fold counts as a single row when scrolling
+        w.size.height = 3;
+        w.scroll(&Direction::Down, 5);
+        assert_eq!(w.visible_area_buff(), vec!["+--  3 lines: b", "e", "f"]);
+        w.scroll(&Direction::Up, 1);
+        assert_eq!(w.document_line_number_from_cursor(1), Some(1));
+    }
 }
@@ -1,10 +1,20 @@
 use crate::{
     document::Document,
-    editor::Window,
-    terminal::Terminal,
+    editor::{ScreenHighlight, Window, WindowOptions},
+    syntax::Span,
     utils::{Position, Size},
 };
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, cmp, ops::Range, rc::Rc};
+
+/// Screen column or line left between windows, dragging it resizes the
+/// windows on both sides.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Separator {
+    /// column between windows side by side
+    Vertical(usize),
+    /// line between windows one above the other
+    Horizontal(usize),
+}
 
 pub struct WindowManager<'a> {
     windows: &'a Rc<RefCell<Vec<Window>>>,
@@ -16,45 +26,68 @@ impl<'a> WindowManager<'a> {
         Self { windows, size }
     }
 
-    pub fn clean_screen_buff(&self) -> Vec<String> {
-        (0..self.size.height)
-            .into_iter()
-            .map(|_| {
-                (0..self.size.width)
-                    .map(|_| String::from(" "))
-                    .collect::<String>()
-            })
-            .collect()
-    }
+    /// Screen lines of the windows, as spans styled by their highlighting.
+    pub fn visible_area_buff(&self) -> Vec<Vec<Span>> {
+        let width = self.size.width as usize;
+        let mut screen = vec![vec![(' ', None); width]; self.size.height as usize];
 
-    pub fn visible_area_buff(&self) -> Vec<String> {
-        let mut screen = self.clean_screen_buff();
         for window in self.windows.borrow().iter() {
-            let buff = window.visible_area_buff();
             let pos = window.position();
-            let size = window.size();
+            let window_width = window.size().width as usize;
 
-            for row_i in 0..buff.len() {
-                let start_at = pos.x;
-                let end_at = pos.x + size.width as usize;
+            for (row_i, spans) in window.visible_area_spans().iter().enumerate() {
+                let Some(line) = screen.get_mut(pos.y + row_i) else {
+                    break;
+                };
 
-                let correction_y = pos.y + row_i;
-                screen[correction_y].replace_range(start_at..end_at, &buff[row_i]);
+                let mut cells = Span::cells(spans);
+                cells.resize(window_width, (' ', None));
+                let end = cmp::min(pos.x + window_width, line.len());
+                if pos.x < end {
+                    line.splice(pos.x..end, cells.into_iter().take(end - pos.x));
+                }
             }
         }
 
-        screen
+        screen.iter().map(|line| Span::from_cells(line)).collect()
+    }
+
+    pub fn visible_highlights(&self) -> Vec<ScreenHighlight> {
+        self.windows
+            .borrow()
+            .iter()
+            .flat_map(Window::visible_highlights)
+            .collect()
     }
 
-    pub fn new_window(&mut self, doc: Document) {
-        let window = Window::new(
+    pub fn set_size(&mut self, size: Size) {
+        self.size = size;
+    }
+
+    /// Adds a window of `width` columns and `height` lines at the top left
+    /// of the screen, a size of 0 taking the whole screen but the message
+    /// line.
+    pub fn new_window(
+        &mut self,
+        doc: Document,
+        options: WindowOptions,
+        width: usize,
+        height: usize,
+    ) {
+        let fit = |wanted: usize, available: u16| match wanted {
+            0 => available,
+            wanted => u16::try_from(wanted).unwrap_or(u16::MAX).min(available),
+        };
+        let mut window = Window::new(
             doc,
             Size {
-                width: 30,
-                height: 10,
+                width: fit(width, self.size.width),
+                height: fit(height, self.size.height.saturating_sub(1)),
             },
             Position { x: 0, y: 0 },
         );
+        *window.options_mut() = options;
+        window.update_folds();
 
         self.windows.borrow_mut().push(window);
     }
@@ -63,3 +96,106 @@ impl<'a> WindowManager<'a> {
         self.windows.borrow().is_empty()
     }
 }
+
+/// Screen columns of `window` for a vertical separator, its lines for a
+/// horizontal one: where the separator moves.
+fn along(window: &Window, separator: Separator) -> Range<usize> {
+    let (start, len) = match separator {
+        Separator::Vertical(_) => (window.position().x, window.size().width),
+        Separator::Horizontal(_) => (window.position().y, window.size().height),
+    };
+    start..start + len as usize
+}
+
+/// Screen lines of `window` for a vertical separator, its columns for a
+/// horizontal one: where the separator runs.
+fn across(window: &Window, separator: Separator) -> Range<usize> {
+    let (start, len) = match separator {
+        Separator::Vertical(_) => (window.position().y, window.size().height),
+        Separator::Horizontal(_) => (window.position().x, window.size().width),
+    };
+    start..start + len as usize
+}
+
+/// Separator at `screen`, a cell outside the windows with one ending right
+/// before it and another starting right after it.
+pub fn separator_at(windows: &[Window], screen: &Position) -> Option<Separator> {
+    if windows.iter().any(|window| window.contains(screen)) {
+        return None;
+    }
+
+    [
+        (Separator::Vertical(screen.x), screen.x, screen.y),
+        (Separator::Horizontal(screen.y), screen.y, screen.x),
+    ]
+    .into_iter()
+    .find(|(separator, at, beside)| {
+        let side = |window: &Window| {
+            across(window, *separator)
+                .contains(beside)
+                .then(|| along(window, *separator))
+        };
+        windows.iter().filter_map(side).any(|span| span.end == *at)
+            && windows
+                .iter()
+                .filter_map(side)
+                .any(|span| span.start == at + 1)
+    })
+    .map(|(separator, ..)| separator)
+}
+
+/// Moves `separator` to the column or line of `screen`, growing the windows
+/// on one side and shrinking those on the other, which keep at least a
+/// column or a line. Returns where the separator went.
+pub fn move_separator(
+    windows: &mut [Window],
+    separator: Separator,
+    screen: &Position,
+) -> Separator {
+    let (at, wanted) = match separator {
+        Separator::Vertical(x) => (x, screen.x),
+        Separator::Horizontal(y) => (y, screen.y),
+    };
+
+    let spans: Vec<Range<usize>> = windows
+        .iter()
+        .map(|window| along(window, separator))
+        .collect();
+    let lowest = spans
+        .iter()
+        .filter(|span| span.end == at)
+        .map(|span| span.start + 1)
+        .max()
+        .unwrap_or(at);
+    let highest = spans
+        .iter()
+        .filter(|span| span.start == at + 1)
+        .map(|span| span.end.saturating_sub(2))
+        .min()
+        .unwrap_or(at);
+    let to = wanted.clamp(lowest.min(at), highest.max(at));
+
+    for (window, span) in windows.iter_mut().zip(spans) {
+        let span = if span.end == at {
+            span.start..to
+        } else if span.start == at + 1 {
+            to + 1..span.end
+        } else {
+            continue;
+        };
+
+        let (mut position, size) = (window.position(), window.size());
+        let (mut width, mut height) = (size.width, size.height);
+        let len = u16::try_from(span.len()).unwrap_or(u16::MAX);
+        match separator {
+            Separator::Vertical(_) => (position.x, width) = (span.start, len),
+            Separator::Horizontal(_) => (position.y, height) = (span.start, len),
+        }
+        window.set_area(position, Size { width, height });
+    }
+
+    match separator {
+        Separator::Vertical(_) => Separator::Vertical(to),
+        Separator::Horizontal(_) => Separator::Horizontal(to),
+    }
+}
@@ -1,7 +1,9 @@
 #![warn(clippy::all, clippy::pedantic)]
 mod document;
 mod editor;
+mod syntax;
 mod terminal;
+mod theme;
 mod utils;
 
 use std::{cell::RefCell, env::args, rc::Rc};
@@ -20,11 +22,11 @@ fn main() {
     let windows = Rc::new(RefCell::new(Vec::new()));
 
     let mut control = Control::new(&windows);
-    let mut e = Editor::new(&mut control, &windows, terminal).unwrap();
+    let mut e = Editor::new(&mut control, &windows, terminal);
 
     if let Some(path) = args.get(1) {
         e.open_document(path).unwrap();
-    };
+    }
 
     e.run();
 }
@@ -0,0 +1,300 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use regex::Regex;
+use syntect::parsing::{self, ParseState, ScopeStack, SyntaxDefinition, SyntaxSetBuilder};
+
+use crate::syntax::{tm_language, LineState, Scope, Syntax, Token};
+
+/// Grammar scopes given the style of a scope, the first one found from the
+/// innermost scope of a piece of text wins.
+const SCOPES: &[(&str, Scope)] = &[
+    ("comment", Scope::Comment),
+    ("string", Scope::String),
+    ("constant.character.escape", Scope::String),
+    ("constant.numeric", Scope::Number),
+    ("constant", Scope::Constant),
+    ("storage.type", Scope::Keyword),
+    ("keyword", Scope::Keyword),
+    ("storage", Scope::Keyword),
+    ("entity.name.function", Scope::Function),
+    ("support.function", Scope::Function),
+    ("variable.function", Scope::Function),
+    ("entity.name.type", Scope::Type),
+    ("entity.name.class", Scope::Type),
+    ("support.type", Scope::Type),
+    ("support.class", Scope::Type),
+    ("variable", Scope::Variable),
+    ("markup.heading", Scope::Heading),
+    ("entity.name.section", Scope::Heading),
+    ("markup.bold", Scope::Emphasis),
+    ("markup.italic", Scope::Emphasis),
+    ("markup.underline.link", Scope::Link),
+    ("markup.link", Scope::Link),
+];
+
+/// Where a grammar stopped parsing a row, the next row goes on from there.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct GrammarState {
+    parse: ParseState,
+    scopes: ScopeStack,
+}
+
+/// A language read from a `.sublime-syntax` or `.tmLanguage` file.
+pub struct Grammar {
+    /// every grammar loaded, as they can include each other
+    grammars: Rc<parsing::SyntaxSet>,
+    index: usize,
+    first_line: Option<Regex>,
+    scopes: Rc<Vec<(parsing::Scope, Scope)>>,
+}
+
+impl Grammar {
+    fn syntax(&self) -> &parsing::SyntaxReference {
+        &self.grammars.syntaxes()[self.index]
+    }
+
+    /// Scope of text inside the grammar `scopes`.
+    fn scope(&self, scopes: &ScopeStack) -> Option<Scope> {
+        scopes.as_slice().iter().rev().find_map(|scope| {
+            self.scopes
+                .iter()
+                .find(|(prefix, _)| prefix.is_prefix_of(*scope))
+                .map(|(_, scope)| *scope)
+        })
+    }
+
+    /// Loads the grammars of `dir`, the ones that can't be read are left out.
+    /// Returns them with the errors of the others, naming their file.
+    pub fn load_dir(dir: &Path) -> (Vec<Rc<dyn Syntax>>, Vec<String>) {
+        let mut paths: Vec<PathBuf> = match fs::read_dir(dir) {
+            Ok(entries) => entries
+                .filter_map(|entry| Some(entry.ok()?.path()))
+                .collect(),
+            Err(_) => return (Vec::new(), Vec::new()),
+        };
+        paths.sort();
+
+        let mut builder = SyntaxSetBuilder::new();
+        let mut errors = Vec::new();
+        for path in paths {
+            let definition = match path.extension().and_then(|extension| extension.to_str()) {
+                Some("sublime-syntax") => Self::load_sublime_syntax(&path),
+                Some("tmLanguage") => Self::load_tm_language(&path),
+                _ => continue,
+            };
+            match definition {
+                Ok(definition) => builder.add(definition),
+                Err(err) => errors.push(format!("{}: {err}", path.display())),
+            }
+        }
+
+        let grammars = Rc::new(builder.build());
+        let scopes: Rc<Vec<_>> = Rc::new(
+            SCOPES
+                .iter()
+                .filter_map(|(name, scope)| Some((parsing::Scope::new(name).ok()?, *scope)))
+                .collect(),
+        );
+        let syntaxes = (0..grammars.syntaxes().len())
+            .map(|index| {
+                let first_line = grammars.syntaxes()[index]
+                    .first_line_match
+                    .as_deref()
+                    .and_then(|regex| Regex::new(regex).ok());
+                Rc::new(Self {
+                    grammars: Rc::clone(&grammars),
+                    index,
+                    first_line,
+                    scopes: Rc::clone(&scopes),
+                }) as Rc<dyn Syntax>
+            })
+            .collect();
+
+        (syntaxes, errors)
+    }
+
+    fn load_sublime_syntax(path: &Path) -> Result<SyntaxDefinition, String> {
+        let text = fs::read_to_string(path).map_err(|err| err.to_string())?;
+        let name = path.file_stem().and_then(|name| name.to_str());
+
+        SyntaxDefinition::load_from_str(&text, true, name).map_err(|err| err.to_string())
+    }
+
+    fn load_tm_language(path: &Path) -> Result<SyntaxDefinition, String> {
+        let grammar = plist::Value::from_file(path).map_err(|err| err.to_string())?;
+        let syntax = tm_language::to_sublime_syntax(&grammar)?;
+        let name = path.file_stem().and_then(|name| name.to_str());
+
+        // JSON is YAML too
+        SyntaxDefinition::load_from_str(&syntax.to_string(), true, name)
+            .map_err(|err| err.to_string())
+    }
+}
+
+impl Syntax for Grammar {
+    fn name(&self) -> &str {
+        &self.syntax().name
+    }
+
+    fn matches_file(&self, file_name: &str) -> bool {
+        self.syntax().file_extensions.iter().any(|extension| {
+            file_name == extension
+                || file_name
+                    .strip_suffix(extension.as_str())
+                    .is_some_and(|name| name.ends_with('.'))
+        })
+    }
+
+    fn matches_first_line(&self, line: &str) -> bool {
+        self.first_line
+            .as_ref()
+            .is_some_and(|regex| regex.is_match(line))
+    }
+
+    fn highlight_line(&self, line: &str, state: &mut LineState) -> Vec<Token> {
+        let (mut parse, mut scopes) = match std::mem::take(state) {
+            LineState::Grammar(grammar) => (grammar.parse, grammar.scopes),
+            _ => (ParseState::new(self.syntax()), ScopeStack::new()),
+        };
+
+        // grammars are loaded to match lines with their line break
+        let ops = parse
+            .parse_line(&format!("{line}\n"), &self.grammars)
+            .unwrap_or_default();
+
+        let mut tokens: Vec<Token> = Vec::new();
+        let mut x = 0;
+        let ends = ops.iter().map(|(at, _)| *at).chain([line.len()]);
+        for (end, op) in ends.zip(ops.iter().map(Some).chain([None])) {
+            let end = end.min(line.len());
+            if let Some(scope) = self.scope(&scopes).filter(|_| x < end) {
+                match tokens.last_mut() {
+                    Some(last) if last.range.end == x && last.scope == scope => {
+                        last.range.end = end;
+                    }
+                    _ => tokens.push(Token {
+                        range: x..end,
+                        scope,
+                    }),
+                }
+            }
+            x = x.max(end);
+            if let Some((_, op)) = op {
+                // a wrong pop leaves the scopes as they were
+                let _ = scopes.apply(op);
+            }
+        }
+
+        *state = LineState::Grammar(Box::new(GrammarState { parse, scopes }));
+        tokens
+    }
+}
+
+mod test {
+    #[cfg(test)]
+    use super::Grammar;
+    #[cfg(test)]
+    use crate::syntax::Syntax;
+    #[cfg(test)]
+    use std::rc::Rc;
+
+    #[cfg(test)]
+    const INI: &str = "%YAML 1.2\n---\nname: INI\nfile_extensions: [ini]\nscope: source.ini\ncontexts:\n  main:\n    - match: '^;.*'\n      scope: comment.line.ini\n    - match: '\\['\n      push:\n        - meta_scope: entity.name.section.ini\n        - match: '\\]'\n          pop: true\n";
+
+    #[cfg(test)]
+    const PROTO: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<plist version="1.0"><dict>
+  <key>name</key><string>Protobuf</string>
+  <key>scopeName</key><string>source.proto</string>
+  <key>fileTypes</key><array><string>proto</string></array>
+  <key>patterns</key><array>
+    <dict><key>include</key><string>#keywords</string></dict>
+    <dict>
+      <key>begin</key><string>/\*</string>
+      <key>end</key><string>\*/</string>
+      <key>name</key><string>comment.block.proto</string>
+    </dict>
+  </array>
+  <key>repository</key><dict>
+    <key>keywords</key><dict>
+      <key>match</key><string>\b(message|syntax)\b</string>
+      <key>name</key><string>keyword.other.proto</string>
+    </dict>
+  </dict>
+</dict></plist>"#;
+
+    /// Grammars loaded from a directory of `files`, named and written as
+    /// given, and the errors reading them.
+    #[cfg(test)]
+    fn load(name: &str, files: &[(&str, &str)]) -> (Vec<Rc<dyn Syntax>>, Vec<String>) {
+        use std::{env, fs, process};
+
+        let dir = env::temp_dir().join(format!("text-editor-{name}-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for (file, text) in files {
+            fs::write(dir.join(file), text).unwrap();
+        }
+
+        let loaded = Grammar::load_dir(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+        loaded
+    }
+
+    #[test]
+    fn should_report_broken_grammar_files() {
+        let (grammars, errors) = load(
+            "broken-grammars",
+            &[
+                ("ini.sublime-syntax", INI),
+                ("broken.sublime-syntax", "name: ["),
+            ],
+        );
+
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("broken.sublime-syntax: Invalid YAML"));
+        assert_eq!(grammars.len(), 1);
+    }
+
+    #[test]
+    fn should_load_sublime_syntax_files() {
+        use crate::syntax::{LineState, Scope};
+
+        let (grammars, _) = load("sublime-syntax", &[("ini.sublime-syntax", INI)]);
+        let ini = grammars.iter().find(|g| g.matches_file("a.ini")).unwrap();
+
+        let mut state = LineState::default();
+        let tokens = ini.highlight_line("[core] x", &mut state);
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(
+            (tokens[0].range.clone(), tokens[0].scope),
+            (0..6, Scope::Heading)
+        );
+    }
+
+    #[test]
+    fn should_load_tm_language_files() {
+        use crate::syntax::{LineState, Scope};
+
+        let (grammars, _) = load("tm-language", &[("proto.tmLanguage", PROTO)]);
+        let proto = grammars.iter().find(|g| g.matches_file("a.proto")).unwrap();
+
+        // the comment goes on to the next row
+        let mut state = LineState::default();
+        let tokens = proto.highlight_line("message M { /* a", &mut state);
+        let scopes: Vec<_> = tokens.iter().map(|t| (t.range.clone(), t.scope)).collect();
+        assert_eq!(
+            scopes,
+            vec![(0..7, Scope::Keyword), (12..16, Scope::Comment)]
+        );
+        let tokens = proto.highlight_line("b */ syntax", &mut state);
+        let scopes: Vec<_> = tokens.iter().map(|t| (t.range.clone(), t.scope)).collect();
+        assert_eq!(
+            scopes,
+            vec![(0..4, Scope::Comment), (5..11, Scope::Keyword)]
+        );
+    }
+}
@@ -0,0 +1,122 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    document::Row,
+    syntax::{LineState, Syntax, Token},
+};
+
+/// Tokens of a row and the state the next row starts in.
+#[derive(Clone)]
+struct HighlightedRow {
+    tokens: Vec<Token>,
+    end: LineState,
+}
+
+/// Highlighting of the rows of a document. Rows are highlighted when they
+/// are first shown, and kept until a row above them is edited.
+#[derive(Clone, Default)]
+pub struct Highlighting {
+    syntax: Option<Rc<dyn Syntax>>,
+    /// rows from the first one, highlighting a row needs the ones above it
+    rows: RefCell<Vec<HighlightedRow>>,
+}
+
+impl Highlighting {
+    pub fn new(syntax: Option<Rc<dyn Syntax>>) -> Self {
+        Self {
+            syntax,
+            rows: RefCell::default(),
+        }
+    }
+
+    pub fn syntax(&self) -> Option<&dyn Syntax> {
+        self.syntax.as_deref()
+    }
+
+    /// Tokens of row `line` of `rows`, highlighting the rows above it that
+    /// are not yet.
+    pub fn tokens(&self, rows: &[Row], line: usize) -> Vec<Token> {
+        let Some(syntax) = &self.syntax else {
+            return Vec::new();
+        };
+        if line >= rows.len() {
+            return Vec::new();
+        }
+
+        let mut highlighted = self.rows.borrow_mut();
+        while highlighted.len() <= line {
+            let mut state = highlighted
+                .last()
+                .map(|row| row.end.clone())
+                .unwrap_or_default();
+            let tokens = syntax.highlight_line(&rows[highlighted.len()].content, &mut state);
+            highlighted.push(HighlightedRow { tokens, end: state });
+        }
+
+        highlighted[line].tokens.clone()
+    }
+
+    /// Forgets the highlighting from row `line` down, after it was edited.
+    pub fn invalidate(&mut self, line: usize) {
+        self.rows.get_mut().truncate(line);
+    }
+
+    /// Rows whose highlighting is kept.
+    #[cfg(test)]
+    pub fn highlighted_rows(&self) -> usize {
+        self.rows.borrow().len()
+    }
+}
+
+mod test {
+    #[cfg(test)]
+    use super::Highlighting;
+    #[cfg(test)]
+    use crate::document::Row;
+
+    /// Rows with a comment through the second and third ones, highlighted
+    /// as Rust.
+    #[cfg(test)]
+    fn commented_rows() -> (Vec<Row>, Highlighting) {
+        use crate::syntax::languages::RUST;
+        use std::rc::Rc;
+
+        let rows = ["a", "/* b", "c */ d", "e"]
+            .into_iter()
+            .map(Row::from)
+            .collect();
+        (rows, Highlighting::new(Some(Rc::new(RUST))))
+    }
+
+    #[test]
+    fn should_highlight_rows_up_to_the_one_asked() {
+        use crate::syntax::Scope;
+
+        let (rows, highlighting) = commented_rows();
+
+        assert_eq!(highlighting.tokens(&rows, 2)[0].scope, Scope::Comment);
+        assert_eq!(highlighting.highlighted_rows(), 3);
+    }
+
+    #[test]
+    fn should_forget_rows_below_an_edit() {
+        let (mut rows, mut highlighting) = commented_rows();
+        highlighting.tokens(&rows, 2);
+
+        rows[1] = Row::from("b");
+        highlighting.invalidate(1);
+        assert_eq!(highlighting.highlighted_rows(), 1);
+    }
+
+    #[test]
+    fn should_highlight_rows_below_an_edit_again() {
+        let (mut rows, mut highlighting) = commented_rows();
+        highlighting.tokens(&rows, 2);
+        rows[1] = Row::from("b");
+        highlighting.invalidate(1);
+
+        assert_eq!(highlighting.tokens(&rows, 2), Vec::new());
+        assert_eq!(highlighting.tokens(&rows, 3), Vec::new());
+        assert_eq!(highlighting.highlighted_rows(), 4);
+    }
+}
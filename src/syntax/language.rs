@@ -0,0 +1,411 @@
+use crate::syntax::{IndentRules, LineState, Scope, Syntax, Token};
+
+/// String delimiters of a language.
+pub struct StringRule {
+    pub open: &'static str,
+    pub close: &'static str,
+    /// the string can go on past the end of its line
+    pub multiline: bool,
+    /// `\` escapes the char after it
+    pub escapes: bool,
+}
+
+impl StringRule {
+    pub const fn new(open: &'static str, close: &'static str, multiline: bool) -> Self {
+        Self {
+            open,
+            close,
+            multiline,
+            escapes: true,
+        }
+    }
+
+    pub const fn raw(open: &'static str, close: &'static str, multiline: bool) -> Self {
+        Self {
+            open,
+            close,
+            multiline,
+            escapes: false,
+        }
+    }
+}
+
+/// A language highlighted from lists of words and its comment and string
+/// delimiters.
+#[allow(clippy::struct_excessive_bools)]
+pub struct Language {
+    pub name: &'static str,
+    /// extensions, or whole names of files without one like `.bashrc`
+    pub extensions: &'static [&'static str],
+    /// programs of `#!` lines, `python` also matching `python3`
+    pub interpreters: &'static [&'static str],
+    pub keywords: &'static [&'static str],
+    pub types: &'static [&'static str],
+    pub constants: &'static [&'static str],
+    pub line_comments: &'static [&'static str],
+    pub block_comment: Option<(&'static str, &'static str)>,
+    /// block comments can be nested, like in Rust
+    pub nested_comments: bool,
+    /// checked in order, so longer delimiters go first
+    pub strings: &'static [StringRule],
+    /// `'a'` is a char while `'a` is a lifetime
+    pub char_literals: bool,
+    /// words starting with an uppercase letter are types
+    pub capitalized_types: bool,
+    /// `name!` calls a macro
+    pub macros: bool,
+    /// `$name` and `${...}` are variables
+    pub variables: bool,
+    /// lines starting with `[` are section headers
+    pub sections: bool,
+    pub indent: IndentRules,
+    /// opening and closing chars typed together
+    pub pairs: &'static [(char, char)],
+}
+
+impl Language {
+    fn string_rule(&self, close: &str) -> Option<&StringRule> {
+        self.strings.iter().find(|rule| rule.close == close)
+    }
+
+    /// End of the string whose text starts at `from`, `None` if it goes past
+    /// the end of the line.
+    fn string_end(line: &str, from: usize, rule: &StringRule) -> Option<usize> {
+        let mut chars = line[from..].char_indices();
+        while let Some((i, c)) = chars.next() {
+            if rule.escapes && c == '\\' {
+                chars.next();
+            } else if line[from + i..].starts_with(rule.close) {
+                return Some(from + i + rule.close.len());
+            }
+        }
+        None
+    }
+
+    /// End of the block comment whose text starts at `from` nested `depth`
+    /// times, or the depth it is left open with.
+    fn comment_end(&self, line: &str, from: usize, mut depth: usize) -> Result<usize, usize> {
+        let Some((open, close)) = self.block_comment else {
+            return Ok(from);
+        };
+
+        let mut i = from;
+        while i < line.len() {
+            let rest = &line[i..];
+            if rest.starts_with(close) {
+                depth -= 1;
+                i += close.len();
+                if depth == 0 {
+                    return Ok(i);
+                }
+            } else if self.nested_comments && rest.starts_with(open) {
+                depth += 1;
+                i += open.len();
+            } else {
+                i += rest.chars().next().map_or(1, char::len_utf8);
+            }
+        }
+        Err(depth)
+    }
+
+    /// Length of a char literal like `'a'` or `'\n'` at the start of `rest`.
+    fn char_literal_len(rest: &str) -> Option<usize> {
+        let mut chars = rest.char_indices().skip(1);
+        let (_, c) = chars.next()?;
+        let end = if c == '\\' {
+            rest[1..].find('\'').map(|i| i + 1)
+        } else {
+            chars.next().filter(|(_, c)| *c == '\'').map(|(i, _)| i)
+        }?;
+        Some(end + 1)
+    }
+
+    fn word_scope(&self, word: &str, next: Option<char>) -> Option<Scope> {
+        if self.keywords.contains(&word) {
+            Some(Scope::Keyword)
+        } else if self.types.contains(&word)
+            || (self.capitalized_types && word.starts_with(|c: char| c.is_uppercase()))
+        {
+            Some(Scope::Type)
+        } else if self.constants.contains(&word) {
+            Some(Scope::Constant)
+        } else if next == Some('(') || (self.macros && next == Some('!')) {
+            Some(Scope::Function)
+        } else {
+            None
+        }
+    }
+
+    /// End of the comment or string `state` was left in, read from `from`,
+    /// or the state it is still open with at the end of the line.
+    fn region_end(&self, line: &str, from: usize, state: &LineState) -> Result<usize, LineState> {
+        match state {
+            LineState::Comment(depth) => self
+                .comment_end(line, from, *depth)
+                .map_err(LineState::Comment),
+            LineState::String(close) => {
+                let Some(rule) = self.string_rule(close) else {
+                    return Ok(line.len());
+                };
+                Self::string_end(line, from, rule).ok_or(if rule.multiline {
+                    LineState::String(rule.close)
+                } else {
+                    LineState::Normal
+                })
+            }
+            LineState::Normal | LineState::Fence(_) | LineState::Grammar(_) => Ok(from),
+        }
+    }
+
+    /// End and scope of the word, number, char or variable at `i`, or of
+    /// the char at `i` alone.
+    fn next_token(&self, line: &str, i: usize) -> (usize, Option<Scope>) {
+        let rest = &line[i..];
+        let c = rest.chars().next().unwrap_or_default();
+        let after_word = line[..i].chars().next_back().is_some_and(is_word_char);
+
+        if self.char_literals && c == '\'' {
+            // a lifetime is left as it is
+            match Self::char_literal_len(rest) {
+                Some(len) => (i + len, Some(Scope::String)),
+                None => (i + 1, None),
+            }
+        } else if self.variables && c == '$' {
+            (Self::variable_end(line, i + 1), Some(Scope::Variable))
+        } else if c.is_ascii_digit() && !after_word {
+            (i + number_len(rest), Some(Scope::Number))
+        } else if is_word_char(c) {
+            let end = i + word_len(rest);
+            (
+                end,
+                self.word_scope(&line[i..end], line[end..].chars().next()),
+            )
+        } else {
+            (i + c.len_utf8(), None)
+        }
+    }
+
+    /// End of the variable at `from`, after its `$`.
+    fn variable_end(line: &str, from: usize) -> usize {
+        let rest = &line[from..];
+        if rest.starts_with('{') {
+            return rest.find('}').map_or(line.len(), |i| from + i + 1);
+        }
+        match rest.chars().next() {
+            Some(c) if c.is_alphabetic() || c == '_' => from + word_len(rest),
+            Some(c) => from + c.len_utf8(),
+            None => from,
+        }
+    }
+}
+
+fn push_token(tokens: &mut Vec<Token>, start: usize, end: usize, scope: Scope) {
+    if start < end {
+        tokens.push(Token {
+            range: start..end,
+            scope,
+        });
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn word_len(text: &str) -> usize {
+    text.find(|c| !is_word_char(c)).unwrap_or(text.len())
+}
+
+/// Length of the number at the start of `text`, with its suffix and
+/// fraction, but not a range like `1..2`.
+fn number_len(text: &str) -> usize {
+    let bytes = text.as_bytes();
+    let mut len = 0;
+    while len < bytes.len() {
+        let c = bytes[len];
+        let fraction = c == b'.' && bytes.get(len + 1).is_some_and(u8::is_ascii_digit);
+        if !(c.is_ascii_alphanumeric() || c == b'_' || fraction) {
+            break;
+        }
+        len += 1;
+    }
+    len
+}
+
+impl Syntax for Language {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn indent_rules(&self) -> &IndentRules {
+        &self.indent
+    }
+
+    fn pairs(&self) -> &[(char, char)] {
+        self.pairs
+    }
+
+    fn line_comment(&self) -> Option<&str> {
+        self.line_comments.first().copied()
+    }
+
+    fn block_comment(&self) -> Option<(&str, &str)> {
+        self.block_comment
+    }
+
+    fn matches_file(&self, file_name: &str) -> bool {
+        self.extensions.iter().any(|extension| {
+            file_name == *extension
+                || file_name
+                    .strip_suffix(extension)
+                    .is_some_and(|name| name.ends_with('.'))
+        })
+    }
+
+    fn matches_first_line(&self, line: &str) -> bool {
+        let Some(command) = line.strip_prefix("#!") else {
+            return false;
+        };
+
+        let mut words = command.split_whitespace();
+        let program = words.next().unwrap_or_default();
+        let program = match program.rsplit('/').next() {
+            // `#!/usr/bin/env python3` runs the program after `env`
+            Some("env") => words.next().unwrap_or_default(),
+            Some(name) => name,
+            None => program,
+        };
+        self.interpreters
+            .iter()
+            .any(|interpreter| program.starts_with(interpreter))
+    }
+
+    fn highlight_line(&self, line: &str, state: &mut LineState) -> Vec<Token> {
+        let mut tokens = Vec::new();
+        // comment or string being read, from where it started
+        let mut open = match state {
+            LineState::Comment(_) => Some((0, Scope::Comment)),
+            LineState::String(_) => Some((0, Scope::String)),
+            LineState::Normal | LineState::Fence(_) | LineState::Grammar(_) => None,
+        };
+        let mut i = 0;
+
+        if open.is_none() && self.sections && line.trim_start().starts_with('[') {
+            i = self
+                .line_comments
+                .iter()
+                .filter_map(|comment| line.find(comment))
+                .min()
+                .unwrap_or(line.len());
+            push_token(&mut tokens, 0, i, Scope::Heading);
+        }
+
+        loop {
+            if let Some((start, scope)) = open.take() {
+                match self.region_end(line, i, state) {
+                    Ok(end) => {
+                        push_token(&mut tokens, start, end, scope);
+                        *state = LineState::Normal;
+                        i = end;
+                    }
+                    Err(left_open) => {
+                        push_token(&mut tokens, start, line.len(), scope);
+                        *state = left_open;
+                        break;
+                    }
+                }
+            }
+            if i >= line.len() {
+                break;
+            }
+
+            let rest = &line[i..];
+            if let Some((comment, _)) = self
+                .block_comment
+                .filter(|(comment, _)| rest.starts_with(comment))
+            {
+                open = Some((i, Scope::Comment));
+                *state = LineState::Comment(1);
+                i += comment.len();
+            } else if self
+                .line_comments
+                .iter()
+                .any(|comment| rest.starts_with(comment))
+            {
+                push_token(&mut tokens, i, line.len(), Scope::Comment);
+                break;
+            } else if let Some(rule) = self.strings.iter().find(|rule| rest.starts_with(rule.open))
+            {
+                open = Some((i, Scope::String));
+                *state = LineState::String(rule.close);
+                i += rule.open.len();
+            } else {
+                let (end, scope) = self.next_token(line, i);
+                if let Some(scope) = scope {
+                    push_token(&mut tokens, i, end, scope);
+                }
+                i = end;
+            }
+        }
+
+        tokens
+    }
+}
+
+mod test {
+    #[test]
+    fn should_highlight_words_strings_and_comments() {
+        use crate::syntax::{languages::RUST, LineState, Scope, Syntax, Token};
+
+        let mut state = LineState::Normal;
+        let tokens = RUST.highlight_line("let s: String = \"a\\\"b\"; // 12", &mut state);
+        assert_eq!(
+            tokens,
+            vec![
+                Token {
+                    range: 0..3,
+                    scope: Scope::Keyword
+                },
+                Token {
+                    range: 7..13,
+                    scope: Scope::Type
+                },
+                Token {
+                    range: 16..22,
+                    scope: Scope::String
+                },
+                Token {
+                    range: 24..29,
+                    scope: Scope::Comment
+                },
+            ]
+        );
+
+        // a comment left open goes on over the next rows
+        let tokens = RUST.highlight_line("x /* a /* b */", &mut state);
+        assert_eq!(tokens[0].range, 2..14);
+        assert_eq!(state, LineState::Comment(1));
+        let tokens = RUST.highlight_line("c */ 42", &mut state);
+        assert_eq!(
+            tokens,
+            vec![
+                Token {
+                    range: 0..4,
+                    scope: Scope::Comment
+                },
+                Token {
+                    range: 5..7,
+                    scope: Scope::Number
+                },
+            ]
+        );
+        assert_eq!(state, LineState::Normal);
+
+        let tokens = RUST.highlight_line("fn f<'a>(c: &'a char) { g('x') }", &mut state);
+        let scopes: Vec<Scope> = tokens.iter().map(|token| token.scope).collect();
+        assert_eq!(
+            scopes,
+            vec![Scope::Keyword, Scope::Type, Scope::Function, Scope::String]
+        );
+    }
+}
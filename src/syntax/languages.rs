@@ -0,0 +1,147 @@
+use crate::syntax::{IndentRules, Language, StringRule, PAIRS};
+
+pub const RUST: Language = Language {
+    name: "rust",
+    extensions: &["rs"],
+    interpreters: &[],
+    keywords: &[
+        "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+        "extern", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut",
+        "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait", "type",
+        "unsafe", "use", "where", "while",
+    ],
+    types: &[
+        "bool", "char", "str", "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32",
+        "i64", "i128", "isize", "f32", "f64",
+    ],
+    constants: &["true", "false"],
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    nested_comments: true,
+    strings: &[StringRule::new("\"", "\"", true)],
+    char_literals: true,
+    capitalized_types: true,
+    macros: true,
+    variables: false,
+    sections: false,
+    indent: IndentRules::BRACKETS,
+    // `'` also starts lifetimes
+    pairs: &[('(', ')'), ('[', ']'), ('{', '}'), ('"', '"')],
+};
+
+pub const TOML: Language = Language {
+    name: "toml",
+    extensions: &["toml", "Cargo.lock"],
+    interpreters: &[],
+    keywords: &[],
+    types: &[],
+    constants: &["true", "false"],
+    line_comments: &["#"],
+    block_comment: None,
+    nested_comments: false,
+    strings: &[
+        StringRule::new("\"\"\"", "\"\"\"", true),
+        StringRule::raw("'''", "'''", true),
+        StringRule::new("\"", "\"", false),
+        StringRule::raw("'", "'", false),
+    ],
+    char_literals: false,
+    capitalized_types: false,
+    macros: false,
+    variables: false,
+    sections: true,
+    indent: IndentRules::BRACKETS,
+    pairs: PAIRS,
+};
+
+pub const PYTHON: Language = Language {
+    name: "python",
+    extensions: &["py", "pyi", "pyw"],
+    interpreters: &["python"],
+    keywords: &[
+        "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del",
+        "elif", "else", "except", "finally", "for", "from", "global", "if", "import", "in", "is",
+        "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while", "with",
+        "yield",
+    ],
+    types: &[
+        "bool", "bytes", "dict", "float", "int", "list", "object", "set", "str", "tuple",
+    ],
+    constants: &["True", "False", "None"],
+    line_comments: &["#"],
+    block_comment: None,
+    nested_comments: false,
+    strings: &[
+        StringRule::new("\"\"\"", "\"\"\"", true),
+        StringRule::new("'''", "'''", true),
+        StringRule::new("\"", "\"", false),
+        StringRule::new("'", "'", false),
+    ],
+    char_literals: false,
+    capitalized_types: false,
+    macros: false,
+    variables: false,
+    sections: false,
+    indent: IndentRules {
+        openers: &[":"],
+        closers: &["else:", "except:", "finally:"],
+    },
+    pairs: PAIRS,
+};
+
+pub const JSON: Language = Language {
+    name: "json",
+    extensions: &["json"],
+    interpreters: &[],
+    keywords: &[],
+    types: &[],
+    constants: &["true", "false", "null"],
+    line_comments: &[],
+    block_comment: None,
+    nested_comments: false,
+    strings: &[StringRule::new("\"", "\"", false)],
+    char_literals: false,
+    capitalized_types: false,
+    macros: false,
+    variables: false,
+    sections: false,
+    indent: IndentRules::BRACKETS,
+    pairs: &[('(', ')'), ('[', ']'), ('{', '}'), ('"', '"')],
+};
+
+pub const SHELL: Language = Language {
+    name: "sh",
+    extensions: &[
+        "sh",
+        "bash",
+        "zsh",
+        ".bashrc",
+        ".bash_profile",
+        ".profile",
+        ".zshrc",
+    ],
+    interpreters: &["sh", "bash", "zsh", "dash", "ksh"],
+    keywords: &[
+        "case", "do", "done", "elif", "else", "esac", "export", "fi", "for", "function", "if",
+        "in", "local", "readonly", "return", "select", "then", "until", "while",
+    ],
+    types: &[],
+    constants: &["true", "false"],
+    line_comments: &["#"],
+    block_comment: None,
+    nested_comments: false,
+    strings: &[
+        StringRule::new("\"", "\"", true),
+        StringRule::raw("'", "'", true),
+    ],
+    char_literals: false,
+    capitalized_types: false,
+    macros: false,
+    variables: true,
+    sections: false,
+    indent: IndentRules {
+        openers: &["then", "do", "else", "in"],
+        closers: &["fi", "done", "esac", "else", "elif"],
+    },
+    pairs: PAIRS,
+};
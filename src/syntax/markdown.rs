@@ -0,0 +1,117 @@
+use crate::syntax::{LineState, Scope, Syntax, Token};
+
+/// Markdown, with headings, quotes, list markers, code, emphasis and links.
+pub struct Markdown;
+
+impl Markdown {
+    /// Tokens of the inline markup of `line` from `from`.
+    fn inline(line: &str, from: usize, tokens: &mut Vec<Token>) {
+        let mut i = from;
+        while i < line.len() {
+            let rest = &line[i..];
+            let c = rest.chars().next().unwrap_or_default();
+
+            let found = match c {
+                '`' => rest[1..].find('`').map(|end| (end + 2, Scope::String)),
+                '*' | '_' => {
+                    let marker = if rest[1..].starts_with(c) {
+                        &rest[..2]
+                    } else {
+                        &rest[..1]
+                    };
+                    rest[marker.len()..]
+                        .find(marker)
+                        .filter(|end| *end > 0)
+                        .map(|end| (end + 2 * marker.len(), Scope::Emphasis))
+                }
+                '[' => rest.find("](").and_then(|text_end| {
+                    rest[text_end..]
+                        .find(')')
+                        .map(|end| (text_end + end + 1, Scope::Link))
+                }),
+                _ => None,
+            };
+
+            match found {
+                Some((len, scope)) => {
+                    tokens.push(Token {
+                        range: i..i + len,
+                        scope,
+                    });
+                    i += len;
+                }
+                None => i += c.len_utf8(),
+            }
+        }
+    }
+}
+
+impl Syntax for Markdown {
+    fn name(&self) -> &'static str {
+        "markdown"
+    }
+
+    fn matches_file(&self, file_name: &str) -> bool {
+        [".md", ".markdown"]
+            .iter()
+            .any(|extension| file_name.ends_with(extension))
+    }
+
+    fn matches_first_line(&self, _line: &str) -> bool {
+        false
+    }
+
+    fn block_comment(&self) -> Option<(&str, &str)> {
+        Some(("<!--", "-->"))
+    }
+
+    fn highlight_line(&self, line: &str, state: &mut LineState) -> Vec<Token> {
+        let whole = |scope| {
+            vec![Token {
+                range: 0..line.len(),
+                scope,
+            }]
+        };
+        let trimmed = line.trim_start();
+
+        // code blocks are left as they are, between their fences
+        if let LineState::Fence(fence) = state {
+            if trimmed.starts_with(fence.as_str()) {
+                *state = LineState::Normal;
+            }
+            return whole(Scope::String);
+        }
+        if let Some(fence) = ["```", "~~~"]
+            .into_iter()
+            .find(|fence| trimmed.starts_with(fence))
+        {
+            *state = LineState::Fence(fence.to_string());
+            return whole(Scope::String);
+        }
+
+        if trimmed.starts_with('#') {
+            return whole(Scope::Heading);
+        }
+        if trimmed.starts_with('>') {
+            return whole(Scope::Comment);
+        }
+
+        let mut tokens = Vec::new();
+        let indent = line.len() - trimmed.len();
+        let digits = trimmed.find(|c: char| !c.is_ascii_digit()).unwrap_or(0);
+        let marker = match trimmed.as_bytes() {
+            [b'-' | b'*' | b'+', b' ', ..] => 1,
+            [..] if digits > 0 && trimmed[digits..].starts_with(". ") => digits + 1,
+            _ => 0,
+        };
+        if marker > 0 {
+            tokens.push(Token {
+                range: indent..indent + marker,
+                scope: Scope::Keyword,
+            });
+        }
+
+        Self::inline(line, indent + marker, &mut tokens);
+        tokens
+    }
+}
@@ -0,0 +1,16 @@
+pub mod grammar;
+pub mod highlighting;
+pub mod language;
+pub mod languages;
+pub mod markdown;
+#[allow(clippy::module_inception)]
+pub mod syntax;
+pub mod syntax_set;
+pub mod tm_language;
+
+pub use grammar::Grammar;
+pub use highlighting::Highlighting;
+pub use language::{Language, StringRule};
+pub use markdown::Markdown;
+pub use syntax::{IndentRules, LineState, Scope, Span, Syntax, Token, PAIRS};
+pub use syntax_set::SyntaxSet;
@@ -0,0 +1,148 @@
+use std::ops::Range;
+
+use crate::syntax::grammar::GrammarState;
+
+/// What a piece of text is in its language, styles are picked from it.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum Scope {
+    Keyword,
+    Type,
+    Function,
+    String,
+    Comment,
+    Number,
+    /// `true`, `None`, `null`
+    Constant,
+    /// `$HOME` in shell scripts
+    Variable,
+    /// markdown headings and TOML tables
+    Heading,
+    /// markdown `*emphasis*` and `**strong**` text
+    Emphasis,
+    Link,
+}
+
+/// Bytes of a row in a scope.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Token {
+    pub range: Range<usize>,
+    pub scope: Scope,
+}
+
+/// What a row leaves open for the next one, like a block comment or a
+/// string going over many lines.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub enum LineState {
+    #[default]
+    Normal,
+    /// inside a block comment, nested that many times
+    Comment(usize),
+    /// inside a string closed by this delimiter
+    String(&'static str),
+    /// inside a markdown code block closed by this fence
+    Fence(String),
+    /// where a grammar file stopped parsing
+    Grammar(Box<GrammarState>),
+}
+
+/// Chars typed in pairs by most languages, the closing one coming with the
+/// opening one.
+pub const PAIRS: &[(char, char)] = &[('(', ')'), ('[', ']'), ('{', '}'), ('"', '"'), ('\'', '\'')];
+
+/// What changes the indentation of rows as they are typed, besides the
+/// brackets every language has.
+pub struct IndentRules {
+    /// rows ending with one of these open a block, like `:` in Python
+    pub openers: &'static [&'static str],
+    /// rows made of one of these close a block, like `fi` in shell scripts
+    pub closers: &'static [&'static str],
+}
+
+impl IndentRules {
+    pub const BRACKETS: Self = Self {
+        openers: &[],
+        closers: &[],
+    };
+}
+
+/// A language that can be highlighted, picked for a document from its file
+/// name or its first line.
+pub trait Syntax {
+    fn name(&self) -> &str;
+
+    /// Whether files named `file_name` are written in this language.
+    fn matches_file(&self, file_name: &str) -> bool;
+
+    /// Whether a file starting with `line`, like a `#!` line, is written in
+    /// this language.
+    fn matches_first_line(&self, line: &str) -> bool;
+
+    /// Tokens of `line`, which starts in `state`. `state` is left as the
+    /// next line starts.
+    fn highlight_line(&self, line: &str, state: &mut LineState) -> Vec<Token>;
+
+    /// How rows are indented as they are typed.
+    fn indent_rules(&self) -> &IndentRules {
+        &IndentRules::BRACKETS
+    }
+
+    /// Opening and closing chars typed together.
+    fn pairs(&self) -> &[(char, char)] {
+        PAIRS
+    }
+
+    /// What starts a comment going to the end of the row.
+    fn line_comment(&self) -> Option<&str> {
+        None
+    }
+
+    /// What starts and ends a comment.
+    fn block_comment(&self) -> Option<(&str, &str)> {
+        None
+    }
+}
+
+/// Text drawn with the style of its scope, if it has one.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Span {
+    pub text: String,
+    pub scope: Option<Scope>,
+}
+
+impl Span {
+    pub fn plain(text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+            scope: None,
+        }
+    }
+
+    /// Chars of `spans` with their scope, one per screen cell.
+    pub fn cells(spans: &[Span]) -> Vec<(char, Option<Scope>)> {
+        spans
+            .iter()
+            .flat_map(|span| span.text.chars().map(|c| (c, span.scope)))
+            .collect()
+    }
+
+    /// Joins cells with the same scope back into spans.
+    pub fn from_cells(cells: &[(char, Option<Scope>)]) -> Vec<Span> {
+        let mut spans: Vec<Span> = Vec::new();
+        for (c, scope) in cells {
+            match spans.last_mut() {
+                Some(span) if span.scope == *scope => span.text.push(*c),
+                _ => spans.push(Span {
+                    text: c.to_string(),
+                    scope: *scope,
+                }),
+            }
+        }
+        spans
+    }
+
+    /// Text of `spans` without styles.
+    #[cfg(test)]
+    pub fn text(spans: &[Span]) -> String {
+        spans.iter().map(|span| span.text.as_str()).collect()
+    }
+}
@@ -0,0 +1,60 @@
+use std::{path::Path, rc::Rc};
+
+use crate::syntax::{
+    languages::{JSON, PYTHON, RUST, SHELL, TOML},
+    Grammar, Markdown, Syntax,
+};
+
+/// Languages documents can be highlighted with.
+pub struct SyntaxSet {
+    syntaxes: Vec<Rc<dyn Syntax>>,
+}
+
+impl Default for SyntaxSet {
+    fn default() -> Self {
+        Self {
+            syntaxes: vec![
+                Rc::new(RUST),
+                Rc::new(TOML),
+                Rc::new(Markdown),
+                Rc::new(PYTHON),
+                Rc::new(JSON),
+                Rc::new(SHELL),
+            ],
+        }
+    }
+}
+
+impl SyntaxSet {
+    /// Adds the grammars of `dir` before the built-in languages, so that
+    /// they are picked over them. Returns the errors of the grammars that
+    /// could not be loaded.
+    pub fn load_grammars(&mut self, dir: &Path) -> Vec<String> {
+        let (grammars, errors) = Grammar::load_dir(dir);
+        self.syntaxes.splice(0..0, grammars);
+
+        errors
+    }
+
+    /// Language of the file at `path` starting with `first_line`, from its
+    /// name first then from a `#!` line.
+    pub fn detect(&self, path: Option<&str>, first_line: &str) -> Option<Rc<dyn Syntax>> {
+        let file_name = path
+            .map(Path::new)
+            .and_then(Path::file_name)
+            .and_then(|name| name.to_str());
+
+        file_name
+            .and_then(|name| {
+                self.syntaxes
+                    .iter()
+                    .find(|syntax| syntax.matches_file(name))
+            })
+            .or_else(|| {
+                self.syntaxes
+                    .iter()
+                    .find(|syntax| syntax.matches_first_line(first_line))
+            })
+            .cloned()
+    }
+}
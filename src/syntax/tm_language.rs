@@ -0,0 +1,142 @@
+use plist::{Dictionary, Value};
+use serde_json::{json, Map, Value as Json};
+
+/// Converts a `.tmLanguage` grammar to the contexts of a
+/// `.sublime-syntax` one. Regions between `begin` and `end` become pushed
+/// contexts popped by `end`, and `while` regions are left at the first line
+/// not matching it.
+pub fn to_sublime_syntax(grammar: &Value) -> Result<Json, String> {
+    let grammar = grammar
+        .as_dictionary()
+        .ok_or("the grammar is not a dictionary")?;
+    let scope = string(grammar, "scopeName").ok_or("missing key scopeName")?;
+
+    let mut contexts = Map::new();
+    contexts.insert(String::from("main"), Json::Array(patterns(grammar)));
+    if let Some(repository) = grammar.get("repository").and_then(Value::as_dictionary) {
+        for (name, rule) in repository {
+            let rule = rule
+                .as_dictionary()
+                .ok_or(format!("repository item {name} is not a dictionary"))?;
+            contexts.insert(name.clone(), Json::Array(convert_rule(rule)));
+        }
+    }
+
+    let file_extensions: Vec<&str> = grammar
+        .get("fileTypes")
+        .and_then(Value::as_array)
+        .map(|types| types.iter().filter_map(Value::as_string).collect())
+        .unwrap_or_default();
+
+    let mut syntax = json!({
+        "scope": scope,
+        "file_extensions": file_extensions,
+        "contexts": contexts,
+    });
+    if let Some(name) = string(grammar, "name") {
+        syntax["name"] = json!(name);
+    }
+    if let Some(first_line) = string(grammar, "firstLineMatch") {
+        syntax["first_line_match"] = json!(first_line);
+    }
+
+    Ok(syntax)
+}
+
+fn string<'a>(dict: &'a Dictionary, key: &str) -> Option<&'a str> {
+    dict.get(key).and_then(Value::as_string)
+}
+
+/// Rules of the `patterns` of `dict`.
+fn patterns(dict: &Dictionary) -> Vec<Json> {
+    dict.get("patterns")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_dictionary)
+        .flat_map(convert_rule)
+        .collect()
+}
+
+/// `{"1": {"name": "x"}}` captures as `{"1": "x"}`.
+fn captures(dict: &Dictionary, keys: &[&str]) -> Option<Json> {
+    let captures = keys
+        .iter()
+        .find_map(|key| dict.get(key).and_then(Value::as_dictionary))?;
+
+    let scopes: Map<String, Json> = captures
+        .iter()
+        .filter_map(|(group, capture)| {
+            let name = capture
+                .as_dictionary()
+                .and_then(|capture| string(capture, "name"))?;
+            Some((group.clone(), json!(name)))
+        })
+        .collect();
+    (!scopes.is_empty()).then_some(Json::Object(scopes))
+}
+
+/// Context items for a rule, a rule only grouping patterns giving them all.
+fn convert_rule(rule: &Dictionary) -> Vec<Json> {
+    if let Some(include) = string(rule, "include") {
+        let context = match include {
+            "$self" | "$base" => String::from("main"),
+            _ => match include.strip_prefix('#') {
+                Some(name) => name.to_string(),
+                // another grammar, as a whole
+                None => format!("scope:{}", include.split('#').next().unwrap_or_default()),
+            },
+        };
+        return vec![json!({ "include": context })];
+    }
+
+    let mut item = Map::new();
+    let name = string(rule, "name");
+
+    if let Some(regex) = string(rule, "match") {
+        item.insert(String::from("match"), json!(regex));
+        if let Some(name) = name {
+            item.insert(String::from("scope"), json!(name));
+        }
+        if let Some(captures) = captures(rule, &["captures"]) {
+            item.insert(String::from("captures"), captures);
+        }
+        return vec![Json::Object(item)];
+    }
+
+    let Some(begin) = string(rule, "begin") else {
+        return patterns(rule);
+    };
+    let end = match (string(rule, "end"), string(rule, "while")) {
+        (Some(end), _) => end.to_string(),
+        (None, Some(continues)) => format!("^(?!{continues})"),
+        (None, None) => String::from("$"),
+    };
+
+    let mut pop = Map::new();
+    pop.insert(String::from("match"), json!(end));
+    if let Some(captures) = captures(rule, &["endCaptures", "captures"]) {
+        pop.insert(String::from("captures"), captures);
+    }
+    pop.insert(String::from("pop"), json!(true));
+
+    let mut context = Vec::new();
+    if let Some(name) = name {
+        context.push(json!({ "meta_scope": name }));
+    }
+    if let Some(content) = string(rule, "contentName") {
+        context.push(json!({ "meta_content_scope": content }));
+    }
+    context.push(Json::Object(pop));
+    context.extend(patterns(rule));
+
+    item.insert(String::from("match"), json!(begin));
+    if let Some(name) = name {
+        item.insert(String::from("scope"), json!(name));
+    }
+    if let Some(captures) = captures(rule, &["beginCaptures", "captures"]) {
+        item.insert(String::from("captures"), captures);
+    }
+    item.insert(String::from("push"), Json::Array(context));
+    vec![Json::Object(item)]
+}
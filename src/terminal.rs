@@ -1,26 +1,61 @@
-use crate::utils::{Position, Size};
-use std::io::{stdin, stdout, Error, Stdout, Write};
+use crate::{
+    theme::{ColorDepth, Style},
+    utils::{base64, Position, Size},
+};
+use std::{
+    env,
+    io::{stdin, stdout, Error, ErrorKind, Read, Stdout, Write},
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError},
+        Mutex, OnceLock,
+    },
+    thread,
+    time::Duration,
+};
 use termion::{
-    event::Key,
-    input::TermRead,
+    event::{self, Event, Key, MouseEvent},
     raw::{IntoRawMode, RawTerminal},
     terminal_size,
 };
 
+/// Sent by the terminal around pasted text once bracketed paste is enabled.
+const PASTE_START: &[u8] = b"\x1b[200~";
+const PASTE_END: &[u8] = b"\x1b[201~";
+
+/// What the user did, typing a key, pasting text or using the mouse.
+#[derive(Debug, PartialEq)]
+pub enum Input {
+    Key(Key),
+    Paste(String),
+    Mouse(MouseEvent),
+}
+
 pub struct Terminal {
+    /// the terminal rows left for the editor
     size: Size,
+    rows: u16,
+    color_depth: ColorDepth,
     _stdout: RawTerminal<Stdout>,
 }
 
 impl Terminal {
     pub fn default() -> Result<Self, Error> {
         let size = terminal_size()?;
+        let stdout = stdout().into_raw_mode()?;
+        // pasted text comes between PASTE_START and PASTE_END
+        print!("\x1b[?2004h");
+
         Ok(Self {
             size: Size {
-                width: size.0,      // this -1 is beacause I use tmux and it uses one line at the
-                height: size.1 - 1, // end of the terminal
+                width: size.0,
+                height: size.1,
             },
-            _stdout: stdout().into_raw_mode()?,
+            rows: size.1,
+            color_depth: ColorDepth::detect(
+                env::var("COLORTERM").ok().as_deref(),
+                env::var("TERM").ok().as_deref(),
+            ),
+            _stdout: stdout,
         })
     }
 
@@ -28,6 +63,12 @@ impl Terminal {
         &self.size
     }
 
+    /// Leaves the last `rows` rows of the terminal out of the screen.
+    pub fn reserve_rows(&mut self, rows: usize) {
+        let rows = u16::try_from(rows).unwrap_or(u16::MAX);
+        self.size.height = self.rows.saturating_sub(rows);
+    }
+
     pub fn clear_screen() {
         print!("{}", termion::clear::All);
     }
@@ -49,12 +90,150 @@ impl Terminal {
         stdout().flush()
     }
 
-    pub fn read_key() -> Result<Key, Error> {
-        loop {
-            if let Some(key_result) = stdin().lock().keys().next() {
-                return key_result;
+    pub fn read_input() -> Result<Input, Error> {
+        Self::inputs()
+            .lock()
+            .map_err(|_| Error::other("the input thread stopped"))?
+            .recv()
+            .map_err(Error::other)?
+    }
+
+    /// Input coming before `timeout`, `None` once it is over.
+    pub fn read_input_timeout(timeout: Duration) -> Result<Option<Input>, Error> {
+        let inputs = Self::inputs()
+            .lock()
+            .map_err(|_| Error::other("the input thread stopped"))?;
+
+        match inputs.recv_timeout(timeout) {
+            Ok(input) => input.map(Some),
+            Err(RecvTimeoutError::Timeout) => Ok(None),
+            Err(err) => Err(Error::other(err)),
+        }
+    }
+
+    /// Inputs read from stdin by a thread of their own, so that waiting for
+    /// one can time out.
+    fn inputs() -> &'static Mutex<Receiver<Result<Input, Error>>> {
+        static INPUTS: OnceLock<Mutex<Receiver<Result<Input, Error>>>> = OnceLock::new();
+
+        INPUTS.get_or_init(|| {
+            let (sender, receiver) = mpsc::channel();
+            thread::spawn(move || {
+                let mut stdin = stdin().lock();
+                let mut sent = [0; 1024];
+                loop {
+                    let inputs = match stdin.read(&mut sent) {
+                        Ok(0) => Err(Error::new(ErrorKind::UnexpectedEof, "stdin was closed")),
+                        Ok(len) => Self::parse_inputs(&sent[..len], stdin.by_ref().bytes()),
+                        Err(err) => Err(err),
+                    };
+
+                    let failed = inputs.is_err();
+                    let inputs = match inputs {
+                        Ok(inputs) => inputs.into_iter().map(Ok).collect(),
+                        Err(err) => vec![Err(err)],
+                    };
+                    for input in inputs {
+                        if sender.send(input).is_err() {
+                            return;
+                        }
+                    }
+                    if failed {
+                        break;
+                    }
+                }
+            });
+            Mutex::new(receiver)
+        })
+    }
+
+    /// Inputs in the bytes the terminal `sent` at once, an escape alone
+    /// being the Esc key. Sequences and pastes going on past them are read
+    /// from the `next` bytes.
+    pub fn parse_inputs(
+        sent: &[u8],
+        next: impl Iterator<Item = Result<u8, Error>>,
+    ) -> Result<Vec<Input>, Error> {
+        let mut bytes = Bytes { sent, next };
+        let mut inputs = Vec::new();
+
+        while let Some((&byte, rest)) = bytes.sent.split_first() {
+            bytes.sent = rest;
+            if byte == b'\x1b' && rest.is_empty() {
+                inputs.push(Input::Key(Key::Esc));
+                break;
+            }
+
+            // termion can't parse the sequences it doesn't know, like the
+            // paste markers, so they are kept to tell them
+            let mut sequence = vec![byte];
+            let event = event::parse_event(
+                byte,
+                &mut bytes.by_ref().inspect(|byte| {
+                    if let Ok(byte) = byte {
+                        sequence.push(*byte);
+                    }
+                }),
+            );
+
+            match event {
+                Ok(Event::Key(key)) => inputs.push(Input::Key(key)),
+                Ok(Event::Mouse(event)) => inputs.push(Input::Mouse(event)),
+                Err(_) if sequence == PASTE_START => {
+                    inputs.push(Input::Paste(Self::parse_paste(&mut bytes)?));
+                }
+                // other unknown sequences are left out
+                Ok(Event::Unsupported(_)) | Err(_) => {}
+            }
+        }
+
+        Ok(inputs)
+    }
+
+    /// Text pasted after `PASTE_START`, read as it is so none of it is taken
+    /// as keys.
+    fn parse_paste(bytes: impl Iterator<Item = Result<u8, Error>>) -> Result<String, Error> {
+        let mut pasted = Vec::new();
+
+        for byte in bytes {
+            pasted.push(byte?);
+            if pasted.ends_with(PASTE_END) {
+                pasted.truncate(pasted.len() - PASTE_END.len());
+
+                // terminals send line breaks as carriage returns
+                return Ok(String::from_utf8_lossy(&pasted)
+                    .replace("\r\n", "\n")
+                    .replace('\r', "\n"));
             }
         }
+
+        Err(Error::new(ErrorKind::UnexpectedEof, "the paste has no end"))
+    }
+
+    /// `text` drawn in `style`, with the colors the terminal has.
+    pub fn paint(&self, text: &str, style: Style) -> String {
+        let escape = style.escape(self.color_depth);
+        if escape.is_empty() {
+            text.to_string()
+        } else {
+            format!("{escape}{text}{}", termion::style::Reset)
+        }
+    }
+
+    /// Asks the terminal to put `text` in the system clipboard with the OSC 52
+    /// escape sequence, which also works over SSH and inside tmux.
+    pub fn copy_to_clipboard(text: &str) {
+        print!("\x1b]52;c;{}\x07", base64::encode(text.as_bytes()));
+    }
+
+    /// Reports clicks, drags and the wheel in SGR mode, which has no limit
+    /// on the screen size.
+    pub fn enable_mouse() {
+        print!("\x1b[?1000h\x1b[?1002h\x1b[?1006h");
+    }
+
+    pub fn disable_mouse() {
+        print!("\x1b[?1006l\x1b[?1002l\x1b[?1000l");
     }
 
     pub fn hide_cursor() {
@@ -65,3 +244,121 @@ impl Terminal {
         print!("{}", termion::cursor::Show);
     }
 }
+
+/// Bytes the terminal sent at once, followed by the ones it sends next.
+struct Bytes<'a, I> {
+    sent: &'a [u8],
+    next: I,
+}
+
+impl<I: Iterator<Item = Result<u8, Error>>> Iterator for Bytes<'_, I> {
+    type Item = Result<u8, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.sent.split_first() {
+            Some((&byte, rest)) => {
+                self.sent = rest;
+                Some(Ok(byte))
+            }
+            None => self.next.next(),
+        }
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        Self::disable_mouse();
+        print!("\x1b[?2004l");
+        let _ = Self::flush();
+    }
+}
+
+mod test {
+    #[cfg(test)]
+    use std::io::Error;
+
+    /// Bytes the terminal sends after the ones given at once.
+    #[cfg(test)]
+    fn next(bytes: &[u8]) -> impl Iterator<Item = Result<u8, Error>> + '_ {
+        bytes.iter().map(|&byte| Ok(byte))
+    }
+
+    #[test]
+    fn should_read_a_paste_between_its_markers() {
+        use super::{Input, Terminal};
+        use termion::event::Key;
+
+        let inputs = Terminal::parse_inputs(b"a\x1b[200~one\x1b[201~b", next(b"")).unwrap();
+
+        assert_eq!(
+            inputs,
+            vec![
+                Input::Key(Key::Char('a')),
+                Input::Paste(String::from("one")),
+                Input::Key(Key::Char('b')),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_read_the_rest_of_a_paste_sent_later() {
+        use super::{Input, Terminal};
+
+        let inputs = Terminal::parse_inputs(b"\x1b[200~on", next(b"e\x1b[201~x")).unwrap();
+
+        assert_eq!(inputs, vec![Input::Paste(String::from("one"))]);
+    }
+
+    #[test]
+    fn should_fail_on_a_paste_without_its_end() {
+        use super::Terminal;
+        use std::io::ErrorKind;
+
+        let err = Terminal::parse_inputs(b"\x1b[200~one", next(b"\x1b[201")).unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn should_paste_carriage_returns_as_line_breaks() {
+        use super::{Input, Terminal};
+
+        let inputs = Terminal::parse_inputs(b"\x1b[200~a\r\nb\rc\nd\x1b[201~", next(b"")).unwrap();
+
+        assert_eq!(inputs, vec![Input::Paste(String::from("a\nb\nc\nd"))]);
+    }
+
+    #[test]
+    fn should_read_an_escape_alone_as_esc() {
+        use super::{Input, Terminal};
+        use termion::event::Key;
+
+        let inputs = Terminal::parse_inputs(b"\x1b", next(b"j")).unwrap();
+        assert_eq!(inputs, vec![Input::Key(Key::Esc)]);
+
+        let inputs = Terminal::parse_inputs(b"\x1bj", next(b"")).unwrap();
+        assert_eq!(inputs, vec![Input::Key(Key::Alt('j'))]);
+    }
+
+    #[test]
+    fn should_read_sgr_mouse_sequences() {
+        use super::{Input, Terminal};
+        use termion::event::{MouseButton, MouseEvent};
+
+        let inputs = Terminal::parse_inputs(
+            b"\x1b[<0;3;5M\x1b[<32;4;6M\x1b[<0;4;6m\x1b[<65;120;40M",
+            next(b""),
+        )
+        .unwrap();
+
+        assert_eq!(
+            inputs,
+            vec![
+                Input::Mouse(MouseEvent::Press(MouseButton::Left, 3, 5)),
+                Input::Mouse(MouseEvent::Hold(4, 6)),
+                Input::Mouse(MouseEvent::Release(4, 6)),
+                Input::Mouse(MouseEvent::Press(MouseButton::WheelDown, 120, 40)),
+            ]
+        );
+    }
+}
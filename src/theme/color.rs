@@ -0,0 +1,209 @@
+/// RGB values of the 16 basic colors, as xterm shows them.
+const BASIC: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+/// Names of the 16 basic colors, `bright_` ones being the last 8.
+const NAMES: [&str; 8] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+
+/// Levels of the 6x6x6 cube of the 256 colors palette.
+const CUBE: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// Colors a terminal can show.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, PartialOrd, Ord)]
+pub enum ColorDepth {
+    Ansi16,
+    Ansi256,
+    TrueColor,
+}
+
+impl ColorDepth {
+    /// Depth told by the `COLORTERM` and `TERM` variables.
+    pub fn detect(colorterm: Option<&str>, term: Option<&str>) -> Self {
+        let term = term.unwrap_or_default();
+
+        if matches!(colorterm, Some("truecolor" | "24bit"))
+            || term.ends_with("-direct")
+            || term.contains("truecolor")
+        {
+            Self::TrueColor
+        } else if term.contains("256color") {
+            Self::Ansi256
+        } else {
+            Self::Ansi16
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Color {
+    /// one of the 256 colors palette, the first 16 being the basic ones
+    Ansi(u8),
+    Rgb(u8, u8, u8),
+}
+
+impl Color {
+    /// Parses `#rrggbb`, a palette index or a basic color name like `red`
+    /// or `bright_red`.
+    pub fn parse(text: &str) -> Option<Self> {
+        if let Some(hex) = text.strip_prefix('#') {
+            let value = u32::from_str_radix(hex, 16)
+                .ok()
+                .filter(|_| hex.len() == 6)?;
+            let [_, r, g, b] = value.to_be_bytes();
+            return Some(Self::Rgb(r, g, b));
+        }
+        if let Ok(index) = text.parse() {
+            return Some(Self::Ansi(index));
+        }
+
+        let (name, bright) = match text.strip_prefix("bright_") {
+            Some(name) => (name, 8),
+            None => (text, 0),
+        };
+        let index = NAMES.iter().position(|known| *known == name)?;
+        u8::try_from(index + bright).ok().map(Self::Ansi)
+    }
+
+    fn rgb(self) -> (u8, u8, u8) {
+        match self {
+            Self::Rgb(r, g, b) => (r, g, b),
+            Self::Ansi(index @ 0..=15) => BASIC[index as usize],
+            Self::Ansi(index @ 16..=231) => {
+                let index = index - 16;
+                (
+                    CUBE[(index / 36) as usize],
+                    CUBE[(index / 6 % 6) as usize],
+                    CUBE[(index % 6) as usize],
+                )
+            }
+            Self::Ansi(index) => {
+                let level = 8 + (index - 232) * 10;
+                (level, level, level)
+            }
+        }
+    }
+
+    /// The closest color a terminal showing `depth` colors has.
+    pub fn downgrade(self, depth: ColorDepth) -> Self {
+        match (self, depth) {
+            (_, ColorDepth::TrueColor)
+            | (Self::Ansi(0..=15), _)
+            | (Self::Ansi(_), ColorDepth::Ansi256) => self,
+            (Self::Rgb(r, g, b), ColorDepth::Ansi256) => {
+                let cube = |value: u8| {
+                    (0..CUBE.len())
+                        .min_by_key(|i| CUBE[*i].abs_diff(value))
+                        .unwrap_or_default()
+                };
+                let (r6, g6, b6) = (cube(r), cube(g), cube(b));
+                let in_cube = Self::Ansi(u8::try_from(16 + 36 * r6 + 6 * g6 + b6).unwrap_or(16));
+
+                let gray = (u16::from(r) + u16::from(g) + u16::from(b)) / 3;
+                let gray_index = u8::try_from((gray.saturating_sub(3) / 10).min(23)).unwrap_or(0);
+                let in_ramp = Self::Ansi(232 + gray_index);
+
+                if distance(self.rgb(), in_ramp.rgb()) < distance(self.rgb(), in_cube.rgb()) {
+                    in_ramp
+                } else {
+                    in_cube
+                }
+            }
+            (_, ColorDepth::Ansi16) => {
+                let index = (0..BASIC.len())
+                    .min_by_key(|i| distance(self.rgb(), BASIC[*i]))
+                    .unwrap_or_default();
+                Self::Ansi(u8::try_from(index).unwrap_or_default())
+            }
+        }
+    }
+
+    /// SGR parameters setting this color as the foreground, or the
+    /// background.
+    pub fn sgr(self, background: bool) -> String {
+        let base = if background { 40 } else { 30 };
+        match self {
+            Self::Ansi(index @ 0..=7) => (base + index).to_string(),
+            Self::Ansi(index @ 8..=15) => (base + 60 + index - 8).to_string(),
+            Self::Ansi(index) => format!("{};5;{index}", base + 8),
+            Self::Rgb(r, g, b) => format!("{};2;{r};{g};{b}", base + 8),
+        }
+    }
+}
+
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let square = |x: u8, y: u8| u32::from(x.abs_diff(y)).pow(2);
+    square(a.0, b.0) + square(a.1, b.1) + square(a.2, b.2)
+}
+
+mod test {
+    #[test]
+    fn should_detect_the_terminal_depth() {
+        use super::ColorDepth;
+
+        assert_eq!(
+            ColorDepth::detect(Some("truecolor"), Some("xterm")),
+            ColorDepth::TrueColor
+        );
+        assert_eq!(
+            ColorDepth::detect(None, Some("tmux-256color")),
+            ColorDepth::Ansi256
+        );
+        assert_eq!(ColorDepth::detect(None, Some("linux")), ColorDepth::Ansi16);
+    }
+
+    #[test]
+    fn should_downgrade_colors_to_the_terminal_depth() {
+        use super::{Color, ColorDepth};
+
+        let orange = Color::parse("#ff8700").unwrap();
+        assert_eq!(orange.downgrade(ColorDepth::TrueColor), orange);
+        assert_eq!(orange.downgrade(ColorDepth::Ansi256), Color::Ansi(208));
+        assert_eq!(orange.downgrade(ColorDepth::Ansi16), Color::Ansi(3));
+    }
+
+    #[test]
+    fn should_downgrade_grays_to_the_gray_ramp() {
+        use super::{Color, ColorDepth};
+
+        assert_eq!(
+            Color::parse("#303030")
+                .unwrap()
+                .downgrade(ColorDepth::Ansi256),
+            Color::Ansi(236)
+        );
+    }
+
+    #[test]
+    fn should_parse_color_names() {
+        use super::Color;
+
+        assert_eq!(Color::parse("bright_blue"), Some(Color::Ansi(12)));
+        assert_eq!(Color::parse("#12345"), None);
+    }
+
+    #[test]
+    fn should_write_sgr_parameters() {
+        use super::Color;
+
+        assert_eq!(Color::Ansi(12).sgr(false), "94");
+        assert_eq!(Color::Ansi(208).sgr(true), "48;5;208");
+    }
+}
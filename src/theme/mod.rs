@@ -0,0 +1,10 @@
+pub mod color;
+pub mod style;
+#[allow(clippy::module_inception)]
+pub mod theme;
+pub mod themes;
+
+pub use color::{Color, ColorDepth};
+pub use style::Style;
+pub use theme::{Face, Theme};
+pub use themes::Themes;
@@ -0,0 +1,51 @@
+use crate::theme::{Color, ColorDepth};
+
+/// How text is drawn, what is not set is taken from the style under it.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct Style {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+    /// swaps the foreground and background colors
+    pub reverse: bool,
+}
+
+impl Style {
+    /// This style drawn over `base`.
+    pub fn over(self, base: Style) -> Style {
+        Style {
+            fg: self.fg.or(base.fg),
+            bg: self.bg.or(base.bg),
+            bold: self.bold || base.bold,
+            italic: self.italic || base.italic,
+            underline: self.underline || base.underline,
+            reverse: self.reverse || base.reverse,
+        }
+    }
+
+    /// SGR escape sequence drawing text in this style with `depth` colors,
+    /// empty for the terminal's own style.
+    pub fn escape(self, depth: ColorDepth) -> String {
+        let mut params: Vec<String> = [
+            (self.bold, "1"),
+            (self.italic, "3"),
+            (self.underline, "4"),
+            (self.reverse, "7"),
+        ]
+        .iter()
+        .filter(|(set, _)| *set)
+        .map(|(_, param)| (*param).to_string())
+        .collect();
+        params.extend(self.fg.map(|color| color.downgrade(depth).sgr(false)));
+        params.extend(self.bg.map(|color| color.downgrade(depth).sgr(true)));
+
+        if params.is_empty() {
+            String::new()
+        } else {
+            format!("\x1b[{}m", params.join(";"))
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use std::collections::HashMap;
+
+use toml::{Table, Value};
+
+use crate::{
+    syntax::Scope,
+    theme::{Color, Style},
+    utils::lines::{line_at, line_of_key},
+};
+
+/// What a theme gives a style to.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Face {
+    /// text without any other face, and the background of the windows
+    Normal,
+    Syntax(Scope),
+    Selection,
+    Search,
+    /// the last line when it shows the mode and the cursor position
+    StatusLine,
+    /// line numbers and marks on the left of the windows
+    Gutter,
+    Error,
+}
+
+impl Face {
+    const NAMES: [(&'static str, Face); 17] = [
+        ("normal", Face::Normal),
+        ("keyword", Face::Syntax(Scope::Keyword)),
+        ("type", Face::Syntax(Scope::Type)),
+        ("function", Face::Syntax(Scope::Function)),
+        ("string", Face::Syntax(Scope::String)),
+        ("comment", Face::Syntax(Scope::Comment)),
+        ("number", Face::Syntax(Scope::Number)),
+        ("constant", Face::Syntax(Scope::Constant)),
+        ("variable", Face::Syntax(Scope::Variable)),
+        ("heading", Face::Syntax(Scope::Heading)),
+        ("emphasis", Face::Syntax(Scope::Emphasis)),
+        ("link", Face::Syntax(Scope::Link)),
+        ("selection", Face::Selection),
+        ("search", Face::Search),
+        ("status_line", Face::StatusLine),
+        ("gutter", Face::Gutter),
+        ("error", Face::Error),
+    ];
+
+    pub fn from_name(name: &str) -> Option<Face> {
+        Self::NAMES
+            .iter()
+            .find(|(known, _)| *known == name)
+            .map(|(_, face)| *face)
+    }
+}
+
+/// Themes every editor has, themes of the config directory with the same
+/// name replace them.
+pub const BUILT_IN: [(&str, &str); 2] = [
+    (
+        "default",
+        r#"
+keyword = "magenta"
+type = "yellow"
+function = "blue"
+string = "green"
+comment = "bright_black"
+number = "red"
+constant = "red"
+variable = "cyan"
+heading = { fg = "yellow", bold = true }
+emphasis = { fg = "cyan", italic = true }
+link = { fg = "blue", underline = true }
+selection = { reverse = true }
+search = { fg = "black", bg = "yellow" }
+status_line = { bold = true }
+gutter = "bright_black"
+error = { fg = "bright_white", bg = "red" }
+"#,
+    ),
+    (
+        "gruvbox",
+        r##"
+normal = { fg = "#ebdbb2", bg = "#282828" }
+keyword = "#fb4934"
+type = "#fabd2f"
+function = "#8ec07c"
+string = "#b8bb26"
+comment = { fg = "#928374", italic = true }
+number = "#d3869b"
+constant = "#d3869b"
+variable = "#83a598"
+heading = { fg = "#fabd2f", bold = true }
+emphasis = { fg = "#8ec07c", italic = true }
+link = { fg = "#83a598", underline = true }
+selection = { bg = "#504945" }
+search = { fg = "#282828", bg = "#fabd2f" }
+status_line = { fg = "#ebdbb2", bg = "#3c3836" }
+gutter = { fg = "#7c6f64", bg = "#282828" }
+error = { fg = "#fb4934", bold = true }
+"##,
+    ),
+];
+
+/// Styles of the faces, written in a TOML file as `face = "color"` or
+/// `face = { fg = "#rrggbb", bg = "blue", bold = true }`.
+#[derive(Clone, Debug)]
+pub struct Theme {
+    name: String,
+    faces: HashMap<Face, Style>,
+}
+
+impl Theme {
+    /// Reads the theme `text`, an error tells its line.
+    pub fn parse(name: &str, text: &str) -> Result<Theme, (usize, String)> {
+        let table: Table = text.parse().map_err(|err: toml::de::Error| {
+            let line = err.span().map_or(1, |span| line_at(text, span.start));
+            (line, err.message().to_string())
+        })?;
+
+        let mut faces = HashMap::new();
+        for (key, value) in &table {
+            let line = line_of_key(text, &[key]);
+            let face = Face::from_name(key).ok_or((line, format!("Unknown face: {key}")))?;
+            let style = parse_style(value).map_err(|message| (line, message))?;
+            faces.insert(face, style);
+        }
+
+        Ok(Theme {
+            name: name.to_string(),
+            faces,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Style of `face`, over the normal one.
+    pub fn style(&self, face: Face) -> Style {
+        let normal = self.faces.get(&Face::Normal).copied().unwrap_or_default();
+        self.faces
+            .get(&face)
+            .map_or(normal, |style| style.over(normal))
+    }
+}
+
+fn parse_color(value: &Value) -> Result<Color, String> {
+    let text = value.as_str().ok_or("Colors are strings")?;
+    Color::parse(text).ok_or(format!("Invalid color: {text}"))
+}
+
+fn parse_style(value: &Value) -> Result<Style, String> {
+    let Some(table) = value.as_table() else {
+        return Ok(Style {
+            fg: Some(parse_color(value)?),
+            ..Style::default()
+        });
+    };
+
+    let mut style = Style::default();
+    for (key, value) in table {
+        match key.as_str() {
+            "fg" => style.fg = Some(parse_color(value)?),
+            "bg" => style.bg = Some(parse_color(value)?),
+            "bold" | "italic" | "underline" | "reverse" => {
+                let set = value.as_bool().ok_or(format!("{key} is true or false"))?;
+                match key.as_str() {
+                    "bold" => style.bold = set,
+                    "italic" => style.italic = set,
+                    "underline" => style.underline = set,
+                    _ => style.reverse = set,
+                }
+            }
+            _ => return Err(format!("Unknown style attribute: {key}")),
+        }
+    }
+
+    Ok(style)
+}
+
+mod test {
+    #[test]
+    fn should_parse_faces() {
+        use super::{Face, Theme};
+        use crate::{
+            syntax::Scope,
+            theme::{Color, Style},
+        };
+
+        let theme = Theme::parse(
+            "test",
+            "normal = { bg = \"#000000\" }\nkeyword = { fg = \"red\", bold = true }\n",
+        )
+        .unwrap();
+        assert_eq!(
+            theme.style(Face::Syntax(Scope::Keyword)),
+            Style {
+                fg: Some(Color::Ansi(1)),
+                bg: Some(Color::Rgb(0, 0, 0)),
+                bold: true,
+                ..Style::default()
+            }
+        );
+    }
+
+    #[test]
+    fn should_take_missing_colors_from_normal() {
+        use super::{Face, Theme};
+        use crate::theme::Color;
+
+        let theme = Theme::parse("test", "normal = { bg = \"#000000\" }\n").unwrap();
+        assert_eq!(theme.style(Face::Search).bg, Some(Color::Rgb(0, 0, 0)));
+    }
+
+    #[test]
+    fn should_report_unknown_faces_with_their_line() {
+        use super::Theme;
+
+        let error = Theme::parse("test", "string = \"green\"\n\nkeywords = \"red\"").unwrap_err();
+        assert_eq!(error, (3, String::from("Unknown face: keywords")));
+    }
+
+    #[test]
+    fn should_report_invalid_colors() {
+        use super::Theme;
+
+        let error = Theme::parse("test", "string = \"#00ff\"").unwrap_err();
+        assert_eq!(error, (1, String::from("Invalid color: #00ff")));
+    }
+
+    #[test]
+    fn should_report_invalid_toml() {
+        use super::Theme;
+
+        let error = Theme::parse("test", "string = \"green\"\nerror = {").unwrap_err();
+        assert_eq!(error.0, 2);
+    }
+}
@@ -0,0 +1,84 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::theme::{theme::BUILT_IN, Theme};
+
+/// Themes that can be picked with `:colorscheme`, and the one in use.
+pub struct Themes {
+    themes: Vec<Theme>,
+    current: usize,
+}
+
+impl Default for Themes {
+    fn default() -> Self {
+        let themes = BUILT_IN
+            .iter()
+            .filter_map(|(name, text)| Theme::parse(name, text).ok())
+            .collect();
+
+        Self { themes, current: 0 }
+    }
+}
+
+impl Themes {
+    /// Adds the `.toml` themes of `dir`, named after their file. Returns the
+    /// errors of the themes that could not be loaded.
+    pub fn load_dir(&mut self, dir: &Path) -> Vec<String> {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == "toml")
+            })
+            .collect();
+        paths.sort();
+
+        let mut errors = Vec::new();
+        for path in paths {
+            let name = path
+                .file_stem()
+                .and_then(|name| name.to_str())
+                .unwrap_or_default();
+            let theme = fs::read_to_string(&path)
+                .map_err(|err| (0, err.to_string()))
+                .and_then(|text| Theme::parse(name, &text));
+
+            match theme {
+                Ok(theme) => match self.themes.iter().position(|known| known.name() == name) {
+                    Some(index) => self.themes[index] = theme,
+                    None => self.themes.push(theme),
+                },
+                Err((0, message)) => errors.push(format!("{}: {message}", path.display())),
+                Err((line, message)) => {
+                    errors.push(format!("{}:{line}: {message}", path.display()));
+                }
+            }
+        }
+
+        errors
+    }
+
+    /// Uses theme `name`, returns `false` if there is none.
+    pub fn select(&mut self, name: &str) -> bool {
+        match self.themes.iter().position(|theme| theme.name() == name) {
+            Some(index) => {
+                self.current = index;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn current(&self) -> &Theme {
+        &self.themes[self.current]
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.themes.iter().map(Theme::name)
+    }
+}
@@ -0,0 +1,5 @@
+pub enum Alignment {
+    Top,
+    Center,
+    Bottom,
+}
@@ -0,0 +1,31 @@
+use toml_edit::{ImDocument, Item, Key, TableLike};
+
+/// Line, from 1, of the byte at `index` of `text`.
+pub fn line_at(text: &str, index: usize) -> usize {
+    text[..index.min(text.len())].matches('\n').count() + 1
+}
+
+/// Line, from 1, where the key at `path` is set in the TOML `text`, the
+/// tables before it being followed down from the top one. A table name
+/// alone gives the line of its header.
+pub fn line_of_key(text: &str, path: &[&str]) -> usize {
+    let Ok(doc) = ImDocument::parse(text) else {
+        return 1;
+    };
+    let Some((key, tables)) = path.split_last() else {
+        return 1;
+    };
+
+    let mut table: &dyn TableLike = doc.as_table();
+    for name in tables {
+        match table.get(name).and_then(Item::as_table_like) {
+            Some(inner) => table = inner,
+            None => return 1,
+        }
+    }
+
+    table
+        .key(key)
+        .and_then(Key::span)
+        .map_or(1, |span| line_at(text, span.start))
+}
@@ -1,7 +1,12 @@
+pub mod alignment;
+pub mod base64;
 pub mod direction;
+pub mod lines;
+pub mod paths;
 pub mod position;
 pub mod size;
 
+pub use alignment::Alignment;
 pub use direction::Direction;
 pub use position::Position;
 pub use size::Size;
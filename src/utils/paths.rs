@@ -0,0 +1,12 @@
+use std::{env, path::PathBuf};
+
+/// Directory of the editor's configuration, `$XDG_CONFIG_HOME/text-editor`
+/// or `~/.config/text-editor`.
+pub fn config_dir() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+    Some(base.join("text-editor"))
+}
@@ -1,3 +1,5 @@
+use std::cmp::Ordering;
+
 #[derive(Clone, Copy, Default, Debug)]
 pub struct Position {
     pub x: usize,
@@ -10,3 +12,16 @@ impl PartialEq for Position {
         self.x == other.x && self.y == other.y
     }
 }
+
+// positions are ordered as they appear in a document, line first
+impl Ord for Position {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.y.cmp(&other.y).then(self.x.cmp(&other.x))
+    }
+}
+
+impl PartialOrd for Position {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}